@@ -0,0 +1,23 @@
+use criterion::{Criterion, criterion_group, criterion_main};
+use std::hint::black_box;
+use xmltree::{Document, LazyDocument};
+
+const SRC: &str = include_str!("../examples/example.xml");
+
+/// Compares the outline scan with full and lazy parsing; run with `--features simd` to measure the `memchr` scan
+fn criterion_benchmark(c: &mut Criterion) {
+    c.bench_function("parse_str", |b| {
+        b.iter(|| Document::parse_str(black_box(SRC)).unwrap())
+    });
+
+    c.bench_function("lazy parse_str", |b| {
+        b.iter(|| LazyDocument::parse_str(black_box(SRC)).unwrap())
+    });
+
+    c.bench_function("lazy scan_str", |b| {
+        b.iter(|| LazyDocument::scan_str(black_box(SRC)).unwrap())
+    });
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,92 @@
+use criterion::{Criterion, criterion_group, criterion_main};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    hint::black_box,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use xmltree::{
+    Document,
+    node::{Node, TagNode},
+};
+
+const SRC: &str = include_str!("../examples/example.xml");
+
+/// Counts allocations and the bytes they request, so the footprint of a tree can be compared across layouts
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        BYTES.fetch_add(new_size.saturating_sub(layout.size()), Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// Counts the tags with attributes stored inline, each of which would otherwise be a heap allocation
+fn inline_attributes(root: &mut TagNode<'_>) -> usize {
+    let mut inline = 0;
+    let mut stack = vec![root];
+    while let Some(tag) = stack.pop() {
+        let attributes = tag.attributes_mut();
+        if attributes.is_inline() && !attributes.is_empty() {
+            inline += 1;
+        }
+        stack.extend(
+            tag.children_mut()
+                .iter_mut()
+                .filter_map(|child| match child {
+                    Node::Child(tag) => Some(tag),
+                    _ => None,
+                }),
+        );
+    }
+    inline
+}
+
+/// Prints the allocations and heap bytes used to parse the source, and the allocations saved by inline attributes
+fn report(name: &str, src: &str) {
+    let (allocations, bytes) = (
+        ALLOCATIONS.load(Ordering::Relaxed),
+        BYTES.load(Ordering::Relaxed),
+    );
+    let mut document = Document::parse_str(src).unwrap();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+    let bytes = BYTES.load(Ordering::Relaxed) - bytes;
+    let saved = inline_attributes(document.root_mut());
+    drop(document);
+
+    println!(
+        "{name}: {allocations} allocations, {bytes} heap bytes; {saved} more with attributes on the heap"
+    );
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let attributes = format!("<r>{}</r>", "<e a='1' />".repeat(1000));
+    let bare = format!("<r>{}</r>", "<e />".repeat(1000));
+    report("example.xml", SRC);
+    report("1000 tags with one attribute", &attributes);
+    report("1000 tags without attributes", &bare);
+
+    c.bench_function("parse example.xml", |b| {
+        b.iter(|| Document::parse_str(black_box(SRC)).unwrap())
+    });
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,251 @@
+//! Appending elements to the root of an existing XML file, for log-style files that grow over time.
+use crate::{
+    LazyDocument, XmlWriteOptions, XmlWriter,
+    error::{XmlError, XmlResult},
+};
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+/// Streams new child elements into the root of an existing XML file.
+///
+/// Opening the file finds the closing tag of the root, and starts a copy of the file up to that point,
+/// in the same directory. Nodes written with [`DocumentAppender::writer`] go directly into the copy, inside the root,
+/// and [`DocumentAppender::finish`] writes the closing tag and anything after it, then replaces the file with the copy.
+///
+/// Only the outline of the file is scanned when opening it, so appending to a large file does not parse every node.
+/// A root written as `<log />` is expanded to `<log>...</log>` once something is appended to it.
+///
+/// The original file is not changed until [`DocumentAppender::finish`] succeeds, so it is never left incomplete.
+/// If the appender is dropped without finishing, or finishing fails, the copy is removed and nothing is appended.
+///
+/// # Example
+/// ```rust
+/// use xmltree::{DocumentAppender, XmlWriteOptions};
+///
+/// let path = std::env::temp_dir().join("xmltree-append-example.xml");
+/// std::fs::write(&path, "<log>\n\t<entry>started</entry>\n</log>\n").unwrap();
+///
+/// let mut appender = DocumentAppender::open(&path, XmlWriteOptions::default()).unwrap();
+/// let writer = appender.writer();
+/// writer.start_element("entry").unwrap();
+/// writer.attribute("level", "warn").unwrap();
+/// writer.text("disk almost full").unwrap();
+/// writer.end_element().unwrap();
+/// appender.finish().unwrap();
+///
+/// assert_eq!(
+///     std::fs::read_to_string(&path).unwrap(),
+///     "<log>\n\t<entry>started</entry>\n\t<entry level=\"warn\">\n\t\tdisk almost full\n\t</entry>\n</log>\n"
+/// );
+/// # std::fs::remove_file(&path).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct DocumentAppender {
+    /// Taken when the appender is finished.
+    writer: Option<XmlWriter<BufWriter<File>>>,
+
+    /// The file being appended to.
+    path: PathBuf,
+
+    /// The copy being written, until it replaces the file.
+    temp: Option<PathBuf>,
+
+    /// The original text from the cut to the end of the file, written back if nothing was appended.
+    unchanged: String,
+
+    /// The text written back after appending; differs from `unchanged` if the root was an empty tag.
+    tail: String,
+}
+
+impl DocumentAppender {
+    /// Opens an XML file to append elements to its root, formatting them with the given options.
+    ///
+    /// Appended elements are indented one level below the root; the existing content is not reformatted.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read, is not a well-formed document, or the copy cannot be created.
+    pub fn open(path: impl AsRef<Path>, options: XmlWriteOptions) -> XmlResult<Self> {
+        let path = path.as_ref();
+        let with_path = |e: std::io::Error| XmlError::from(e).with_path(path.to_path_buf());
+        let src = std::fs::read_to_string(path).map_err(with_path)?;
+
+        let document = LazyDocument::scan_str(&src).map_err(|e| e.with_path(path.to_path_buf()))?;
+        let root = document.root();
+        let name = root.name().to_string();
+        let span = root.span();
+        let end = span.start() + span.len();
+
+        // The copy is cut before the closing tag, or before the `/>` of an empty root
+        let (cut, in_start_tag) = match span.text().strip_suffix("/>") {
+            Some(_) => (end - 2, true),
+            None => (span.start() + span.text().rfind("</").unwrap_or(0), false),
+        };
+
+        let unchanged = src[cut..].to_string();
+        let tail = if in_start_tag {
+            format!("</{name}>{}", &src[end..])
+        } else {
+            unchanged.clone()
+        };
+
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        let temp = path.with_file_name(format!(".{file_name}.{}.tmp", std::process::id()));
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp)
+            .map_err(with_path)?;
+
+        let mut appender = Self {
+            writer: None,
+            path: path.to_path_buf(),
+            temp: Some(temp),
+            unchanged,
+            tail,
+        };
+
+        // The copy keeps the permissions of the file it replaces
+        let permissions = std::fs::metadata(path).map_err(with_path)?.permissions();
+        file.set_permissions(permissions).map_err(with_path)?;
+
+        let mut file = BufWriter::new(file);
+        file.write_all(&src.as_bytes()[..cut]).map_err(with_path)?;
+        appender.writer = Some(XmlWriter::inside(file, options, &name, in_start_tag));
+        Ok(appender)
+    }
+
+    /// Returns the writer for nodes inside the root.
+    ///
+    /// The root itself cannot be ended through it; that is done by [`DocumentAppender::finish`].
+    #[expect(
+        clippy::missing_panics_doc,
+        reason = "the writer is only taken when the appender is consumed"
+    )]
+    pub fn writer(&mut self) -> &mut XmlWriter<BufWriter<File>> {
+        self.writer
+            .as_mut()
+            .expect("the writer is only taken by finish")
+    }
+
+    /// Ends any elements still open, writes the closing tag of the root and anything after it,
+    /// and replaces the file with the completed copy.
+    ///
+    /// # Errors
+    /// Returns an error if the copy cannot be written or cannot replace the file; the file is then left as it was.
+    #[expect(
+        clippy::missing_panics_doc,
+        reason = "the writer and copy are only taken when the appender is consumed"
+    )]
+    pub fn finish(mut self) -> XmlResult<()> {
+        let with_path = |e: std::io::Error| XmlError::from(e).with_path(self.path.clone());
+        let writer = self
+            .writer
+            .take()
+            .expect("the writer is only taken by finish");
+
+        let (mut file, in_start_tag) = writer.finish_inside().map_err(with_path)?;
+        let tail = if in_start_tag {
+            &self.unchanged
+        } else {
+            &self.tail
+        };
+        file.write_all(tail.as_bytes()).map_err(with_path)?;
+        let file = file.into_inner().map_err(|e| with_path(e.into_error()))?;
+        file.sync_all().map_err(with_path)?;
+        drop(file);
+
+        let temp = self
+            .temp
+            .as_ref()
+            .expect("the copy is only taken by finish");
+        std::fs::rename(temp, &self.path).map_err(with_path)?;
+        self.temp = None;
+        Ok(())
+    }
+}
+
+impl Drop for DocumentAppender {
+    /// Removes the copy of an appender that was not finished, leaving the file as it was.
+    fn drop(&mut self) {
+        drop(self.writer.take());
+        if let Some(temp) = self.temp.take() {
+            // Nothing was promised to the file yet, so a copy that cannot be removed is only clutter
+            let _ = std::fs::remove_file(temp);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Document;
+
+    fn append_entry(path: &Path, minify: bool) {
+        let options = XmlWriteOptions::default().with_minify(minify);
+        let mut appender = DocumentAppender::open(path, options).unwrap();
+        let writer = appender.writer();
+        writer.start_element("e").unwrap();
+        writer.text("1").unwrap();
+        writer.end_element().unwrap();
+        assert!(writer.end_element().is_err());
+        appender.finish().unwrap();
+    }
+
+    #[test]
+    fn test_append() {
+        let path = std::env::temp_dir().join(format!("xmltree-append-{}.xml", std::process::id()));
+
+        std::fs::write(&path, "<?xml version='1.0'?><log/><!-- end -->").unwrap();
+        append_entry(&path, true);
+        append_entry(&path, true);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "<?xml version='1.0'?><log><e>1</e><e>1</e></log><!-- end -->"
+        );
+
+        // Unfinished elements are closed
+        std::fs::write(&path, "<log>\n</log>\n").unwrap();
+        let mut appender = DocumentAppender::open(&path, XmlWriteOptions::default()).unwrap();
+        appender.writer().start_element("e").unwrap();
+        appender.finish().unwrap();
+        let xml = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(xml, "<log>\n\t<e />\n</log>\n");
+        assert!(Document::parse_str(&xml).is_ok());
+
+        // Dropping an appender leaves the file as it was, and removes its copy
+        let temp = {
+            let mut appender = DocumentAppender::open(&path, XmlWriteOptions::default()).unwrap();
+            let temp = appender.temp.clone().unwrap();
+            assert!(temp.exists());
+            let writer = appender.writer();
+            writer.start_element("dropped").unwrap();
+            writer.text(&"x".repeat(1 << 16)).unwrap();
+            temp
+        };
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), xml);
+        assert!(!temp.exists());
+
+        // The closing tag is found past brackets and quotes in the internal subset
+        std::fs::write(&path, "<!DOCTYPE log [<!ENTITY e ']>'>]><log>&e;</log>").unwrap();
+        append_entry(&path, true);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "<!DOCTYPE log [<!ENTITY e ']>'>]><log>&e;<e>1</e></log>"
+        );
+
+        // Nothing appended leaves the file as it was
+        std::fs::write(&path, "<log />").unwrap();
+        DocumentAppender::open(&path, XmlWriteOptions::default())
+            .unwrap()
+            .finish()
+            .unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "<log />");
+
+        std::fs::write(&path, "<log>").unwrap();
+        assert!(DocumentAppender::open(&path, XmlWriteOptions::default()).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
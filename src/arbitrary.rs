@@ -0,0 +1,220 @@
+//! Random document generation for property tests, using `proptest`.
+//!
+//! [`OwnedDocument`] and [`OwnedTagNode`] implement [`Arbitrary`], generating trees that are valid XML,
+//! and that survive being written and parsed again unchanged:
+//! - Names have no prefix, and attributes on a tag have distinct names
+//! - Text, CDATA and attribute values never need escaping, and text has no leading or trailing whitespace
+//! - Adjacent text nodes are joined, since they could not be told apart once written
+//!
+//! The size of the trees is bounded by [`TreeLimits`], passed with `any_with`.
+//!
+//! ```rust
+//! use proptest::prelude::*;
+//! use xmltree::{Document, OwnedDocument};
+//!
+//! proptest! {
+//!     fn round_trip(doc in any::<OwnedDocument>()) {
+//!         let xml = doc.to_xml(None).unwrap();
+//!         prop_assert_eq!(Document::parse_str(&xml).unwrap().to_owned(), doc);
+//!     }
+//! }
+//! # round_trip();
+//! ```
+use crate::{
+    OwnedDocument,
+    node::{
+        OwnedCdataNode, OwnedCommentNode, OwnedNode, OwnedNodeAttribute, OwnedTagNode,
+        OwnedTextNode,
+    },
+};
+use proptest::{
+    arbitrary::Arbitrary,
+    collection::{btree_map, vec},
+    prelude::*,
+};
+
+/// Bounds on the size of trees generated by the [`Arbitrary`] implementations.
+///
+/// # Example
+/// ```rust
+/// use proptest::prelude::*;
+/// use xmltree::{TreeLimits, node::OwnedTagNode};
+///
+/// proptest! {
+///     fn flat(tag in any_with::<OwnedTagNode>(TreeLimits::default().with_max_depth(1))) {
+///         prop_assert!(tag.children.iter().all(|c| !matches!(c, xmltree::node::OwnedNode::Tag(_))));
+///     }
+/// }
+/// # flat();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TreeLimits {
+    /// The most levels of tags, counting the root.
+    pub max_depth: u32,
+
+    /// The most children of any tag.
+    pub max_children: usize,
+
+    /// The most attributes on any tag.
+    pub max_attributes: usize,
+}
+impl Default for TreeLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: 4,
+            max_children: 4,
+            max_attributes: 3,
+        }
+    }
+}
+impl TreeLimits {
+    /// Sets the most levels of tags, counting the root.
+    #[must_use]
+    pub fn with_max_depth(mut self, max_depth: u32) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Sets the most children of any tag.
+    #[must_use]
+    pub fn with_max_children(mut self, max_children: usize) -> Self {
+        self.max_children = max_children;
+        self
+    }
+
+    /// Sets the most attributes on any tag.
+    #[must_use]
+    pub fn with_max_attributes(mut self, max_attributes: usize) -> Self {
+        self.max_attributes = max_attributes;
+        self
+    }
+}
+
+fn name() -> impl Strategy<Value = String> {
+    "[a-z_][a-z0-9_.-]{0,7}"
+}
+
+fn text() -> impl Strategy<Value = OwnedNode> {
+    "[A-Za-z0-9]([A-Za-z0-9 .,;:!?()]{0,14}[A-Za-z0-9.!?)])?"
+        .prop_map(|text| OwnedNode::Text(OwnedTextNode::new(text)))
+}
+
+fn comment() -> impl Strategy<Value = OwnedNode> {
+    "[a-z0-9]([a-z0-9 ]{0,10}[a-z0-9])?"
+        .prop_map(|text| OwnedNode::Comment(OwnedCommentNode::new(text)))
+}
+
+fn cdata() -> impl Strategy<Value = OwnedNode> {
+    "[A-Za-z0-9]([A-Za-z0-9 \\[\\]{}]{0,10}[A-Za-z0-9])?"
+        .prop_map(|content| OwnedNode::Cdata(OwnedCdataNode::new(content)))
+}
+
+/// Builds a tag, joining adjacent text nodes.
+fn tag(name: String, attributes: Vec<(String, String)>, children: Vec<OwnedNode>) -> OwnedTagNode {
+    let mut tag = OwnedTagNode::new(name);
+    tag.attributes = attributes
+        .into_iter()
+        .map(|(name, value)| OwnedNodeAttribute::new(name, value))
+        .collect();
+
+    for child in children {
+        match (tag.children.last_mut(), child) {
+            (Some(OwnedNode::Text(last)), OwnedNode::Text(text)) => {
+                last.text.push(' ');
+                last.text.push_str(&text.text);
+            }
+            (_, child) => tag.children.push(child),
+        }
+    }
+    tag
+}
+
+fn tag_strategy(limits: TreeLimits) -> BoxedStrategy<OwnedTagNode> {
+    let attributes = move || {
+        btree_map(name(), "[A-Za-z0-9 ._/-]{0,12}", 0..=limits.max_attributes)
+            .prop_map(|attributes| attributes.into_iter().collect::<Vec<_>>())
+    };
+
+    let leaf = prop_oneof![4 => text(), 1 => comment(), 1 => cdata()];
+
+    // Levels of tags below the root
+    let depth = limits.max_depth.saturating_sub(1);
+    let width = u32::try_from(limits.max_children).unwrap_or(u32::MAX);
+    let node = leaf.prop_recursive(
+        depth,
+        width.saturating_mul(depth + 1),
+        width,
+        move |inner| {
+            (name(), attributes(), vec(inner, 0..=limits.max_children))
+                .prop_map(|(n, a, c)| OwnedNode::Tag(tag(n, a, c)))
+        },
+    );
+
+    (name(), attributes(), vec(node, 0..=limits.max_children))
+        .prop_map(|(n, a, c)| tag(n, a, c))
+        .boxed()
+}
+
+impl Arbitrary for OwnedTagNode {
+    type Parameters = TreeLimits;
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(limits: Self::Parameters) -> Self::Strategy {
+        tag_strategy(limits)
+    }
+}
+
+impl Arbitrary for OwnedDocument {
+    type Parameters = TreeLimits;
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(limits: Self::Parameters) -> Self::Strategy {
+        (
+            vec(comment(), 0..2),
+            tag_strategy(limits),
+            vec(comment(), 0..2),
+        )
+            .prop_map(|(prolog, root, epilog)| {
+                let mut document = OwnedDocument::new(root);
+                document.prolog = prolog;
+                document.epilog = epilog;
+                document
+            })
+            .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Document, XmlWriteOptions};
+
+    proptest! {
+        #[test]
+        fn test_round_trip(doc in any::<OwnedDocument>()) {
+            let xml = doc.to_xml(None).unwrap();
+            prop_assert_eq!(&Document::parse_str(&xml).unwrap().to_owned(), &doc);
+
+            let minified = doc.to_xml_with_options(&XmlWriteOptions::default().with_minify(true)).unwrap();
+            prop_assert_eq!(&Document::parse_str(&minified).unwrap().to_owned(), &doc);
+
+            let bin = doc.to_bin().unwrap();
+            prop_assert_eq!(&OwnedDocument::from_bin(&bin).unwrap(), &doc);
+        }
+
+        #[test]
+        fn test_limits(tag in any_with::<OwnedTagNode>(TreeLimits::default().with_max_depth(2).with_max_children(2))) {
+            let mut stack = vec![(&tag, 1)];
+            while let Some((tag, depth)) = stack.pop() {
+                prop_assert!(depth <= 2);
+                prop_assert!(tag.children.len() <= 2);
+                prop_assert!(tag.attributes.len() <= 3);
+                for child in &tag.children {
+                    if let OwnedNode::Tag(child) = child {
+                        stack.push((child, depth + 1));
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,651 @@
+//! Documents that own their source, so they can be kept or sent to other threads without copying any strings.
+//!
+//! Converting a [`Document`] with [`Document::to_owned`] is the usual way to get a tree that outlives its source,
+//! but it copies every string in the document. An [`ArcDocument`] instead keeps the source itself, shared through an [`Arc<str>`],
+//! and stores every string in the tree as a range of it.
+use crate::{
+    Document, NodePath, Parser, PathSection, StrSpan,
+    document::{DeclarationNode, split_bom},
+    error::XmlResult,
+    node::{
+        CdataNode, CommentNode, DtdEntity, DtdNode, EntityDefinition, ExternalId, Node,
+        NodeAttribute, NodeName, ProcessingInstructionNode, TagNode, TextNode,
+    },
+};
+use std::{ops::Range, sync::Arc};
+
+/// A parsed document that owns its source through an [`Arc<str>`]; it is `Send + Sync + 'static`.
+///
+/// The tree is built once, as a flat table of nodes with every name, value and text stored as a range of the shared source.
+/// Cloning only shares the source and the table.
+///
+/// [`ArcDocument::document`] and [`ArcDocument::tag`] assemble a [`Document`] or [`TagNode`] from the table,
+/// borrowing their strings from the source, so reading the tree never copies a string or re-parses anything.
+/// Neither the table nor the assembly recurses, so any document the parser accepts can be stored.
+///
+/// # Example
+/// ```rust
+/// use std::sync::Arc;
+/// use xmltree::ArcDocument;
+///
+/// let source: Arc<str> = Arc::from("<config><server port=\"80\" /></config>");
+/// let doc = ArcDocument::parse(Arc::clone(&source)).unwrap();
+///
+/// let port = std::thread::spawn(move || {
+///     let document = doc.document();
+///     let server = document.root().first_child_tag().unwrap();
+///     server.get_attribute(None, "port").unwrap().value().text().to_string()
+/// });
+/// assert_eq!(port.join().unwrap(), "80");
+/// ```
+#[derive(Clone)]
+pub struct ArcDocument {
+    source: Arc<str>,
+    tree: Arc<Tree>,
+}
+impl ArcDocument {
+    /// Parses a document from a shared source, which the document keeps.
+    ///
+    /// # Errors
+    /// Returns an error if the source is not valid XML. See [`Document::parse_str`].
+    pub fn parse(source: impl Into<Arc<str>>) -> XmlResult<Self> {
+        Self::parse_with(source, &mut Parser::new())
+    }
+
+    /// Parses a document from a shared source with the given parser, which the document keeps.
+    ///
+    /// # Errors
+    /// Returns an error if the source is not valid XML under the parser's settings. See [`Parser::parse`].
+    pub fn parse_with(source: impl Into<Arc<str>>, parser: &mut Parser) -> XmlResult<Self> {
+        let source: Arc<str> = source.into();
+        let tree = Tree::new(&parser.parse(&source)?);
+        Ok(Self {
+            source,
+            tree: Arc::new(tree),
+        })
+    }
+
+    /// Returns the document, with every string borrowed from the shared source.
+    ///
+    /// The [`Document`] is assembled from the stored table on each call, so keep the result while reading from it,
+    /// or use [`ArcDocument::tag`] to assemble only part of it.
+    /// It can also be edited, or converted with [`Document::to_owned`], without affecting this document.
+    #[must_use]
+    pub fn document(&self) -> Document<'_> {
+        let tree = &self.tree;
+        let src = self.src();
+        let nodes = |range: &Range<usize>| range.clone().map(|i| tree.node(i, src)).collect();
+
+        Document::from_parts(
+            src,
+            tree.bom,
+            tree.declaration.as_ref().map(|d| d.declaration(src)),
+            nodes(&tree.prolog),
+            tree.assemble(tree.tag_entry(tree.root), src),
+            nodes(&tree.epilog),
+        )
+    }
+
+    /// Returns the tag at the given path, including the root node, assembling only that subtree.
+    ///
+    /// Returns None if the path does not lead to a tag.
+    #[must_use]
+    pub fn tag(&self, path: &NodePath) -> Option<TagNode<'_>> {
+        let index = self.tree.find(path)?;
+        Some(self.tag_at(index))
+    }
+
+    /// Returns the index of the root element in the table.
+    pub(crate) fn root_index(&self) -> usize {
+        self.tree.root
+    }
+
+    /// Returns the index of the tag at the given path, if it leads to one.
+    pub(crate) fn find(&self, path: &NodePath) -> Option<usize> {
+        self.tree.find(path)
+    }
+
+    /// Assembles the tag at the given index, which must be a tag, and its subtree.
+    pub(crate) fn tag_at(&self, index: usize) -> TagNode<'_> {
+        self.tree.assemble(self.tree.tag_entry(index), self.src())
+    }
+
+    /// Returns the name of the tag at the given index, without assembling it.
+    pub(crate) fn name_at(&self, index: usize) -> NodeName<'_> {
+        self.tree.tag_entry(index).name.name(self.src())
+    }
+
+    /// Returns the attributes of the tag at the given index, without assembling it.
+    pub(crate) fn attributes_at(&self, index: usize) -> impl Iterator<Item = NodeAttribute<'_>> {
+        let src = self.src();
+        let entry = self.tree.tag_entry(index);
+        entry.attributes.iter().map(move |a| a.attribute(src))
+    }
+
+    /// Returns the indices of the children of the tag at the given index; only some may be tags.
+    pub(crate) fn children_at(&self, index: usize) -> Range<usize> {
+        self.tree.tag_entry(index).children.clone()
+    }
+
+    /// Returns true if the node at the given index is a tag.
+    pub(crate) fn is_tag(&self, index: usize) -> bool {
+        matches!(self.tree.nodes.get(index), Some(Entry::Tag(_)))
+    }
+
+    /// Returns the index of the parent of the tag at the given index, or None for the root.
+    pub(crate) fn parent_at(&self, index: usize) -> Option<usize> {
+        self.tree.tag_entry(index).parent
+    }
+
+    /// Returns the shared source the document borrows from.
+    #[must_use]
+    pub fn source(&self) -> &Arc<str> {
+        &self.source
+    }
+
+    /// Returns the source after any byte order mark, which spans are offsets into.
+    fn src(&self) -> &str {
+        split_bom(&self.source).0
+    }
+}
+impl std::fmt::Debug for ArcDocument {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ArcDocument")
+            .field("document", &self.document())
+            .finish()
+    }
+}
+
+/// The nodes of a document in a flat table, with every string stored as a range of the source.
+///
+/// The prolog, epilog and root come first; the children of each tag are stored next to each other,
+/// after the tag itself.
+#[derive(Debug)]
+struct Tree {
+    bom: bool,
+    declaration: Option<DeclarationEntry>,
+    prolog: Range<usize>,
+    root: usize,
+    epilog: Range<usize>,
+    nodes: Vec<Entry>,
+}
+impl Tree {
+    /// Builds the table from a parsed document, without recursing into its tags.
+    fn new(document: &Document<'_>) -> Self {
+        let mut nodes = Vec::new();
+        let mut pending = Vec::new();
+
+        let prolog = push_nodes(&mut nodes, &mut pending, document.prolog(), None);
+        let epilog = push_nodes(&mut nodes, &mut pending, document.epilog(), None);
+        let root = nodes.len();
+        nodes.push(Entry::Tag(TagEntry::new(document.root(), None)));
+        pending.push((root, document.root()));
+
+        while let Some((index, tag)) = pending.pop() {
+            let children = push_nodes(&mut nodes, &mut pending, tag.children(), Some(index));
+            if let Entry::Tag(entry) = &mut nodes[index] {
+                entry.children = children;
+            }
+        }
+
+        Self {
+            bom: document.has_bom(),
+            declaration: document.declaration().map(DeclarationEntry::new),
+            prolog,
+            root,
+            epilog,
+            nodes,
+        }
+    }
+
+    /// Returns the index of the tag at the given path, if it leads to one.
+    fn find(&self, path: &NodePath) -> Option<usize> {
+        if path.section() != PathSection::Root {
+            return None;
+        }
+
+        let mut index = self.root;
+        for &child in path.indices() {
+            index = self.tag_entry(index).children.clone().nth(child)?;
+            if !matches!(self.nodes[index], Entry::Tag(_)) {
+                return None;
+            }
+        }
+        Some(index)
+    }
+
+    /// Returns the entry of the tag at the given index.
+    ///
+    /// Indices come from the table itself, so one that is not a tag is a bug.
+    fn tag_entry(&self, index: usize) -> &TagEntry {
+        match &self.nodes[index] {
+            Entry::Tag(entry) => entry,
+            Entry::Leaf(_) => unreachable!("node {index} is not a tag"),
+        }
+    }
+
+    /// Assembles the node at the given index, including all of its descendants.
+    fn node<'a>(&self, index: usize, src: &'a str) -> Node<'a> {
+        match &self.nodes[index] {
+            Entry::Tag(entry) => Node::Child(self.assemble(entry, src)),
+            Entry::Leaf(leaf) => leaf.node(src),
+        }
+    }
+
+    /// Assembles a tag and all of its descendants, without recursing.
+    fn assemble<'a>(&self, entry: &TagEntry, src: &'a str) -> TagNode<'a> {
+        let mut tag = entry.shallow(src);
+        let mut children = entry.children.clone();
+
+        // Tags still being filled, with the children they have left
+        let mut parents = Vec::new();
+        loop {
+            if let Some(child) = children.next() {
+                match &self.nodes[child] {
+                    Entry::Tag(entry) => {
+                        let parent = std::mem::replace(&mut tag, entry.shallow(src));
+                        let rest = std::mem::replace(&mut children, entry.children.clone());
+                        parents.push((parent, rest));
+                    }
+                    Entry::Leaf(leaf) => tag.push_child(leaf.node(src)),
+                }
+            } else if let Some((parent, rest)) = parents.pop() {
+                let child = std::mem::replace(&mut tag, parent);
+                children = rest;
+                tag.push_child(Node::Child(child));
+            } else {
+                return tag;
+            }
+        }
+    }
+}
+
+/// Adds entries for a list of sibling nodes, queueing their tags to have their own children added.
+fn push_nodes<'a, 'src>(
+    nodes: &mut Vec<Entry>,
+    pending: &mut Vec<(usize, &'a TagNode<'src>)>,
+    list: &'a [Node<'src>],
+    parent: Option<usize>,
+) -> Range<usize> {
+    let start = nodes.len();
+    for node in list {
+        if let Node::Child(tag) = node {
+            pending.push((nodes.len(), tag));
+            nodes.push(Entry::Tag(TagEntry::new(tag, parent)));
+        } else if let Some(leaf) = LeafEntry::new(node) {
+            nodes.push(Entry::Leaf(leaf));
+        }
+    }
+    start..nodes.len()
+}
+
+/// Returns the span of the source covering a stored range.
+fn span<'a>(src: &'a str, range: &Range<usize>) -> StrSpan<'a> {
+    StrSpan::in_source(src, range.clone())
+}
+
+#[derive(Debug)]
+enum Entry {
+    Tag(TagEntry),
+    Leaf(LeafEntry),
+}
+
+#[derive(Debug)]
+struct TagEntry {
+    span: Range<usize>,
+    name: NameEntry,
+    attributes: Box<[AttributeEntry]>,
+    children: Range<usize>,
+    parent: Option<usize>,
+}
+impl TagEntry {
+    /// Stores a tag without its children, which are added once they have entries of their own.
+    fn new(tag: &TagNode<'_>, parent: Option<usize>) -> Self {
+        Self {
+            span: tag.span().range(),
+            name: NameEntry::new(tag.name()),
+            attributes: tag.attributes().iter().map(AttributeEntry::new).collect(),
+            children: 0..0,
+            parent,
+        }
+    }
+
+    /// Assembles the tag without its children.
+    fn shallow<'a>(&self, src: &'a str) -> TagNode<'a> {
+        let name = self.name.name(src);
+        let mut tag =
+            TagNode::new(name.prefix().copied(), *name.local()).with_span(span(src, &self.span));
+        for attribute in &self.attributes {
+            tag.push_attribute(attribute.attribute(src));
+        }
+        tag
+    }
+}
+
+#[derive(Debug)]
+struct NameEntry {
+    prefix: Option<Range<usize>>,
+    local: Range<usize>,
+}
+impl NameEntry {
+    fn new(name: &NodeName<'_>) -> Self {
+        Self {
+            prefix: name.prefix().map(StrSpan::range),
+            local: name.local().range(),
+        }
+    }
+
+    fn name<'a>(&self, src: &'a str) -> NodeName<'a> {
+        let prefix = self.prefix.as_ref().map(|prefix| span(src, prefix));
+        NodeName::new(prefix, span(src, &self.local))
+    }
+}
+
+#[derive(Debug)]
+struct AttributeEntry {
+    span: Range<usize>,
+    name: NameEntry,
+    value: Range<usize>,
+    defaulted: bool,
+}
+impl AttributeEntry {
+    fn new(attribute: &NodeAttribute<'_>) -> Self {
+        Self {
+            span: attribute.span().range(),
+            name: NameEntry::new(attribute.name()),
+            value: attribute.value().range(),
+            defaulted: attribute.is_defaulted(),
+        }
+    }
+
+    fn attribute<'a>(&self, src: &'a str) -> NodeAttribute<'a> {
+        let name = self.name.name(src);
+        let attribute = NodeAttribute::new(
+            name.prefix().copied(),
+            *name.local(),
+            span(src, &self.value),
+        )
+        .with_span(span(src, &self.span));
+        if self.defaulted {
+            attribute.with_defaulted()
+        } else {
+            attribute
+        }
+    }
+}
+
+#[derive(Debug)]
+enum LeafEntry {
+    Text {
+        span: Range<usize>,
+        text: Range<usize>,
+    },
+    Comment {
+        span: Range<usize>,
+        text: Range<usize>,
+    },
+    ProcessingInstruction {
+        span: Range<usize>,
+        target: Range<usize>,
+        content: Option<Range<usize>>,
+    },
+    DocumentType {
+        span: Range<usize>,
+        name: Range<usize>,
+        external_id: Option<ExternalIdEntry>,
+        entities: Box<[EntityEntry]>,
+        internal_subset: Option<Range<usize>>,
+    },
+    Cdata {
+        span: Range<usize>,
+        content: Range<usize>,
+    },
+}
+impl LeafEntry {
+    /// Stores a node other than a tag, or returns None for a tag.
+    fn new(node: &Node<'_>) -> Option<Self> {
+        Some(match node {
+            Node::Child(_) => return None,
+            Node::Text(text) => Self::Text {
+                span: text.span().range(),
+                text: text.text().range(),
+            },
+            Node::Comment(comment) => Self::Comment {
+                span: comment.span().range(),
+                text: comment.text().range(),
+            },
+            Node::ProcessingInstruction(pi) => Self::ProcessingInstruction {
+                span: pi.span().range(),
+                target: pi.target().range(),
+                content: pi.content().map(StrSpan::range),
+            },
+            Node::DocumentType(dtd) => Self::DocumentType {
+                span: dtd.span().range(),
+                name: dtd.name().range(),
+                external_id: dtd.external_id().map(ExternalIdEntry::new),
+                entities: dtd.entities().iter().map(EntityEntry::new).collect(),
+                internal_subset: dtd.internal_subset().map(StrSpan::range),
+            },
+            Node::Cdata(cdata) => Self::Cdata {
+                span: cdata.span().range(),
+                content: cdata.content().range(),
+            },
+        })
+    }
+
+    fn node<'a>(&self, src: &'a str) -> Node<'a> {
+        match self {
+            Self::Text { span: s, text } => {
+                Node::Text(TextNode::new(span(src, s), span(src, text)))
+            }
+            Self::Comment { span: s, text } => {
+                Node::Comment(CommentNode::new(span(src, s), span(src, text)))
+            }
+            Self::ProcessingInstruction {
+                span: s,
+                target,
+                content,
+            } => Node::ProcessingInstruction(ProcessingInstructionNode::new(
+                span(src, s),
+                span(src, target),
+                content.as_ref().map(|content| span(src, content)),
+            )),
+            Self::DocumentType {
+                span: s,
+                name,
+                external_id,
+                entities,
+                internal_subset,
+            } => Node::DocumentType(
+                DtdNode::new(
+                    span(src, s),
+                    span(src, name),
+                    external_id.as_ref().map(|id| id.external_id(src)),
+                )
+                .with_entities(entities.iter().map(|e| e.entity(src)).collect())
+                .with_internal_subset(internal_subset.as_ref().map(|s| span(src, s))),
+            ),
+            Self::Cdata { span: s, content } => {
+                Node::Cdata(CdataNode::new(span(src, s), span(src, content)))
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+enum ExternalIdEntry {
+    System(Range<usize>),
+    Public(Range<usize>, Range<usize>),
+}
+impl ExternalIdEntry {
+    fn new(external_id: &ExternalId<'_>) -> Self {
+        match external_id {
+            ExternalId::System(system) => Self::System(system.range()),
+            ExternalId::Public(public, system) => Self::Public(public.range(), system.range()),
+        }
+    }
+
+    fn external_id<'a>(&self, src: &'a str) -> ExternalId<'a> {
+        match self {
+            Self::System(system) => ExternalId::new_system(span(src, system)),
+            Self::Public(public, system) => {
+                ExternalId::new_public(span(src, public), span(src, system))
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+enum DefinitionEntry {
+    EntityValue(Range<usize>),
+    ExternalId(ExternalIdEntry),
+}
+
+#[derive(Debug)]
+struct EntityEntry {
+    span: Range<usize>,
+    name: Range<usize>,
+    definition: DefinitionEntry,
+}
+impl EntityEntry {
+    fn new(entity: &DtdEntity<'_>) -> Self {
+        let definition = match &entity.definition {
+            EntityDefinition::EntityValue(value) => DefinitionEntry::EntityValue(value.range()),
+            EntityDefinition::ExternalId(id) => {
+                DefinitionEntry::ExternalId(ExternalIdEntry::new(id))
+            }
+        };
+        Self {
+            span: entity.span.range(),
+            name: entity.name.range(),
+            definition,
+        }
+    }
+
+    fn entity<'a>(&self, src: &'a str) -> DtdEntity<'a> {
+        let definition = match &self.definition {
+            DefinitionEntry::EntityValue(value) => {
+                EntityDefinition::new_entity_value(span(src, value))
+            }
+            DefinitionEntry::ExternalId(id) => {
+                EntityDefinition::new_external_id(id.external_id(src))
+            }
+        };
+        DtdEntity::new(span(src, &self.span), span(src, &self.name), definition)
+    }
+}
+
+#[derive(Debug)]
+struct DeclarationEntry {
+    span: Range<usize>,
+    version: Range<usize>,
+    encoding: Option<Range<usize>>,
+    standalone: Option<bool>,
+}
+impl DeclarationEntry {
+    fn new(declaration: &DeclarationNode<'_>) -> Self {
+        Self {
+            span: declaration.span().range(),
+            version: declaration.version().range(),
+            encoding: declaration.encoding().map(StrSpan::range),
+            standalone: declaration.standalone(),
+        }
+    }
+
+    fn declaration<'a>(&self, src: &'a str) -> DeclarationNode<'a> {
+        DeclarationNode::new(
+            span(src, &self.version),
+            self.encoding.as_ref().map(|encoding| span(src, encoding)),
+            self.standalone,
+        )
+        .with_span(span(src, &self.span))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arc_document() {
+        fn assert_shareable<T: Send + Sync + 'static>(_: &T) {}
+
+        let source: Arc<str> =
+            Arc::from("\u{FEFF}<?xml version='1.0'?><!--c--><a x='1'><b>text</b><c /></a>");
+        let doc = ArcDocument::parse(Arc::clone(&source)).unwrap();
+        assert_shareable(&doc);
+
+        // Strings point into the shared source
+        let document = doc.document();
+        assert!(document.has_bom());
+        let in_source = |text: &str| source.as_bytes().as_ptr_range().contains(&text.as_ptr());
+        let b = document.root().first_child_tag().unwrap();
+        assert!(in_source(b.children()[0].span().text()));
+        assert!(in_source(document.root().attributes()[0].value().text()));
+
+        let copy = doc.clone();
+        drop(document);
+        drop(doc);
+        assert_eq!(Arc::strong_count(&source), 2);
+        assert_eq!(
+            copy.document().to_xml(None).unwrap(),
+            Document::parse_str(&source).unwrap().to_xml(None).unwrap()
+        );
+
+        // Single tags are assembled without the rest of the tree
+        let c = copy.tag(&NodePath::root().child(1)).unwrap();
+        assert_eq!(c.name(), "c");
+        assert!(in_source(c.span().text()));
+        assert_eq!(copy.tag(&NodePath::root()).unwrap().children().len(), 2);
+        assert!(copy.tag(&NodePath::root().child(0).child(0)).is_none());
+        assert!(copy.tag(&NodePath::root().child(2)).is_none());
+        assert!(copy.tag(&NodePath::prolog(0)).is_none());
+
+        assert!(ArcDocument::parse("<a></b>").is_err());
+    }
+
+    #[test]
+    fn test_arc_document_matches_source() {
+        let src = concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n",
+            "<!DOCTYPE a SYSTEM \"a.dtd\" [<!ENTITY e \"v\"><!ENTITY x SYSTEM \"x.xml\">]>\n",
+            "<?pi content?><a xmlns:p=\"urn:p\" p:x=\"1\"><p:b><![CDATA[c]]><!--d-->e</p:b>f</a>"
+        );
+        let doc = ArcDocument::parse(src).unwrap();
+        assert_eq!(doc.document(), Document::parse_str(src).unwrap());
+    }
+
+    #[test]
+    fn test_arc_document_deeply_nested() {
+        const DEPTH: usize = 10_000;
+
+        // Storing and assembling the tree does not recurse, but dropping a parsed tree does
+        let test = || {
+            let src = "<a>".repeat(DEPTH) + &"</a>".repeat(DEPTH);
+            let doc = ArcDocument::parse(src.as_str()).unwrap();
+
+            let mut path = NodePath::root();
+            for _ in 1..DEPTH {
+                path = path.child(0);
+            }
+            assert!(doc.tag(&path).unwrap().children().is_empty());
+            assert!(doc.tag(&path.child(0)).is_none());
+            assert_eq!(
+                doc.document().to_xml(None).unwrap(),
+                Document::parse_str(&src).unwrap().to_xml(None).unwrap()
+            );
+
+            // The parser's depth limit still applies
+            let mut parser = Parser::new().with_max_depth(DEPTH - 2);
+            assert!(ArcDocument::parse_with(src.as_str(), &mut parser).is_err());
+            let mut parser = Parser::new().with_max_depth(DEPTH - 1);
+            assert!(ArcDocument::parse_with(src.as_str(), &mut parser).is_ok());
+        };
+        std::thread::Builder::new()
+            .stack_size(64 << 20)
+            .spawn(test)
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+}
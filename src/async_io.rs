@@ -0,0 +1,139 @@
+//! Async parsing and writing of documents, using tokio's IO traits.
+//!
+//! The parser itself is synchronous, so input is buffered in full before parsing,
+//! and output is formatted in memory before being written.
+use crate::{Document, OwnedDocument, XmlWriteOptions, error::XmlResult};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+impl<'src> Document<'src> {
+    /// Creates a new document by reading the given reader to completion.
+    ///
+    /// The input is read into `buffer`, which the document then borrows from.
+    /// See [`OwnedDocument::parse_async`] if you do not need the source spans.
+    ///
+    /// # Errors
+    /// Returns errors if reading fails, if the input is not valid UTF-8, or if the XML is invalid
+    ///
+    /// # Example
+    /// ```rust
+    /// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+    /// use xmltree::Document;
+    /// let src = b"<test><test2>test</test2></test>";
+    ///
+    /// let mut buffer = String::new();
+    /// let doc = Document::parse_async(&src[..], &mut buffer).await.unwrap();
+    /// assert_eq!(doc.root().name(), "test");
+    /// # });
+    /// ```
+    pub async fn parse_async<R: AsyncRead + Unpin>(
+        mut reader: R,
+        buffer: &'src mut String,
+    ) -> XmlResult<Self> {
+        buffer.clear();
+        reader.read_to_string(buffer).await?;
+        Self::parse_str(buffer)
+    }
+
+    /// Write this document as a formatted XML string to the given async writer.
+    ///
+    /// See [`Document::to_xml`] for more details.
+    ///
+    /// # Errors
+    /// Can fail if a string in the document cannot be entity encoded, or if writing fails.
+    pub async fn to_xml_async<W: AsyncWrite + Unpin>(
+        &self,
+        writer: &mut W,
+        tab_char: Option<&str>,
+    ) -> std::io::Result<()> {
+        let options = XmlWriteOptions::from_tab_char(tab_char);
+        self.to_xml_async_with_options(writer, &options).await
+    }
+
+    /// Write this document as a formatted XML string to the given async writer, using the given options.
+    ///
+    /// See [`Document::to_xml_with_options`] for more details.
+    ///
+    /// # Errors
+    /// Can fail if a string in the document cannot be entity encoded,
+    /// or contains characters rejected by [`XmlWriteOptions::control_chars`], or if writing fails.
+    pub async fn to_xml_async_with_options<W: AsyncWrite + Unpin>(
+        &self,
+        writer: &mut W,
+        options: &XmlWriteOptions,
+    ) -> std::io::Result<()> {
+        let mut buffer = vec![];
+        self.to_xml_with_writer_and_options(&mut buffer, options)?;
+        writer.write_all(&buffer).await?;
+        writer.flush().await
+    }
+}
+
+impl OwnedDocument {
+    /// Creates a new owned document by reading the given reader to completion.
+    ///
+    /// # Errors
+    /// Returns errors if reading fails, if the input is not valid UTF-8, or if the XML is invalid
+    pub async fn parse_async<R: AsyncRead + Unpin>(reader: R) -> XmlResult<Self> {
+        let mut buffer = String::new();
+        let document = Document::parse_async(reader, &mut buffer).await?;
+        Ok(document.to_owned())
+    }
+
+    /// Write this document as a formatted XML string to the given async writer.
+    ///
+    /// See [`Document::to_xml`] for more details.
+    ///
+    /// # Errors
+    /// Can fail if a string in the document cannot be entity encoded, or if writing fails.
+    pub async fn to_xml_async<W: AsyncWrite + Unpin>(
+        &self,
+        writer: &mut W,
+        tab_char: Option<&str>,
+    ) -> std::io::Result<()> {
+        self.borrowed().to_xml_async(writer, tab_char).await
+    }
+
+    /// Write this document as a formatted XML string to the given async writer, using the given options.
+    ///
+    /// See [`Document::to_xml_with_options`] for more details.
+    ///
+    /// # Errors
+    /// Can fail if a string in the document cannot be entity encoded,
+    /// or contains characters rejected by [`XmlWriteOptions::control_chars`], or if writing fails.
+    pub async fn to_xml_async_with_options<W: AsyncWrite + Unpin>(
+        &self,
+        writer: &mut W,
+        options: &XmlWriteOptions,
+    ) -> std::io::Result<()> {
+        self.borrowed()
+            .to_xml_async_with_options(writer, options)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_async_roundtrip() {
+        let src = "<test><test2 a=\"1\">test</test2></test>";
+
+        let mut buffer = String::new();
+        let doc = Document::parse_async(src.as_bytes(), &mut buffer)
+            .await
+            .unwrap();
+        assert_eq!(doc.root().name(), "test");
+
+        let mut output = vec![];
+        doc.to_xml_async(&mut output, None).await.unwrap();
+        assert_eq!(output, doc.to_xml(None).unwrap().into_bytes());
+
+        let owned = OwnedDocument::parse_async(src.as_bytes()).await.unwrap();
+        assert_eq!(owned, doc.to_owned());
+
+        let mut output = vec![];
+        owned.to_xml_async(&mut output, None).await.unwrap();
+        assert_eq!(output, doc.to_xml(None).unwrap().into_bytes());
+    }
+}
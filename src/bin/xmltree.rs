@@ -0,0 +1,555 @@
+//! Command line tool for formatting, checking and converting XML documents.
+//!
+//! Built with the `cli` feature; run `xmltree help` for usage.
+#![warn(clippy::pedantic)]
+use std::{
+    collections::BTreeSet,
+    io::{Read, Write},
+    process::ExitCode,
+};
+use xmltree::{
+    CompareOptions, Document, XmlWriteOptions,
+    node::{Node, NodeAttribute, TagNode},
+};
+
+const USAGE: &str = "\
+Usage: xmltree <command> [options]
+
+Commands:
+  fmt [--indent <text>] [--minify] <file>   Pretty-print a document
+  validate <file>...                        Report every problem found in the documents
+  select <path> <file>                      Print the nodes matching a path
+  to-bin <file> <output>                    Write a document in the binary format
+  from-bin <file>                           Print a binary document as XML
+  diff [--strict] <file> <file>             Compare two documents, ignoring formatting
+  stats <file>                              Count the nodes in a document
+
+Use - as a file to read from standard input.
+
+Paths are a subset of XPath: steps are separated by / or //, and match a name or *.
+Steps can be filtered with [@name], [@name='value'] or [n], and the last step can be @name or text().
+  /catalog/book[2]/title    //book[@lang='en']/@id    //title/text()";
+
+type CliResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match run(&args) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn run(args: &[String]) -> CliResult<ExitCode> {
+    let Some((command, args)) = args.split_first() else {
+        eprintln!("{USAGE}");
+        return Ok(ExitCode::from(2));
+    };
+
+    match (command.as_str(), args) {
+        ("fmt", args) => fmt(args),
+        ("validate", files) if !files.is_empty() => validate(files),
+        ("select", [path, file]) => select(path, file),
+        ("to-bin", [file, output]) => {
+            let src = read_string(file)?;
+            let doc = Document::parse_str(&src)?;
+            std::fs::write(output, doc.to_bin()?)?;
+            Ok(ExitCode::SUCCESS)
+        }
+        ("from-bin", [file]) => {
+            let data = read(file)?;
+            let doc = Document::from_bin(&data)?;
+            print(&doc.to_xml(None)?)
+        }
+        ("diff", [a, b]) => diff(a, b, CompareOptions::default()),
+        ("diff", [flag, a, b]) if flag == "--strict" => diff(a, b, CompareOptions::strict()),
+        ("stats", [file]) => stats(file),
+        ("help" | "--help" | "-h", _) => {
+            println!("{USAGE}");
+            Ok(ExitCode::SUCCESS)
+        }
+        _ => {
+            eprintln!("{USAGE}");
+            Ok(ExitCode::from(2))
+        }
+    }
+}
+
+fn read(file: &str) -> CliResult<Vec<u8>> {
+    if file == "-" {
+        let mut data = vec![];
+        std::io::stdin().read_to_end(&mut data)?;
+        Ok(data)
+    } else {
+        Ok(std::fs::read(file).map_err(|e| format!("{file}: {e}"))?)
+    }
+}
+
+fn read_string(file: &str) -> CliResult<String> {
+    Ok(String::from_utf8(read(file)?).map_err(|e| format!("{file}: {e}"))?)
+}
+
+fn print(text: &str) -> CliResult<ExitCode> {
+    std::io::stdout().write_all(text.as_bytes())?;
+    Ok(ExitCode::SUCCESS)
+}
+
+fn fmt(args: &[String]) -> CliResult<ExitCode> {
+    let mut options = XmlWriteOptions::default();
+    let mut file = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--minify" => options = options.with_minify(true),
+            "--indent" => {
+                let indent = args.next().ok_or("--indent needs a value")?;
+                options = options.with_indent(indent.as_str());
+            }
+            _ if file.is_none() => file = Some(arg.as_str()),
+            _ => return Err(format!("Unexpected argument: {arg}").into()),
+        }
+    }
+
+    let src = read_string(file.ok_or("fmt needs a file")?)?;
+    let doc = Document::parse_str(&src)?;
+    print(&doc.to_xml_with_options(&options)?)
+}
+
+fn validate(files: &[String]) -> CliResult<ExitCode> {
+    let mut failed = false;
+    for file in files {
+        let src = read_string(file)?;
+        let (_, problems) = Document::parse_all_errors(&src);
+        for problem in &problems {
+            let (row, col) = problem.context.position();
+            eprintln!(
+                "{file}:{row}:{col}: {} [{}]: {}",
+                problem.severity,
+                problem.code(),
+                problem.kind
+            );
+        }
+
+        failed |= problems.iter().any(xmltree::error::XmlError::is_error);
+        if problems.is_empty() {
+            println!("{file}: ok");
+        }
+    }
+
+    Ok(if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+fn select(path: &str, file: &str) -> CliResult<ExitCode> {
+    let path = Path::parse(path)?;
+    let src = read_string(file)?;
+    let doc = Document::parse_str(&src)?;
+
+    let found = path.select(&doc);
+    let mut out = std::io::stdout().lock();
+    for item in &found {
+        match item {
+            Item::Document => {}
+            Item::Tag(tag) => writeln!(out, "{tag}")?,
+            Item::Attribute(attr) => writeln!(out, "{}", attr.decoded_value())?,
+            Item::Text(text) => writeln!(out, "{text}")?,
+        }
+    }
+
+    Ok(if found.is_empty() {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+fn diff(a: &str, b: &str, options: CompareOptions) -> CliResult<ExitCode> {
+    let (src_a, src_b) = (read_string(a)?, read_string(b)?);
+    let (doc_a, doc_b) = (Document::parse_str(&src_a)?, Document::parse_str(&src_b)?);
+    if doc_a.semantic_eq(&doc_b, options) {
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    match first_difference(doc_a.root(), doc_b.root(), options) {
+        Some(path) => println!("{a} and {b} differ at {path}"),
+        None => println!("{a} and {b} differ outside the root element"),
+    }
+    Ok(ExitCode::FAILURE)
+}
+
+/// Returns the path of the deepest tag that contains every difference between two trees, or `None` if they are equal.
+fn first_difference(a: &TagNode<'_>, b: &TagNode<'_>, options: CompareOptions) -> Option<String> {
+    if a.semantic_eq(b, options) {
+        return None;
+    }
+
+    let mut path = format!("/{}", a.name());
+    let (mut a, mut b) = (a, b);
+    loop {
+        let children_a: Vec<_> = child_tags(a).collect();
+        let children_b: Vec<_> = child_tags(b).collect();
+        if a.name() != b.name() || children_a.len() != children_b.len() {
+            return Some(path);
+        }
+
+        let differing = children_a
+            .iter()
+            .zip(&children_b)
+            .enumerate()
+            .find(|(_, (a, b))| !a.semantic_eq(b, options));
+        let Some((i, (child_a, child_b))) = differing else {
+            return Some(path);
+        };
+
+        // Numbered among siblings of the same name, so the path can be passed to `select`
+        let position = 1 + children_a[..i]
+            .iter()
+            .filter(|sibling| sibling.name() == child_a.name())
+            .count();
+        path = format!("{path}/{}[{position}]", child_a.name());
+        (a, b) = (child_a, child_b);
+    }
+}
+
+fn child_tags<'a, 'src>(tag: &'a TagNode<'src>) -> impl Iterator<Item = &'a TagNode<'src>> {
+    tag.children().iter().filter_map(|child| match child {
+        Node::Child(tag) => Some(tag),
+        _ => None,
+    })
+}
+
+fn stats(file: &str) -> CliResult<ExitCode> {
+    let src = read_string(file)?;
+    let doc = Document::parse_str(&src)?;
+
+    let root = doc.root();
+    let mut names = BTreeSet::from([root.name().to_string()]);
+    let (mut elements, mut attributes, mut max_depth) = (1, root.attributes().len(), 0);
+    let (mut text, mut cdata, mut comments, mut instructions) = (0, 0, 0, 0);
+    for (depth, node) in &doc {
+        match node {
+            Node::Child(tag) => {
+                elements += 1;
+                attributes += tag.attributes().len();
+                max_depth = max_depth.max(depth);
+                names.insert(tag.name().to_string());
+            }
+            Node::Text(_) => text += 1,
+            Node::Cdata(_) => cdata += 1,
+            Node::Comment(_) => comments += 1,
+            Node::ProcessingInstruction(_) => instructions += 1,
+            Node::DocumentType(_) => {}
+        }
+    }
+
+    print(&format!(
+        "bytes: {}\nelements: {elements}\ndistinct names: {}\nattributes: {attributes}\n\
+         text nodes: {text}\ncdata sections: {cdata}\ncomments: {comments}\n\
+         processing instructions: {instructions}\nmax depth: {max_depth}\n",
+        src.len(),
+        names.len(),
+    ))
+}
+
+/// A node selected by a [`Path`].
+enum Item<'a, 'src> {
+    /// The document itself, which the first step starts from
+    Document,
+    Tag(&'a TagNode<'src>),
+    Attribute(&'a NodeAttribute<'src>),
+    Text(&'a str),
+}
+
+#[derive(Debug, PartialEq)]
+enum Test {
+    Name(String),
+    Any,
+    Attribute(String),
+    Text,
+}
+
+#[derive(Debug, PartialEq)]
+enum Filter {
+    Attribute(String, Option<String>),
+    Index(usize),
+}
+
+#[derive(Debug, PartialEq)]
+struct Step {
+    descendants: bool,
+    test: Test,
+    filters: Vec<Filter>,
+}
+
+/// A parsed path; see the usage text for the syntax.
+#[derive(Debug, PartialEq)]
+struct Path(Vec<Step>);
+impl Path {
+    fn parse(path: &str) -> CliResult<Self> {
+        let mut steps = vec![];
+        let mut rest = path;
+        while !rest.is_empty() {
+            let (descendants, after) = match rest.strip_prefix("//") {
+                Some(after) => (true, after),
+                None => (
+                    false,
+                    rest.strip_prefix('/')
+                        .ok_or_else(|| format!("Paths must start with /: {path}"))?,
+                ),
+            };
+
+            // The step ends at the next / outside of a filter
+            let mut in_quote = None;
+            let mut end = after.len();
+            for (i, c) in after.char_indices() {
+                match (in_quote, c) {
+                    (None, '\'' | '"') => in_quote = Some(c),
+                    (Some(q), c) if q == c => in_quote = None,
+                    (None, '/') => {
+                        end = i;
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+
+            steps.push(Step::parse(&after[..end], descendants)?);
+            rest = &after[end..];
+        }
+
+        let last = steps.len().saturating_sub(1);
+        match steps
+            .iter()
+            .position(|s| matches!(s.test, Test::Attribute(_) | Test::Text))
+        {
+            _ if steps.is_empty() => Err("Empty path".into()),
+            Some(i) if i != last => Err("@name and text() can only be the last step".into()),
+            _ => Ok(Self(steps)),
+        }
+    }
+
+    fn select<'a, 'src>(&self, doc: &'a Document<'src>) -> Vec<Item<'a, 'src>> {
+        let mut context = vec![Item::Document];
+        for step in &self.0 {
+            let mut next = vec![];
+            for item in &context {
+                let mut candidates = step.candidates(doc, item);
+                candidates.retain(|item| step.test.matches(item));
+                for filter in &step.filters {
+                    candidates = filter.apply(candidates);
+                }
+                next.extend(candidates);
+            }
+            context = next;
+        }
+        context
+    }
+}
+
+impl Step {
+    fn parse(step: &str, descendants: bool) -> CliResult<Self> {
+        let (test, mut filters) = match step.find('[') {
+            Some(i) => (&step[..i], &step[i..]),
+            None => (step, ""),
+        };
+
+        let test = match test {
+            "" => return Err("Empty step in path".into()),
+            "*" => Test::Any,
+            "text()" => Test::Text,
+            _ => match test.strip_prefix('@') {
+                Some(name) => Test::Attribute(name.to_string()),
+                None => Test::Name(test.to_string()),
+            },
+        };
+
+        let mut parsed = vec![];
+        while !filters.is_empty() {
+            let inner = filters
+                .strip_prefix('[')
+                .and_then(|f| f.find(']').map(|end| (&f[..end], &f[end + 1..])));
+            let Some((inner, rest)) = inner else {
+                return Err(format!("Malformed filter: {filters}").into());
+            };
+            filters = rest;
+
+            if let Some(attr) = inner.strip_prefix('@') {
+                let filter = match attr.split_once('=') {
+                    Some((name, value)) => {
+                        let value = value.trim_matches(|c| c == '\'' || c == '"');
+                        Filter::Attribute(name.to_string(), Some(value.to_string()))
+                    }
+                    None => Filter::Attribute(attr.to_string(), None),
+                };
+                parsed.push(filter);
+            } else {
+                let index = inner
+                    .parse()
+                    .ok()
+                    .filter(|i| *i > 0)
+                    .ok_or_else(|| format!("Unsupported filter: [{inner}]"))?;
+                parsed.push(Filter::Index(index));
+            }
+        }
+
+        Ok(Self {
+            descendants,
+            test,
+            filters: parsed,
+        })
+    }
+
+    /// Returns the nodes the step's test is applied to, in document order.
+    fn candidates<'a, 'src>(
+        &self,
+        doc: &'a Document<'src>,
+        item: &Item<'a, 'src>,
+    ) -> Vec<Item<'a, 'src>> {
+        let start = match item {
+            Item::Document if !doc.has_root() => return vec![],
+            Item::Document => None,
+            Item::Tag(tag) => Some(*tag),
+            Item::Attribute(_) | Item::Text(_) => return vec![],
+        };
+
+        if !self.descendants {
+            // The root is the only child of the document
+            return match start {
+                Some(tag) => self.items_of(tag),
+                None => vec![Item::Tag(doc.root())],
+            };
+        }
+
+        // Like XPath, `//` looks at the starting tag and everything below it
+        let mut tags = vec![];
+        let mut stack = vec![start.unwrap_or(doc.root())];
+        while let Some(tag) = stack.pop() {
+            tags.push(tag);
+            stack.extend(child_tags(tag).collect::<Vec<_>>().into_iter().rev());
+        }
+
+        match self.test {
+            Test::Name(_) | Test::Any => {
+                // Tags below the starting tag; starting from the document, the root is below it too
+                let skip = usize::from(start.is_some());
+                tags.into_iter().skip(skip).map(Item::Tag).collect()
+            }
+            Test::Attribute(_) | Test::Text => tags
+                .into_iter()
+                .flat_map(|tag| self.items_of(tag))
+                .collect(),
+        }
+    }
+
+    /// Returns the child tags, attributes or text of a tag, depending on the step's test.
+    fn items_of<'a, 'src>(&self, tag: &'a TagNode<'src>) -> Vec<Item<'a, 'src>> {
+        match self.test {
+            Test::Name(_) | Test::Any => child_tags(tag).map(Item::Tag).collect(),
+            Test::Attribute(_) => tag.attributes().iter().map(Item::Attribute).collect(),
+            Test::Text => tag
+                .children()
+                .iter()
+                .filter_map(|child| match child {
+                    Node::Text(text) => Some(Item::Text(text.text().text())),
+                    Node::Cdata(cdata) => Some(Item::Text(cdata.content().text())),
+                    _ => None,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Test {
+    fn matches(&self, item: &Item<'_, '_>) -> bool {
+        match (self, item) {
+            (Test::Any, Item::Tag(_)) | (Test::Text, Item::Text(_)) => true,
+            (Test::Name(name), Item::Tag(tag)) => tag.name() == name.as_str(),
+            (Test::Attribute(name), Item::Attribute(attr)) => attr.name() == name.as_str(),
+            _ => false,
+        }
+    }
+}
+
+impl Filter {
+    fn apply<'a, 'src>(&self, items: Vec<Item<'a, 'src>>) -> Vec<Item<'a, 'src>> {
+        match self {
+            Filter::Index(index) => items.into_iter().nth(index - 1).into_iter().collect(),
+            Filter::Attribute(name, value) => items
+                .into_iter()
+                .filter(|item| {
+                    let Item::Tag(tag) = item else {
+                        return false;
+                    };
+                    tag.attributes().iter().rev().any(|attr| {
+                        attr.name() == name.as_str()
+                            && value.as_deref().is_none_or(|v| attr.decoded_value() == v)
+                    })
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn select(path: &str, src: &str) -> Vec<String> {
+        let doc = Document::parse_str(src).unwrap();
+        Path::parse(path)
+            .unwrap()
+            .select(&doc)
+            .iter()
+            .map(|item| match item {
+                Item::Document => "#document".to_string(),
+                Item::Tag(tag) => tag.name().to_string(),
+                Item::Attribute(attr) => attr.decoded_value().into_owned(),
+                Item::Text(text) => (*text).to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_select() {
+        let src = "<c><b id='1' lang='en'><t>A</t></b><x><b id='2'><t>B</t></b></x><b id='3' lang='en' /></c>";
+        assert_eq!(select("/c", src), ["c"]);
+        assert_eq!(select("/c/b/@id", src), ["1", "3"]);
+        assert_eq!(select("//b/@id", src), ["1", "2", "3"]);
+        assert_eq!(select("//b[@lang='en'][2]/@id", src), ["3"]);
+        assert_eq!(select("/c/*[2]", src), ["x"]);
+        assert_eq!(select("//t/text()", src), ["A", "B"]);
+        assert_eq!(select("//c", src), ["c"]);
+        assert_eq!(select("/c/x//t/text()", src), ["B"]);
+        assert!(select("/b", src).is_empty());
+
+        assert!(Path::parse("c/b").is_err());
+        assert!(Path::parse("/c/@id/b").is_err());
+        assert!(Path::parse("/c[0]").is_err());
+        assert!(Path::parse("/c[@a='x/y']").is_ok());
+    }
+
+    #[test]
+    fn test_first_difference() {
+        let a = Document::parse_str("<a><b /><c><d x='1' /></c><c><d x='1' /></c></a>").unwrap();
+        let b = Document::parse_str("<a><b /><c><d x='1' /></c><c><d x='2' /></c></a>").unwrap();
+        let options = CompareOptions::default();
+
+        assert_eq!(first_difference(a.root(), a.root(), options), None);
+        let path = first_difference(a.root(), b.root(), options).unwrap();
+        assert_eq!(path, "/a/c[2]/d[1]");
+        assert_eq!(
+            select(
+                &format!("{path}/@x"),
+                "<a><b /><c><d x='1' /></c><c><d x='2' /></c></a>"
+            ),
+            ["2"]
+        );
+    }
+}
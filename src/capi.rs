@@ -0,0 +1,294 @@
+//! A C ABI over the parser, for building bindings in other languages without wrapping the Rust types.
+//!
+//! Build the library with `cargo rustc --release --features capi --crate-type cdylib` to get a shared library exporting these functions.
+//!
+//! Everything is reached through opaque handles, which are only ever used through pointers:
+//! - An [`XmlTreeDocument`] is returned by [`xmltree_parse`], and freed with [`xmltree_document_free`]
+//! - An [`XmlTreeStrings`] list is returned by [`xmltree_query`], and freed with [`xmltree_strings_free`]
+//! - Strings returned as `*mut c_char` belong to the caller, and are freed with [`xmltree_string_free`]
+//! - Strings returned as `*const c_char` are borrowed from the handle they came from, and are valid until it is freed
+//!
+//! Strings passed in are NUL-terminated UTF-8. Functions return a null pointer on failure, including when given a null pointer.
+//! [`xmltree_abi_version`] changes whenever a function or these rules change, so bindings can check they match the library.
+use crate::{Document, Matcher, OwnedDocument, XmlWriteOptions};
+use std::ffi::{CStr, CString, c_char};
+
+/// The version of the C ABI; see [`xmltree_abi_version`].
+pub const ABI_VERSION: u32 = 1;
+
+/// Opaque handle to a parsed document.
+#[derive(Debug)]
+pub struct XmlTreeDocument {
+    source: String,
+    document: OwnedDocument,
+}
+
+/// Opaque handle to a list of strings.
+#[derive(Debug, Default)]
+pub struct XmlTreeStrings {
+    items: Vec<CString>,
+}
+
+/// Reads a string passed in, returning `None` if it is null or not UTF-8.
+///
+/// # Safety
+/// `s` must be null, or point to a NUL-terminated string valid for the lifetime `'a`
+unsafe fn read_str<'a>(s: *const c_char) -> Option<&'a str> {
+    if s.is_null() {
+        return None;
+    }
+    unsafe { CStr::from_ptr(s) }.to_str().ok()
+}
+
+/// Hands a string over to the caller, or returns null if it contains a NUL byte.
+fn give_string(s: String) -> *mut c_char {
+    CString::new(s).map_or(std::ptr::null_mut(), CString::into_raw)
+}
+
+/// Returns the version of the C ABI, which changes whenever a function or its ownership rules change.
+#[unsafe(no_mangle)]
+pub extern "C" fn xmltree_abi_version() -> u32 {
+    ABI_VERSION
+}
+
+/// Parses a document, returning a handle to it, or null if it is not valid XML.
+///
+/// On failure, if `error` is not null, it is set to a description of the problem, to be freed with [`xmltree_string_free`].
+/// On success it is set to null.
+///
+/// # Safety
+/// `src` must be null or a NUL-terminated string, and `error` must be null or valid for writes
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn xmltree_parse(
+    src: *const c_char,
+    error: *mut *mut c_char,
+) -> *mut XmlTreeDocument {
+    let Some(src) = (unsafe { read_str(src) }) else {
+        if !error.is_null() {
+            let message = "The document is null or not UTF-8".to_string();
+            unsafe { *error = give_string(message) };
+        }
+        return std::ptr::null_mut();
+    };
+
+    let result = Document::parse_str(src).map(|document| XmlTreeDocument {
+        document: document.to_owned(),
+        source: src.to_string(),
+    });
+
+    let (handle, message) = match result {
+        Ok(handle) => (Box::into_raw(Box::new(handle)), std::ptr::null_mut()),
+        Err(e) => (std::ptr::null_mut(), give_string(e.to_string())),
+    };
+    if error.is_null() {
+        unsafe { xmltree_string_free(message) };
+    } else {
+        unsafe { *error = message };
+    }
+    handle
+}
+
+/// Frees a document returned by [`xmltree_parse`]. Does nothing if `document` is null.
+///
+/// # Safety
+/// `document` must be null or a handle from [`xmltree_parse`] that has not been freed
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn xmltree_document_free(document: *mut XmlTreeDocument) {
+    if !document.is_null() {
+        drop(unsafe { Box::from_raw(document) });
+    }
+}
+
+/// Returns the name of the root element, including any prefix, or null if the document has no root.
+///
+/// The result is freed with [`xmltree_string_free`].
+///
+/// # Safety
+/// `document` must be null or a live handle from [`xmltree_parse`]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn xmltree_root_name(document: *const XmlTreeDocument) -> *mut c_char {
+    match unsafe { document.as_ref() } {
+        Some(handle) if handle.document.has_root() => {
+            give_string(handle.document.root.name.to_string())
+        }
+        _ => std::ptr::null_mut(),
+    }
+}
+
+/// Formats a document as XML, indenting nested nodes with `indent`, or on a single line if `minify` is set.
+/// A null `indent` uses the default indentation.
+///
+/// The result is freed with [`xmltree_string_free`].
+///
+/// # Safety
+/// `document` must be null or a live handle from [`xmltree_parse`], and `indent` must be null or a NUL-terminated string
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn xmltree_serialize(
+    document: *const XmlTreeDocument,
+    indent: *const c_char,
+    minify: bool,
+) -> *mut c_char {
+    let Some(handle) = (unsafe { document.as_ref() }) else {
+        return std::ptr::null_mut();
+    };
+
+    let mut options = XmlWriteOptions::default().with_minify(minify);
+    if !indent.is_null() {
+        let Some(indent) = (unsafe { read_str(indent) }) else {
+            return std::ptr::null_mut();
+        };
+        options = options.with_indent(indent);
+    }
+
+    handle
+        .document
+        .to_xml_with_options(&options)
+        .map_or(std::ptr::null_mut(), give_string)
+}
+
+/// Finds the elements with the given name, written as `local` or `prefix:local`, returning a list of each one formatted as XML.
+///
+/// The result is freed with [`xmltree_strings_free`].
+///
+/// # Safety
+/// `document` must be null or a live handle from [`xmltree_parse`], and `name` must be null or a NUL-terminated string
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn xmltree_query(
+    document: *const XmlTreeDocument,
+    name: *const c_char,
+) -> *mut XmlTreeStrings {
+    let (Some(handle), Some(name)) = (unsafe { document.as_ref() }, unsafe { read_str(name) })
+    else {
+        return std::ptr::null_mut();
+    };
+
+    // The handle only holds the owned tree, so matching runs on a fresh parse of the source
+    let Ok(document) = Document::parse_str(&handle.source) else {
+        return std::ptr::null_mut();
+    };
+    let matcher = match name.split_once(':') {
+        Some((prefix, local)) => Matcher::new().with_name(Some(prefix), local),
+        None => Matcher::new().with_name(None, name),
+    };
+
+    let items = document
+        .root()
+        .find_all(&matcher)
+        .into_iter()
+        .filter_map(|tag| CString::new(tag.to_string()).ok())
+        .collect();
+    Box::into_raw(Box::new(XmlTreeStrings { items }))
+}
+
+/// Returns the number of strings in a list, or 0 if `list` is null.
+///
+/// # Safety
+/// `list` must be null or a live handle from [`xmltree_query`]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn xmltree_strings_len(list: *const XmlTreeStrings) -> usize {
+    unsafe { list.as_ref() }.map_or(0, |list| list.items.len())
+}
+
+/// Returns a string from a list, or null if `index` is out of range.
+///
+/// The string is borrowed from the list, and is valid until the list is freed.
+///
+/// # Safety
+/// `list` must be null or a live handle from [`xmltree_query`]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn xmltree_strings_get(
+    list: *const XmlTreeStrings,
+    index: usize,
+) -> *const c_char {
+    unsafe { list.as_ref() }
+        .and_then(|list| list.items.get(index))
+        .map_or(std::ptr::null(), |item| item.as_ptr())
+}
+
+/// Frees a list returned by [`xmltree_query`]. Does nothing if `list` is null.
+///
+/// # Safety
+/// `list` must be null or a handle from [`xmltree_query`] that has not been freed
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn xmltree_strings_free(list: *mut XmlTreeStrings) {
+    if !list.is_null() {
+        drop(unsafe { Box::from_raw(list) });
+    }
+}
+
+/// Frees a string returned by one of these functions. Does nothing if `s` is null.
+///
+/// # Safety
+/// `s` must be null or a string returned as `*mut c_char` by one of these functions, that has not been freed
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn xmltree_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(unsafe { CString::from_raw(s) });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Takes a string returned to the caller, freeing it.
+    fn take(s: *mut c_char) -> Option<String> {
+        if s.is_null() {
+            return None;
+        }
+        let owned = unsafe { CStr::from_ptr(s) }.to_str().unwrap().to_string();
+        unsafe { xmltree_string_free(s) };
+        Some(owned)
+    }
+
+    #[test]
+    fn test_capi() {
+        let src = CString::new("<n:a xmlns:n='urn:n'><n:b>1</n:b><b/></n:a>").unwrap();
+        let mut error = std::ptr::null_mut();
+        let document = unsafe { xmltree_parse(src.as_ptr(), &raw mut error) };
+        assert!(!document.is_null());
+        assert!(error.is_null());
+
+        assert_eq!(
+            take(unsafe { xmltree_root_name(document) }).as_deref(),
+            Some("n:a")
+        );
+
+        let indent = CString::new("").unwrap();
+        assert_eq!(
+            take(unsafe { xmltree_serialize(document, indent.as_ptr(), true) }).as_deref(),
+            Some("<n:a xmlns:n=\"urn:n\"><n:b>1</n:b><b /></n:a>\n")
+        );
+
+        let name = CString::new("b").unwrap();
+        let list = unsafe { xmltree_query(document, name.as_ptr()) };
+        assert_eq!(unsafe { xmltree_strings_len(list) }, 1);
+        let item = unsafe { CStr::from_ptr(xmltree_strings_get(list, 0)) };
+        assert_eq!(item.to_str().unwrap(), "<b />");
+        assert!(unsafe { xmltree_strings_get(list, 1) }.is_null());
+
+        unsafe {
+            xmltree_strings_free(list);
+            xmltree_document_free(document);
+        }
+    }
+
+    #[test]
+    fn test_capi_errors() {
+        let src = CString::new("<a></b>").unwrap();
+        let mut error = std::ptr::null_mut();
+        let document = unsafe { xmltree_parse(src.as_ptr(), &raw mut error) };
+        assert!(document.is_null());
+        assert!(take(error).is_some_and(|e| e.contains('b')));
+
+        // Null pointers are rejected, not dereferenced
+        assert!(unsafe { xmltree_parse(std::ptr::null(), std::ptr::null_mut()) }.is_null());
+        assert!(unsafe { xmltree_root_name(std::ptr::null()) }.is_null());
+        assert!(unsafe { xmltree_query(std::ptr::null(), std::ptr::null()) }.is_null());
+        assert_eq!(unsafe { xmltree_strings_len(std::ptr::null()) }, 0);
+        unsafe {
+            xmltree_document_free(std::ptr::null_mut());
+            xmltree_string_free(std::ptr::null_mut());
+        }
+    }
+}
@@ -0,0 +1,253 @@
+//! Semantic comparison of documents, ignoring differences in formatting.
+use crate::{
+    Document, OwnedDocument,
+    node::{Node, OwnedTagNode, TagNode},
+};
+
+/// Options controlling which differences are ignored by [`Document::semantic_eq`].
+///
+/// Spans are never compared. By default, attribute order, insignificant whitespace and comments are all ignored.
+///
+/// # Example
+/// ```rust
+/// use xmltree::{CompareOptions, Document};
+///
+/// let a = Document::parse_str("<a x=\"1\" y=\"2\"><!-- note --><b>some  text</b></a>").unwrap();
+/// let b = Document::parse_str("<a y=\"2\" x=\"1\">\n  <b>some text</b>\n</a>").unwrap();
+///
+/// assert!(a.semantic_eq(&b, CompareOptions::default()));
+/// assert!(!a.semantic_eq(&b, CompareOptions::default().with_ignore_comments(false)));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[expect(clippy::struct_excessive_bools, reason = "Independent switches")]
+pub struct CompareOptions {
+    /// Compare attributes as an unordered set.
+    pub ignore_attribute_order: bool,
+
+    /// Collapse runs of whitespace inside text nodes before comparing them.
+    ///
+    /// Leading and trailing whitespace is always trimmed by the parser.
+    pub ignore_whitespace: bool,
+
+    /// Skip comments anywhere in the document.
+    pub ignore_comments: bool,
+
+    /// Skip processing instructions anywhere in the document.
+    pub ignore_processing_instructions: bool,
+}
+impl Default for CompareOptions {
+    fn default() -> Self {
+        Self {
+            ignore_attribute_order: true,
+            ignore_whitespace: true,
+            ignore_comments: true,
+            ignore_processing_instructions: false,
+        }
+    }
+}
+impl CompareOptions {
+    /// Options that only ignore spans; everything else must match exactly.
+    #[must_use]
+    pub fn strict() -> Self {
+        Self {
+            ignore_attribute_order: false,
+            ignore_whitespace: false,
+            ignore_comments: false,
+            ignore_processing_instructions: false,
+        }
+    }
+
+    /// Sets whether attributes are compared as an unordered set.
+    #[must_use]
+    pub fn with_ignore_attribute_order(mut self, ignore: bool) -> Self {
+        self.ignore_attribute_order = ignore;
+        self
+    }
+
+    /// Sets whether runs of whitespace inside text nodes are collapsed before comparing.
+    #[must_use]
+    pub fn with_ignore_whitespace(mut self, ignore: bool) -> Self {
+        self.ignore_whitespace = ignore;
+        self
+    }
+
+    /// Sets whether comments are skipped.
+    #[must_use]
+    pub fn with_ignore_comments(mut self, ignore: bool) -> Self {
+        self.ignore_comments = ignore;
+        self
+    }
+
+    /// Sets whether processing instructions are skipped.
+    #[must_use]
+    pub fn with_ignore_processing_instructions(mut self, ignore: bool) -> Self {
+        self.ignore_processing_instructions = ignore;
+        self
+    }
+
+    fn is_ignored(self, node: &Node<'_>) -> bool {
+        match node {
+            Node::Comment(_) => self.ignore_comments,
+            Node::ProcessingInstruction(_) => self.ignore_processing_instructions,
+            _ => false,
+        }
+    }
+}
+
+impl Document<'_> {
+    /// Compares two documents by content, ignoring spans and the differences selected in `options`.
+    ///
+    /// Unlike `PartialEq`, documents parsed from differently formatted sources can compare equal.
+    /// Metadata is not compared.
+    #[must_use]
+    pub fn semantic_eq(&self, other: &Document<'_>, options: CompareOptions) -> bool {
+        let declarations_eq = match (self.declaration(), other.declaration()) {
+            (Some(a), Some(b)) => a.to_owned() == b.to_owned(),
+            (None, None) => true,
+            _ => false,
+        };
+
+        declarations_eq
+            && nodes_eq(self.prolog(), other.prolog(), options)
+            && self.root().semantic_eq(other.root(), options)
+            && nodes_eq(self.epilog(), other.epilog(), options)
+    }
+}
+
+impl OwnedDocument {
+    /// Compares two documents by content, ignoring the differences selected in `options`.
+    ///
+    /// See [`Document::semantic_eq`] for details.
+    #[must_use]
+    pub fn semantic_eq(&self, other: &OwnedDocument, options: CompareOptions) -> bool {
+        self.borrowed().semantic_eq(&other.borrowed(), options)
+    }
+}
+
+impl TagNode<'_> {
+    /// Compares two subtrees by content, ignoring spans and the differences selected in `options`.
+    ///
+    /// See [`Document::semantic_eq`] for details.
+    #[must_use]
+    pub fn semantic_eq(&self, other: &TagNode<'_>, options: CompareOptions) -> bool {
+        let mut stack = vec![(self, other)];
+        while let Some((a, b)) = stack.pop() {
+            if a.name() != b.name() || !attributes_eq(a, b, options) {
+                return false;
+            }
+
+            let a_children = a.children().iter().filter(|n| !options.is_ignored(n));
+            let mut b_children = b.children().iter().filter(|n| !options.is_ignored(n));
+            for a_child in a_children {
+                let Some(b_child) = b_children.next() else {
+                    return false;
+                };
+
+                match (a_child, b_child) {
+                    (Node::Child(a), Node::Child(b)) => stack.push((a, b)),
+                    (a, b) if !leaf_eq(a, b, options) => return false,
+                    _ => {}
+                }
+            }
+
+            if b_children.next().is_some() {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+impl OwnedTagNode {
+    /// Compares two subtrees by content, ignoring the differences selected in `options`.
+    ///
+    /// See [`Document::semantic_eq`] for details.
+    #[must_use]
+    pub fn semantic_eq(&self, other: &OwnedTagNode, options: CompareOptions) -> bool {
+        self.borrowed().semantic_eq(&other.borrowed(), options)
+    }
+}
+
+fn attributes_eq(a: &TagNode<'_>, b: &TagNode<'_>, options: CompareOptions) -> bool {
+    if a.attributes().len() != b.attributes().len() {
+        return false;
+    }
+
+    let pairs = |node: &TagNode<'_>| {
+        let mut pairs: Vec<_> = node
+            .attributes()
+            .iter()
+            .map(|attr| (attr.name().to_string(), attr.value().text().to_string()))
+            .collect();
+        if options.ignore_attribute_order {
+            pairs.sort();
+        }
+        pairs
+    };
+
+    pairs(a) == pairs(b)
+}
+
+fn nodes_eq(a: &[Node<'_>], b: &[Node<'_>], options: CompareOptions) -> bool {
+    let mut a = a.iter().filter(|n| !options.is_ignored(n));
+    let mut b = b.iter().filter(|n| !options.is_ignored(n));
+    loop {
+        match (a.next(), b.next()) {
+            (None, None) => return true,
+            (Some(Node::Child(a)), Some(Node::Child(b))) if a.semantic_eq(b, options) => {}
+            (Some(a), Some(b)) if !matches!(a, Node::Child(_)) && leaf_eq(a, b, options) => {}
+            _ => return false,
+        }
+    }
+}
+
+/// Compares two nodes that are not tags.
+fn leaf_eq(a: &Node<'_>, b: &Node<'_>, options: CompareOptions) -> bool {
+    match (a, b) {
+        (Node::Text(a), Node::Text(b)) if options.ignore_whitespace => a
+            .text()
+            .text()
+            .split_whitespace()
+            .eq(b.text().text().split_whitespace()),
+        (a, b) => a.to_owned() == b.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_semantic_eq() {
+        let a = Document::parse_str("<?xml version='1.0'?><a x='1' y='2'><b>t  u</b><?pi?></a>")
+            .unwrap();
+        let b = Document::parse_str(
+            "<?xml version=\"1.0\"?>\n<a y='2' x='1'>\n\t<!--c--><b>t\nu</b>\n<?pi?></a>",
+        )
+        .unwrap();
+        assert_ne!(a, b);
+        assert!(a.semantic_eq(&b, CompareOptions::default()));
+        assert!(
+            a.to_owned()
+                .semantic_eq(&b.to_owned(), CompareOptions::default())
+        );
+
+        let options = CompareOptions::default();
+        assert!(!a.semantic_eq(&b, options.with_ignore_attribute_order(false)));
+        assert!(!a.semantic_eq(&b, options.with_ignore_whitespace(false)));
+        assert!(!a.semantic_eq(&b, options.with_ignore_comments(false)));
+        assert!(!a.semantic_eq(&b, CompareOptions::strict()));
+
+        let c = Document::parse_str("<a x='1' y='2'><b>t u</b></a>").unwrap();
+        assert!(!a.semantic_eq(&c, options));
+        assert!(!a.root().semantic_eq(c.root(), options));
+        assert!(
+            a.root()
+                .semantic_eq(c.root(), options.with_ignore_processing_instructions(true))
+        );
+
+        let d = Document::parse_str("<a x='1' y='3'><b>t u</b></a>").unwrap();
+        assert!(!c.semantic_eq(&d, options));
+    }
+}
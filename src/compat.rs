@@ -0,0 +1,310 @@
+//! A DOM-like API in the style of the original `xmltree` crate, to ease migration from it.
+//!
+//! [`Element`] keeps attributes in a `HashMap` and children in a `Vec`, and converts to and from
+//! [`OwnedTagNode`] for use with the rest of this crate.
+//!
+//! ```rust
+//! use xmltree::compat::Element;
+//!
+//! let mut root = Element::parse("<names><name first='bob' /><name first='alice' /></names>".as_bytes()).unwrap();
+//! let bob = root.take_child("name").unwrap();
+//! assert_eq!(bob.attributes["first"], "bob");
+//!
+//! let mut out = vec![];
+//! root.write(&mut out).unwrap();
+//! ```
+use crate::{
+    Document, Origin, OwnedDeclarationNode, OwnedDocument,
+    error::XmlResult,
+    node::{
+        OwnedCdataNode, OwnedCommentNode, OwnedNode, OwnedNodeAttribute, OwnedNodeName,
+        OwnedProcessingInstructionNode, OwnedTagNode, OwnedTextNode,
+    },
+};
+use std::{borrow::Cow, collections::HashMap};
+
+/// A child of an [`Element`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum XMLNode {
+    /// A nested element.
+    Element(Element),
+
+    /// A comment, without the `<!--` and `-->` delimiters.
+    Comment(String),
+
+    /// The content of a CDATA section.
+    CData(String),
+
+    /// A text node.
+    Text(String),
+
+    /// A processing instruction, with its target and optional content.
+    ProcessingInstruction(String, Option<String>),
+}
+impl XMLNode {
+    /// Returns the element, if this node is one.
+    #[must_use]
+    pub fn as_element(&self) -> Option<&Element> {
+        match self {
+            Self::Element(element) => Some(element),
+            _ => None,
+        }
+    }
+
+    /// Returns the element mutably, if this node is one.
+    pub fn as_mut_element(&mut self) -> Option<&mut Element> {
+        match self {
+            Self::Element(element) => Some(element),
+            _ => None,
+        }
+    }
+
+    /// Returns the text, if this node is a text node.
+    #[must_use]
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            Self::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Returns the content, if this node is a CDATA section.
+    #[must_use]
+    pub fn as_cdata(&self) -> Option<&str> {
+        match self {
+            Self::CData(content) => Some(content),
+            _ => None,
+        }
+    }
+
+    /// Returns the text, if this node is a comment.
+    #[must_use]
+    pub fn as_comment(&self) -> Option<&str> {
+        match self {
+            Self::Comment(text) => Some(text),
+            _ => None,
+        }
+    }
+}
+
+/// An XML element, with its attributes and children.
+///
+/// Attribute keys include the prefix, if any, such as `xml:lang`.
+/// Attribute order is not kept; when written, attributes are sorted by key.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Element {
+    /// The namespace prefix of the element, if any.
+    pub prefix: Option<String>,
+
+    /// The local name of the element.
+    pub name: String,
+
+    /// The attributes of the element, keyed by their full name.
+    pub attributes: HashMap<String, String>,
+
+    /// The children of the element.
+    pub children: Vec<XMLNode>,
+}
+impl Element {
+    /// Creates an element with the given name and no attributes or children.
+    ///
+    /// A name of the form `prefix:local` sets the prefix.
+    #[must_use]
+    pub fn new(name: &str) -> Self {
+        let (prefix, name) = match name.split_once(':') {
+            Some((prefix, local)) => (Some(prefix.to_string()), local.to_string()),
+            None => (None, name.to_string()),
+        };
+
+        Self {
+            prefix,
+            name,
+            ..Self::default()
+        }
+    }
+
+    /// Parses a document from a reader, and returns its root element.
+    ///
+    /// Everything outside of the root, such as the declaration and comments, is discarded.
+    ///
+    /// # Errors
+    /// Returns errors if the reader fails, the input is not UTF-8, or the XML is invalid
+    pub fn parse<R: std::io::Read>(mut reader: R) -> XmlResult<Self> {
+        let mut src = String::new();
+        reader.read_to_string(&mut src)?;
+
+        let document = Document::parse_str(&src)?;
+        Ok(Self::from(document.root().to_owned()))
+    }
+
+    /// Writes the element as a document, with an XML declaration and the default indentation.
+    ///
+    /// # Errors
+    /// Returns errors if the writer fails, or the element cannot be written as valid XML
+    pub fn write<W: std::io::Write>(&self, mut writer: W) -> std::io::Result<()> {
+        let mut document = OwnedDocument::new(OwnedTagNode::from(self.clone()));
+        document.declaration = Some(OwnedDeclarationNode::new("1.0", Some("UTF-8"), None));
+        document.to_xml_with_writer(&mut writer, None)
+    }
+
+    /// Returns true if the element's full name, or its local name when no prefix is given, matches.
+    fn is_named(&self, name: &str) -> bool {
+        match (name.split_once(':'), &self.prefix) {
+            (Some((prefix, local)), Some(own)) => own == prefix && self.name == local,
+            (Some(_), None) => false,
+            (None, _) => self.name == name,
+        }
+    }
+
+    /// Returns the first child element with the given name.
+    ///
+    /// The name may be `prefix:local`, or just `local` to match any prefix.
+    #[must_use]
+    pub fn get_child(&self, name: &str) -> Option<&Element> {
+        self.children
+            .iter()
+            .filter_map(XMLNode::as_element)
+            .find(|child| child.is_named(name))
+    }
+
+    /// Returns the first child element with the given name, mutably. See [`Element::get_child`].
+    pub fn get_mut_child(&mut self, name: &str) -> Option<&mut Element> {
+        self.children
+            .iter_mut()
+            .filter_map(XMLNode::as_mut_element)
+            .find(|child| child.is_named(name))
+    }
+
+    /// Removes and returns the first child element with the given name. See [`Element::get_child`].
+    pub fn take_child(&mut self, name: &str) -> Option<Element> {
+        let index = self
+            .children
+            .iter()
+            .position(|child| child.as_element().is_some_and(|e| e.is_named(name)))?;
+
+        match self.children.remove(index) {
+            XMLNode::Element(element) => Some(element),
+            _ => unreachable!("the child at the index is an element"),
+        }
+    }
+
+    /// Returns the text and CDATA content directly inside this element, joined together.
+    ///
+    /// Returns `None` if there is none.
+    #[must_use]
+    pub fn get_text(&self) -> Option<Cow<'_, str>> {
+        let mut parts = self
+            .children
+            .iter()
+            .filter_map(|child| child.as_text().or_else(|| child.as_cdata()));
+
+        let first = parts.next()?;
+        match parts.next() {
+            None => Some(Cow::Borrowed(first)),
+            Some(second) => {
+                let mut text = format!("{first}{second}");
+                text.extend(parts);
+                Some(Cow::Owned(text))
+            }
+        }
+    }
+}
+
+impl From<OwnedTagNode> for Element {
+    /// Converts a tag into an element. Document type nodes are dropped, since elements cannot hold them.
+    ///
+    /// Of several attributes with the same name, the last one is kept, matching [`OwnedTagNode::get_attribute`].
+    fn from(tag: OwnedTagNode) -> Self {
+        let attributes = tag
+            .attributes
+            .into_iter()
+            .map(|attr| (attr.name.to_string(), attr.value))
+            .collect();
+
+        let children = tag
+            .children
+            .into_iter()
+            .filter_map(|child| match child {
+                OwnedNode::Tag(tag) => Some(XMLNode::Element(Self::from(tag))),
+                OwnedNode::Text(text) => Some(XMLNode::Text(text.text)),
+                OwnedNode::Comment(comment) => Some(XMLNode::Comment(comment.text)),
+                OwnedNode::Cdata(cdata) => Some(XMLNode::CData(cdata.content)),
+                OwnedNode::ProcessingInstruction(pi) => {
+                    Some(XMLNode::ProcessingInstruction(pi.target, pi.content))
+                }
+                OwnedNode::DocumentType(_) => None,
+            })
+            .collect();
+
+        Self {
+            prefix: tag.name.prefix.map(|prefix| prefix.to_string()),
+            name: tag.name.local.to_string(),
+            attributes,
+            children,
+        }
+    }
+}
+
+impl From<Element> for OwnedTagNode {
+    /// Converts an element into a tag, with its attributes sorted by key.
+    fn from(element: Element) -> Self {
+        let mut attributes: Vec<_> = element.attributes.into_iter().collect();
+        attributes.sort_unstable();
+
+        let children = element
+            .children
+            .into_iter()
+            .map(|child| match child {
+                XMLNode::Element(element) => OwnedNode::Tag(Self::from(element)),
+                XMLNode::Text(text) => OwnedNode::Text(OwnedTextNode::new(text)),
+                XMLNode::Comment(text) => OwnedNode::Comment(OwnedCommentNode::new(text)),
+                XMLNode::CData(content) => OwnedNode::Cdata(OwnedCdataNode::new(content)),
+                XMLNode::ProcessingInstruction(target, content) => {
+                    OwnedNode::ProcessingInstruction(OwnedProcessingInstructionNode::new(
+                        target, content,
+                    ))
+                }
+            })
+            .collect();
+
+        Self {
+            name: OwnedNodeName::new(element.prefix, element.name),
+            attributes: attributes
+                .into_iter()
+                .map(|(name, value)| OwnedNodeAttribute::new(name.as_str(), value))
+                .collect(),
+            children,
+            origin: Origin::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_element_round_trip() {
+        let src =
+            "<a:root b='1' a:c='2'>text<![CDATA[more]]><!-- note --><?pi data?><child /></a:root>";
+        let mut element = Element::parse(src.as_bytes()).unwrap();
+        assert_eq!(element.prefix.as_deref(), Some("a"));
+        assert_eq!(element.attributes["a:c"], "2");
+        assert_eq!(element.get_text().unwrap(), "textmore");
+        assert!(element.get_child("child").is_some());
+        assert!(element.get_child("x:child").is_none());
+
+        let tag = OwnedTagNode::from(element.clone());
+        assert_eq!(tag.get_attribute(Some("a"), "c").unwrap().value, "2");
+        assert_eq!(Element::from(tag), element);
+
+        let mut out = vec![];
+        element.write(&mut out).unwrap();
+        let written = String::from_utf8(out).unwrap();
+        assert!(written.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\""));
+        assert_eq!(Element::parse(written.as_bytes()).unwrap(), element);
+
+        assert!(element.take_child("child").is_some());
+        assert!(element.get_child("child").is_none());
+    }
+}
@@ -0,0 +1,454 @@
+//! A hybrid tree for cheap partial edits of a parsed document.
+//!
+//! Converting a whole document to owned just to change one attribute copies every string in it.
+//! A [`CowDocument`] instead borrows every node from the parsed [`Document`], and only tags on the path
+//! to an edit are tracked separately; the edited parts are owned, and everything else is still borrowed.
+use crate::{
+    DeclarationNode, Document, NodePath, OwnedDocument, PathSection, XmlWriteOptions,
+    node::{
+        Node, NodeAttribute, NodeName, OwnedNode, OwnedNodeAttribute, OwnedNodeName, OwnedTagNode,
+        TagNode,
+    },
+};
+use std::borrow::Cow;
+
+/// A document where unchanged nodes borrow from a parsed [`Document`], and only edited nodes are owned.
+///
+/// Writing it out with [`CowDocument::to_xml`] walks the borrowed and owned parts together.
+/// The declaration, prolog and epilog are always borrowed; only the root element can be edited.
+///
+/// # Example
+/// ```rust
+/// use xmltree::{Document, NodePath};
+///
+/// let doc = Document::parse_str("<config><server port=\"80\" /><client /></config>").unwrap();
+/// let mut cow = doc.to_cow();
+///
+/// let server = cow.tag_mut(&NodePath::root().child(0)).unwrap();
+/// server.set_attribute("port", "8080");
+///
+/// let xml = cow.to_xml_with_options(&xmltree::XmlWriteOptions::default().with_minify(true)).unwrap();
+/// assert_eq!(xml, "<config><server port=\"8080\" /><client /></config>\n");
+///
+/// // Only the edited tag and its ancestors are tracked; the client is still borrowed
+/// assert!(!cow.root().children()[1].is_modified());
+/// ```
+#[derive(Debug, Clone)]
+pub struct CowDocument<'a, 'src> {
+    document: &'a Document<'src>,
+    root: CowTag<'a, 'src>,
+}
+impl<'a, 'src> CowDocument<'a, 'src> {
+    /// Creates an unchanged view of the document.
+    #[must_use]
+    pub fn new(document: &'a Document<'src>) -> Self {
+        Self {
+            document,
+            root: CowTag::new(document.root()),
+        }
+    }
+
+    /// Returns the document the unchanged nodes are borrowed from.
+    #[must_use]
+    pub fn original(&self) -> &'a Document<'src> {
+        self.document
+    }
+
+    /// Returns the root element.
+    #[must_use]
+    pub fn root(&self) -> &CowTag<'a, 'src> {
+        &self.root
+    }
+
+    /// Returns the root element, for editing.
+    pub fn root_mut(&mut self) -> &mut CowTag<'a, 'src> {
+        &mut self.root
+    }
+
+    /// Returns the tag at the given path, for editing.
+    ///
+    /// Tags along the path stop being borrowed as a whole, but only their child lists are copied.
+    /// Returns None if the path is not in the root section, or does not lead to a tag.
+    pub fn tag_mut(&mut self, path: &NodePath) -> Option<&mut CowTag<'a, 'src>> {
+        if path.section() != PathSection::Root {
+            return None;
+        }
+
+        let mut tag = &mut self.root;
+        for &index in path.indices() {
+            tag = tag.child_mut(index)?;
+        }
+        Some(tag)
+    }
+
+    /// Returns true if anything in the document was edited.
+    #[must_use]
+    pub fn is_modified(&self) -> bool {
+        self.root.is_modified()
+    }
+
+    /// Returns an owned version of the document, with the edits applied.
+    #[must_use]
+    pub fn to_owned(&self) -> OwnedDocument {
+        let document = self.document;
+        OwnedDocument {
+            bom: document.has_bom(),
+            declaration: document.declaration().map(DeclarationNode::to_owned),
+            prolog: OwnedNode::from_nodes(document.prolog()),
+            root: self.root.to_owned(),
+            epilog: OwnedNode::from_nodes(document.epilog()),
+            metadata: document.metadata().clone(),
+        }
+    }
+
+    /// Create a formatted XML string from this document, with the edits applied.
+    ///
+    /// See [`Document::to_xml`] for more details.
+    ///
+    /// # Errors
+    /// Can fail if a string in the document cannot be entity encoded.
+    pub fn to_xml(&self, tab_char: Option<&str>) -> std::io::Result<String> {
+        self.to_xml_with_options(&XmlWriteOptions::from_tab_char(tab_char))
+    }
+
+    /// Create a formatted XML string from this document, using the given options.
+    ///
+    /// See [`XmlWriteOptions`] for the available options.
+    ///
+    /// # Errors
+    /// Can fail if a string in the document cannot be entity encoded,
+    /// or contains characters rejected by [`XmlWriteOptions::control_chars`].
+    pub fn to_xml_with_options(&self, options: &XmlWriteOptions) -> std::io::Result<String> {
+        let mut buffer = vec![];
+        self.to_xml_with_writer_and_options(&mut buffer, options)?;
+
+        String::from_utf8(buffer).map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Failed to convert to UTF-8: {e}"),
+            )
+        })
+    }
+
+    /// Write this document as a formatted XML string using the given writer and options.
+    ///
+    /// See [`CowDocument::to_xml_with_options`] for more details.
+    ///
+    /// # Errors
+    /// Can fail if a string in the document cannot be entity encoded,
+    /// or contains characters rejected by [`XmlWriteOptions::control_chars`].
+    pub fn to_xml_with_writer_and_options<W: std::io::Write>(
+        &self,
+        writer: &mut W,
+        options: &XmlWriteOptions,
+    ) -> std::io::Result<()> {
+        crate::to_xml::write_document(writer, self.document, options, |writer| {
+            crate::to_xml::write_cow_tag(
+                writer,
+                &self.root,
+                options,
+                &mut crate::to_xml::Ancestors::default(),
+            )
+        })
+    }
+}
+
+/// A node of a [`CowDocument`].
+#[derive(Debug, Clone)]
+pub enum CowNode<'a, 'src> {
+    /// An unchanged node, borrowed from the document.
+    Borrowed(&'a Node<'src>),
+
+    /// A tag borrowed from the document, with edits to it or its descendants.
+    Tag(CowTag<'a, 'src>),
+
+    /// A node added or replaced since the view was created.
+    Owned(OwnedNode),
+}
+impl CowNode<'_, '_> {
+    /// Returns true if the node was edited, added, or replaced.
+    #[must_use]
+    pub fn is_modified(&self) -> bool {
+        match self {
+            Self::Borrowed(_) => false,
+            Self::Tag(tag) => tag.is_modified(),
+            Self::Owned(_) => true,
+        }
+    }
+
+    /// Returns an owned version of the node, with the edits applied.
+    #[must_use]
+    pub fn to_owned(&self) -> OwnedNode {
+        match self {
+            Self::Borrowed(node) => Node::to_owned(node),
+            Self::Tag(tag) => OwnedNode::Tag(tag.to_owned()),
+            Self::Owned(node) => node.clone(),
+        }
+    }
+}
+impl From<OwnedNode> for CowNode<'_, '_> {
+    fn from(node: OwnedNode) -> Self {
+        Self::Owned(node)
+    }
+}
+
+/// A tag of a [`CowDocument`], borrowed from the document until its name, attributes or children are edited.
+///
+/// Editing the attributes copies only this tag's attributes, and editing the children
+/// copies only the list of children; the children themselves stay borrowed.
+#[derive(Debug, Clone)]
+pub struct CowTag<'a, 'src> {
+    tag: &'a TagNode<'src>,
+    name: Option<OwnedNodeName>,
+    attributes: Option<Vec<OwnedNodeAttribute>>,
+    children: Option<Vec<CowNode<'a, 'src>>>,
+}
+impl<'a, 'src> CowTag<'a, 'src> {
+    /// Creates an unchanged view of the tag.
+    #[must_use]
+    pub fn new(tag: &'a TagNode<'src>) -> Self {
+        Self {
+            tag,
+            name: None,
+            attributes: None,
+            children: None,
+        }
+    }
+
+    /// Returns the tag the unchanged parts are borrowed from.
+    #[must_use]
+    pub fn original(&self) -> &'a TagNode<'src> {
+        self.tag
+    }
+
+    /// Returns true if the tag or any of its descendants was edited.
+    #[must_use]
+    pub fn is_modified(&self) -> bool {
+        self.name.is_some() || self.attributes.is_some() || self.children_modified()
+    }
+
+    /// Returns true if the children were added, removed, reordered or edited.
+    fn children_modified(&self) -> bool {
+        let Some(children) = &self.children else {
+            return false;
+        };
+
+        let original = self.tag.children();
+        children.len() != original.len()
+            || children
+                .iter()
+                .zip(original)
+                .any(|(child, original)| match child {
+                    CowNode::Borrowed(node) => !std::ptr::eq(*node, original),
+                    CowNode::Tag(tag) => {
+                        tag.is_modified()
+                            || !matches!(original, Node::Child(node) if std::ptr::eq(tag.tag, node))
+                    }
+                    CowNode::Owned(_) => true,
+                })
+    }
+
+    /// Returns the name of the tag.
+    #[must_use]
+    pub fn name(&self) -> NodeName<'_> {
+        match &self.name {
+            Some(name) => name.borrowed(),
+            None => self.tag.name().clone(),
+        }
+    }
+
+    /// Renames the tag.
+    pub fn set_name(&mut self, name: impl Into<OwnedNodeName>) {
+        self.name = Some(name.into());
+    }
+
+    /// Get the value of an attribute by name, with the edits applied.
+    ///
+    /// Like [`TagNode::get_attribute`], the last attribute with the name is used.
+    #[must_use]
+    pub fn get_attribute(&self, prefix: Option<&str>, name: &str) -> Option<&str> {
+        match &self.attributes {
+            Some(attributes) => attributes
+                .iter()
+                .rev()
+                .find(|a| a.name.equals(prefix, name))
+                .map(|a| a.value.as_str()),
+            None => self
+                .tag
+                .get_attribute(prefix, name)
+                .map(|a| a.value().text()),
+        }
+    }
+
+    /// Returns the attributes of the tag for editing, copying them on first use.
+    pub fn attributes_mut(&mut self) -> &mut Vec<OwnedNodeAttribute> {
+        self.attributes.get_or_insert_with(|| {
+            self.tag
+                .attributes()
+                .iter()
+                .map(NodeAttribute::to_owned)
+                .collect()
+        })
+    }
+
+    /// Sets the value of an attribute, replacing the last one with the same name, or adding it at the end.
+    pub fn set_attribute(&mut self, name: impl Into<OwnedNodeName>, value: impl Into<String>) {
+        let name = name.into();
+        let attributes = self.attributes_mut();
+        match attributes.iter_mut().rev().find(|a| a.name == name) {
+            Some(attr) => attr.value = value.into(),
+            None => attributes.push(OwnedNodeAttribute::new(name, value)),
+        }
+    }
+
+    /// Removes every attribute with the given name, returning true if any were found.
+    pub fn remove_attribute(&mut self, prefix: Option<&str>, name: &str) -> bool {
+        if self.get_attribute(prefix, name).is_none() {
+            return false;
+        }
+
+        self.attributes_mut()
+            .retain(|a| !a.name.equals(prefix, name));
+        true
+    }
+
+    /// Returns the children of the tag.
+    ///
+    /// Unless the children were edited, each one is [`CowNode::Borrowed`].
+    #[must_use]
+    pub fn children(&self) -> Cow<'_, [CowNode<'a, 'src>]> {
+        match &self.children {
+            Some(children) => Cow::Borrowed(children),
+            None => Cow::Owned(self.tag.children().iter().map(CowNode::Borrowed).collect()),
+        }
+    }
+
+    /// Returns the children of the tag for editing, copying the list on first use.
+    ///
+    /// The children themselves stay borrowed; replace one with [`CowNode::Owned`] to change it outright.
+    pub fn children_mut(&mut self) -> &mut Vec<CowNode<'a, 'src>> {
+        let tag = self.tag;
+        self.children
+            .get_or_insert_with(|| tag.children().iter().map(CowNode::Borrowed).collect())
+    }
+
+    /// Returns the child at the given index for editing, if it is a tag.
+    ///
+    /// Owned children are not returned; edit them directly through [`CowTag::children_mut`].
+    pub fn child_mut(&mut self, index: usize) -> Option<&mut CowTag<'a, 'src>> {
+        let child = self.children_mut().get_mut(index)?;
+        if let CowNode::Borrowed(Node::Child(tag)) = child {
+            *child = CowNode::Tag(CowTag::new(tag));
+        }
+
+        match child {
+            CowNode::Tag(tag) => Some(tag),
+            _ => None,
+        }
+    }
+
+    /// Returns an owned version of the tag, with the edits applied.
+    #[must_use]
+    pub fn to_owned(&self) -> OwnedTagNode {
+        let mut owned = OwnedTagNode::new(match &self.name {
+            Some(name) => name.clone(),
+            None => self.tag.name().to_owned(),
+        });
+        owned.attributes = match &self.attributes {
+            Some(attributes) => attributes.clone(),
+            None => self
+                .tag
+                .attributes()
+                .iter()
+                .map(NodeAttribute::to_owned)
+                .collect(),
+        };
+        owned.children = match &self.children {
+            Some(children) => children.iter().map(CowNode::to_owned).collect(),
+            None => OwnedNode::from_nodes(self.tag.children()),
+        };
+        owned.origin = crate::Origin::from(self.tag.span());
+        owned
+    }
+
+    /// Returns the attributes to write, as name and raw value pairs.
+    pub(crate) fn written_attributes(&self) -> Vec<(String, &str)> {
+        match &self.attributes {
+            Some(attributes) => attributes
+                .iter()
+                .map(|a| (a.name.to_string(), a.value.as_str()))
+                .collect(),
+            None => self
+                .tag
+                .attributes()
+                .iter()
+                .map(|a| (a.name().to_string(), a.value().text()))
+                .collect(),
+        }
+    }
+}
+
+impl<'src> Document<'src> {
+    /// Returns a view of the document for cheap partial edits. See [`CowDocument`].
+    #[must_use]
+    pub fn to_cow(&self) -> CowDocument<'_, 'src> {
+        CowDocument::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::OwnedTextNode;
+
+    #[test]
+    fn test_cow_edits() {
+        let src = "<?xml version=\"1.0\"?><!-- c --><a x=\"1\"><b><c y=\"2\" /></b><d>text</d></a>";
+        let doc = Document::parse_str(src).unwrap();
+        let mut cow = doc.to_cow();
+
+        // Walking to a tag without changing it writes the same document
+        cow.tag_mut(&NodePath::root().child(0).child(0)).unwrap();
+        assert!(!cow.is_modified());
+        assert_eq!(cow.to_xml(None).unwrap(), doc.to_xml(None).unwrap());
+
+        let c = cow.tag_mut(&NodePath::root().child(0).child(0)).unwrap();
+        c.set_attribute("y", "3");
+        c.set_attribute("z", "4");
+        assert_eq!(c.get_attribute(None, "y"), Some("3"));
+        assert!(c.remove_attribute(None, "z"));
+        assert!(!c.remove_attribute(None, "z"));
+
+        let root = cow.root_mut();
+        root.set_name("root");
+        root.children_mut().remove(1);
+        root.children_mut()
+            .push(OwnedNode::Text(OwnedTextNode::new("tail")).into());
+
+        let mut expected = doc.to_owned();
+        expected.root.name = "root".into();
+        expected.root.children.remove(1);
+        expected
+            .root
+            .children
+            .push(OwnedNode::Text(OwnedTextNode::new("tail")));
+        let OwnedNode::Tag(b) = &mut expected.root.children[0] else {
+            panic!("Expected a tag");
+        };
+        let OwnedNode::Tag(c) = &mut b.children[0] else {
+            panic!("Expected a tag");
+        };
+        c.attributes[0].value = "3".to_string();
+
+        assert_eq!(cow.to_owned(), expected);
+        assert_eq!(cow.to_xml(None).unwrap(), expected.to_xml(None).unwrap());
+        assert_eq!(
+            cow.to_xml(Some("  ")).unwrap(),
+            expected.to_xml(Some("  ")).unwrap()
+        );
+
+        // Text is not a tag, and the epilog cannot be edited
+        assert!(cow.tag_mut(&NodePath::root().child(1)).is_none());
+        assert!(cow.tag_mut(&NodePath::epilog(0)).is_none());
+    }
+}
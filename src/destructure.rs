@@ -0,0 +1,131 @@
+//! Splitting the children of a tag into groups by name, for hand-written typed extraction.
+use crate::node::{Node, TagNode};
+
+/// The children of a tag, split into one group per expected name, plus everything else.
+///
+/// Created by [`TagNode::destructure`].
+///
+/// # Example
+/// ```rust
+/// use xmltree::Document;
+///
+/// let src = "<book><title>Dune</title><author>Herbert</author><isbn>0441013597</isbn></book>";
+/// let doc = Document::parse_str(src).unwrap();
+///
+/// let parts = doc.root().destructure(&["title", "author", "price"]);
+/// assert_eq!(parts.first("title").unwrap().children().len(), 1);
+/// assert_eq!(parts.get("author").len(), 1);
+/// assert!(parts.get("price").is_empty());
+/// assert_eq!(parts.leftovers().len(), 1);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Destructured<'a, 'src> {
+    names: Vec<&'a str>,
+    groups: Vec<Vec<&'a TagNode<'src>>>,
+    leftovers: Vec<&'a Node<'src>>,
+}
+impl<'a, 'src> Destructured<'a, 'src> {
+    /// Returns the tags with the given name, in document order.
+    ///
+    /// Empty if there were none, or the name was not one of the expected names.
+    #[must_use]
+    pub fn get(&self, name: &str) -> &[&'a TagNode<'src>] {
+        match self.names.iter().position(|n| *n == name) {
+            Some(index) => &self.groups[index],
+            None => &[],
+        }
+    }
+
+    /// Returns the first tag with the given name.
+    #[must_use]
+    pub fn first(&self, name: &str) -> Option<&'a TagNode<'src>> {
+        self.get(name).first().copied()
+    }
+
+    /// Returns the groups of tags, in the same order as the expected names.
+    #[must_use]
+    pub fn groups(&self) -> &[Vec<&'a TagNode<'src>>] {
+        &self.groups
+    }
+
+    /// Returns the children that did not match any expected name, in document order.
+    ///
+    /// This includes text, comments and every other kind of node.
+    #[must_use]
+    pub fn leftovers(&self) -> &[&'a Node<'src>] {
+        &self.leftovers
+    }
+}
+
+impl<'src> TagNode<'src> {
+    /// Splits the children of this tag into a group for each of the given names, and leftovers.
+    ///
+    /// Names can have a prefix, as `prefix:local`, and are matched exactly.
+    /// If a name appears more than once, only its first group is filled.
+    ///
+    /// See [`Destructured`].
+    #[must_use]
+    pub fn destructure<'a>(&'a self, names: &[&'a str]) -> Destructured<'a, 'src> {
+        let split: Vec<_> = names
+            .iter()
+            .map(|name| match name.split_once(':') {
+                Some((prefix, local)) => (Some(prefix), local),
+                None => (None, *name),
+            })
+            .collect();
+
+        let mut groups = vec![vec![]; names.len()];
+        let mut leftovers = vec![];
+        for child in self.children() {
+            let group = match child {
+                Node::Child(tag) => split
+                    .iter()
+                    .position(|(prefix, local)| tag.name().equals(*prefix, local))
+                    .map(|index| (index, tag)),
+                _ => None,
+            };
+
+            match group {
+                Some((index, tag)) => groups[index].push(tag),
+                None => leftovers.push(child),
+            }
+        }
+
+        Destructured {
+            names: names.to_vec(),
+            groups,
+            leftovers,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Document;
+
+    #[test]
+    fn test_destructure() {
+        let src = r#"<entry xmlns:dc="urn:dc">
+            <dc:title>a</dc:title>
+            <!-- note -->
+            <link href="1" />
+            <title>plain</title>
+            <link href="2" />
+            text
+        </entry>"#;
+        let doc = Document::parse_str(src).unwrap();
+        let parts = doc.root().destructure(&["link", "dc:title", "link"]);
+
+        let hrefs: Vec<_> = parts
+            .get("link")
+            .iter()
+            .map(|t| t.get_attribute(None, "href").unwrap().value().text())
+            .collect();
+        assert_eq!(hrefs, ["1", "2"]);
+        assert!(parts.groups()[2].is_empty());
+
+        assert_eq!(parts.get("dc:title").len(), 1);
+        assert!(parts.get("title").is_empty());
+        assert_eq!(parts.leftovers().len(), 3);
+    }
+}
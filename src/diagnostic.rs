@@ -0,0 +1,117 @@
+//! Rich diagnostics for errors, through `miette`.
+//!
+//! The error's code, severity, location and related spans become a [`miette::Diagnostic`],
+//! so CLI tools can render them with any `miette` report handler.
+//!
+//! Errors only keep an excerpt around each location, so the source given to report handlers
+//! is made of those excerpts, and no lines of context are shown around them.
+use crate::{
+    StringSpan,
+    error::{ErrorContext, Excerpt, Severity, XmlError},
+};
+use miette::{
+    Diagnostic, LabeledSpan, MietteError, MietteSpanContents, SourceCode, SourceSpan, SpanContents,
+};
+
+impl Diagnostic for XmlError {
+    fn code<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
+        Some(Box::new(XmlError::code(self)))
+    }
+
+    fn severity(&self) -> Option<miette::Severity> {
+        Some(match self.severity {
+            Severity::Error => miette::Severity::Error,
+            Severity::Warning => miette::Severity::Warning,
+        })
+    }
+
+    fn source_code(&self) -> Option<&dyn SourceCode> {
+        if self.context.excerpt.text.is_empty() {
+            None
+        } else {
+            Some(&*self.context)
+        }
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        if self.context.excerpt.text.is_empty() {
+            return None;
+        }
+
+        let primary = LabeledSpan::new_primary_with_span(
+            Some(self.kind.to_string()),
+            source_span(&self.context.excerpt, &self.context.span),
+        );
+        let related = self.context.related.iter().map(|related| {
+            LabeledSpan::new_with_span(
+                Some(related.note.clone()),
+                source_span(&related.excerpt, &related.span),
+            )
+        });
+        Some(Box::new(std::iter::once(primary).chain(related)))
+    }
+}
+
+impl SourceCode for ErrorContext {
+    fn read_span<'a>(
+        &'a self,
+        span: &SourceSpan,
+        _context_lines_before: usize,
+        _context_lines_after: usize,
+    ) -> Result<Box<dyn SpanContents<'a> + 'a>, MietteError> {
+        let excerpt = std::iter::once(&self.excerpt)
+            .chain(self.related.iter().map(|related| &related.excerpt))
+            .find(|excerpt| {
+                let start = excerpt.text.start();
+                span.offset() >= start && span.offset() + span.len() <= start + excerpt.text.len()
+            })
+            .ok_or(MietteError::OutOfBounds)?;
+
+        Ok(Box::new(MietteSpanContents::new(
+            excerpt.text.as_str().as_bytes(),
+            SourceSpan::new(excerpt.text.start().into(), excerpt.text.len()),
+            excerpt.row - 1,
+            excerpt.col - 1,
+            1,
+        )))
+    }
+}
+
+/// Converts a span to a `miette` span, clamped to its excerpt so report handlers can always read it.
+fn source_span(excerpt: &Excerpt, span: &StringSpan) -> SourceSpan {
+    let (text_start, text_len) = (excerpt.text.start(), excerpt.text.len());
+    let start = span.start().clamp(text_start, text_start + text_len);
+    let len = span.len().min(text_start + text_len - start);
+    SourceSpan::new(start.into(), len)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Document;
+    use miette::Diagnostic;
+
+    #[test]
+    fn test_diagnostic() {
+        let err = Document::parse_str("<a>\n  <b></a>").unwrap_err();
+        assert_eq!(Diagnostic::code(&err).unwrap().to_string(), "XML0003");
+        assert_eq!(Diagnostic::severity(&err), Some(miette::Severity::Error));
+
+        let labels: Vec<_> = err.labels().unwrap().collect();
+        assert_eq!(labels.len(), 2);
+        assert!(labels[0].primary());
+        assert_eq!(labels[0].offset(), 9);
+        assert_eq!(labels[0].len(), 4);
+        assert_eq!(labels[1].label(), Some("Opening tag is here"));
+        assert_eq!(labels[1].offset(), 6);
+
+        let source = err.source_code().unwrap();
+        let contents = source.read_span(labels[1].inner(), 0, 0).unwrap();
+        assert_eq!(contents.data(), b"  <b></a>");
+        assert_eq!(contents.line(), 1);
+
+        // Errors without a source have nothing to point at
+        let err = crate::error::XmlError::from(std::io::Error::other("gone"));
+        assert!(err.source_code().is_none());
+        assert!(err.labels().is_none());
+    }
+}
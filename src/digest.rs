@@ -0,0 +1,211 @@
+//! Stable structural digests of subtrees, for change detection.
+use crate::node::{EntityDefinition, ExternalId, Node, OwnedTagNode, TagNode};
+
+/// 64-bit FNV-1a; simple, and stable across platforms and compiler versions, unlike `std::hash`.
+struct Fnv(u64);
+impl Fnv {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    /// Length-prefixed, so adjacent strings cannot run into each other
+    fn str(&mut self, value: &str) {
+        self.u64(value.len() as u64);
+        self.bytes(value.as_bytes());
+    }
+
+    fn kind(&mut self, kind: u8) {
+        self.bytes(&[kind]);
+    }
+
+    fn tag(tag: &TagNode<'_>) -> Self {
+        let mut hasher = Self::new();
+        hasher.kind(0);
+        hasher.str(&tag.name().to_string());
+        hasher.u64(tag.attributes().len() as u64);
+        for attr in tag.attributes() {
+            hasher.str(&attr.name().to_string());
+            hasher.str(attr.value().text());
+        }
+        hasher
+    }
+
+    fn leaf(&mut self, node: &Node<'_>) {
+        match node {
+            Node::Child(_) => {}
+            Node::Text(text) => {
+                self.kind(1);
+                self.str(text.text().text());
+            }
+            Node::Cdata(cdata) => {
+                self.kind(2);
+                self.str(cdata.content().text());
+            }
+            Node::Comment(comment) => {
+                self.kind(3);
+                self.str(comment.text().text());
+            }
+            Node::ProcessingInstruction(pi) => {
+                self.kind(4);
+                self.str(pi.target().text());
+                self.str(pi.content().map_or("", |c| c.text()));
+            }
+            Node::DocumentType(dtd) => {
+                self.kind(5);
+                self.str(dtd.name().text());
+                if let Some(external_id) = dtd.external_id() {
+                    self.external_id(external_id);
+                }
+                for entity in dtd.entities() {
+                    self.str(entity.name.text());
+                    match &entity.definition {
+                        EntityDefinition::EntityValue(value) => self.str(value.text()),
+                        EntityDefinition::ExternalId(external_id) => self.external_id(external_id),
+                    }
+                }
+            }
+        }
+    }
+
+    fn external_id(&mut self, external_id: &ExternalId<'_>) {
+        if let ExternalId::Public(public, _) = external_id {
+            self.str(public.text());
+        }
+        self.str(external_id.system().text());
+    }
+}
+
+struct Frame<'a, 'src> {
+    tag: &'a TagNode<'src>,
+    next: usize,
+    hasher: Fnv,
+}
+
+impl TagNode<'_> {
+    /// Computes a stable digest of this node and its descendants.
+    ///
+    /// The digest is Merkle-style: each element's digest covers its name, its attributes in order,
+    /// its leaf children, and the digests of its child elements. Spans are not included.
+    ///
+    /// Two subtrees with equal digests are almost certainly identical, so unchanged parts of two versions of a document
+    /// can be skipped by comparing the digests of matching elements, descending only where they differ.
+    ///
+    /// The digest is stable across platforms and versions of the crate, and can be stored.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{Document, NodePath};
+    ///
+    /// let a = Document::parse_str("<a><b>1</b><c>2</c></a>").unwrap();
+    /// let b = Document::parse_str("<a>\n  <b>1</b>\n  <c>3</c>\n</a>").unwrap();
+    /// assert_ne!(a.root().content_hash(), b.root().content_hash());
+    ///
+    /// // <b> is unchanged, <c> is not
+    /// let hash = |doc: &Document, i| doc.resolve_tag(&NodePath::root().child(i)).unwrap().content_hash();
+    /// assert_eq!(hash(&a, 0), hash(&b, 0));
+    /// assert_ne!(hash(&a, 1), hash(&b, 1));
+    /// ```
+    #[must_use]
+    pub fn content_hash(&self) -> u64 {
+        self.content_hash_inner(true)
+    }
+
+    /// Computes a stable digest of this node and its descendants, ignoring comments.
+    ///
+    /// See [`TagNode::content_hash`] for details.
+    #[must_use]
+    pub fn content_hash_without_comments(&self) -> u64 {
+        self.content_hash_inner(false)
+    }
+
+    fn content_hash_inner(&self, include_comments: bool) -> u64 {
+        let mut stack = vec![Frame {
+            tag: self,
+            next: 0,
+            hasher: Fnv::tag(self),
+        }];
+
+        loop {
+            let Some(frame) = stack.last_mut() else {
+                unreachable!("The root frame returns before the stack empties");
+            };
+
+            if let Some(child) = frame.tag.children().get(frame.next) {
+                frame.next += 1;
+                match child {
+                    Node::Child(tag) => stack.push(Frame {
+                        tag,
+                        next: 0,
+                        hasher: Fnv::tag(tag),
+                    }),
+                    Node::Comment(_) if !include_comments => {}
+                    leaf => frame.hasher.leaf(leaf),
+                }
+                continue;
+            }
+
+            let Some(Frame { hasher, .. }) = stack.pop() else {
+                unreachable!("Frame was just inspected");
+            };
+            let Some(parent) = stack.last_mut() else {
+                return hasher.0;
+            };
+            parent.hasher.kind(6);
+            parent.hasher.u64(hasher.0);
+        }
+    }
+}
+
+impl OwnedTagNode {
+    /// Computes a stable digest of this node and its descendants.
+    ///
+    /// Equal to the digest of the borrowed node it was created from. See [`TagNode::content_hash`] for details.
+    #[must_use]
+    pub fn content_hash(&self) -> u64 {
+        self.borrowed().content_hash()
+    }
+
+    /// Computes a stable digest of this node and its descendants, ignoring comments.
+    ///
+    /// See [`TagNode::content_hash`] for details.
+    #[must_use]
+    pub fn content_hash_without_comments(&self) -> u64 {
+        self.borrowed().content_hash_without_comments()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Document;
+
+    #[test]
+    fn test_content_hash() {
+        let a = Document::parse_str("<a x='1'><b>1</b><!-- c --><c><d /></c></a>").unwrap();
+        let b = Document::parse_str("<a x=\"1\">\n<b>1</b>\n<!-- c -->\n<c><d/></c></a>").unwrap();
+        let c = Document::parse_str("<a x='1'><b>1</b><c><d /></c></a>").unwrap();
+        let d = Document::parse_str("<a x='1'><c><d /></c><b>1</b></a>").unwrap();
+
+        assert_eq!(a.root().content_hash(), b.root().content_hash());
+        assert_eq!(a.root().content_hash(), a.root().to_owned().content_hash());
+        assert_ne!(a.root().content_hash(), c.root().content_hash());
+        assert_eq!(
+            a.root().content_hash_without_comments(),
+            c.root().content_hash_without_comments()
+        );
+        assert_ne!(c.root().content_hash(), d.root().content_hash());
+
+        // Pinned, so changes to the digest are caught
+        assert_eq!(c.root().content_hash(), 1_411_609_794_878_305_503);
+    }
+}
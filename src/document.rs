@@ -1,14 +1,51 @@
 use crate::{
-    NamedElement, StrSpan,
+    Metadata, NamedElement, Origin, StrSpan, XmlWriteOptions,
     error::{ErrorContext, XmlError, XmlErrorKind, XmlResult},
     node::{
-        CdataNode, DtdNode, Node, NodeAttribute, NodeName, OwnedNode, OwnedTagNode,
-        ProcessingInstructionNode, TagNode, TextNode,
+        AttributeDefault, CdataNode, CommentNode, DtdNode, NameInterner, Node, NodeAttribute,
+        NodeName, OwnedNode, OwnedTagNode, ProcessingInstructionNode, TagNode, TextNode,
     },
-    to_bin::{BinDecodeError, Decoder, Encoder, ToBinHandler},
+    parser::{
+        ParseConfig, WarningSink, check_char_references, misplaced_markup, suspicious_references,
+    },
+    to_bin::{BinDecodeError, Capabilities, Decoder, Encoder, ToBinHandler},
 };
 use xmlparser::{ElementEnd, Token};
 
+/// Splits a name such as `prefix:local` into its prefix and local name.
+fn split_qualified_name(name: StrSpan<'_>) -> (Option<StrSpan<'_>>, StrSpan<'_>) {
+    match name.text().find(':') {
+        Some(i) => (Some(name.slice(0..i)), name.slice(i + 1..name.len())),
+        None => (None, name),
+    }
+}
+
+/// Splits a leading UTF-8 byte order mark off the source, returning the rest and whether there was one.
+pub(crate) fn split_bom(src: &str) -> (&str, bool) {
+    match src.strip_prefix('\u{FEFF}') {
+        Some(rest) => (rest, true),
+        None => (src, false),
+    }
+}
+
+/// Splits a leading byte order mark off a source to be parsed, failing if another one follows it.
+///
+/// The tokenizer skips a byte order mark at the start of its input, so a second one would otherwise be accepted.
+fn split_source_bom(src: &str) -> XmlResult<(&str, bool)> {
+    let (rest, bom) = split_bom(src);
+    if rest.starts_with('\u{FEFF}') {
+        let span = StrSpan::new(&rest[..'\u{FEFF}'.len_utf8()], 0);
+        bail!(
+            rest,
+            &span,
+            XmlErrorKind::InvalidStructure(
+                "A byte order mark can only appear once, at the start of the document".to_string()
+            )
+        );
+    }
+    Ok((rest, bom))
+}
+
 #[derive(PartialEq, Debug)]
 enum ParserState {
     Prolog,
@@ -30,23 +67,34 @@ enum ParserState {
 /// - Names of nodes and properties can have a namespace prefix
 /// - Node attributes can have duplicates, but `Node::get_attribute` will only return the last one defined
 /// - The parser will not attempt to recover from invalid closing tags, or unclosed tags.
+/// - Documents are `Send` and `Sync`; see [`SharedDocument`](crate::SharedDocument) to query one from many threads.
 #[derive(Debug, Clone, PartialEq)]
 pub struct Document<'src> {
     src: Option<&'src str>,
 
+    /// The source started with a UTF-8 byte order mark.
+    bom: bool,
+
     declaration: Option<DeclarationNode<'src>>,
     prolog: Vec<Node<'src>>,
     root: TagNode<'src>,
     epilog: Vec<Node<'src>>,
+
+    metadata: Metadata,
 }
 impl<'src> Document<'src> {
     const HEADER_SOURCED: &'static [u8] = b"XML1";
     const HEADER_UNSOURCED: &'static [u8] = b"XML2";
+    const HEADER_STRING_TABLE: &'static [u8] = b"XML3";
+    const HEADER_METADATA: &'static [u8] = b"XMLM";
 
     /// Creates a new document from the given source string.
     ///
     /// Source string must live at least as long as the document.  
     ///
+    /// A leading UTF-8 byte order mark is skipped, and recorded; see [`Document::has_bom`].
+    /// Spans are then offsets into the source after the byte order mark, which [`Document::source`] returns.
+    ///
     /// # Errors
     /// Returns errors if the XML is invalid
     ///
@@ -62,6 +110,131 @@ impl<'src> Document<'src> {
         Self::parse(source)
     }
 
+    /// Parses a source containing several root-level elements, such as a concatenated XML log,
+    /// or a stream of documents separated only by whitespace, returning a document for each one.
+    ///
+    /// Comments and processing instructions between two elements belong to the prolog of the one after them,
+    /// and any after the last element to its epilog. Only the first document can have a declaration or DOCTYPE.
+    ///
+    /// Every document keeps the whole source, so spans and error positions are relative to it.
+    ///
+    /// # Errors
+    /// Returns errors if any of the elements is invalid, or there are none
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::Document;
+    ///
+    /// let src = "<event id='1' />\n<!-- retry -->\n<event id='2'><ok /></event>\n";
+    /// let events = Document::parse_forest(src).unwrap();
+    /// assert_eq!(events.len(), 2);
+    /// assert_eq!(events[1].root().children().len(), 1);
+    /// assert_eq!(events[1].prolog().len(), 1);
+    /// ```
+    pub fn parse_forest(src: &'src str) -> XmlResult<Vec<Self>> {
+        let (src, bom) = split_source_bom(src)?;
+        let config = ParseConfig {
+            allow_missing_root: true,
+            stop_after_root: true,
+            ..ParseConfig::default()
+        };
+
+        let mut documents: Vec<Self> = vec![];
+        let mut stack = vec![];
+        let mut offset = 0;
+        while documents.is_empty() || !src[offset..].trim().is_empty() {
+            // Later documents are read as content, which allows more than one element at the top level
+            let tokenizer = if documents.is_empty() {
+                xmlparser::Tokenizer::from(src)
+            } else {
+                xmlparser::Tokenizer::from_fragment(src, offset..src.len())
+            };
+            let mut document = Self::parse_tokens(
+                src,
+                tokenizer,
+                &mut stack,
+                config,
+                &mut WarningSink::default(),
+            )?;
+
+            if !document.has_root() {
+                // Only comments and processing instructions were left
+                let Some(last) = documents.last_mut() else {
+                    bail!(src, XmlErrorKind::UnexpectedEof);
+                };
+                last.epilog = document.prolog;
+                break;
+            }
+
+            let root = document.root.span();
+            offset = root.start() + root.len();
+            document.bom = bom && documents.is_empty();
+            documents.push(document);
+        }
+
+        Ok(documents)
+    }
+
+    /// Checks that the nodes around the root follow the ordering rules of the XML spec:
+    /// - At most one DTD is allowed, and only before the root
+    /// - Only comments and processing instructions are allowed otherwise; no text or CDATA sections
+    ///
+    /// Parsed source always follows these rules, but documents that were edited, built by hand,
+    /// or decoded from the binary format may not, and would be written as invalid XML.
+    ///
+    /// # Errors
+    /// Returns an [`XmlErrorKind::InvalidStructure`] error pointing at the first misplaced node
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{Document, node::{CdataNode, Node}};
+    ///
+    /// let mut doc = Document::parse_str("<!DOCTYPE a><a />").unwrap();
+    /// assert!(doc.validate_prolog().is_ok());
+    ///
+    /// doc.epilog_mut().push(Node::Cdata(CdataNode::detached("text")));
+    /// assert!(doc.validate_prolog().is_err());
+    /// ```
+    pub fn validate_prolog(&self) -> XmlResult<()> {
+        let src = self.src.unwrap_or_default();
+
+        let mut seen_dtd = false;
+        for node in &self.prolog {
+            match node {
+                Node::DocumentType(_) if seen_dtd => bail!(
+                    src,
+                    node.span(),
+                    XmlErrorKind::InvalidStructure("A document can only have one DTD".to_string())
+                ),
+                Node::DocumentType(_) => seen_dtd = true,
+                Node::Comment(_) | Node::ProcessingInstruction(_) => {}
+                _ => bail!(
+                    src,
+                    node.span(),
+                    XmlErrorKind::InvalidStructure(format!(
+                        "{} is not allowed before the root element",
+                        node.kind_name()
+                    ))
+                ),
+            }
+        }
+
+        for node in &self.epilog {
+            if !matches!(node, Node::Comment(_) | Node::ProcessingInstruction(_)) {
+                bail!(
+                    src,
+                    node.span(),
+                    XmlErrorKind::InvalidStructure(format!(
+                        "{} is not allowed after the root element",
+                        node.kind_name()
+                    ))
+                );
+            }
+        }
+
+        Ok(())
+    }
+
     /// Returns the original source string of the document, if it was provided.
     #[must_use]
     pub fn source(&self) -> Option<&'src str> {
@@ -81,6 +254,64 @@ impl<'src> Document<'src> {
         &self.prolog
     }
 
+    /// Creates a document with no root, holding only the nodes around where the root would be.
+    ///
+    /// Meant for recovery tools, such as ones salvaging the comments of a truncated file.
+    /// Such a document is not valid XML; see [`Document::has_root`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{Document, node::{CommentNode, Node}};
+    ///
+    /// let doc = Document::without_root(vec![Node::Comment(CommentNode::detached("salvaged"))], vec![]);
+    /// assert!(!doc.has_root());
+    /// assert!(doc.require_root().is_err());
+    /// assert_eq!(doc.to_xml(None).unwrap(), "<!--salvaged-->\n");
+    /// ```
+    #[must_use]
+    pub fn without_root(prolog: Vec<Node<'src>>, epilog: Vec<Node<'src>>) -> Self {
+        Self {
+            src: None,
+            bom: false,
+            declaration: None,
+            prolog,
+            root: Self::missing_root(),
+            epilog,
+            metadata: Metadata::default(),
+        }
+    }
+
+    /// Placeholder used in place of the root of a document without one; a real tag never has an empty name
+    pub(crate) fn missing_root() -> TagNode<'src> {
+        TagNode::new(None, "")
+    }
+
+    /// Returns false if the document has no root element.
+    ///
+    /// Parsed documents always have a root, unless parsed with [`Parser::with_allow_missing_root`](crate::Parser::with_allow_missing_root);
+    /// otherwise, only [`Document::without_root`] creates documents without one.
+    /// For those, [`Document::root`] returns an empty placeholder tag, which is never written out.
+    #[must_use]
+    pub fn has_root(&self) -> bool {
+        !self.root.name().local().is_empty()
+    }
+
+    /// Returns an error if the document has no root element; see [`Document::has_root`].
+    ///
+    /// # Errors
+    /// Returns an [`XmlErrorKind::InvalidStructure`] error if the root is missing
+    pub fn require_root(&self) -> XmlResult<()> {
+        if !self.has_root() {
+            let src = self.src.unwrap_or_default();
+            bail!(
+                src,
+                XmlErrorKind::InvalidStructure("Document has no root element".to_string())
+            );
+        }
+
+        Ok(())
+    }
+
     /// Returns the root node of the document.
     #[must_use]
     pub fn root(&self) -> &TagNode<'src> {
@@ -94,12 +325,148 @@ impl<'src> Document<'src> {
         &self.epilog
     }
 
+    /// Returns true if the source started with a UTF-8 byte order mark.
+    ///
+    /// The byte order mark is not part of [`Document::source`], and is only written back
+    /// with [`XmlWriteOptions::with_preserve_bom`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{Document, XmlWriteOptions};
+    ///
+    /// let doc = Document::parse_str("\u{FEFF}<test />").unwrap();
+    /// assert!(doc.has_bom());
+    /// assert_eq!(doc.root().span().start(), 0);
+    ///
+    /// let options = XmlWriteOptions::default().with_preserve_bom(true);
+    /// assert_eq!(doc.to_xml_with_options(&options).unwrap(), "\u{FEFF}<test />\n");
+    /// ```
+    #[must_use]
+    pub fn has_bom(&self) -> bool {
+        self.bom
+    }
+
+    /// Returns the metadata side table of the document. See [`Metadata`].
+    #[must_use]
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Returns a mutable reference to the metadata side table of the document.
+    #[must_use]
+    pub fn metadata_mut(&mut self) -> &mut Metadata {
+        &mut self.metadata
+    }
+
+    /// Returns a mutable reference to the prolog of the document.
+    ///
+    /// New nodes can be created with constructors such as [`TagNode::detached`].
+    #[must_use]
+    pub fn prolog_mut(&mut self) -> &mut Vec<Node<'src>> {
+        &mut self.prolog
+    }
+
+    /// Returns a mutable reference to the root node of the document.
+    ///
+    /// New nodes can be created with constructors such as [`TagNode::detached`].
+    #[must_use]
+    pub fn root_mut(&mut self) -> &mut TagNode<'src> {
+        &mut self.root
+    }
+
+    /// Returns a mutable reference to the epilog of the document.
+    ///
+    /// New nodes can be created with constructors such as [`TagNode::detached`].
+    #[must_use]
+    pub fn epilog_mut(&mut self) -> &mut Vec<Node<'src>> {
+        &mut self.epilog
+    }
+
+    /// Adds the attribute defaults declared by `<!ATTLIST>` declarations in the DTD to every tag that does not set them.
+    ///
+    /// The added attributes are flagged with [`NodeAttribute::is_defaulted`], and their span is their definition in the DTD.
+    /// As in the XML spec, the first definition of an attribute for an element is used. Only the internal subset is read;
+    /// see [`DtdNode::attribute_defaults`].
+    ///
+    /// Returns the number of attributes added.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{Document, XmlWriteOptions, node::Node};
+    ///
+    /// let mut doc = Document::parse_str(r#"<!DOCTYPE list [
+    ///     <!ATTLIST item status CDATA "open">
+    /// ]><list><item /><item status="closed" /></list>"#).unwrap();
+    /// assert_eq!(doc.apply_dtd_defaults(), 1);
+    ///
+    /// let xml = doc.root().to_xml_with_options(&XmlWriteOptions::default().with_minify(true)).unwrap();
+    /// assert_eq!(xml, "<list><item status=\"open\" /><item status=\"closed\" /></list>");
+    ///
+    /// let Node::Child(item) = &doc.root().children()[0] else { unreachable!() };
+    /// assert!(item.get_attribute(None, "status").unwrap().is_defaulted());
+    /// ```
+    pub fn apply_dtd_defaults(&mut self) -> usize {
+        let mut defaults: Vec<AttributeDefault<'src>> = vec![];
+        for node in &self.prolog {
+            let Node::DocumentType(dtd) = node else {
+                continue;
+            };
+            for default in dtd.attribute_defaults() {
+                let declared = defaults.iter().any(|d| {
+                    d.element.text() == default.element.text()
+                        && d.name.text() == default.name.text()
+                });
+                if !declared {
+                    defaults.push(default);
+                }
+            }
+        }
+        if defaults.is_empty() {
+            return 0;
+        }
+
+        let mut added = 0;
+        let mut stack = vec![&mut self.root];
+        while let Some(tag) = stack.pop() {
+            for default in &defaults {
+                let (prefix, local) = split_qualified_name(default.element);
+                if !tag.name().equals(prefix.map(|p| p.text()), local.text()) {
+                    continue;
+                }
+
+                let (prefix, local) = split_qualified_name(default.name);
+                if tag
+                    .get_attribute(prefix.map(|p| p.text()), local.text())
+                    .is_none()
+                {
+                    let attribute = NodeAttribute::new(prefix, local, default.value)
+                        .with_span(default.span)
+                        .with_defaulted();
+                    tag.push_attribute(attribute);
+                    added += 1;
+                }
+            }
+
+            stack.extend(
+                tag.children_mut()
+                    .iter_mut()
+                    .filter_map(|child| match child {
+                        Node::Child(child) => Some(child),
+                        _ => None,
+                    }),
+            );
+        }
+
+        added
+    }
+
     /// Write this document as a flat binary format.
     ///
     /// If src is provided, it will be written as a header before the document.  
     /// All strings will be stored as references to the source string, making deserialization faster.
     ///
-    /// However, if you have modified the document after parsing and provide a source string, deserialization will fail.
+    /// If the document was modified after parsing so that some strings no longer point into the source,
+    /// the strings are stored inline instead, and no source header is written.
     ///
     /// # Errors
     /// Returns errors if the encoding fails
@@ -190,60 +557,364 @@ impl<'src> Document<'src> {
         writer: &mut W,
         tab_char: Option<&str>,
     ) -> std::io::Result<()> {
-        crate::to_xml::write_xml(writer, self, tab_char)
+        let options = XmlWriteOptions::from_tab_char(tab_char);
+        self.to_xml_with_writer_and_options(writer, &options)
+    }
+
+    /// Create a formatted XML string from this document, using the given options.
+    ///
+    /// See [`XmlWriteOptions`] for the available options.
+    ///
+    /// # Errors
+    /// Can fail if a string in the document cannot be entity encoded,
+    /// or contains characters rejected by [`XmlWriteOptions::control_chars`].
+    pub fn to_xml_with_options(&self, options: &XmlWriteOptions) -> std::io::Result<String> {
+        let mut buffer = vec![];
+        self.to_xml_with_writer_and_options(&mut buffer, options)?;
+
+        let buffer = String::from_utf8(buffer).map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Failed to convert to UTF-8: {e}"),
+            )
+        })?;
+        Ok(buffer)
+    }
+
+    /// Write this document as a formatted XML string using the given writer and options.
+    ///
+    /// See [`Document::to_xml_with_options`] for more details.
+    ///
+    /// # Errors
+    /// Can fail if a string in the document cannot be entity encoded,
+    /// or contains characters rejected by [`XmlWriteOptions::control_chars`].
+    pub fn to_xml_with_writer_and_options<W: std::io::Write>(
+        &self,
+        writer: &mut W,
+        options: &XmlWriteOptions,
+    ) -> std::io::Result<()> {
+        crate::to_xml::write_xml(writer, self, options)
+    }
+
+    /// Write each child of the root as a minified record on its own line, for log processors and line-based tools.
+    ///
+    /// The root tag and the nodes around it are not written, and [`XmlWriteOptions::indent`] is ignored.
+    /// Line breaks inside a record are written as character references; see [`XmlWriteOptions::minify`].
+    ///
+    /// # Errors
+    /// Can fail if a string in the document cannot be entity encoded,
+    /// or contains characters rejected by [`XmlWriteOptions::control_chars`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{Document, XmlWriteOptions};
+    ///
+    /// let doc = Document::parse_str("<log>\n  <event level=\"info\">\n    <msg>started</msg>\n  </event>\n  <event level=\"warn\" />\n</log>").unwrap();
+    ///
+    /// let mut lines = vec![];
+    /// doc.to_xml_records_with_writer(&mut lines, &XmlWriteOptions::default()).unwrap();
+    /// assert_eq!(
+    ///     String::from_utf8(lines).unwrap(),
+    ///     "<event level=\"info\"><msg>started</msg></event>\n<event level=\"warn\" />\n"
+    /// );
+    /// ```
+    pub fn to_xml_records_with_writer<W: std::io::Write>(
+        &self,
+        writer: &mut W,
+        options: &XmlWriteOptions,
+    ) -> std::io::Result<()> {
+        crate::to_xml::write_records(writer, &self.root, options)
     }
 
     /// Returns an owned version of this document, with no source span information.
+    ///
+    /// Repeated element and attribute names share one allocation each. See [`NameInterner`].
+    #[must_use]
     pub fn to_owned(&self) -> OwnedDocument {
+        self.to_owned_in(&mut NameInterner::new())
+    }
+
+    /// Returns an owned version of this document, sharing names through the given interner.
+    ///
+    /// Reusing one interner across several documents also shares names between them.
+    #[must_use]
+    pub fn to_owned_in(&self, interner: &mut NameInterner) -> OwnedDocument {
         OwnedDocument {
+            bom: self.bom,
             declaration: self.declaration.as_ref().map(DeclarationNode::to_owned),
-            prolog: self.prolog.iter().map(Node::to_owned).collect(),
-            root: self.root.to_owned(),
-            epilog: self.epilog.iter().map(Node::to_owned).collect(),
+            prolog: OwnedNode::from_nodes_in(&self.prolog, interner),
+            root: self.root.to_owned_in(interner),
+            epilog: OwnedNode::from_nodes_in(&self.epilog, interner),
+            metadata: self.metadata.clone(),
         }
     }
 
-    #[expect(clippy::too_many_lines, reason = "State machine; what did you expect")]
+    /// Re-tokenizes the given range of the source string, and returns a fresh element parsed from it.
+    ///
+    /// The span must cover exactly one element, such as the span of an existing [`TagNode`].
+    /// Spans in the returned subtree are relative to the full source, like the rest of the document.
+    ///
+    /// This allows a single subtree to be refreshed without reparsing the whole document.
+    ///
+    /// # Errors
+    /// Returns errors if the document has no source, if the span is out of bounds,
+    /// or if it does not contain a single well-formed element.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::Document;
+    /// let src = "<test><test2>test</test2></test>";
+    ///
+    /// let doc = Document::parse_str(src).unwrap();
+    /// let node = doc.root().children()[0].span();
+    ///
+    /// let fresh = doc.reparse_node(node).unwrap();
+    /// assert_eq!(fresh.name(), "test2");
+    /// ```
+    pub fn reparse_node(&self, span: &StrSpan<'_>) -> XmlResult<TagNode<'src>> {
+        let Some(src) = self.src else {
+            bail!(
+                "",
+                *span,
+                msg = "Cannot reparse a node in a document with no source"
+            );
+        };
+
+        let range = span.start()..span.start() + span.len();
+        let Some(text) = src.get(range.clone()) else {
+            bail!(src, *span, msg = "Span is outside of the document source");
+        };
+
+        // Leading whitespace would be tokenized as text outside of the element
+        let start = range.start + (text.len() - text.trim_start().len());
+        let end = range.start + text.trim_end().len();
+        Self::parse_element(src, start..end.max(start))
+    }
+
+    /// Parses the single element at the given range of the source, keeping offsets into the whole source.
+    pub(crate) fn parse_element(
+        src: &'src str,
+        range: std::ops::Range<usize>,
+    ) -> XmlResult<TagNode<'src>> {
+        let span = StrSpan::new(&src[range.clone()], range.start);
+        let tokenizer = xmlparser::Tokenizer::from_fragment(src, range);
+
+        let fragment = Self::parse_tokens(
+            src,
+            tokenizer,
+            &mut vec![],
+            ParseConfig::default(),
+            &mut WarningSink::default(),
+        )?;
+        if !fragment.prolog.is_empty() || !fragment.epilog.is_empty() {
+            bail!(src, span, msg = "Span must contain exactly one element");
+        }
+
+        Ok(fragment.root)
+    }
+
     fn parse(src: &'src str) -> XmlResult<Self> {
-        let mut tokenizer = xmlparser::Tokenizer::from(src);
+        Self::parse_with(
+            src,
+            &mut vec![],
+            ParseConfig::default(),
+            &mut WarningSink::default(),
+        )
+    }
+
+    /// Parses a whole document, skipping and recording a leading byte order mark.
+    pub(crate) fn parse_with(
+        src: &'src str,
+        stack: &mut Vec<TagNode<'src>>,
+        config: ParseConfig,
+        warnings: &mut WarningSink,
+    ) -> XmlResult<Self> {
+        let (src, bom) = split_source_bom(src)?;
+        let tokenizer = xmlparser::Tokenizer::from(src);
+        let mut document = Self::parse_tokens(src, tokenizer, stack, config, warnings)?;
+        document.bom = bom;
+        Ok(document)
+    }
+
+    /// Parses a document, collecting every problem found instead of stopping at the first; meant for fuzzing and batch linting.
+    ///
+    /// The problems include the warnings described in [`Parser::with_warning_handler`](crate::Parser::with_warning_handler),
+    /// the error that stopped the parser, if any, and the problems found by [`Document::validate_prolog`], in the order found.
+    ///
+    /// If parsing fails after the root element has started, the tree parsed so far is salvaged: tags still open are closed
+    /// where the error was found, and anything after the root is dropped. No document is returned only if nothing usable was found.
+    /// The tree is complete if none of the problems are errors; see [`XmlError::is_error`].
+    ///
+    /// Like [`Document::parse_str`], this never panics or recurses, whatever the input.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{Document, error::ErrorCode};
+    ///
+    /// let (doc, errors) = Document::parse_all_errors("<a x='1' x='2'><b>text</b><c>");
+    /// let codes: Vec<_> = errors.iter().map(|e| e.code()).collect();
+    /// assert_eq!(codes, [ErrorCode::DuplicateAttribute, ErrorCode::UnclosedTag]);
+    ///
+    /// let doc = doc.unwrap();
+    /// assert_eq!(doc.root().children().len(), 2);
+    ///
+    /// let (doc, errors) = Document::parse_all_errors("<!-- no root -->");
+    /// assert!(doc.is_none());
+    /// assert_eq!(errors[0].code(), ErrorCode::UnexpectedEof);
+    /// ```
+    #[must_use]
+    pub fn parse_all_errors(src: &'src str) -> (Option<Self>, Vec<XmlError>) {
+        let (src, bom) = match split_source_bom(src) {
+            Ok(split) => split,
+            Err(error) => return (None, vec![error]),
+        };
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let mut warnings = WarningSink::new(move |warning| {
+            let _ = sender.send(warning);
+        });
 
-        let mut state = ParserState::Prolog;
         let mut stack = vec![];
+        let tokenizer = xmlparser::Tokenizer::from(src);
+        let result = Self::parse_tokens(
+            src,
+            tokenizer,
+            &mut stack,
+            ParseConfig::default(),
+            &mut warnings,
+        );
+        drop(warnings);
+
+        let mut errors: Vec<XmlError> = receiver.try_iter().collect();
+        let mut document = match result {
+            Ok(document) => Some(document),
+            Err(error) => {
+                errors.push(error);
+                Self::salvage(src, stack)
+            }
+        };
+        if let Some(document) = &mut document {
+            document.bom = bom;
+        }
+
+        if let Some(document) = &document
+            && let Err(error) = document.validate_prolog()
+        {
+            errors.push(error);
+        }
+        (document, errors)
+    }
+
+    /// Fails on the first entity reference in raw text that would be reported as suspicious; see [`Parser::with_strict`](crate::Parser::with_strict).
+    fn check_references_strict(src: &str, text: StrSpan<'_>, entities: &[&str]) -> XmlResult<()> {
+        match suspicious_references(text, entities).next() {
+            Some(span) => bail!(
+                src,
+                &span,
+                XmlErrorKind::SuspiciousEntity(span.text().to_string())
+            ),
+            None => Ok(()),
+        }
+    }
+
+    /// Builds a document from the tags left open by a failed parse, closing them all; see [`Document::parse_all_errors`].
+    fn salvage(src: &'src str, mut stack: Vec<TagNode<'src>>) -> Option<Self> {
+        let root_start = stack.first()?.span().start();
+        while stack.len() > 1 {
+            let node = stack.pop()?;
+            stack.last_mut()?.push_child(Node::Child(node));
+        }
+        let root = stack.pop()?;
+
+        // The prolog was parsed before the failure, so parsing it alone succeeds, with the same offsets
+        let config = ParseConfig {
+            allow_missing_root: true,
+            ..ParseConfig::default()
+        };
+        let tokenizer = xmlparser::Tokenizer::from(&src[..root_start]);
+        let prolog = Self::parse_tokens(
+            src,
+            tokenizer,
+            &mut vec![],
+            config,
+            &mut WarningSink::default(),
+        );
+        let (declaration, prolog) = match prolog {
+            Ok(document) => (document.declaration, document.prolog),
+            Err(_) => (None, vec![]),
+        };
+
+        Some(Self {
+            src: Some(src),
+            bom: false,
+            declaration,
+            prolog,
+            root,
+            epilog: vec![],
+            metadata: Metadata::default(),
+        })
+    }
+
+    #[expect(clippy::too_many_lines, reason = "State machine; what did you expect")]
+    pub(crate) fn parse_tokens(
+        src: &'src str,
+        mut tokenizer: xmlparser::Tokenizer<'src>,
+        stack: &mut Vec<TagNode<'src>>,
+        config: ParseConfig,
+        warnings: &mut WarningSink,
+    ) -> XmlResult<Self> {
+        let mut state = ParserState::Prolog;
+
+        // Entities declared by the DTD, for reporting undeclared references; only kept when warnings are wanted
+        let mut entities: Vec<&'src str> = vec![];
+        stack.clear();
 
         let mut prolog = vec![];
         let mut epilog = vec![];
         let mut declaration = None;
 
-        loop {
+        // Restricted characters can be referred to in XML 1.1 documents
+        let mut xml11 = false;
+
+        // End of the last token read, where a token the tokenizer rejects begins, after any whitespace
+        let mut last_end = 0;
+
+        let root = loop {
             // Get the next token
             let Some(next) = tokenizer.next() else {
-                let root = match stack.len() {
+                break match stack.len() {
+                    0 if config.allow_missing_root => Self::missing_root(),
                     0 => bail!(src, XmlErrorKind::UnexpectedEof),
                     1 => stack.pop().unwrap(),
                     _ => {
-                        let last: TagNode = stack.pop().unwrap();
-                        bail!(
-                            src,
-                            last.span(),
-                            XmlErrorKind::UnclosedTag(last.name().to_string())
+                        // Left open, so the tree can be salvaged
+                        let last = &stack[stack.len() - 1];
+                        let error = XmlError::new(
+                            XmlErrorKind::UnclosedTag(last.name().to_string()),
+                            ErrorContext::new(src, *last.span()),
                         );
+                        return Err(error.with_related(
+                            src,
+                            StrSpan::end(src),
+                            "Document ends here",
+                        ));
                     }
                 };
-
-                return Ok(Self {
-                    src: Some(src),
-                    declaration,
-                    prolog,
-                    root,
-                    epilog,
-                });
             };
             let next = match next {
                 Ok(token) => token,
                 Err(e) => {
+                    let around_root = matches!(state, ParserState::Prolog | ParserState::Epilog);
+                    if config.strict
+                        && around_root
+                        && let Some(error) =
+                            misplaced_markup(src, last_end, state == ParserState::Epilog)
+                    {
+                        return Err(error);
+                    }
                     bail!(src, XmlErrorKind::Xml(e));
                 }
             };
+            last_end = next.span().end();
 
             //
             // At this point, next is significant.
@@ -258,7 +929,9 @@ impl<'src> Document<'src> {
                         state = ParserState::TagAttributes;
                     }
 
-                    Token::Comment { text, .. } => prolog.push(Node::Comment(text.into())),
+                    Token::Comment { text, span } => {
+                        prolog.push(Node::Comment(CommentNode::new(span, text)));
+                    }
 
                     Token::Declaration {
                         version,
@@ -271,6 +944,7 @@ impl<'src> Document<'src> {
                             bail!(src, span, XmlErrorKind::DeclarationNotFirst);
                         }
 
+                        xml11 = version.as_str() == "1.1";
                         declaration = Some(
                             DeclarationNode::new(version, encoding, standalone).with_span(span),
                         );
@@ -287,14 +961,32 @@ impl<'src> Document<'src> {
 
                     Token::EmptyDtd { .. } | Token::DtdStart { .. } => {
                         let node = DtdNode::parse(next, &mut tokenizer, src)?;
+                        last_end = node.span().start() + node.span().len();
+                        if warnings.is_active() && !prolog.is_empty() {
+                            warnings.warn(src, *node.span(), XmlErrorKind::LateDoctype);
+                        }
+                        if warnings.is_active() || config.strict {
+                            entities.extend(node.entities().iter().map(|e| e.name.text()));
+                        }
                         prolog.push(Node::DocumentType(node));
                     }
 
                     Token::Cdata { text, span } => {
+                        if config.strict {
+                            let message = "CDATA is not allowed before the root element";
+                            bail!(
+                                src,
+                                &span,
+                                XmlErrorKind::InvalidStructure(message.to_string())
+                            );
+                        }
                         let node = CdataNode::new(span, text);
                         prolog.push(Node::Cdata(node));
                     }
 
+                    // Only seen when tokenizing resumed after a DTD; see `DtdNode::parse`
+                    Token::Text { text } if text.trim().is_empty() => {}
+
                     _ => {
                         let span = next.span();
                         bail!(
@@ -325,10 +1017,46 @@ impl<'src> Document<'src> {
                             );
                         };
 
+                        if warnings.is_active() || config.strict {
+                            let prefix = attr.name().prefix().map(StrSpan::text);
+                            let local = attr.name().local().text();
+                            let first = node
+                                .attributes()
+                                .iter()
+                                .find(|a| a.name().equals(prefix, local));
+                            if let Some(first) = first {
+                                let kind =
+                                    XmlErrorKind::DuplicateAttribute(attr.name().to_string());
+                                if config.strict {
+                                    let error =
+                                        XmlError::new(kind, ErrorContext::new(src, *attr.span()));
+                                    return Err(error.with_related(
+                                        src,
+                                        *first.span(),
+                                        "First defined here",
+                                    ));
+                                }
+                                warnings.warn_related(
+                                    src,
+                                    *attr.span(),
+                                    kind,
+                                    *first.span(),
+                                    "First defined here",
+                                );
+                            }
+                            if config.strict {
+                                Self::check_references_strict(src, *attr.value(), &entities)?;
+                            }
+                            warnings.check_references(src, *attr.value(), &entities);
+                        }
+                        if config.strict_chars {
+                            check_char_references(src, *attr.value(), xml11)?;
+                        }
+
                         node.push_attribute(attr);
                     }
 
-                    Token::Comment { text, .. } => {
+                    Token::Comment { text, span } => {
                         let Some(node) = stack.last_mut() else {
                             let span = next.span();
                             bail!(
@@ -338,7 +1066,7 @@ impl<'src> Document<'src> {
                             );
                         };
 
-                        node.push_child(Node::Comment(text.into()));
+                        node.push_child(Node::Comment(CommentNode::new(span, text)));
                     }
 
                     Token::ElementEnd {
@@ -360,6 +1088,9 @@ impl<'src> Document<'src> {
                         node.extend_span(&next.span().into(), src);
 
                         let Some(parent) = stack.last_mut() else {
+                            if config.stop_after_root {
+                                break node;
+                            }
                             state = ParserState::Epilog;
                             stack.push(node);
                             continue;
@@ -391,6 +1122,13 @@ impl<'src> Document<'src> {
                         span,
                         ..
                     } => {
+                        // The root is at depth 0, so the new tag is at the current stack length
+                        if let Some(max_depth) = config.max_depth
+                            && stack.len() > max_depth
+                        {
+                            bail!(src, &span, XmlErrorKind::DepthLimitExceeded(max_depth));
+                        }
+
                         stack.push(TagNode::new(maybe_empty(prefix), local).with_span(span));
                         state = ParserState::TagAttributes;
                     }
@@ -411,20 +1149,29 @@ impl<'src> Document<'src> {
                             bail!(src, span, msg = "Bug; Cannot apply text; stack is empty!");
                         };
 
-                        // Translate the reference to a source reference
-                        let start = text.start();
-                        let text = src[start..text.end()].trim();
-                        if text.is_empty() {
+                        // Translate the reference to a source reference, starting after any leading whitespace
+                        let raw = &src[text.start()..text.end()];
+                        let trimmed = raw.trim();
+                        if trimmed.is_empty() {
                             continue;
                         }
 
-                        let text = StrSpan::new(text, start);
+                        let start = text.start() + (raw.len() - raw.trim_start().len());
+                        let text = StrSpan::new(trimmed, start);
+                        if config.strict {
+                            Self::check_references_strict(src, text, &entities)?;
+                        }
+                        warnings.check_references(src, text, &entities);
+                        if config.strict_chars {
+                            check_char_references(src, text, xml11)?;
+                        }
+
                         let span = next.span();
                         let text = TextNode::new(span, text);
                         node.push_child(Node::Text(text));
                     }
 
-                    Token::Comment { text, .. } => {
+                    Token::Comment { text, span } => {
                         let Some(node) = stack.last_mut() else {
                             let span = next.span();
                             bail!(
@@ -434,7 +1181,7 @@ impl<'src> Document<'src> {
                             );
                         };
 
-                        node.push_child(Node::Comment(text.into()));
+                        node.push_child(Node::Comment(CommentNode::new(span, text)));
                     }
 
                     Token::ProcessingInstruction {
@@ -464,21 +1211,30 @@ impl<'src> Document<'src> {
                             bail!(src, span, msg = "Bug; Cannot close tag; stack is empty!");
                         };
 
-                        node.extend_span(&next.span().into(), src);
-
                         let name = NodeName::new(maybe_empty(prefix), local);
                         if node.name() != &name {
-                            let span = next.span();
-                            bail!(
+                            let error = XmlError::new(
+                                XmlErrorKind::UnclosedTag(node.name().to_string()),
+                                ErrorContext::new(src, next.span().into()),
+                            )
+                            .with_related(
                                 src,
-                                span,
-                                XmlErrorKind::UnclosedTag(node.name().to_string())
+                                *node.span(),
+                                "Opening tag is here",
                             );
+
+                            // Left open, so the tree can be salvaged
+                            stack.push(node);
+                            return Err(error);
                         }
 
+                        node.extend_span(&next.span().into(), src);
+
                         state = ParserState::TagChildren;
                         if let Some(parent) = stack.last_mut() {
                             parent.push_child(Node::Child(node));
+                        } else if config.stop_after_root {
+                            break node;
                         } else {
                             state = ParserState::Epilog;
                             stack.push(node);
@@ -493,9 +1249,23 @@ impl<'src> Document<'src> {
                 },
 
                 ParserState::Epilog => match next {
-                    Token::Comment { text, .. } => epilog.push(Node::Comment(text.into())),
+                    Token::Comment { text, span } => {
+                        let kind = XmlErrorKind::EpilogContent("comment".to_string());
+                        warnings.warn(src, span.into(), kind);
+                        epilog.push(Node::Comment(CommentNode::new(span, text)));
+                    }
 
                     Token::Cdata { text, span } => {
+                        if config.strict {
+                            let message = "CDATA is not allowed after the root element";
+                            bail!(
+                                src,
+                                &span,
+                                XmlErrorKind::InvalidStructure(message.to_string())
+                            );
+                        }
+                        let kind = XmlErrorKind::EpilogContent("CDATA".to_string());
+                        warnings.warn(src, span.into(), kind);
                         let node = CdataNode::new(span, text);
                         epilog.push(Node::Cdata(node));
                     }
@@ -505,39 +1275,133 @@ impl<'src> Document<'src> {
                         content,
                         span,
                     } => {
+                        let kind =
+                            XmlErrorKind::EpilogContent("processing instruction".to_string());
+                        warnings.warn(src, span.into(), kind);
                         let node = ProcessingInstructionNode::new(span, target, content);
                         epilog.push(Node::ProcessingInstruction(node));
                     }
 
+                    Token::Text { text } if text.trim().is_empty() => {}
+
                     _ => {
                         let span = next.span();
                         bail!(src, span, msg = "Unexpected {} in after root", next.name());
                     }
                 },
             }
-        }
+        };
+
+        Ok(Self {
+            src: Some(src),
+            bom: false,
+            declaration,
+            prolog,
+            root,
+            epilog,
+            metadata: Metadata::default(),
+        })
     }
 }
 
-impl<'src> ToBinHandler<'src> for Document<'src> {
-    fn write(&self, encoder: &mut Encoder) -> std::io::Result<()> {
+impl<'src> Document<'src> {
+    fn write_nodes(&self, encoder: &mut Encoder) -> std::io::Result<()> {
+        self.declaration.write(encoder)?;
+        self.prolog.write(encoder)?;
+        self.root.write(encoder)?;
+        self.epilog.write(encoder)?;
+        Ok(())
+    }
+
+    /// Assembles a parsed document from its parts, with spans that are offsets into `src`.
+    pub(crate) fn from_parts(
+        src: &'src str,
+        bom: bool,
+        declaration: Option<DeclarationNode<'src>>,
+        prolog: Vec<Node<'src>>,
+        root: TagNode<'src>,
+        epilog: Vec<Node<'src>>,
+    ) -> Self {
+        Self {
+            src: Some(src),
+            bom,
+            declaration,
+            prolog,
+            root,
+            epilog,
+            metadata: Metadata::default(),
+        }
+    }
+
+    /// Writes everything between the version header and the checksum
+    fn write_body(&self, encoder: &mut Encoder) -> std::io::Result<()> {
+        // Metadata is an optional section before the main header, so documents without it are unchanged
+        if !self.metadata.is_empty() {
+            encoder.write_all(Self::HEADER_METADATA)?;
+            self.metadata.write(encoder)?;
+        }
+
+        // Nodes added or modified after parsing may have spans that no longer match the source,
+        // in which case the whole document is written again with the strings inline
         if let Some(src) = self.src {
+            let start = encoder.len();
             encoder.write_all(Self::HEADER_SOURCED)?;
-            encoder.with_source_header();
             src.write(encoder)?;
-        } else {
+            encoder.with_checked_source(src);
+            self.write_nodes(encoder)?;
+
+            let mismatch = encoder.has_source_mismatch();
+            encoder.without_source();
+            if !mismatch {
+                return Ok(());
+            }
+
+            encoder.truncate(start);
+        }
+
+        if !encoder.capabilities().contains(Capabilities::STRING_TABLE) {
             encoder.write_all(Self::HEADER_UNSOURCED)?;
+            return self.write_nodes(encoder);
         }
 
-        self.declaration.write(encoder)?;
-        self.prolog.write(encoder)?;
-        self.root.write(encoder)?;
-        self.epilog.write(encoder)?;
+        // Without a source, repeated names are written once and referenced afterwards
+        encoder.write_all(Self::HEADER_STRING_TABLE)?;
+        encoder.with_string_table();
+        self.write_nodes(encoder)?;
+        encoder.without_string_table();
         Ok(())
     }
 
-    fn read(decoder: &mut Decoder<'src>) -> Result<Self, BinDecodeError> {
-        let header = decoder.read_all(4)?;
+    fn read_body(decoder: &mut Decoder<'src>) -> Result<Self, BinDecodeError> {
+        let (src, metadata) = Self::read_preamble(decoder)?;
+        let declaration = Option::<DeclarationNode>::read(decoder)?;
+        let prolog = Vec::<Node>::read(decoder)?;
+        let root = TagNode::read(decoder)?;
+        let epilog = Vec::<Node>::read(decoder)?;
+        decoder.without_string_table();
+
+        Ok(Self {
+            src,
+            bom: false,
+            declaration,
+            prolog,
+            root,
+            epilog,
+            metadata,
+        })
+    }
+
+    /// Reads the metadata and the main header, and prepares the decoder for the nodes that follow
+    fn read_preamble(
+        decoder: &mut Decoder<'src>,
+    ) -> Result<(Option<&'src str>, Metadata), BinDecodeError> {
+        let mut header = decoder.read_all(4)?;
+        let mut metadata = Metadata::default();
+        if header == Self::HEADER_METADATA {
+            metadata = Metadata::read(decoder)?;
+            header = decoder.read_all(4)?;
+        }
+
         let src = match header {
             Self::HEADER_SOURCED => {
                 let src = <&str>::read(decoder)?;
@@ -545,29 +1409,68 @@ impl<'src> ToBinHandler<'src> for Document<'src> {
                 Some(src)
             }
             Self::HEADER_UNSOURCED => None,
+            Self::HEADER_STRING_TABLE
+                if decoder.capabilities().contains(Capabilities::STRING_TABLE) =>
+            {
+                decoder.with_string_table();
+                None
+            }
             _ => {
                 return Err(BinDecodeError::InvalidHeader);
             }
         };
 
-        let declaration = Option::<DeclarationNode>::read(decoder)?;
-        let prolog = Vec::<Node>::read(decoder)?;
-        let root = TagNode::read(decoder)?;
-        let epilog = Vec::<Node>::read(decoder)?;
+        Ok((src, metadata))
+    }
+}
 
-        Ok(Self {
-            src,
-            declaration,
-            prolog,
-            root,
-            epilog,
-        })
+impl<'src> ToBinHandler<'src> for Document<'src> {
+    fn write(&self, encoder: &mut Encoder) -> std::io::Result<()> {
+        let start = encoder.len();
+        encoder.write_format_header()?;
+        self.write_body(encoder)?;
+
+        if encoder.capabilities().contains(Capabilities::CHECKSUM) {
+            encoder.write_checksum(start)?;
+        }
+        Ok(())
+    }
+
+    fn read(decoder: &mut Decoder<'src>) -> Result<Self, BinDecodeError> {
+        let start = decoder.cursor();
+        decoder.read_format_header()?;
+        let checked = decoder.capabilities().contains(Capabilities::CHECKSUM);
+
+        match Self::read_body(decoder) {
+            Ok(document) if checked => {
+                decoder.read_checksum(start)?;
+                Ok(document)
+            }
+
+            // Corrupted data usually fails to decode before the footer is reached,
+            // so check the footer at the end of the buffer to report it as corruption
+            Err(e) if checked => {
+                decoder.verify_trailing_checksum(start)?;
+                Err(e)
+            }
+
+            result => result,
+        }
     }
 }
 
 /// An owned version of the XML document, with no source span information. See [`Document`].
-#[derive(Debug, Clone, PartialEq)]
+///
+/// Equality and hashing are structural, and include the metadata: every node is compared in document order,
+/// and metadata entries are compared sorted by key.
+/// Use [`OwnedDocument::semantic_eq`] to ignore attribute order or formatting.
+///
+/// Owned documents are guaranteed to be `Send` and `Sync`, so they can be moved to or shared between threads.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct OwnedDocument {
+    /// Whether the source started with a UTF-8 byte order mark. See [`Document::has_bom`].
+    pub bom: bool,
+
     /// The XML declaration node, if present.
     pub declaration: Option<OwnedDeclarationNode>,
 
@@ -578,33 +1481,119 @@ pub struct OwnedDocument {
     /// The root node of the document.
     pub root: OwnedTagNode,
 
-    /// The epilog of the document, which is everything after the root.  
-    /// Technically this is not valid XML, but it is parsed anyway.
-    pub epilog: Vec<OwnedNode>,
-}
-impl OwnedDocument {
-    /// Create a new document from the given root node.
+    /// The epilog of the document, which is everything after the root.  
+    /// Technically this is not valid XML, but it is parsed anyway.
+    pub epilog: Vec<OwnedNode>,
+
+    /// The metadata side table of the document. See [`Metadata`].
+    pub metadata: Metadata,
+}
+impl OwnedDocument {
+    /// Create a new document from the given root node.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{OwnedDocument, node::OwnedTagNode};
+    ///
+    /// let root = OwnedTagNode::new("root");
+    /// let doc = OwnedDocument::new(root);
+    /// assert_eq!(doc.root.name, "root");
+    /// ```
+    pub fn new(root: impl Into<OwnedTagNode>) -> Self {
+        Self {
+            bom: false,
+            declaration: None,
+            prolog: vec![],
+            root: root.into(),
+            epilog: vec![],
+            metadata: Metadata::default(),
+        }
+    }
+
+    /// Creates a document with no root, holding only the nodes around where the root would be.
+    ///
+    /// See [`Document::without_root`] for details.
+    #[must_use]
+    pub fn without_root(prolog: Vec<OwnedNode>, epilog: Vec<OwnedNode>) -> Self {
+        Self {
+            bom: false,
+            declaration: None,
+            prolog,
+            root: OwnedTagNode::new(""),
+            epilog,
+            metadata: Metadata::default(),
+        }
+    }
+
+    /// Returns false if the document has no root element.
+    ///
+    /// See [`Document::has_root`] for details.
+    #[must_use]
+    pub fn has_root(&self) -> bool {
+        !self.root.name.local.is_empty()
+    }
+
+    /// Returns an error if the document has no root element; see [`Document::has_root`].
+    ///
+    /// # Errors
+    /// Returns an [`XmlErrorKind::InvalidStructure`] error if the root is missing
+    pub fn require_root(&self) -> XmlResult<()> {
+        self.borrowed().require_root()
+    }
+
+    /// Replaces every element and attribute name in the document with the interner's shared copies.
+    ///
+    /// Documents converted with [`Document::to_owned`] already share their names;
+    /// this is for trees built or edited by hand, or to share names across documents.
+    pub fn intern_names(&mut self, interner: &mut NameInterner) {
+        self.root.intern_names(interner);
+        for node in self.prolog.iter_mut().chain(&mut self.epilog) {
+            node.intern_names(interner);
+        }
+    }
+
+    /// Removes all content nested deeper than `depth`, where the root is at depth 0.
+    ///
+    /// Useful for previews and summaries of large documents. The prolog and epilog are kept.
+    /// Returns the number of nodes removed, including their descendants.
     ///
     /// # Example
     /// ```rust
-    /// use xmltree::{OwnedDocument, node::OwnedTagNode};
+    /// use xmltree::Document;
     ///
-    /// let root = OwnedTagNode::new("root");
-    /// let doc = OwnedDocument::new(root);
-    /// assert_eq!(doc.root.name, "root");
+    /// let src = "<library><shelf><book><title>A</title></book></shelf></library>";
+    /// let mut doc = Document::parse_str(src).unwrap().to_owned();
+    /// assert_eq!(doc.truncate_depth_with_marker(1), 3);
+    /// assert_eq!(
+    ///     doc.to_xml_with_options(&xmltree::XmlWriteOptions::default().with_minify(true)).unwrap(),
+    ///     "<library><shelf><!-- truncated 3 nodes (2 elements) --></shelf></library>\n"
+    /// );
     /// ```
-    pub fn new(root: impl Into<OwnedTagNode>) -> Self {
-        Self {
-            declaration: None,
-            prolog: vec![],
-            root: root.into(),
-            epilog: vec![],
-        }
+    pub fn truncate_depth(&mut self, depth: usize) -> usize {
+        self.root.truncate_depth(depth)
+    }
+
+    /// Like [`OwnedDocument::truncate_depth`], but leaves a comment with the number of removed nodes in their place.
+    ///
+    /// See [`OwnedTagNode::truncate_depth_with_marker`].
+    pub fn truncate_depth_with_marker(&mut self, depth: usize) -> usize {
+        self.root.truncate_depth_with_marker(depth)
+    }
+
+    /// Checks that the nodes around the root follow the ordering rules of the XML spec.
+    ///
+    /// See [`Document::validate_prolog`] for details.
+    ///
+    /// # Errors
+    /// Returns an [`XmlErrorKind::InvalidStructure`] error describing the first misplaced node
+    pub fn validate_prolog(&self) -> XmlResult<()> {
+        self.borrowed().validate_prolog()
     }
 
     pub(crate) fn borrowed(&self) -> Document<'_> {
         Document {
             src: None,
+            bom: self.bom,
             declaration: self
                 .declaration
                 .as_ref()
@@ -612,6 +1601,7 @@ impl OwnedDocument {
             prolog: self.prolog.iter().map(OwnedNode::borrowed).collect(),
             root: self.root.borrowed(),
             epilog: self.epilog.iter().map(OwnedNode::borrowed).collect(),
+            metadata: self.metadata.clone(),
         }
     }
 
@@ -620,7 +1610,7 @@ impl OwnedDocument {
     /// If src is provided, it will be written as a header before the document.  
     /// All strings will be stored as references to the source string, making deserialization faster.
     ///
-    /// However, if you have modified the document after parsing and provide a source string, deserialization will fail.
+    /// Owned documents have no source, so strings are always stored inline.
     ///
     /// # Errors
     /// Returns errors if the encoding fails
@@ -660,6 +1650,39 @@ impl OwnedDocument {
         Ok(document)
     }
 
+    /// Reads only the name and attributes of the root from a binary document, leaving its children unread.
+    ///
+    /// Much cheaper than a full decode for large documents, such as when checking identifying
+    /// attributes of a cached document before deciding whether to load it.
+    /// The checksum is not verified, since the rest of the data is never read.
+    ///
+    /// # Errors
+    /// Returns errors if the decoding fails
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{Document, OwnedDocument};
+    ///
+    /// let doc = Document::parse_str("<cache key='abc' version='3'><entry /><entry /></cache>").unwrap();
+    /// let bin = doc.to_bin().unwrap();
+    ///
+    /// let root = OwnedDocument::read_root_shallow(&bin).unwrap();
+    /// assert_eq!(root.get_attribute(None, "key").unwrap().value, "abc");
+    /// assert!(root.children.is_empty());
+    /// ```
+    pub fn read_root_shallow(data: &[u8]) -> Result<OwnedTagNode, BinDecodeError> {
+        let mut decoder = Decoder::new(data);
+        decoder.read_format_header()?;
+        Document::read_preamble(&mut decoder)?;
+        Option::<DeclarationNode>::read(&mut decoder)?;
+        for _ in 0..usize::read(&mut decoder)? {
+            decoder.skip_node()?;
+        }
+
+        let root = TagNode::read_shallow(&mut decoder)?;
+        Ok(root.to_owned())
+    }
+
     /// Create a formatted XML string from this document.
     ///
     /// This is mostly used to format the document, or to get a source string for a programatically created document.
@@ -708,9 +1731,51 @@ impl OwnedDocument {
         &self,
         writer: &mut W,
         tab_char: Option<&str>,
+    ) -> std::io::Result<()> {
+        let options = XmlWriteOptions::from_tab_char(tab_char);
+        self.to_xml_with_writer_and_options(writer, &options)
+    }
+
+    /// Create a formatted XML string from this document, using the given options.
+    ///
+    /// See [`XmlWriteOptions`] for the available options.
+    ///
+    /// # Errors
+    /// Can fail if a string in the document cannot be entity encoded,
+    /// or contains characters rejected by [`XmlWriteOptions::control_chars`].
+    pub fn to_xml_with_options(&self, options: &XmlWriteOptions) -> std::io::Result<String> {
+        self.borrowed().to_xml_with_options(options)
+    }
+
+    /// Write this document as a formatted XML string using the given writer and options.
+    ///
+    /// See [`Document::to_xml_with_options`] for more details.
+    ///
+    /// # Errors
+    /// Can fail if a string in the document cannot be entity encoded,
+    /// or contains characters rejected by [`XmlWriteOptions::control_chars`].
+    pub fn to_xml_with_writer_and_options<W: std::io::Write>(
+        &self,
+        writer: &mut W,
+        options: &XmlWriteOptions,
     ) -> std::io::Result<()> {
         let doc = self.borrowed();
-        crate::to_xml::write_xml(writer, &doc, tab_char)
+        crate::to_xml::write_xml(writer, &doc, options)
+    }
+
+    /// Write each child of the root as a minified record on its own line.
+    ///
+    /// See [`Document::to_xml_records_with_writer`] for more details.
+    ///
+    /// # Errors
+    /// Can fail if a string in the document cannot be entity encoded,
+    /// or contains characters rejected by [`XmlWriteOptions::control_chars`].
+    pub fn to_xml_records_with_writer<W: std::io::Write>(
+        &self,
+        writer: &mut W,
+        options: &XmlWriteOptions,
+    ) -> std::io::Result<()> {
+        crate::to_xml::write_records(writer, &self.root.borrowed(), options)
     }
 }
 impl<'src> ToBinHandler<'src> for OwnedDocument {
@@ -731,6 +1796,7 @@ pub struct DeclarationNode<'src> {
     version: StrSpan<'src>,
     encoding: Option<StrSpan<'src>>,
     standalone: Option<bool>,
+    standalone_span: Option<StrSpan<'src>>,
 }
 impl<'src> DeclarationNode<'src> {
     pub(crate) fn new<T: Into<StrSpan<'src>>>(
@@ -743,11 +1809,13 @@ impl<'src> DeclarationNode<'src> {
             version: version.into(),
             encoding: encoding.map(Into::into),
             standalone,
+            standalone_span: None,
         }
     }
 
     pub(crate) fn with_span(mut self, span: impl Into<StrSpan<'src>>) -> Self {
         self.span = span.into();
+        self.standalone_span = self.standalone.and_then(|_| find_standalone(&self.span));
         self
     }
 
@@ -758,12 +1826,16 @@ impl<'src> DeclarationNode<'src> {
     }
 
     /// Returns the version of the XML declaration.
+    ///
+    /// The span covers only the value, without the quotes.
     #[must_use]
     pub fn version(&self) -> &StrSpan<'src> {
         &self.version
     }
 
     /// Returns the encoding of the XML declaration, if present.
+    ///
+    /// The span covers only the value, without the quotes.
     #[must_use]
     pub fn encoding(&self) -> Option<&StrSpan<'src>> {
         self.encoding.as_ref()
@@ -775,11 +1847,20 @@ impl<'src> DeclarationNode<'src> {
         self.standalone
     }
 
+    /// Returns the span of the standalone attribute's value (`yes` or `no`), without the quotes.
+    ///
+    /// Only available if the declaration was parsed from a source string.
+    #[must_use]
+    pub fn standalone_span(&self) -> Option<&StrSpan<'src>> {
+        self.standalone_span.as_ref()
+    }
+
     pub(crate) fn to_owned(&self) -> OwnedDeclarationNode {
         OwnedDeclarationNode {
             version: self.version.text().to_string(),
             encoding: self.encoding.as_ref().map(|s| s.text().to_string()),
             standalone: self.standalone,
+            origin: Origin::from(&self.span),
         }
     }
 }
@@ -799,17 +1880,21 @@ impl<'src> ToBinHandler<'src> for DeclarationNode<'src> {
         let encoding = Option::<StrSpan>::read(decoder)?;
         let standalone = Option::<bool>::read(decoder)?;
 
+        // Not stored in the binary format; recovered from the declaration's span instead
+        let standalone_span = standalone.and_then(|_| find_standalone(&span));
+
         Ok(Self {
             span,
             version,
             encoding,
             standalone,
+            standalone_span,
         })
     }
 }
 
 /// Owned version of the XML declaration node, with no span metadata. See [`DeclarationNode`].
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct OwnedDeclarationNode {
     /// The version of the XML declaration.
     pub version: String,
@@ -819,6 +1904,9 @@ pub struct OwnedDeclarationNode {
 
     /// The standalone attribute of the XML declaration, if present.
     pub standalone: Option<bool>,
+
+    /// Where the node was converted from, if it was parsed. See [`Origin`].
+    pub origin: Origin,
 }
 impl OwnedDeclarationNode {
     /// Create a new XML declaration node.
@@ -831,6 +1919,7 @@ impl OwnedDeclarationNode {
             version: version.into(),
             encoding: encoding.map(Into::into),
             standalone,
+            origin: Origin::default(),
         }
     }
 
@@ -853,13 +1942,344 @@ impl<'src> ToBinHandler<'src> for OwnedDeclarationNode {
     }
 }
 
-fn maybe_empty(s: xmlparser::StrSpan) -> Option<xmlparser::StrSpan<'_>> {
+pub(crate) fn maybe_empty(s: xmlparser::StrSpan) -> Option<xmlparser::StrSpan<'_>> {
     if s.is_empty() { None } else { Some(s) }
 }
 
+/// Locates the value of the standalone attribute within the span of an XML declaration.
+fn find_standalone<'src>(span: &StrSpan<'src>) -> Option<StrSpan<'src>> {
+    let text = span.text();
+    let name_end = text.find("standalone")? + "standalone".len();
+
+    let rest = text[name_end..]
+        .trim_start()
+        .strip_prefix('=')?
+        .trim_start();
+    let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let value = &rest[1..];
+    let value = &value[..value.find(quote)?];
+
+    let offset = value.as_ptr() as usize - text.as_ptr() as usize;
+    Some(StrSpan::new(value, span.start() + offset))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::node::{Ambiguity, OwnedCommentNode};
+
+    #[test]
+    fn test_detached_nodes() {
+        let src = "<root><a /></root>";
+        let mut doc = Document::parse_str(src).unwrap();
+
+        let mut child = TagNode::detached(Some("ns"), "b");
+        child.push_attribute(NodeAttribute::detached(None, "id", "1"));
+        child.push_child(Node::Text(TextNode::detached("hello")));
+        doc.root_mut().push_child(Node::Child(child));
+        doc.root_mut().children_mut().remove(0);
+        doc.epilog_mut().push(Node::ProcessingInstruction(
+            ProcessingInstructionNode::detached("pi", None),
+        ));
+
+        let xml = doc.to_xml(None).unwrap();
+        assert_eq!(
+            xml,
+            "<root>\n\t<ns:b id=\"1\">\n\t\thello\n\t</ns:b>\n</root>\n<?pi?>\n"
+        );
+    }
+
+    #[test]
+    fn test_declaration_spans() {
+        let src = "<?xml version='1.0' encoding=\"UTF-8\" standalone = 'yes' ?><root/>";
+        let doc = Document::parse_str(src).unwrap();
+        let declaration = doc.declaration().unwrap();
+
+        let version = declaration.version();
+        assert_eq!(
+            &src[version.start()..version.start() + version.len()],
+            "1.0"
+        );
+
+        let encoding = declaration.encoding().unwrap();
+        assert_eq!(
+            &src[encoding.start()..encoding.start() + encoding.len()],
+            "UTF-8"
+        );
+
+        let standalone = declaration.standalone_span().unwrap();
+        assert_eq!(
+            &src[standalone.start()..standalone.start() + standalone.len()],
+            "yes"
+        );
+
+        let bin = doc.to_bin().unwrap();
+        let doc = Document::from_bin(&bin).unwrap();
+        assert_eq!(
+            doc.declaration().unwrap().standalone_span(),
+            Some(standalone)
+        );
+
+        let doc = Document::parse_str("<?xml version='1.0'?><root/>").unwrap();
+        assert!(doc.declaration().unwrap().standalone_span().is_none());
+    }
+
+    #[test]
+    fn test_reparse_node() {
+        let src = "<root>\n  <a x=\"1\"><b>text</b><!-- c --></a>\n  <d/>\n</root>";
+        let doc = Document::parse_str(src).unwrap();
+
+        let Node::Child(a) = &doc.root().children()[0] else {
+            panic!("Expected tag");
+        };
+        let fresh = doc.reparse_node(a.span()).unwrap();
+        assert_eq!(&fresh, a);
+
+        let padded = StrSpan::new(&src[6..src.len() - 7], 6);
+        assert!(doc.reparse_node(&padded).is_err());
+
+        let whitespace = StrSpan::new(&src[6..44], 6);
+        let fresh = doc.reparse_node(&whitespace).unwrap();
+        assert_eq!(&fresh, a);
+
+        assert!(doc.reparse_node(&StrSpan::new("", 1000)).is_err());
+        assert!(doc.to_owned().borrowed().reparse_node(a.span()).is_err());
+    }
+
+    #[test]
+    fn test_node_conversions() {
+        let src = "<root><!-- pi a  b --><?x y z ?>text<![CDATA[c]]><!-- --></root>";
+        let mut doc = Document::parse_str(src).unwrap();
+        let children = doc.root_mut().children_mut();
+
+        assert!(children[0].comment_to_processing_instruction());
+        let Node::ProcessingInstruction(pi) = &children[0] else {
+            panic!("Expected processing instruction");
+        };
+        assert_eq!(pi.target().text(), "pi");
+        assert_eq!(pi.content().unwrap().text(), "a  b");
+        assert_eq!(&src[pi.target().start()..pi.target().start() + 2], "pi");
+
+        assert!(children[1].processing_instruction_to_comment());
+        let Node::Comment(comment) = &children[1] else {
+            panic!("Expected comment");
+        };
+        let text = comment.text();
+        assert_eq!(text.text(), "x y z");
+        assert_eq!(&src[text.start()..text.start() + text.len()], "x y z");
+
+        assert!(children[2].text_to_cdata());
+        assert!(children[3].cdata_to_text());
+        assert!(!children[4].comment_to_processing_instruction());
+        assert!(!children[4].text_to_cdata());
+
+        let xml = doc.to_xml(None).unwrap();
+        assert_eq!(
+            xml,
+            "<root>\n\t<?pi a  b?>\n\t<!--x y z-->\n\t<![CDATA[text]]>\n\tc\n\t<!-- -->\n</root>\n"
+        );
+
+        let mut owned = OwnedNode::Comment(OwnedCommentNode::new("pi a b"));
+        assert!(owned.comment_to_processing_instruction());
+        assert!(owned.processing_instruction_to_comment());
+        assert_eq!(owned, OwnedNode::Comment(OwnedCommentNode::new("pi a b")));
+
+        let mut detached =
+            Node::ProcessingInstruction(ProcessingInstructionNode::detached("a", Some("b")));
+        assert!(!detached.processing_instruction_to_comment());
+    }
+
+    #[test]
+    fn test_owned_hash() {
+        use std::collections::HashSet;
+
+        let a = Document::parse_str("<a x='1'><b>t</b></a>")
+            .unwrap()
+            .to_owned();
+        let b = Document::parse_str("<a x=\"1\">\n\t<b>t</b>\n</a>")
+            .unwrap()
+            .to_owned();
+        let c = Document::parse_str("<a x='2'><b>t</b></a>")
+            .unwrap()
+            .to_owned();
+
+        let documents: HashSet<_> = [&a, &b, &c].into_iter().collect();
+        assert_eq!(documents.len(), 2);
+
+        let roots: HashSet<_> = [&a.root, &b.root, &c.root].into_iter().collect();
+        assert_eq!(roots.len(), 2);
+    }
+
+    /// Skips the version header to the sourced or unsourced header
+    fn main_header(bin: &[u8]) -> &[u8] {
+        &bin[9..13]
+    }
+
+    #[test]
+    fn test_bin_string_table() {
+        let rows = "<row><cell /><cell /></row>".repeat(50);
+        let doc = Document::parse_str(&format!("<table>{rows}</table>"))
+            .unwrap()
+            .to_owned();
+        let bin = doc.to_bin().unwrap();
+        assert_eq!(main_header(&bin), Document::HEADER_STRING_TABLE);
+
+        // Each repeated name costs a 4-byte reference instead of a length and the text
+        let mut encoder = Encoder::new();
+        encoder.with_format_version(1);
+        doc.write(&mut encoder).unwrap();
+        let legacy = encoder.into_inner();
+        assert_eq!(&legacy[..4], Document::HEADER_UNSOURCED);
+        assert!(bin.len() < legacy.len());
+
+        assert_eq!(OwnedDocument::from_bin(&bin).unwrap(), doc);
+        assert_eq!(OwnedDocument::from_bin(&legacy).unwrap(), doc);
+    }
+
+    #[test]
+    fn test_bin_checksum() {
+        let doc = Document::parse_str("<root><a b='c'>text</a></root>").unwrap();
+        let bin = doc.to_bin().unwrap();
+        assert!(Document::from_bin(&bin).is_ok());
+
+        for i in 9..bin.len() {
+            let mut corrupted = bin.clone();
+            corrupted[i] ^= 0x10;
+            assert!(
+                matches!(
+                    Document::from_bin(&corrupted),
+                    Err(BinDecodeError::ChecksumMismatch { .. })
+                ),
+                "corruption at byte {i} was not detected"
+            );
+        }
+    }
+
+    #[test]
+    fn test_read_root_shallow() {
+        let src = "<!-- a --><?pi x?><root id='1'><root id='2'><root /></root></root>";
+        let doc = Document::parse_str(src).unwrap();
+        for bin in [doc.to_bin().unwrap(), doc.to_owned().to_bin().unwrap()] {
+            let root = OwnedDocument::read_root_shallow(&bin).unwrap();
+            assert_eq!(root.name, "root");
+            assert_eq!(root.get_attribute(None, "id").unwrap().value, "1");
+            assert!(root.children.is_empty());
+        }
+
+        // Skipping keeps the string table in step, so names after a skipped subtree still resolve
+        let bin = doc.to_owned().to_bin().unwrap();
+        let mut decoder = Decoder::new(&bin);
+        decoder.read_format_header().unwrap();
+        Document::read_preamble(&mut decoder).unwrap();
+        Option::<DeclarationNode>::read(&mut decoder).unwrap();
+        assert_eq!(usize::read(&mut decoder).unwrap(), 2);
+        decoder.skip_node().unwrap();
+        decoder.skip_node().unwrap();
+        assert!(TagNode::skip(&mut decoder).is_ok());
+        assert_eq!(usize::read(&mut decoder).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_interned_names() {
+        let src = "<table><row id='1' /><row id='2' /></table>";
+        let doc = Document::parse_str(src).unwrap().to_owned();
+
+        let (OwnedNode::Tag(a), OwnedNode::Tag(b)) = (&doc.root.children[0], &doc.root.children[1])
+        else {
+            panic!("expected two rows");
+        };
+        assert!(std::sync::Arc::ptr_eq(&a.name.local, &b.name.local));
+        assert!(std::sync::Arc::ptr_eq(
+            &a.attributes[0].name.local,
+            &b.attributes[0].name.local
+        ));
+
+        let mut built = OwnedDocument::new(OwnedTagNode::new("table"));
+        built
+            .root
+            .children
+            .push(OwnedNode::Tag(OwnedTagNode::new("table")));
+        let mut interner = NameInterner::new();
+        built.intern_names(&mut interner);
+        assert_eq!(interner.len(), 1);
+        let OwnedNode::Tag(child) = &built.root.children[0] else {
+            unreachable!()
+        };
+        assert!(std::sync::Arc::ptr_eq(
+            &built.root.name.local,
+            &child.name.local
+        ));
+    }
+
+    #[test]
+    fn test_bin_modified() {
+        let src = "<root><a /></root>";
+        let mut doc = Document::parse_str(src).unwrap();
+        let bin = doc.to_bin().unwrap();
+        assert_eq!(main_header(&bin), Document::HEADER_SOURCED);
+
+        let extra = String::from("b");
+        doc.root_mut()
+            .push_child(Node::Child(TagNode::detached(None, &extra)));
+        let bin = doc.to_bin().unwrap();
+        assert_eq!(main_header(&bin), Document::HEADER_STRING_TABLE);
+        assert_eq!(Document::from_bin(&bin).unwrap().to_owned(), doc.to_owned());
+
+        // A span that points into the source, but at the wrong offset
+        let mut doc = Document::parse_str(src).unwrap();
+        doc.root_mut()
+            .push_child(Node::Text(TextNode::detached(&src[1..5])));
+        let bin = doc.to_bin().unwrap();
+        assert_eq!(main_header(&bin), Document::HEADER_STRING_TABLE);
+        assert_eq!(Document::from_bin(&bin).unwrap().to_owned(), doc.to_owned());
+    }
+
+    #[test]
+    fn test_validate_prolog() {
+        let src = "<?xml version='1.0'?><!-- a --><!DOCTYPE a><?pi?><a /><!-- b -->";
+        let mut doc = Document::parse_str(src).unwrap();
+        assert!(doc.validate_prolog().is_ok());
+
+        let dtd = doc.prolog()[1].clone();
+        doc.prolog_mut().push(dtd);
+        let err = doc.validate_prolog().unwrap_err();
+        assert!(matches!(err.kind, XmlErrorKind::InvalidStructure(_)));
+        assert_eq!(err.context.span.as_str(), "<!DOCTYPE a>");
+
+        doc.prolog_mut().pop();
+        let mut owned = doc.to_owned();
+        owned
+            .epilog
+            .push(OwnedNode::Text(crate::node::OwnedTextNode::new("x")));
+        assert!(owned.validate_prolog().is_err());
+    }
+
+    #[test]
+    fn test_metadata() {
+        let src = "<test />";
+        let mut doc = Document::parse_str(src).unwrap();
+        let plain_bin = doc.to_bin().unwrap();
+
+        doc.metadata_mut()
+            .insert("source", "https://example.com/test.xml");
+        doc.metadata_mut().insert("checksum", "abc");
+        assert_eq!(doc.to_xml(None).unwrap(), "<test />\n");
+
+        let owned = doc.to_owned();
+        assert_eq!(owned.metadata.get("checksum"), Some("abc"));
+        assert_eq!(owned.borrowed().metadata(), doc.metadata());
+
+        let bin = doc.to_bin().unwrap();
+        assert_ne!(bin, plain_bin);
+        assert_eq!(Document::from_bin(&bin).unwrap(), doc);
+        assert_eq!(
+            OwnedDocument::from_bin(&owned.to_bin().unwrap()).unwrap(),
+            owned
+        );
+
+        let doc = Document::from_bin(&plain_bin).unwrap();
+        assert!(doc.metadata().is_empty());
+    }
 
     #[test]
     fn test_bin() {
@@ -891,4 +2311,443 @@ mod tests {
         let owned_doc = OwnedDocument::from_bin(&owned_bin).unwrap();
         assert_eq!(owned_doc, doc2);
     }
+
+    #[test]
+    fn test_comment_spans() {
+        let src = "<root><!-- note --></root>";
+        let doc = Document::parse_str(src).unwrap();
+        let Node::Comment(comment) = &doc.root().children()[0] else {
+            panic!("Expected comment");
+        };
+        assert_eq!(comment.span().text(), "<!-- note -->");
+        assert_eq!(comment.span().start(), 6);
+        assert_eq!(comment.trimmed().text(), "note");
+
+        let bin = doc.to_bin().unwrap();
+        let decoded = Document::from_bin(&bin).unwrap();
+        assert_eq!(decoded.root().children()[0].span().text(), "<!-- note -->");
+
+        // Comments encoded as a bare text span still decode
+        let mut encoder = Encoder::new();
+        2u8.write(&mut encoder).unwrap();
+        StrSpan::from("legacy").write(&mut encoder).unwrap();
+        let buffer = encoder.into_inner();
+        let node = Node::read(&mut Decoder::new(buffer.as_slice())).unwrap();
+        let Node::Comment(comment) = node else {
+            panic!("Expected comment");
+        };
+        assert_eq!(comment.text().text(), "legacy");
+
+        // Older format versions write comments as their text alone
+        for version in [1, 2] {
+            let mut encoder = Encoder::new();
+            encoder.with_format_version(version);
+            doc.write(&mut encoder).unwrap();
+            let bin = encoder.into_inner();
+            let decoded = Document::from_bin(&bin).unwrap();
+            let Node::Comment(comment) = &decoded.root().children()[0] else {
+                panic!("Expected comment");
+            };
+            assert_eq!(comment.text().text(), " note ");
+            assert_eq!(comment.span().text(), " note ");
+        }
+    }
+
+    #[test]
+    fn test_parameter_entities() {
+        let src = r#"<?xml version="1.0"?>
+<!DOCTYPE doc [
+    <!ENTITY % common SYSTEM "common.ent">
+    %common;
+    <!-- note -->
+    <!ENTITY title "A > B">
+    <![IGNORE[ <!ENTITY hidden "x"> <![INCLUDE[ ]]> ]]>
+    <![ INCLUDE [ <!ENTITY shown "y"> ]]>
+]>
+<!-- after -->
+<doc>&title;</doc>
+<!-- end -->"#;
+        let doc = Document::parse_str(src).unwrap();
+        assert_eq!(doc.prolog().len(), 2);
+        assert_eq!(doc.epilog().len(), 1);
+        assert_eq!(doc.root().name(), "doc");
+
+        let Node::DocumentType(dtd) = &doc.prolog()[0] else {
+            panic!("expected a DTD");
+        };
+        assert!(dtd.span().text().starts_with("<!DOCTYPE"));
+        assert!(dtd.span().text().ends_with("]>"));
+
+        let names = |entities: &[crate::node::DtdEntity]| {
+            entities
+                .iter()
+                .map(|e| e.name.text().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(dtd.entities()), ["title", "shown"]);
+        assert_eq!(names(&dtd.parameter_entities()), ["common"]);
+        assert_eq!(dtd.conditional_sections().len(), 2);
+
+        // Subsets the tokenizer can read keep parameter entities out of the general entities too
+        let doc = Document::parse_str(
+            "<!DOCTYPE a [<!-- c --><!ENTITY % p 'x'><?pi?><!ENTITY e 'y'>]><a />",
+        )
+        .unwrap();
+        let Node::DocumentType(dtd) = &doc.prolog()[0] else {
+            panic!("expected a DTD");
+        };
+        assert_eq!(names(dtd.entities()), ["e"]);
+        assert_eq!(names(&dtd.parameter_entities()), ["p"]);
+
+        assert!(Document::parse_str("<!DOCTYPE a [ %p; <!ENTITY e> ]><a />").is_err());
+
+        let doc = Document::parse_str(
+            "<!DOCTYPE a [<!UNKNOWN a 'b>c'><!ENTITY e 'y'><!ATTLIST a b CDATA #IMPLIED>]><a />",
+        )
+        .unwrap();
+        let Node::DocumentType(dtd) = &doc.prolog()[0] else {
+            panic!("expected a DTD");
+        };
+        assert_eq!(names(dtd.entities()), ["e"]);
+        assert_eq!(
+            dtd.other_declarations(),
+            ["<!UNKNOWN a 'b>c'>", "<!ATTLIST a b CDATA #IMPLIED>"]
+        );
+        assert!(Document::parse_str("<!DOCTYPE a [ %p; <a />").is_err());
+
+        // Reading the subset here is no less strict than the tokenizer
+        for src in [
+            "<!DOCTYPE r [ %p; garbage here ]><r />",
+            "<!DOCTYPE r [ %p; <TTLIST r> ]><r />",
+            "<!DOCTYPE r [ %p; <!ENTITY &e 'x'> ]><r />",
+            "<!DOCTYPE r [ %p; <!ENTITY 1e 'x'> ]><r />",
+            "<!DOCTYPE r [ %p; % q; ]><r />",
+            "<!DOCTYPE r [ <![INCLUDE[ ]]> garbage ]><r />",
+            "<!DOCTYPE r [ <!UNKNOWN> <!ENTITY &e 'x'> ]><r />",
+        ] {
+            assert!(Document::parse_str(src).is_err(), "{src}");
+        }
+    }
+
+    #[test]
+    fn test_internal_subset_round_trip() {
+        let src = r#"<!DOCTYPE doc [<!NOTATION n SYSTEM "x"><!ATTLIST doc a CDATA "d"><!ENTITY % p "v">%p;<![INCLUDE[<!ELEMENT doc EMPTY>]]><!-- end -->]><doc/>"#;
+        let check = |doc: &Document| {
+            let Node::DocumentType(dtd) = &doc.prolog()[0] else {
+                panic!("expected a DTD");
+            };
+            assert_eq!(
+                dtd.other_declarations(),
+                [
+                    r#"<!NOTATION n SYSTEM "x">"#,
+                    r#"<!ATTLIST doc a CDATA "d">"#,
+                    "<!ELEMENT doc EMPTY>"
+                ]
+            );
+            assert_eq!(dtd.attribute_defaults()[0].value.text(), "d");
+            assert_eq!(dtd.parameter_entities()[0].name.text(), "p");
+            assert_eq!(dtd.conditional_sections().len(), 1);
+        };
+
+        let doc = Document::parse_str(src).unwrap();
+        check(&doc);
+        let xml = doc.to_xml(None).unwrap();
+        assert!(xml.starts_with(
+            "<!DOCTYPE doc [\n\t<!NOTATION n SYSTEM \"x\">\n\t<!ATTLIST doc a CDATA \"d\">\n\t<!ENTITY % p \"v\">\n\t%p;\n"
+        ));
+        check(&Document::parse_str(&xml).unwrap());
+
+        // Nodes without a source keep the subset through owned and binary forms
+        let owned = doc.to_owned();
+        assert_eq!(owned.to_xml(None).unwrap(), xml);
+        let bin = owned.to_bin().unwrap();
+        check(&Document::from_bin(&bin).unwrap());
+        assert_eq!(
+            OwnedDocument::from_bin(&bin).unwrap().to_xml(None).unwrap(),
+            xml
+        );
+
+        // Older format versions find the subset in the span of the DTD
+        let mut encoder = Encoder::new();
+        encoder.with_format_version(3);
+        doc.write(&mut encoder).unwrap();
+        let bin = encoder.into_inner();
+        let decoded = Document::from_bin(&bin).unwrap();
+        check(&decoded);
+        assert_eq!(decoded.to_xml(None).unwrap(), xml);
+    }
+
+    #[test]
+    fn test_apply_dtd_defaults() {
+        let src = r#"<!DOCTYPE x:doc [
+            <!ENTITY e "<!ATTLIST x:doc skipped CDATA 'no'>">
+            <!ATTLIST x:doc xml:lang CDATA "en" mode (on|off) 'on'>
+            <!ATTLIST x:doc mode CDATA "later" %extra; CDATA "no">
+            <!ATTLIST item>
+        ]><x:doc mode="off"><item /></x:doc>"#;
+        let mut doc = Document::parse_str(src).unwrap();
+        assert_eq!(doc.apply_dtd_defaults(), 1);
+
+        let root = doc.root();
+        let lang = root.get_attribute(Some("xml"), "lang").unwrap();
+        assert!(lang.is_defaulted());
+        assert_eq!(lang.value().text(), "en");
+        assert_eq!(lang.span().text(), r#"xml:lang CDATA "en""#);
+
+        let mode = root.get_attribute(None, "mode").unwrap();
+        assert!(!mode.is_defaulted());
+        assert_eq!(mode.value().text(), "off");
+        assert_eq!(root.attributes().len(), 2);
+
+        // Applying again adds nothing
+        assert_eq!(doc.apply_dtd_defaults(), 0);
+    }
+
+    #[test]
+    fn test_attribute_values() {
+        let src = "<root a=\"x\r\ny\tz\" b='&#65;&#x42;&bogus;&amp' c=\"plain\" />";
+        let doc = Document::parse_str(src).unwrap();
+        let attr = |name| doc.root().get_attribute(None, name).unwrap();
+
+        assert_eq!(attr("a").decoded_value(), "x y z");
+        assert_eq!(attr("a").quote(), '"');
+        assert_eq!(attr("b").decoded_value(), "AB&bogus;&amp");
+        assert_eq!(attr("b").quote(), '\'');
+        assert!(matches!(
+            attr("c").decoded_value(),
+            std::borrow::Cow::Borrowed("plain")
+        ));
+
+        let value = attr("b").value();
+        assert_eq!(&src[value.start()..value.start() + 5], "&#65;");
+        assert_eq!(NodeAttribute::detached(None, "d", "v").quote(), '"');
+    }
+
+    #[test]
+    fn test_without_root() {
+        let doc = crate::Parser::new()
+            .with_allow_missing_root(true)
+            .parse("<!-- a --><?pi?>")
+            .unwrap();
+        assert!(!doc.has_root());
+        assert_eq!(doc.prolog().len(), 2);
+        assert_eq!(doc.to_xml(None).unwrap(), "<!-- a -->\n<?pi?>\n");
+
+        // Survives the binary format
+        let bin = doc.to_bin().unwrap();
+        let decoded = Document::from_bin(&bin).unwrap();
+        assert!(!decoded.has_root());
+        assert_eq!(decoded, doc);
+
+        let owned = OwnedDocument::without_root(
+            vec![],
+            vec![OwnedNode::Comment(OwnedCommentNode::new("x"))],
+        );
+        assert!(!owned.has_root());
+        assert!(owned.require_root().is_err());
+        assert!(OwnedDocument::from_bin(&owned.to_bin().unwrap()).unwrap() == owned);
+
+        assert!(Document::parse_str("<!-- a -->").is_err());
+    }
+
+    #[test]
+    fn test_owned_origins() {
+        let src = "<?xml version=\"1.0\"?><!-- c --><a x=\"1\">text<![CDATA[d]]><?p q?></a>";
+        let doc = Document::parse_str(src).unwrap();
+        let owned = doc.to_owned();
+
+        let span = |origin: &crate::Origin| origin.span(src).unwrap().text();
+        assert_eq!(
+            span(&owned.declaration.as_ref().unwrap().origin),
+            "<?xml version=\"1.0\"?>"
+        );
+        let OwnedNode::Comment(comment) = &owned.prolog[0] else {
+            panic!("Expected a comment");
+        };
+        assert_eq!(span(&comment.origin), "<!-- c -->");
+        assert_eq!(span(&owned.root.origin), &src[31..]);
+        assert_eq!(span(&owned.root.attributes[0].origin), "x=\"1\"");
+
+        let origins: Vec<_> = owned
+            .root
+            .children
+            .iter()
+            .map(|child| match child {
+                OwnedNode::Text(n) => span(&n.origin),
+                OwnedNode::Cdata(n) => span(&n.origin),
+                OwnedNode::ProcessingInstruction(n) => span(&n.origin),
+                _ => panic!("Unexpected node"),
+            })
+            .collect();
+        assert_eq!(origins, ["text", "<![CDATA[d]]>", "<?p q?>"]);
+
+        // Origins do not take part in equality, and new nodes have none
+        let built = OwnedTagNode::new("a");
+        assert!(!built.origin.is_known());
+        assert_eq!(
+            owned,
+            Document::parse_str(&src.replace("<a", "\n<a"))
+                .unwrap()
+                .to_owned()
+        );
+    }
+
+    #[test]
+    fn test_parse_all_errors() {
+        use crate::error::ErrorCode;
+        let codes = |errors: &[XmlError]| errors.iter().map(XmlError::code).collect::<Vec<_>>();
+
+        let (doc, errors) = Document::parse_all_errors("<a><b /></a>");
+        assert_eq!(doc.unwrap(), Document::parse_str("<a><b /></a>").unwrap());
+        assert!(errors.is_empty());
+
+        // Open tags are closed, and the prolog is kept
+        let src = "<?xml version=\"1.0\"?><!-- c --><a><b x='1' x='2'><c>text</b></a>";
+        let (doc, errors) = Document::parse_all_errors(src);
+        assert_eq!(
+            codes(&errors),
+            [ErrorCode::DuplicateAttribute, ErrorCode::UnclosedTag]
+        );
+        let doc = doc.unwrap();
+        assert!(doc.declaration().is_some());
+        assert_eq!(doc.prolog().len(), 1);
+        let expected = Document::parse_str("<a><b x='1' x='2'><c>text</c></b></a>").unwrap();
+        assert_eq!(doc.to_owned().root, expected.to_owned().root);
+
+        // A complete root is kept when the epilog is invalid
+        let (doc, errors) = Document::parse_all_errors("<a /><!-- c -->text");
+        assert_eq!(codes(&errors), [ErrorCode::EpilogContent, ErrorCode::Xml]);
+        assert_eq!(doc.unwrap().root().name(), "a");
+
+        // Nothing is salvaged from a broken prolog, but the problem is still reported
+        let (doc, errors) = Document::parse_all_errors("<!-- c --><?xml version=\"1.0\"?><a />");
+        assert!(doc.is_none());
+        assert_eq!(codes(&errors), [ErrorCode::Xml]);
+    }
+
+    #[test]
+    fn test_get_attribute_local() {
+        let src =
+            r#"<a xmlns:x="urn:x" xmlns:y="urn:y" y:id="2" x:id="1" xmlns:id="urn:id" id="0" />"#;
+        let doc = Document::parse_str(src).unwrap();
+
+        let names: Vec<_> = doc
+            .root()
+            .get_attribute_local("id")
+            .iter()
+            .map(|a| a.name().to_string())
+            .collect();
+        assert_eq!(names, ["y:id", "x:id", "id"]);
+        assert!(doc.root().get_attribute_local("missing").is_empty());
+
+        let owned = doc.to_owned();
+        let values: Vec<_> = owned
+            .root
+            .get_attribute_local("id")
+            .iter()
+            .map(|a| a.value.as_str())
+            .collect();
+        assert_eq!(values, ["2", "1", "0"]);
+    }
+
+    #[test]
+    fn test_child_accessors() {
+        let src = "<feed>text<!--c--><x:title>A</x:title><title><!--c-->B</title><title/></feed>";
+        let doc = Document::parse_str(src).unwrap();
+        let root = doc.root();
+        assert_eq!(root.first_child_tag().unwrap().name(), "x:title");
+        assert_eq!(
+            root.only_child_named(Some("x"), "title").unwrap().name(),
+            "x:title"
+        );
+        assert_eq!(
+            root.only_child_named(None, "title").unwrap_err(),
+            Ambiguity::Multiple(2)
+        );
+        assert_eq!(root.child_text(None, "title"), Some("B"));
+
+        let owned = doc.to_owned();
+        let root = &owned.root;
+        assert_eq!(root.first_child_tag().unwrap().name.to_string(), "x:title");
+        assert!(root.only_child_named(Some("x"), "title").is_ok());
+        assert_eq!(
+            root.only_child_named(None, "title").unwrap_err(),
+            Ambiguity::Multiple(2)
+        );
+        assert_eq!(
+            root.only_child_named(None, "x").unwrap_err(),
+            Ambiguity::Missing
+        );
+        assert_eq!(root.child_text(Some("x"), "title"), Some("A"));
+        assert_eq!(root.child_text(None, "missing"), None);
+        assert_eq!(OwnedTagNode::new("a").first_child_tag(), None);
+    }
+
+    #[test]
+    fn test_parse_forest() {
+        let src = "<?xml version='1.0'?>\n<a>1</a>\n<?pi?><b/>\n<c x='2'/><!-- end -->\n";
+        let docs = Document::parse_forest(src).unwrap();
+        let names: Vec<_> = docs.iter().map(|d| d.root().name().to_string()).collect();
+        assert_eq!(names, ["a", "b", "c"]);
+
+        assert!(docs[0].declaration().is_some());
+        assert!(docs[1].declaration().is_none());
+        assert_eq!(docs[1].prolog().len(), 1);
+        assert_eq!(docs[2].epilog().len(), 1);
+        assert_eq!(docs[2].root().span().text(), "<c x='2'/>");
+        assert_eq!(
+            docs[2].to_xml(None).unwrap(),
+            "<c x=\"2\" />\n<!-- end -->\n"
+        );
+
+        // A single element is a forest of one
+        assert_eq!(Document::parse_forest("<a />").unwrap().len(), 1);
+
+        // Errors point into the whole source
+        let err = Document::parse_forest("<a />\n<b>\n</c>").unwrap_err();
+        assert_eq!(err.context.position(), (3, 1));
+
+        assert!(Document::parse_forest(" <!-- only --> ").is_err());
+        assert!(Document::parse_forest("<a /> text").is_err());
+    }
+
+    #[test]
+    fn test_bom() {
+        let src = "\u{FEFF}<?xml version='1.0'?><a x='1'>t</a>";
+        let doc = Document::parse_str(src).unwrap();
+        assert!(doc.has_bom());
+        assert_eq!(doc.source(), Some(&src[3..]));
+        assert_eq!(doc.root().span().start(), 21);
+        assert_eq!(doc.root().span().text(), "<a x='1'>t</a>");
+
+        // Writing drops the byte order mark unless asked to keep it
+        let options = XmlWriteOptions::default().with_minify(true);
+        let xml = doc.to_xml_with_options(&options).unwrap();
+        assert!(xml.starts_with("<?xml"));
+        let owned = doc.to_owned();
+        assert!(owned.bom);
+        let xml = owned
+            .to_xml_with_options(&options.with_preserve_bom(true))
+            .unwrap();
+        assert_eq!(xml, "\u{FEFF}<?xml version=\"1.0\" ?><a x=\"1\">t</a>\n");
+        assert!(Document::parse_str(&xml).unwrap().has_bom());
+
+        // Error positions do not count it as a column
+        let err = Document::parse_str("\u{FEFF}<a></b>").unwrap_err();
+        assert_eq!(err.context.position(), (1, 4));
+
+        let (doc, _) = Document::parse_all_errors("\u{FEFF}<a><b>");
+        assert!(doc.unwrap().has_bom());
+        let docs = Document::parse_forest("\u{FEFF}<a /><b />").unwrap();
+        assert!(docs[0].has_bom() && !docs[1].has_bom());
+        assert!(!Document::parse_str("<a />").unwrap().has_bom());
+
+        // Only one byte order mark is skipped
+        let err = Document::parse_str("\u{FEFF}\u{FEFF}<a/>").unwrap_err();
+        assert_eq!(err.context.position(), (1, 1));
+        assert!(Document::parse_forest("\u{FEFF}\u{FEFF}<a/>").is_err());
+        let (doc, errors) = Document::parse_all_errors("\u{FEFF}\u{FEFF}<a/>");
+        assert!(doc.is_none() && errors.len() == 1);
+        assert!(Document::parse_str("<a>\u{FEFF}</a>").is_ok());
+    }
 }
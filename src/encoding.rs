@@ -0,0 +1,207 @@
+use crate::{
+    Document, StrSpan,
+    error::{ErrorContext, XmlError, XmlErrorKind, XmlResult},
+};
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+impl<'src> Document<'src> {
+    /// Creates a new document from raw bytes, detecting the character encoding.
+    ///
+    /// The encoding is detected from the byte order mark if present, then from the `encoding` of the
+    /// `<?xml?>` declaration, and defaults to UTF-8.
+    ///
+    /// UTF-8 input is parsed in place. Other encodings are transcoded into `buffer`, which the document then borrows from.
+    ///
+    /// Without the `encoding_rs` feature, only UTF-8 input is supported.
+    ///
+    /// With it, any encoding in the [WHATWG Encoding Standard](https://encoding.spec.whatwg.org/) can be used,
+    /// including UTF-16 and ISO-8859-1 (decoded as windows-1252, as browsers do).
+    ///
+    /// Spans in the document are offsets into the decoded text, without the byte order mark.
+    /// A UTF-8 byte order mark is recorded, as with [`Document::parse_str`].
+    ///
+    /// # Errors
+    /// Returns errors if the encoding is not supported, if the input is not valid in its encoding, or if the XML is invalid
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::Document;
+    /// let bytes = b"\xEF\xBB\xBF<test>text</test>";
+    ///
+    /// let mut buffer = String::new();
+    /// let doc = Document::parse_bytes(bytes, &mut buffer).unwrap();
+    /// assert_eq!(doc.root().name(), "test");
+    /// ```
+    pub fn parse_bytes(bytes: &'src [u8], buffer: &'src mut String) -> XmlResult<Self> {
+        let src = decode(bytes, buffer)?;
+        Self::parse_str(src)
+    }
+}
+
+/// Decodes the input into a string, borrowing it directly if it is UTF-8.
+fn decode<'src>(bytes: &'src [u8], buffer: &'src mut String) -> XmlResult<&'src str> {
+    // Kept, so that the parser records it
+    if bytes.starts_with(UTF8_BOM) {
+        return decode_utf8(bytes);
+    }
+
+    let label = declared_encoding(bytes);
+    match label {
+        None => decode_utf8(bytes),
+        Some(label) if is_utf8_label(label) => decode_utf8(bytes),
+        Some(label) => transcode(bytes, label, buffer),
+    }
+}
+
+fn decode_utf8(bytes: &[u8]) -> XmlResult<&str> {
+    std::str::from_utf8(bytes).map_err(|e| {
+        XmlError::new(
+            XmlErrorKind::MalformedEncoding(format!("UTF-8: {e}")),
+            ErrorContext::new("", StrSpan::default()),
+        )
+    })
+}
+
+fn is_utf8_label(label: &str) -> bool {
+    ["utf-8", "utf8", "us-ascii", "ascii"]
+        .iter()
+        .any(|l| l.eq_ignore_ascii_case(label))
+}
+
+/// Returns the encoding named by the `<?xml?>` declaration, if the input starts with one.
+///
+/// UTF-16 input is detected from its byte order mark, or from the layout of the opening `<?`.
+fn declared_encoding(bytes: &[u8]) -> Option<&str> {
+    match bytes {
+        [0xFF, 0xFE, ..] | [0x3C, 0x00, 0x3F, 0x00, ..] => return Some("utf-16le"),
+        [0xFE, 0xFF, ..] | [0x00, 0x3C, 0x00, 0x3F, ..] => return Some("utf-16be"),
+        _ => {}
+    }
+
+    let bytes = bytes.strip_prefix(b"<?xml")?;
+    let end = bytes.windows(2).position(|w| w == b"?>")?;
+    let declaration = std::str::from_utf8(&bytes[..end]).ok()?;
+
+    let (_, value) = declaration.split_once("encoding")?;
+    let value = value.trim_start().strip_prefix('=')?.trim_start();
+    let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let value = &value[1..];
+    let end = value.find(quote)?;
+    Some(&value[..end])
+}
+
+#[cfg(feature = "encoding_rs")]
+fn transcode<'src>(
+    bytes: &'src [u8],
+    label: &str,
+    buffer: &'src mut String,
+) -> XmlResult<&'src str> {
+    let (encoding, bytes) = if let Some((encoding, bom_len)) = encoding_rs::Encoding::for_bom(bytes)
+    {
+        (encoding, &bytes[bom_len..])
+    } else {
+        let Some(encoding) = encoding_rs::Encoding::for_label(label.as_bytes()) else {
+            return Err(XmlError::new(
+                XmlErrorKind::UnsupportedEncoding(label.to_string()),
+                ErrorContext::new("", StrSpan::default()),
+            ));
+        };
+        (encoding, bytes)
+    };
+
+    let Some(text) = encoding.decode_without_bom_handling_and_without_replacement(bytes) else {
+        return Err(XmlError::new(
+            XmlErrorKind::MalformedEncoding(encoding.name().to_string()),
+            ErrorContext::new("", StrSpan::default()),
+        ));
+    };
+
+    *buffer = text.into_owned();
+    Ok(buffer.as_str())
+}
+
+#[cfg(not(feature = "encoding_rs"))]
+fn transcode<'src>(
+    _bytes: &'src [u8],
+    label: &str,
+    _buffer: &'src mut String,
+) -> XmlResult<&'src str> {
+    Err(XmlError::new(
+        XmlErrorKind::UnsupportedEncoding(format!(
+            "{label} (enable the `encoding_rs` feature to transcode it)"
+        )),
+        ErrorContext::new("", StrSpan::default()),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bytes_utf8() {
+        let src = "<?xml version=\"1.0\" encoding=\"UTF-8\"?><test>héllo</test>";
+        let mut buffer = String::new();
+        let doc = Document::parse_bytes(src.as_bytes(), &mut buffer).unwrap();
+        assert_eq!(doc.source(), Some(src));
+        assert!(!doc.has_bom());
+
+        let bytes = [UTF8_BOM, src.as_bytes()].concat();
+        let mut buffer = String::new();
+        let doc = Document::parse_bytes(&bytes, &mut buffer).unwrap();
+        assert_eq!(doc.source(), Some(src));
+        assert!(doc.has_bom());
+
+        let mut buffer = String::new();
+        let result = Document::parse_bytes(b"<test>\xFF</test>", &mut buffer);
+        assert!(matches!(
+            result.unwrap_err().kind,
+            XmlErrorKind::MalformedEncoding(_)
+        ));
+    }
+
+    #[test]
+    fn test_declared_encoding() {
+        assert_eq!(
+            declared_encoding(b"<?xml version='1.0' encoding = 'ISO-8859-1' ?><a/>"),
+            Some("ISO-8859-1")
+        );
+        assert_eq!(declared_encoding(b"<?xml version='1.0'?><a/>"), None);
+        assert_eq!(declared_encoding(b"<a/>"), None);
+        assert_eq!(declared_encoding(b"<\0?\0x\0"), Some("utf-16le"));
+    }
+
+    #[cfg(not(feature = "encoding_rs"))]
+    #[test]
+    fn test_parse_bytes_unsupported() {
+        let mut buffer = String::new();
+        let result =
+            Document::parse_bytes(b"<?xml version='1.0' encoding='latin1'?><a/>", &mut buffer);
+        assert!(matches!(
+            result.unwrap_err().kind,
+            XmlErrorKind::UnsupportedEncoding(_)
+        ));
+    }
+
+    #[cfg(feature = "encoding_rs")]
+    #[test]
+    fn test_parse_bytes_transcoded() {
+        let mut bytes = vec![0xFF, 0xFE];
+        for unit in "<test>héllo</test>".encode_utf16() {
+            bytes.extend_from_slice(&unit.to_le_bytes());
+        }
+
+        let mut buffer = String::new();
+        let doc = Document::parse_bytes(&bytes, &mut buffer).unwrap();
+        assert_eq!(doc.source(), Some("<test>héllo</test>"));
+
+        let bytes = b"<?xml version='1.0' encoding='ISO-8859-1'?><test>h\xE9llo</test>";
+        let mut buffer = String::new();
+        let doc = Document::parse_bytes(bytes, &mut buffer).unwrap();
+        let crate::node::Node::Text(text) = &doc.root().children()[0] else {
+            panic!("Expected text");
+        };
+        assert_eq!(text.text(), "héllo");
+    }
+}
@@ -0,0 +1,412 @@
+//! Expansion of entity references declared in the DTD, with limits against expansion attacks.
+use crate::{
+    Document, OwnedDocument, StrSpan,
+    error::{ErrorContext, XmlError, XmlErrorKind, XmlResult},
+    node::{EntityDefinition, Node, OwnedNode, OwnedTagNode, decode_attribute_value},
+    to_xml::is_xml10_char,
+};
+use std::{borrow::Cow, collections::HashMap};
+
+/// Limits applied while expanding entity references, guarding against documents like the "billion laughs" attack.
+///
+/// Limits are always applied; the defaults allow ordinary documents while stopping expansion bombs early.
+///
+/// # Example
+/// ```rust
+/// use xmltree::{Document, EntityLimits, error::XmlErrorKind, node::Node};
+///
+/// let src = r#"<!DOCTYPE a [
+///     <!ENTITY lol "lol">
+///     <!ENTITY lol2 "&lol;&lol;&lol;&lol;&lol;&lol;&lol;&lol;">
+///     <!ENTITY lol3 "&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;">
+/// ]><a>&lol3;</a>"#;
+/// let doc = Document::parse_str(src).unwrap();
+/// let Node::Text(text) = &doc.root().children()[0] else { unreachable!() };
+///
+/// assert_eq!(doc.expand_entities(text.text()).unwrap().len(), 192);
+///
+/// let limits = EntityLimits::default().with_max_expanded_size(100);
+/// let err = doc.expand_entities_with_limits(text.text(), &limits).unwrap_err();
+/// assert!(matches!(err.kind, XmlErrorKind::EntityExpansionLimit(_)));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EntityLimits {
+    /// How deeply entities can refer to other entities; a reference in the text itself is at depth 1.
+    pub max_depth: usize,
+
+    /// The most bytes of replacement text expanded in total, counting every reference expanded,
+    /// even those inside other entities. An entity that expands to nothing still counts as one byte.
+    pub max_expanded_size: usize,
+
+    /// Fail as soon as an entity refers back to itself, directly or through others, instead of when the depth limit is reached.
+    pub detect_recursion: bool,
+}
+impl Default for EntityLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: 16,
+            max_expanded_size: 1024 * 1024,
+            detect_recursion: true,
+        }
+    }
+}
+impl EntityLimits {
+    /// Sets how deeply entities can refer to other entities.
+    #[must_use]
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Sets the most bytes of replacement text expanded in total.
+    #[must_use]
+    pub fn with_max_expanded_size(mut self, max_expanded_size: usize) -> Self {
+        self.max_expanded_size = max_expanded_size;
+        self
+    }
+
+    /// Sets whether recursive entities are reported as soon as they are found.
+    #[must_use]
+    pub fn with_detect_recursion(mut self, detect: bool) -> Self {
+        self.detect_recursion = detect;
+        self
+    }
+}
+
+/// Resolves a predefined entity or character reference, given the text between `&` and `;`.
+pub(crate) fn resolve_builtin_reference(name: &str) -> Option<char> {
+    match name {
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "amp" => Some('&'),
+        "apos" => Some('\''),
+        "quot" => Some('"'),
+        _ => name
+            .strip_prefix("#x")
+            .map(|hex| u32::from_str_radix(hex, 16))
+            .or_else(|| name.strip_prefix('#').map(str::parse))
+            .and_then(Result::ok)
+            .and_then(char::from_u32),
+    }
+}
+
+/// Finds the next reference in `text`, as the offset of its `&` and the name up to its `;`.
+fn next_reference(text: &str) -> Option<(usize, &str)> {
+    let mut offset = 0;
+    while let Some(i) = text[offset..].find('&') {
+        let start = offset + i;
+        let rest = &text[start + 1..];
+        match rest.find(|c: char| c == ';' || c == '&' || c == '<' || c.is_whitespace()) {
+            Some(end) if rest[end..].starts_with(';') => return Some((start, &rest[..end])),
+            _ => offset = start + 1,
+        }
+    }
+    None
+}
+
+/// Decodes character references and predefined entities in raw text, keeping other references as written.
+///
+/// References to characters that are not allowed in XML are also kept, since they could not be written back.
+pub(crate) fn decode_references(raw: &str) -> Cow<'_, str> {
+    let Some((start, _)) = next_reference(raw) else {
+        return Cow::Borrowed(raw);
+    };
+
+    let mut decoded = String::with_capacity(raw.len());
+    decoded.push_str(&raw[..start]);
+    let mut rest = &raw[start..];
+    let mut changed = false;
+    while let Some((start, name)) = next_reference(rest) {
+        decoded.push_str(&rest[..start]);
+        match resolve_builtin_reference(name).filter(|c| is_xml10_char(*c)) {
+            Some(c) => {
+                decoded.push(c);
+                changed = true;
+            }
+            None => decoded.push_str(&rest[start..start + name.len() + 2]),
+        }
+        rest = &rest[start + name.len() + 2..];
+    }
+
+    if !changed {
+        return Cow::Borrowed(raw);
+    }
+    decoded.push_str(rest);
+    Cow::Owned(decoded)
+}
+
+impl<'src> Document<'src> {
+    /// Returns the internal general entities declared in the DTD, by name; the first declaration of a name wins.
+    fn internal_entities(&self) -> HashMap<&'src str, &'src str> {
+        let mut entities = HashMap::new();
+        for node in self.prolog() {
+            let Node::DocumentType(dtd) = node else {
+                continue;
+            };
+            for entity in dtd.entities() {
+                if let EntityDefinition::EntityValue(value) = entity.definition {
+                    entities.entry(entity.name.text()).or_insert(value.text());
+                }
+            }
+        }
+        entities
+    }
+
+    /// Expands the entity references in raw text from this document, using the default [`EntityLimits`].
+    ///
+    /// Predefined entities, character references and entities declared with a value in the DTD are expanded,
+    /// including references inside entity values. References to undeclared or external entities are left as they are.
+    ///
+    /// # Errors
+    /// Returns an [`XmlErrorKind::EntityExpansionLimit`] error, pointing at the reference in `text` that caused it,
+    /// if the expansion breaks a limit.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{Document, node::Node};
+    ///
+    /// let doc = Document::parse_str("<!DOCTYPE a [<!ENTITY co 'ACME &amp; Co'>]><a>&co; &lt;3 &other;</a>").unwrap();
+    /// let Node::Text(text) = &doc.root().children()[0] else { unreachable!() };
+    /// assert_eq!(doc.expand_entities(text.text()).unwrap(), "ACME & Co <3 &other;");
+    /// ```
+    pub fn expand_entities(&self, text: &StrSpan<'src>) -> XmlResult<Cow<'src, str>> {
+        self.expand_entities_with_limits(text, &EntityLimits::default())
+    }
+
+    /// Expands the entity references in raw text from this document, using the given limits.
+    ///
+    /// See [`Document::expand_entities`].
+    ///
+    /// # Errors
+    /// Returns an [`XmlErrorKind::EntityExpansionLimit`] error if the expansion breaks a limit.
+    pub fn expand_entities_with_limits(
+        &self,
+        text: &StrSpan<'src>,
+        limits: &EntityLimits,
+    ) -> XmlResult<Cow<'src, str>> {
+        let raw = text.text();
+        if !raw.contains('&') {
+            return Ok(Cow::Borrowed(raw));
+        }
+
+        let entities = self.internal_entities();
+        let mut expanded = String::with_capacity(raw.len());
+        let mut total = 0usize;
+
+        // Text still to expand, and the entity it is the value of; the first entry is the text itself
+        let mut stack: Vec<(&str, Option<&str>)> = vec![(raw, None)];
+
+        // Span of the reference in `text` being expanded, for errors
+        let mut reference = *text;
+        let fail = |reference: StrSpan<'_>, message: String| {
+            XmlError::new(
+                XmlErrorKind::EntityExpansionLimit(message),
+                ErrorContext::new(self.source().unwrap_or_default(), reference),
+            )
+        };
+
+        loop {
+            let depth = stack.len();
+            let Some((rest, _)) = stack.last_mut() else {
+                break;
+            };
+            let Some((start, name)) = next_reference(rest) else {
+                expanded.push_str(rest);
+                stack.pop();
+                continue;
+            };
+
+            expanded.push_str(&rest[..start]);
+            let after = &rest[start + name.len() + 2..];
+            if depth == 1 {
+                let offset = raw.len() - rest.len() + start;
+                reference = text.slice(offset..offset + name.len() + 2);
+            }
+            *rest = after;
+
+            if let Some(c) = resolve_builtin_reference(name) {
+                expanded.push(c);
+                continue;
+            }
+            let Some(value) = entities.get(name) else {
+                expanded.push('&');
+                expanded.push_str(name);
+                expanded.push(';');
+                continue;
+            };
+
+            if limits.detect_recursion && stack.iter().any(|(_, entity)| *entity == Some(name)) {
+                return Err(fail(
+                    reference,
+                    format!("The entity '{name}' refers to itself"),
+                ));
+            }
+            if depth > limits.max_depth {
+                return Err(fail(
+                    reference,
+                    format!(
+                        "Entities are nested deeper than the limit of {}",
+                        limits.max_depth
+                    ),
+                ));
+            }
+            total = total.saturating_add(value.len().max(1));
+            if total > limits.max_expanded_size {
+                return Err(fail(
+                    reference,
+                    format!(
+                        "Expansion is larger than the limit of {} bytes",
+                        limits.max_expanded_size
+                    ),
+                ));
+            }
+
+            stack.push((value, Some(name)));
+        }
+
+        Ok(Cow::Owned(expanded))
+    }
+}
+
+impl OwnedTagNode {
+    /// Decodes references in the attribute values and text at or below this tag; see [`OwnedDocument::decode_references`].
+    pub fn decode_references(&mut self) -> usize {
+        let mut count = 0;
+        let mut stack = vec![self];
+        while let Some(tag) = stack.pop() {
+            for attr in &mut tag.attributes {
+                if let Cow::Owned(value) = decode_attribute_value(&attr.value) {
+                    attr.value = value;
+                    count += 1;
+                }
+            }
+
+            for child in &mut tag.children {
+                match child {
+                    OwnedNode::Tag(child) => stack.push(child),
+                    OwnedNode::Text(text) => {
+                        if let Cow::Owned(decoded) = decode_references(&text.text) {
+                            text.text = decoded;
+                            count += 1;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        count
+    }
+}
+
+impl OwnedDocument {
+    /// Decodes character references and predefined entities in attribute values and text across the document,
+    /// returning how many values were changed.
+    ///
+    /// Parsed values are kept as written in the source, while values in an owned tree are written as plain text and escaped.
+    /// Run this pass after converting a parsed document to get plain values, so that they can be matched directly,
+    /// and so that references are not escaped a second time when the document is written.
+    ///
+    /// Attribute values are normalized as with [`NodeAttribute::decoded_value`](crate::node::NodeAttribute::decoded_value),
+    /// and text as with [`StrSpan::decoded`]. References to other entities are kept as written.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{Document, XmlWriteOptions};
+    ///
+    /// let doc = Document::parse_str("<a title='Tom &amp; Jerry'>1 &lt; 2</a>").unwrap();
+    /// let mut owned = doc.to_owned();
+    /// assert_eq!(owned.decode_references(), 2);
+    /// assert_eq!(owned.root.attributes[0].value, "Tom & Jerry");
+    ///
+    /// let xml = owned.to_xml_with_options(&XmlWriteOptions::default().with_minify(true)).unwrap();
+    /// assert_eq!(xml, "<a title=\"Tom &amp; Jerry\">1 &lt; 2</a>\n");
+    /// ```
+    pub fn decode_references(&mut self) -> usize {
+        self.root.decode_references()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand(src: &str, limits: &EntityLimits) -> XmlResult<String> {
+        let doc = Document::parse_str(src).unwrap();
+        let Node::Text(text) = &doc.root().children()[0] else {
+            panic!("Expected text");
+        };
+        doc.expand_entities_with_limits(text.text(), limits)
+            .map(Cow::into_owned)
+    }
+
+    fn limit_message(result: XmlResult<String>) -> String {
+        match result.unwrap_err().kind {
+            XmlErrorKind::EntityExpansionLimit(message) => message,
+            kind => panic!("Unexpected error: {kind}"),
+        }
+    }
+
+    #[test]
+    fn test_expand_entities() {
+        let limits = EntityLimits::default();
+        let src = "<!DOCTYPE a [<!ENTITY a 'x&b;z'><!ENTITY b '&#x79;'><!ENTITY a 'ignored'><!ENTITY e SYSTEM 'e.ent'>]><a>&a;&amp;&e; & &a</a>";
+        assert_eq!(expand(src, &limits).unwrap(), "xyz&&e; & &a");
+
+        // Depth counts the reference in the text itself
+        assert!(expand(src, &limits.with_max_depth(2)).is_ok());
+        let message = limit_message(expand(src, &limits.with_max_depth(1)));
+        assert_eq!(message, "Entities are nested deeper than the limit of 1");
+
+        // Each expansion counts its replacement text, even when nested
+        assert!(expand(src, &limits.with_max_expanded_size(11)).is_ok());
+        assert!(expand(src, &limits.with_max_expanded_size(10)).is_err());
+    }
+
+    #[test]
+    fn test_recursive_entities() {
+        let src =
+            "<!DOCTYPE a [<!ENTITY a '&b;'><!ENTITY b '&a;'><!ENTITY e ''>]><a>&e;&e; &a;</a>";
+        let doc = Document::parse_str(src).unwrap();
+        let Node::Text(text) = &doc.root().children()[0] else {
+            panic!("Expected text");
+        };
+
+        let err = doc.expand_entities(text.text()).unwrap_err();
+        assert_eq!(err.context.span.as_str(), "&a;");
+        assert!(
+            matches!(err.kind, XmlErrorKind::EntityExpansionLimit(m) if m == "The entity 'a' refers to itself")
+        );
+
+        // Without detection, the depth limit still stops it
+        let limits = EntityLimits::default().with_detect_recursion(false);
+        let message = limit_message(expand(src, &limits));
+        assert_eq!(message, "Entities are nested deeper than the limit of 16");
+
+        // Empty entities still count towards the size
+        let limits = EntityLimits::default().with_max_expanded_size(1);
+        assert!(limit_message(expand(src, &limits)).starts_with("Expansion is larger"));
+    }
+
+    #[test]
+    fn test_decode_references() {
+        assert!(matches!(decode_references("a & b"), Cow::Borrowed("a & b")));
+        assert_eq!(
+            decode_references("&lt;&#x41;&#66;&x; &amp &gt;"),
+            "<AB&x; &amp >"
+        );
+
+        let doc =
+            Document::parse_str("<a x='1&#9;&amp;\t2'>&quot;q&quot;<b y='plain'>&#0;</b></a>")
+                .unwrap();
+        let mut owned = doc.to_owned();
+        assert_eq!(owned.decode_references(), 2);
+        assert_eq!(owned.root.attributes[0].value, "1\t& 2");
+        assert!(matches!(&owned.root.children[0], OwnedNode::Text(text) if text.text == "\"q\""));
+
+        // Invalid characters are kept as written
+        let OwnedNode::Tag(b) = &owned.root.children[1] else {
+            panic!("Expected a tag");
+        };
+        assert!(matches!(&b.children[0], OwnedNode::Text(text) if text.text == "&#0;"));
+    }
+}
@@ -15,6 +15,9 @@ pub struct XmlError {
 
     /// The kind of error that occurred while parsing a document
     pub kind: XmlErrorKind,
+
+    /// How serious the problem is; errors are [`Severity::Error`] unless marked otherwise
+    pub severity: Severity,
 }
 impl XmlError {
     /// Creates a new `XmlError`
@@ -23,9 +26,75 @@ impl XmlError {
         Self {
             context: Box::new(context),
             kind,
+            severity: Severity::Error,
         }
     }
 
+    /// Sets the severity of the error.
+    #[must_use]
+    pub fn with_severity(mut self, severity: Severity) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    /// Adds a secondary location to the error, with a note explaining how it relates.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::Document;
+    ///
+    /// let err = Document::parse_str("<a><b></a>").unwrap_err();
+    /// let related = &err.context.related[0];
+    /// assert_eq!(related.position(), (1, 4));
+    /// assert_eq!(related.note, "Opening tag is here");
+    /// ```
+    #[must_use]
+    pub fn with_related(mut self, source: &str, span: StrSpan, note: impl Into<String>) -> Self {
+        self.context
+            .related
+            .push(RelatedSpan::new(source, span, note));
+        self
+    }
+
+    /// Returns the stable code identifying the kind of error.
+    ///
+    /// Tooling should match on this instead of the `Display` output, which may change between versions.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{Document, error::{ErrorCode, Severity}};
+    ///
+    /// let err = Document::parse_str("<a><b></a>").unwrap_err();
+    /// assert_eq!(err.code(), ErrorCode::UnclosedTag);
+    /// assert_eq!(err.code().as_str(), "XML0003");
+    /// assert!(err.is_error());
+    ///
+    /// let warning = err.with_severity(Severity::Warning);
+    /// assert_eq!(warning.severity, Severity::Warning);
+    /// ```
+    #[must_use]
+    pub fn code(&self) -> ErrorCode {
+        self.kind.code()
+    }
+
+    /// Returns true if the error has [`Severity::Error`].
+    #[must_use]
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// Returns true if the error has [`Severity::Warning`].
+    #[must_use]
+    pub fn is_warning(&self) -> bool {
+        self.severity == Severity::Warning
+    }
+
+    /// Returns the message describing the error, without the source location.
+    #[must_use]
+    pub fn message(&self) -> String {
+        self.kind.to_string()
+    }
+
     /// Adds a path to the error context.
     #[must_use]
     pub fn with_path(mut self, path: PathBuf) -> Self {
@@ -40,9 +109,24 @@ impl std::fmt::Display for XmlError {
         for line in self.kind.to_string().lines() {
             writeln!(f, "= {line}")?;
         }
+
+        for related in &self.context.related {
+            let (row, col) = related.position();
+            writeln!(f, "= Note: {note}, at {row}:{col}", note = related.note)?;
+
+            let line = related.span.as_str();
+            if !line.is_empty() {
+                writeln!(f, "| {line}")?;
+            }
+        }
         Ok(())
     }
 }
+impl std::error::Error for XmlError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.kind)
+    }
+}
 impl From<BinDecodeError> for XmlError {
     fn from(err: BinDecodeError) -> Self {
         Self::new(
@@ -79,11 +163,72 @@ pub enum XmlErrorKind {
     #[error("End of file reached unexpectedly")]
     UnexpectedEof,
 
+    /// The document uses a character encoding that is not supported
+    #[error("Unsupported encoding: {0}")]
+    UnsupportedEncoding(String),
+
+    /// The document contains bytes that are not valid in its character encoding
+    #[error("Document is not valid {0}")]
+    MalformedEncoding(String),
+
+    /// Access to an external resource was refused by the resolver
+    #[error("Access to external resource denied: {0}")]
+    ExternalAccessDenied(String),
+
+    /// A node appeared outside of the root in a position the XML spec does not allow
+    #[error("Invalid document structure: {0}")]
+    InvalidStructure(String),
+
+    /// Namespace prefixes could not be rebound without changing the meaning of a name
+    #[error("Namespace conflict: {0}")]
+    NamespaceConflict(String),
+
+    /// An attribute appears more than once on the same tag; reported as a warning
+    #[error("Duplicate attribute: {0}")]
+    DuplicateAttribute(String),
+
+    /// The DOCTYPE follows comments or processing instructions; reported as a warning
+    #[error("The DOCTYPE should appear before any other content")]
+    LateDoctype,
+
+    /// Something other than whitespace follows the root element; reported as a warning
+    #[error("Content after the root element: {0}")]
+    EpilogContent(String),
+
+    /// An entity reference is malformed, or refers to an entity that was not declared; reported as a warning
+    #[error("Suspicious entity reference: {0}")]
+    SuspiciousEntity(String),
+
+    /// Expanding entity references broke one of the [`EntityLimits`](crate::EntityLimits)
+    #[error("Entity expansion limit: {0}")]
+    EntityExpansionLimit(String),
+
+    /// Content was refused by a [`Sanitizer`](crate::Sanitizer) set to reject instead of strip
+    #[error("Content not allowed: {0}")]
+    Disallowed(String),
+
+    /// A tag could not be converted into a typed value; see [`FromXml`](crate::from_xml::FromXml)
+    #[error("Conversion failed: {0}")]
+    Conversion(String),
+
+    /// A character reference refers to a character XML does not allow; see [`Parser::with_strict_chars`](crate::Parser::with_strict_chars)
+    #[error("Character not allowed in XML: {0}")]
+    InvalidCharacter(String),
+
+    /// Tags were nested deeper than the configured limit
+    #[error("Tags are nested deeper than the limit of {0}")]
+    DepthLimitExceeded(usize),
+
     /// XML parsing failed
     #[from(xmlparser::Error)]
     #[error("XML parser error: {0}")]
     Xml(xmlparser::Error),
 
+    /// Conversion to a `roxmltree` document failed
+    #[cfg(feature = "roxmltree")]
+    #[error("roxmltree error: {0}")]
+    Roxmltree(roxmltree::Error),
+
     /// IO error occurred while reading a file
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
@@ -92,18 +237,225 @@ pub enum XmlErrorKind {
     #[error("Invalid bytecode: {0}")]
     Decode(#[from] BinDecodeError),
 }
+impl XmlErrorKind {
+    /// Returns the stable code identifying this kind of error.
+    #[must_use]
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::Custom(_) => ErrorCode::Custom,
+            Self::DeclarationNotFirst => ErrorCode::DeclarationNotFirst,
+            Self::UnclosedTag(_) => ErrorCode::UnclosedTag,
+            Self::UnexpectedEof => ErrorCode::UnexpectedEof,
+            Self::UnsupportedEncoding(_) => ErrorCode::UnsupportedEncoding,
+            Self::MalformedEncoding(_) => ErrorCode::MalformedEncoding,
+            Self::ExternalAccessDenied(_) => ErrorCode::ExternalAccessDenied,
+            Self::InvalidStructure(_) => ErrorCode::InvalidStructure,
+            Self::NamespaceConflict(_) => ErrorCode::NamespaceConflict,
+            Self::DepthLimitExceeded(_) => ErrorCode::DepthLimitExceeded,
+            Self::Xml(_) => ErrorCode::Xml,
+            #[cfg(feature = "roxmltree")]
+            Self::Roxmltree(_) => ErrorCode::Roxmltree,
+            Self::Io(_) => ErrorCode::Io,
+            Self::Decode(_) => ErrorCode::Decode,
+            Self::DuplicateAttribute(_) => ErrorCode::DuplicateAttribute,
+            Self::LateDoctype => ErrorCode::LateDoctype,
+            Self::EpilogContent(_) => ErrorCode::EpilogContent,
+            Self::SuspiciousEntity(_) => ErrorCode::SuspiciousEntity,
+            Self::Disallowed(_) => ErrorCode::Disallowed,
+            Self::EntityExpansionLimit(_) => ErrorCode::EntityExpansionLimit,
+            Self::Conversion(_) => ErrorCode::Conversion,
+            Self::InvalidCharacter(_) => ErrorCode::InvalidCharacter,
+        }
+    }
+}
+
+/// A stable, machine-readable identifier for a kind of error.
+///
+/// Each code has a fixed string form, like `XML0003`, that never changes meaning between versions;
+/// new kinds of error get new codes. Codes exist for every kind of error, whether or not
+/// the feature producing it is enabled.
+///
+/// # Example
+/// ```rust
+/// use xmltree::error::ErrorCode;
+///
+/// let code = ErrorCode::UnclosedTag;
+/// assert_eq!(code.as_str(), "XML0003");
+/// assert_eq!(ErrorCode::from_str_code("XML0003"), Some(code));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ErrorCode {
+    /// See [`XmlErrorKind::Custom`]
+    Custom,
+
+    /// See [`XmlErrorKind::DeclarationNotFirst`]
+    DeclarationNotFirst,
+
+    /// See [`XmlErrorKind::UnclosedTag`]
+    UnclosedTag,
+
+    /// See [`XmlErrorKind::UnexpectedEof`]
+    UnexpectedEof,
+
+    /// See [`XmlErrorKind::UnsupportedEncoding`]
+    UnsupportedEncoding,
+
+    /// See [`XmlErrorKind::MalformedEncoding`]
+    MalformedEncoding,
+
+    /// See [`XmlErrorKind::ExternalAccessDenied`]
+    ExternalAccessDenied,
+
+    /// See [`XmlErrorKind::InvalidStructure`]
+    InvalidStructure,
+
+    /// See [`XmlErrorKind::NamespaceConflict`]
+    NamespaceConflict,
+
+    /// See [`XmlErrorKind::DepthLimitExceeded`]
+    DepthLimitExceeded,
+
+    /// See [`XmlErrorKind::Xml`]
+    Xml,
+
+    /// Conversion to a `roxmltree` document failed
+    Roxmltree,
+
+    /// See [`XmlErrorKind::Io`]
+    Io,
+
+    /// See [`XmlErrorKind::Decode`]
+    Decode,
+
+    /// See [`XmlErrorKind::DuplicateAttribute`]
+    DuplicateAttribute,
+
+    /// See [`XmlErrorKind::LateDoctype`]
+    LateDoctype,
+
+    /// See [`XmlErrorKind::EpilogContent`]
+    EpilogContent,
+
+    /// See [`XmlErrorKind::SuspiciousEntity`]
+    SuspiciousEntity,
+
+    /// See [`XmlErrorKind::Disallowed`]
+    Disallowed,
+
+    /// See [`XmlErrorKind::EntityExpansionLimit`]
+    EntityExpansionLimit,
+
+    /// See [`XmlErrorKind::Conversion`]
+    Conversion,
+
+    /// See [`XmlErrorKind::InvalidCharacter`]
+    InvalidCharacter,
+}
+impl ErrorCode {
+    /// Every code, in order of their string forms.
+    pub const ALL: [ErrorCode; 22] = [
+        Self::Custom,
+        Self::DeclarationNotFirst,
+        Self::UnclosedTag,
+        Self::UnexpectedEof,
+        Self::UnsupportedEncoding,
+        Self::MalformedEncoding,
+        Self::ExternalAccessDenied,
+        Self::InvalidStructure,
+        Self::NamespaceConflict,
+        Self::DepthLimitExceeded,
+        Self::Xml,
+        Self::Roxmltree,
+        Self::Io,
+        Self::Decode,
+        Self::DuplicateAttribute,
+        Self::LateDoctype,
+        Self::EpilogContent,
+        Self::SuspiciousEntity,
+        Self::Disallowed,
+        Self::EntityExpansionLimit,
+        Self::Conversion,
+        Self::InvalidCharacter,
+    ];
+
+    /// Returns the string form of the code.
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Custom => "XML0001",
+            Self::DeclarationNotFirst => "XML0002",
+            Self::UnclosedTag => "XML0003",
+            Self::UnexpectedEof => "XML0004",
+            Self::UnsupportedEncoding => "XML0005",
+            Self::MalformedEncoding => "XML0006",
+            Self::ExternalAccessDenied => "XML0007",
+            Self::InvalidStructure => "XML0008",
+            Self::NamespaceConflict => "XML0009",
+            Self::DepthLimitExceeded => "XML0010",
+            Self::Xml => "XML0011",
+            Self::Roxmltree => "XML0012",
+            Self::Io => "XML0013",
+            Self::Decode => "XML0014",
+            Self::DuplicateAttribute => "XML0015",
+            Self::LateDoctype => "XML0016",
+            Self::EpilogContent => "XML0017",
+            Self::SuspiciousEntity => "XML0018",
+            Self::Disallowed => "XML0019",
+            Self::EntityExpansionLimit => "XML0020",
+            Self::Conversion => "XML0021",
+            Self::InvalidCharacter => "XML0022",
+        }
+    }
+
+    /// Finds the code with the given string form.
+    #[must_use]
+    pub fn from_str_code(code: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.as_str() == code)
+    }
+}
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// How serious a reported problem is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub enum Severity {
+    /// The document could not be processed
+    #[default]
+    Error,
+
+    /// The document was processed, but something in it is likely a mistake
+    Warning,
+}
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Error => f.write_str("error"),
+            Self::Warning => f.write_str("warning"),
+        }
+    }
+}
 
 /// Context describing the error location in the source code.
+///
+/// Only the part of the source around each location is kept, so errors stay small for large inputs.
 #[derive(Debug, Clone)]
 pub struct ErrorContext {
     /// The path to the file that was parsed, if available.
     pub path: Option<PathBuf>,
 
-    /// Full source code of the file that was parsed, for row/col calculation.
-    pub source: String,
-
     /// Position of the error in the source code.
+    ///
+    /// The text is cut at the end of its first line, and to at most [`Excerpt::RADIUS`] bytes.
     pub span: StringSpan,
+
+    /// The source line around the error.
+    pub excerpt: Excerpt,
+
+    /// Other locations involved in the error, each with a note explaining its part.
+    pub related: Vec<RelatedSpan>,
 }
 impl ErrorContext {
     /// Creates a new `ErrorContext` with the given source, and span.
@@ -111,8 +463,9 @@ impl ErrorContext {
     pub fn new(source: &str, span: StrSpan) -> Self {
         Self {
             path: None,
-            source: source.to_string(),
-            span: span.into(),
+            span: Excerpt::cut(span),
+            excerpt: Excerpt::new(source, span.start()),
+            related: Vec::new(),
         }
     }
 
@@ -121,25 +474,22 @@ impl ErrorContext {
     pub fn with_path(path: PathBuf, source: &str, span: StrSpan) -> Self {
         Self {
             path: Some(path),
-            source: source.to_string(),
-            span: span.into(),
+            ..Self::new(source, span)
         }
     }
 
     /// Returns the row and column of the error in the source code.
     #[must_use]
     pub fn position(&self) -> (usize, usize) {
-        self.span.position(&self.source)
+        self.excerpt.position_of(self.span.start())
     }
 }
 impl std::fmt::Display for ErrorContext {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let path = self.path.as_ref().map(|p| p.display());
 
-        let span = self.span.as_ref();
-        let line = span.split('\n').next().unwrap_or(span);
-
-        let (row, col) = self.span.position(&self.source);
+        let line = self.span.as_str();
+        let (row, col) = self.position();
 
         if !line.is_empty() {
             writeln!(f, "| {line}")?;
@@ -159,3 +509,122 @@ impl std::fmt::Display for ErrorContext {
         Ok(())
     }
 }
+
+/// A secondary location involved in an error. See [`ErrorContext::related`].
+#[derive(Debug, Clone)]
+pub struct RelatedSpan {
+    /// Position in the source code, cut like [`ErrorContext::span`].
+    pub span: StringSpan,
+
+    /// The source line around the location.
+    pub excerpt: Excerpt,
+
+    /// Explains how the location relates to the error.
+    pub note: String,
+}
+impl RelatedSpan {
+    /// Creates a new `RelatedSpan` from the given source, span, and note.
+    #[must_use]
+    pub fn new(source: &str, span: StrSpan, note: impl Into<String>) -> Self {
+        Self {
+            span: Excerpt::cut(span),
+            excerpt: Excerpt::new(source, span.start()),
+            note: note.into(),
+        }
+    }
+
+    /// Returns the row and column of the location in the source code.
+    #[must_use]
+    pub fn position(&self) -> (usize, usize) {
+        self.excerpt.position_of(self.span.start())
+    }
+}
+
+/// The part of a source line around a location, with its position precomputed.
+///
+/// Errors keep excerpts instead of the whole source. Lines longer than twice [`Excerpt::RADIUS`]
+/// are cut around the location, so minified documents do not end up copied either.
+///
+/// # Example
+/// ```rust
+/// use xmltree::error::Excerpt;
+///
+/// let src = format!("<a>\n{}<b>", "x".repeat(1000));
+/// let excerpt = Excerpt::new(&src, 504);
+/// assert_eq!(excerpt.text.len(), 2 * Excerpt::RADIUS);
+/// assert_eq!((excerpt.row, excerpt.col), (2, 245));
+/// assert_eq!(excerpt.position_of(504), (2, 501));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Excerpt {
+    /// The text of the excerpt, and its offset in the source.
+    pub text: StringSpan,
+
+    /// The row of the excerpt in the source, starting at 1.
+    pub row: usize,
+
+    /// The column the excerpt starts at, starting at 1.
+    pub col: usize,
+}
+impl Excerpt {
+    /// The most bytes kept on each side of a location.
+    pub const RADIUS: usize = 256;
+
+    /// Creates the excerpt of `source` around the byte offset.
+    #[must_use]
+    pub fn new(source: &str, offset: usize) -> Self {
+        let offset = floor_char_boundary(source, offset.min(source.len()));
+        let line_start = source[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[offset..]
+            .find('\n')
+            .map_or(source.len(), |i| offset + i);
+
+        let start = offset.saturating_sub(Self::RADIUS).max(line_start);
+        let start = ceil_char_boundary(source, start);
+        let end = floor_char_boundary(source, line_end.min(offset + Self::RADIUS));
+
+        let row = 1 + source[..line_start].matches('\n').count();
+        let col = 1 + source[line_start..start].chars().count();
+
+        Self {
+            text: StringSpan::new(source[start..end].to_string(), start),
+            row,
+            col,
+        }
+    }
+
+    /// Returns the row and column of a byte offset inside the excerpt.
+    ///
+    /// Offsets outside the excerpt are clamped to its ends.
+    #[must_use]
+    pub fn position_of(&self, offset: usize) -> (usize, usize) {
+        let text = self.text.as_str();
+        let offset = offset.saturating_sub(self.text.start()).min(text.len());
+        let offset = floor_char_boundary(text, offset);
+        (self.row, self.col + text[..offset].chars().count())
+    }
+
+    /// Cuts a span to the end of its first line, and to at most [`Excerpt::RADIUS`] bytes.
+    fn cut(span: StrSpan) -> StringSpan {
+        let text = span.text();
+        let text = text.split('\n').next().unwrap_or(text);
+        let text = &text[..floor_char_boundary(text, text.len().min(Self::RADIUS))];
+        StringSpan::new(text.to_string(), span.start())
+    }
+}
+
+/// Moves a byte offset back to the nearest character boundary.
+fn floor_char_boundary(text: &str, mut offset: usize) -> usize {
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+/// Moves a byte offset forward to the nearest character boundary.
+fn ceil_char_boundary(text: &str, mut offset: usize) -> usize {
+    while !text.is_char_boundary(offset) {
+        offset += 1;
+    }
+    offset
+}
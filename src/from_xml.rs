@@ -0,0 +1,364 @@
+//! Typed conversion of tags into Rust values, with errors that point at the offending node.
+//!
+//! Types implement [`FromXml`] to be built from a tag, and [`FromXmlValue`] to be built from the text of an attribute or tag.
+//! Every [`FromXmlValue`] type is also [`FromXml`], using the text of the tag.
+//!
+//! With the `derive` feature, [`FromXml`] can be derived for structs with named fields:
+//! - Fields are read from the only child tag named after the field, by default
+//! - `#[xml(attribute)]` reads a field from an attribute instead, and `#[xml(text)]` from the text of the tag itself
+//! - `#[xml(rename = "name")]` sets the name to look for, which can have a prefix as `prefix:local`
+//! - `Option<T>` fields may be missing, and `Vec<T>` fields collect every child tag with the name
+//!
+//! ```rust
+//! # #[cfg(feature = "derive")] {
+//! use xmltree::{Document, from_xml::FromXml};
+//!
+//! #[derive(Debug, FromXml)]
+//! struct Book {
+//!     #[xml(attribute)]
+//!     id: u32,
+//!     title: String,
+//!     #[xml(rename = "author")]
+//!     authors: Vec<String>,
+//!     year: Option<i32>,
+//! }
+//!
+//! let doc = Document::parse_str("<book id='7'><title>Good Omens</title><author>Pratchett</author><author>Gaiman</author></book>").unwrap();
+//! let book: Book = doc.parse_into().unwrap();
+//! assert_eq!(book.id, 7);
+//! assert_eq!(book.authors, ["Pratchett", "Gaiman"]);
+//! assert_eq!(book.year, None);
+//!
+//! let doc = Document::parse_str("<book id='x'><title>Dune</title></book>").unwrap();
+//! let err = doc.parse_into::<Book>().unwrap_err();
+//! assert_eq!(err.context.span.as_str(), "id='x'");
+//! # }
+//! ```
+use crate::{
+    Document, StrSpan,
+    error::{ErrorContext, XmlError, XmlErrorKind, XmlResult},
+    node::{Ambiguity, Node, TagNode},
+};
+use std::borrow::Cow;
+
+#[cfg(feature = "derive")]
+pub use xmltree_derive::FromXml;
+
+/// A type that can be built from a tag.
+///
+/// # Example
+/// ```rust
+/// use xmltree::{Document, error::XmlResult, from_xml::{self, FromXml}, node::TagNode};
+///
+/// struct Point {
+///     x: f64,
+///     y: f64,
+/// }
+/// impl<'src> FromXml<'src> for Point {
+///     fn from_xml(tag: &TagNode<'src>, source: &'src str) -> XmlResult<Self> {
+///         Ok(Self {
+///             x: from_xml::required_attribute(tag, source, "x")?,
+///             y: from_xml::required_attribute(tag, source, "y")?,
+///         })
+///     }
+/// }
+///
+/// let doc = Document::parse_str("<point x='1.5' y='-2' />").unwrap();
+/// let point: Point = doc.parse_into().unwrap();
+/// assert_eq!((point.x, point.y), (1.5, -2.0));
+/// ```
+pub trait FromXml<'src>: Sized {
+    /// Builds the value from a tag, where `source` is the document the tag was parsed from, used to locate errors.
+    ///
+    /// # Errors
+    /// Returns an error if the tag does not have the expected structure, or a value cannot be converted.
+    fn from_xml(tag: &TagNode<'src>, source: &'src str) -> XmlResult<Self>;
+}
+
+/// A type that can be built from the text of an attribute or tag.
+///
+/// The text has character references and the predefined entities decoded, and line breaks normalized to `\n`.
+pub trait FromXmlValue<'src>: Sized {
+    /// Converts the text.
+    ///
+    /// # Errors
+    /// Returns a description of the problem if the text is not a valid value.
+    fn from_xml_value(text: Cow<'src, str>) -> Result<Self, String>;
+}
+
+impl<'src, T: FromXmlValue<'src>> FromXml<'src> for T {
+    fn from_xml(tag: &TagNode<'src>, source: &'src str) -> XmlResult<Self> {
+        text(tag, source)
+    }
+}
+
+impl<'src> FromXmlValue<'src> for Cow<'src, str> {
+    fn from_xml_value(text: Cow<'src, str>) -> Result<Self, String> {
+        Ok(text)
+    }
+}
+
+impl<'src> FromXmlValue<'src> for String {
+    fn from_xml_value(text: Cow<'src, str>) -> Result<Self, String> {
+        Ok(text.into_owned())
+    }
+}
+
+impl<'src> FromXmlValue<'src> for bool {
+    /// Accepts the forms allowed by XML Schema; `true`, `false`, `1` and `0`.
+    fn from_xml_value(text: Cow<'src, str>) -> Result<Self, String> {
+        match text.trim() {
+            "true" | "1" => Ok(true),
+            "false" | "0" => Ok(false),
+            other => Err(format!("`{other}` is not a boolean")),
+        }
+    }
+}
+
+macro_rules! impl_from_str {
+    ($($ty:ty),*) => {
+        $(
+            impl<'src> FromXmlValue<'src> for $ty {
+                fn from_xml_value(text: Cow<'src, str>) -> Result<Self, String> {
+                    text.trim()
+                        .parse()
+                        .map_err(|e| format!("`{}` is not a valid {}: {e}", text.trim(), stringify!($ty)))
+                }
+            }
+        )*
+    };
+}
+impl_from_str!(
+    i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64, char
+);
+
+/// Splits a name written as `prefix:local`.
+fn split_name(name: &str) -> (Option<&str>, &str) {
+    match name.split_once(':') {
+        Some((prefix, local)) => (Some(prefix), local),
+        None => (None, name),
+    }
+}
+
+fn conversion_error(source: &str, span: StrSpan<'_>, message: String) -> XmlError {
+    XmlError::new(
+        XmlErrorKind::Conversion(message),
+        ErrorContext::new(source, span),
+    )
+}
+
+/// Returns the error for a required attribute or child that is missing from `tag`.
+#[must_use]
+pub fn missing(tag: &TagNode<'_>, source: &str, what: &str) -> XmlError {
+    let message = format!("`{}` is missing {what}", tag.name());
+    conversion_error(source, *tag.span(), message)
+}
+
+/// Reads an attribute of `tag`, named as `local` or `prefix:local`, or returns `None` if it is missing.
+///
+/// # Errors
+/// Returns an error pointing at the attribute if its value cannot be converted.
+pub fn attribute<'src, T: FromXmlValue<'src>>(
+    tag: &TagNode<'src>,
+    source: &'src str,
+    name: &str,
+) -> XmlResult<Option<T>> {
+    let (prefix, local) = split_name(name);
+    let Some(attr) = tag.get_attribute(prefix, local) else {
+        return Ok(None);
+    };
+
+    T::from_xml_value(attr.decoded_value())
+        .map(Some)
+        .map_err(|message| {
+            let message = format!("Attribute `{name}`: {message}");
+            conversion_error(source, *attr.span(), message)
+        })
+}
+
+/// Reads an attribute of `tag`, named as `local` or `prefix:local`.
+///
+/// # Errors
+/// Returns an error if the attribute is missing, or its value cannot be converted.
+pub fn required_attribute<'src, T: FromXmlValue<'src>>(
+    tag: &TagNode<'src>,
+    source: &'src str,
+    name: &str,
+) -> XmlResult<T> {
+    attribute(tag, source, name)?
+        .ok_or_else(|| missing(tag, source, &format!("attribute `{name}`")))
+}
+
+/// Reads the text of `tag`; its direct text and CDATA children, joined.
+///
+/// Child tags are skipped. Text borrows from the source when it is a single node with nothing to decode.
+///
+/// # Errors
+/// Returns an error pointing at the text if it cannot be converted.
+pub fn text<'src, T: FromXmlValue<'src>>(tag: &TagNode<'src>, source: &'src str) -> XmlResult<T> {
+    let mut parts = tag.children().iter().filter_map(|child| match child {
+        Node::Text(text) => Some((text.text().decoded(), *text.text())),
+        Node::Cdata(cdata) => Some((cdata.content().normalized(), *cdata.content())),
+        _ => None,
+    });
+
+    let (value, span) = match parts.next() {
+        Some((first, span)) => {
+            let mut value = first;
+            for (part, _) in parts {
+                value.to_mut().push_str(&part);
+            }
+            (value, span)
+        }
+        None => (Cow::Borrowed(""), *tag.span()),
+    };
+
+    T::from_xml_value(value).map_err(|message| {
+        let message = format!("Text of `{}`: {message}", tag.name());
+        conversion_error(source, span, message)
+    })
+}
+
+/// Reads the only child tag of `tag` with the given name, written as `local` or `prefix:local`, or returns `None` if there is none.
+///
+/// # Errors
+/// Returns an error if there is more than one child with the name, or the child cannot be converted.
+pub fn child<'src, T: FromXml<'src>>(
+    tag: &TagNode<'src>,
+    source: &'src str,
+    name: &str,
+) -> XmlResult<Option<T>> {
+    let (prefix, local) = split_name(name);
+    match tag.only_child_named(prefix, local) {
+        Ok(child) => T::from_xml(child, source).map(Some),
+        Err(Ambiguity::Missing) => Ok(None),
+        Err(Ambiguity::Multiple(count)) => {
+            let second = tag
+                .children()
+                .iter()
+                .filter_map(|child| match child {
+                    Node::Child(child) if child.name().equals(prefix, local) => Some(child),
+                    _ => None,
+                })
+                .nth(1)
+                .map_or(*tag.span(), |child| *child.span());
+            let message = format!("Expected one `{name}`, found {count}");
+            Err(conversion_error(source, second, message))
+        }
+    }
+}
+
+/// Reads the only child tag of `tag` with the given name, written as `local` or `prefix:local`.
+///
+/// # Errors
+/// Returns an error if there is not exactly one child with the name, or the child cannot be converted.
+pub fn required_child<'src, T: FromXml<'src>>(
+    tag: &TagNode<'src>,
+    source: &'src str,
+    name: &str,
+) -> XmlResult<T> {
+    child(tag, source, name)?.ok_or_else(|| missing(tag, source, &format!("child `{name}`")))
+}
+
+/// Reads every child tag of `tag` with the given name, written as `local` or `prefix:local`, in order.
+///
+/// # Errors
+/// Returns the first error from converting a child.
+pub fn children<'src, T: FromXml<'src>>(
+    tag: &TagNode<'src>,
+    source: &'src str,
+    name: &str,
+) -> XmlResult<Vec<T>> {
+    let (prefix, local) = split_name(name);
+    tag.children()
+        .iter()
+        .filter_map(|child| match child {
+            Node::Child(child) if child.name().equals(prefix, local) => Some(child),
+            _ => None,
+        })
+        .map(|child| T::from_xml(child, source))
+        .collect()
+}
+
+impl<'src> TagNode<'src> {
+    /// Converts this tag into a typed value; see [`FromXml`].
+    ///
+    /// `source` is the document the tag was parsed from, used to locate errors.
+    ///
+    /// # Errors
+    /// Returns an error if the tag does not have the structure the type expects.
+    pub fn parse_into<T: FromXml<'src>>(&self, source: &'src str) -> XmlResult<T> {
+        T::from_xml(self, source)
+    }
+}
+
+impl<'src> Document<'src> {
+    /// Converts the root of the document into a typed value; see [`FromXml`].
+    ///
+    /// # Errors
+    /// Returns an error if the root does not have the structure the type expects.
+    pub fn parse_into<T: FromXml<'src>>(&self) -> XmlResult<T> {
+        self.root().parse_into(self.source().unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(err: &XmlError) -> &str {
+        match &err.kind {
+            XmlErrorKind::Conversion(message) => message,
+            kind => panic!("Unexpected error: {kind}"),
+        }
+    }
+
+    #[test]
+    fn test_values() {
+        let src = "<a flag='1' n=' 42 ' bad='4x' s='x &amp; y'>a &lt; <b/><![CDATA[&b]]></a>";
+        let doc = Document::parse_str(src).unwrap();
+        let root = doc.root();
+
+        assert_eq!(attribute::<bool>(root, src, "flag").unwrap(), Some(true));
+        assert_eq!(attribute::<u8>(root, src, "n").unwrap(), Some(42));
+        assert_eq!(
+            attribute::<String>(root, src, "s").unwrap().unwrap(),
+            "x & y"
+        );
+        assert_eq!(attribute::<u8>(root, src, "missing").unwrap(), None);
+        assert_eq!(text::<String>(root, src).unwrap(), "a <&b");
+
+        let err = attribute::<u8>(root, src, "bad").unwrap_err();
+        assert_eq!(err.context.span.as_str(), "bad='4x'");
+        assert!(message(&err).starts_with("Attribute `bad`: `4x` is not a valid u8"));
+
+        let err = required_attribute::<u8>(root, src, "x:id").unwrap_err();
+        assert_eq!(message(&err), "`a` is missing attribute `x:id`");
+    }
+
+    #[test]
+    fn test_children() {
+        let src = "<list><n>1</n><x:n>2</x:n><n>3</n><one>ok</one><empty/></list>";
+        let doc = Document::parse_str(src).unwrap();
+        let root = doc.root();
+
+        assert_eq!(children::<u32>(root, src, "n").unwrap(), [1, 3]);
+        assert_eq!(required_child::<u32>(root, src, "x:n").unwrap(), 2);
+        assert_eq!(
+            child::<String>(root, src, "one").unwrap().as_deref(),
+            Some("ok")
+        );
+        assert_eq!(
+            child::<String>(root, src, "empty").unwrap().as_deref(),
+            Some("")
+        );
+        assert_eq!(child::<String>(root, src, "none").unwrap(), None);
+
+        let err = child::<u32>(root, src, "n").unwrap_err();
+        assert_eq!(message(&err), "Expected one `n`, found 2");
+        assert_eq!(err.context.position(), (1, 27));
+
+        let err = child::<u32>(root, src, "one").unwrap_err();
+        assert_eq!(err.context.span.as_str(), "ok");
+    }
+}
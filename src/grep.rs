@@ -0,0 +1,149 @@
+//! Regular expression search over the text content of a document.
+use crate::{Document, NodePath, StrSpan, node::Node};
+use regex::Regex;
+
+/// A match of a regular expression in a document, found with [`Document::grep`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match<'src> {
+    /// The matched text, and where it is in the source.
+    pub span: StrSpan<'src>,
+
+    /// The node the match is in; for attribute values, the element the attribute belongs to.
+    pub node_path: NodePath,
+}
+
+impl<'src> Document<'src> {
+    /// Searches the text, CDATA, comments and attribute values of the document, returning every match in document order.
+    ///
+    /// Content is searched as written in the source, so references such as `&amp;` are not decoded,
+    /// and the span of each match gives its exact byte offset in the source.
+    /// Nodes added without a source position, such as detached nodes, are not searched.
+    ///
+    /// # Example
+    /// ```rust
+    /// use regex::Regex;
+    /// use xmltree::{Document, NodePath};
+    ///
+    /// let src = r#"<log level="error"><!-- error codes --><entry>error 42</entry></log>"#;
+    /// let doc = Document::parse_str(src).unwrap();
+    ///
+    /// let matches = doc.grep(&Regex::new(r"error \d+").unwrap());
+    /// assert_eq!(matches.len(), 1);
+    /// assert_eq!(matches[0].span.start(), src.find("error 42").unwrap());
+    /// assert_eq!(matches[0].node_path, NodePath::root().child(1).child(0));
+    ///
+    /// assert_eq!(doc.grep(&Regex::new("error").unwrap()).len(), 3);
+    /// ```
+    #[must_use]
+    pub fn grep(&self, regex: &Regex) -> Vec<Match<'src>> {
+        let mut matches = vec![];
+        for (index, node) in self.prolog().iter().enumerate() {
+            find_in_node(regex, node, &NodePath::prolog(index), &mut matches);
+        }
+
+        // Popped in document order; an element's attributes are searched before its children are queued
+        let mut stack = vec![];
+        let mut tag = Some((NodePath::root(), self.root()));
+        loop {
+            if let Some((path, tag)) = tag.take() {
+                for attr in tag.attributes() {
+                    if !attr.span().is_empty() {
+                        find(regex, attr.value(), &path, &mut matches);
+                    }
+                }
+                for (index, child) in tag.children().iter().enumerate().rev() {
+                    stack.push((path.child(index), child));
+                }
+            }
+
+            let Some((path, node)) = stack.pop() else {
+                break;
+            };
+            match node {
+                Node::Child(child) => tag = Some((path, child)),
+                _ => find_in_node(regex, node, &path, &mut matches),
+            }
+        }
+
+        for (index, node) in self.epilog().iter().enumerate() {
+            find_in_node(regex, node, &NodePath::epilog(index), &mut matches);
+        }
+        matches
+    }
+}
+
+/// Searches the content of a text, CDATA or comment node.
+fn find_in_node<'src>(
+    regex: &Regex,
+    node: &Node<'src>,
+    path: &NodePath,
+    matches: &mut Vec<Match<'src>>,
+) {
+    if node.span().is_empty() {
+        return;
+    }
+
+    match node {
+        Node::Text(text) => find(regex, text.text(), path, matches),
+        Node::Cdata(cdata) => find(regex, cdata.content(), path, matches),
+        Node::Comment(comment) => find(regex, comment.text(), path, matches),
+        _ => {}
+    }
+}
+
+fn find<'src>(
+    regex: &Regex,
+    span: &StrSpan<'src>,
+    path: &NodePath,
+    matches: &mut Vec<Match<'src>>,
+) {
+    for m in regex.find_iter(span.text()) {
+        matches.push(Match {
+            span: span.slice(m.range()),
+            node_path: path.clone(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::{NodeAttribute, TextNode};
+
+    #[test]
+    fn test_grep() {
+        let src =
+            "<!-- a1 --><r k='b2 c3'><x>d4<![CDATA[e5]]></x>f6 &amp;<y z='g7' /></r><!--h8-->";
+        let mut doc = Document::parse_str(src).unwrap();
+        let digits = Regex::new(r"\d").unwrap();
+
+        let matches = doc.grep(&digits);
+        for m in &matches {
+            assert_eq!(
+                &src[m.span.start()..m.span.start() + m.span.len()],
+                m.span.text()
+            );
+        }
+        let found: Vec<_> = matches.iter().map(|m| m.span.text()).collect();
+        assert_eq!(found, ["1", "2", "3", "4", "5", "6", "7", "8"]);
+
+        let paths: Vec<_> = matches.iter().map(|m| m.node_path.to_string()).collect();
+        assert_eq!(
+            paths,
+            ["prolog/0", "/", "/", "/0/0", "/0/1", "/1", "/2", "epilog/0"]
+        );
+
+        // Content is searched as written
+        let amp = doc.grep(&Regex::new("&amp;").unwrap());
+        assert_eq!(amp[0].span.start(), src.find("&amp;").unwrap());
+
+        // Detached nodes have no source position
+        doc.root_mut()
+            .children_mut()
+            .push(Node::Text(TextNode::detached("i9")));
+        doc.root_mut()
+            .attributes_mut()
+            .push(NodeAttribute::detached(None, "j", "10"));
+        assert_eq!(doc.grep(&digits).len(), 8);
+    }
+}
@@ -0,0 +1,168 @@
+//! Typed access to the `xml-stylesheet` and `xml-model` processing instructions of a document.
+use crate::{Document, StrSpan, error::XmlResult, node::Node, resolve::UriResolver};
+
+/// A stylesheet associated with a document by an `xml-stylesheet` processing instruction.
+///
+/// See [`Document::stylesheets`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stylesheet<'src> {
+    /// The location of the stylesheet.
+    pub href: StrSpan<'src>,
+
+    /// The media type of the stylesheet, such as `text/xsl`, from the `type` pseudo-attribute.
+    pub kind: Option<StrSpan<'src>>,
+
+    /// The media the stylesheet applies to, such as `screen`.
+    pub media: Option<StrSpan<'src>>,
+
+    /// The title of the stylesheet.
+    pub title: Option<StrSpan<'src>>,
+
+    /// The character encoding of the stylesheet.
+    pub charset: Option<StrSpan<'src>>,
+
+    /// True if the stylesheet is an alternative, with `alternate="yes"`.
+    pub alternate: bool,
+}
+
+/// A schema associated with a document by an `xml-model` processing instruction.
+///
+/// See [`Document::schema_hints`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaHint<'src> {
+    /// The location of the schema.
+    pub href: StrSpan<'src>,
+
+    /// The media type of the schema, from the `type` pseudo-attribute.
+    pub kind: Option<StrSpan<'src>>,
+
+    /// The namespace of the schema language, such as `http://relaxng.org/ns/structure/1.0`.
+    pub schematypens: Option<StrSpan<'src>>,
+
+    /// The phase to validate with, for Schematron schemas.
+    pub phase: Option<StrSpan<'src>>,
+
+    /// The title of the schema.
+    pub title: Option<StrSpan<'src>>,
+}
+
+impl Stylesheet<'_> {
+    /// Fetches the stylesheet through the given resolver.
+    ///
+    /// `base` is the URI of the referencing document, used to resolve a relative `href`.
+    ///
+    /// # Errors
+    /// Returns an error if the resolver refuses or fails to load the stylesheet
+    pub fn load(&self, resolver: &dyn UriResolver, base: Option<&str>) -> XmlResult<Vec<u8>> {
+        resolver.resolve(self.href.text(), base)
+    }
+}
+
+impl SchemaHint<'_> {
+    /// Fetches the schema through the given resolver.
+    ///
+    /// `base` is the URI of the referencing document, used to resolve a relative `href`.
+    ///
+    /// # Errors
+    /// Returns an error if the resolver refuses or fails to load the schema
+    pub fn load(&self, resolver: &dyn UriResolver, base: Option<&str>) -> XmlResult<Vec<u8>> {
+        resolver.resolve(self.href.text(), base)
+    }
+}
+
+/// Returns the value of the first pseudo-attribute with the given name.
+fn find_pseudo_attribute<'src>(
+    pairs: &[(StrSpan<'src>, StrSpan<'src>)],
+    name: &str,
+) -> Option<StrSpan<'src>> {
+    pairs
+        .iter()
+        .find(|(n, _)| n.text() == name)
+        .map(|(_, value)| *value)
+}
+
+impl<'src> Document<'src> {
+    /// Returns the pseudo-attributes of each processing instruction in the prolog with the given target.
+    ///
+    /// Instructions whose content is not a list of pseudo-attributes are skipped.
+    fn prolog_pseudo_attributes(
+        &self,
+        target: &str,
+    ) -> impl Iterator<Item = Vec<(StrSpan<'src>, StrSpan<'src>)>> {
+        self.prolog().iter().filter_map(move |node| match node {
+            Node::ProcessingInstruction(pi) if pi.target().text() == target => {
+                pi.pseudo_attributes()
+            }
+            _ => None,
+        })
+    }
+
+    /// Returns the stylesheets declared by `xml-stylesheet` processing instructions in the prolog, in order.
+    ///
+    /// Instructions without an `href`, or whose content cannot be parsed, are skipped.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::Document;
+    ///
+    /// let doc = Document::parse_str(r#"<?xml-stylesheet type="text/xsl" href="style.xsl"?>
+    /// <?xml-stylesheet href="print.css" media="print" alternate="yes"?>
+    /// <root />"#).unwrap();
+    ///
+    /// let stylesheets = doc.stylesheets();
+    /// assert_eq!(stylesheets[0].href.text(), "style.xsl");
+    /// assert_eq!(stylesheets[0].kind.unwrap().text(), "text/xsl");
+    /// assert_eq!(stylesheets[1].media.unwrap().text(), "print");
+    /// assert!(stylesheets[1].alternate);
+    /// ```
+    #[must_use]
+    pub fn stylesheets(&self) -> Vec<Stylesheet<'src>> {
+        self.prolog_pseudo_attributes("xml-stylesheet")
+            .filter_map(|pairs| {
+                let get = |name| find_pseudo_attribute(&pairs, name);
+
+                Some(Stylesheet {
+                    href: get("href")?,
+                    kind: get("type"),
+                    media: get("media"),
+                    title: get("title"),
+                    charset: get("charset"),
+                    alternate: get("alternate").is_some_and(|v| v.text() == "yes"),
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the schemas declared by `xml-model` processing instructions in the prolog, in order.
+    ///
+    /// Instructions without an `href`, or whose content cannot be parsed, are skipped.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::Document;
+    ///
+    /// let doc = Document::parse_str(r#"<?xml-model href="book.rng" schematypens="http://relaxng.org/ns/structure/1.0"?>
+    /// <book />"#).unwrap();
+    ///
+    /// let hints = doc.schema_hints();
+    /// assert_eq!(hints[0].href.text(), "book.rng");
+    /// assert_eq!(hints[0].schematypens.unwrap().text(), "http://relaxng.org/ns/structure/1.0");
+    /// assert!(hints[0].phase.is_none());
+    /// ```
+    #[must_use]
+    pub fn schema_hints(&self) -> Vec<SchemaHint<'src>> {
+        self.prolog_pseudo_attributes("xml-model")
+            .filter_map(|pairs| {
+                let get = |name| find_pseudo_attribute(&pairs, name);
+
+                Some(SchemaHint {
+                    href: get("href")?,
+                    kind: get("type"),
+                    schematypens: get("schematypens"),
+                    phase: get("phase"),
+                    title: get("title"),
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,230 @@
+//! Rendering Rust values as tags, the inverse of [`from_xml`](crate::from_xml).
+//!
+//! Types implement [`ToXml`] to be rendered as a tag, and [`ToXmlValue`] to be rendered as the text of an attribute or tag.
+//! Every [`ToXmlValue`] type is also [`ToXml`], as a tag containing the text.
+//!
+//! The result is an [`OwnedTagNode`], which can then be formatted or encoded like any other tree.
+//! Values are stored as plain text, and escaped when the tree is written.
+//!
+//! With the `derive` feature, [`ToXml`] can be derived for structs with named fields,
+//! using the same `#[xml(...)]` field attributes as [`FromXml`](crate::from_xml::FromXml):
+//! - Fields are written as child tags named after the field, by default
+//! - `#[xml(attribute)]` writes a field as an attribute instead, and `#[xml(text)]` as the text of the tag itself
+//! - `#[xml(rename = "name")]` sets the name to write, which can have a prefix as `prefix:local`
+//! - `Option<T>` fields are skipped if `None`, and `Vec<T>` fields write one child tag per item
+//!
+//! ```rust
+//! # #[cfg(feature = "derive")] {
+//! use xmltree::{XmlWriteOptions, into_xml::ToXml};
+//!
+//! #[derive(ToXml)]
+//! struct Book {
+//!     #[xml(attribute)]
+//!     id: u32,
+//!     title: String,
+//!     #[xml(rename = "author")]
+//!     authors: Vec<String>,
+//!     year: Option<i32>,
+//! }
+//!
+//! let book = Book {
+//!     id: 7,
+//!     title: "Good Omens".to_string(),
+//!     authors: vec!["Pratchett".to_string(), "Gaiman".to_string()],
+//!     year: None,
+//! };
+//! let xml = book.to_xml_tag("book").to_xml_with_options(&XmlWriteOptions::default().with_minify(true)).unwrap();
+//! assert_eq!(xml, "<book id=\"7\"><title>Good Omens</title><author>Pratchett</author><author>Gaiman</author></book>");
+//! # }
+//! ```
+use crate::{
+    OwnedDocument,
+    node::{OwnedNode, OwnedNodeAttribute, OwnedTagNode, OwnedTextNode, canonical_double},
+};
+use std::borrow::Cow;
+
+#[cfg(feature = "derive")]
+pub use xmltree_derive::ToXml;
+
+/// A type that can be rendered as a tag.
+///
+/// # Example
+/// ```rust
+/// use xmltree::{into_xml::{self, ToXml}, node::OwnedTagNode};
+///
+/// struct Point {
+///     x: f64,
+///     y: f64,
+/// }
+/// impl ToXml for Point {
+///     fn to_xml_tag(&self, name: &str) -> OwnedTagNode {
+///         let mut tag = OwnedTagNode::new(name);
+///         into_xml::push_attribute(&mut tag, "x", &self.x);
+///         into_xml::push_attribute(&mut tag, "y", &self.y);
+///         tag
+///     }
+/// }
+///
+/// let tag = Point { x: 1.5, y: -2.0 }.to_xml_tag("point");
+/// assert_eq!(tag.to_string(), "<point x=\"1.5E0\" y=\"-2.0E0\" />");
+/// ```
+pub trait ToXml {
+    /// Renders the value as a tag with the given name, written as `local` or `prefix:local`.
+    fn to_xml_tag(&self, name: &str) -> OwnedTagNode;
+
+    /// Renders the value as the root of a new document.
+    fn to_xml_document(&self, name: &str) -> OwnedDocument {
+        OwnedDocument::new(self.to_xml_tag(name))
+    }
+}
+
+/// A type that can be rendered as the text of an attribute or tag.
+pub trait ToXmlValue {
+    /// Returns the text of the value.
+    fn to_xml_value(&self) -> Cow<'_, str>;
+}
+
+impl<T: ToXmlValue + ?Sized> ToXml for T {
+    /// Renders the value as a tag containing its text, or an empty tag if the text is empty.
+    fn to_xml_tag(&self, name: &str) -> OwnedTagNode {
+        let mut tag = OwnedTagNode::new(name);
+        push_text(&mut tag, self);
+        tag
+    }
+}
+
+impl ToXmlValue for str {
+    fn to_xml_value(&self) -> Cow<'_, str> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl ToXmlValue for String {
+    fn to_xml_value(&self) -> Cow<'_, str> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl ToXmlValue for Cow<'_, str> {
+    fn to_xml_value(&self) -> Cow<'_, str> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl ToXmlValue for bool {
+    fn to_xml_value(&self) -> Cow<'_, str> {
+        Cow::Borrowed(if *self { "true" } else { "false" })
+    }
+}
+
+impl ToXmlValue for f64 {
+    /// Written in the canonical form of an XML schema `double`; see [`canonical_double`].
+    fn to_xml_value(&self) -> Cow<'_, str> {
+        Cow::Owned(canonical_double(*self))
+    }
+}
+
+impl ToXmlValue for f32 {
+    /// Written in the canonical form of an XML schema `double`; see [`canonical_double`].
+    fn to_xml_value(&self) -> Cow<'_, str> {
+        Cow::Owned(canonical_double(f64::from(*self)))
+    }
+}
+
+impl<T: ToXmlValue + ?Sized> ToXmlValue for &T {
+    fn to_xml_value(&self) -> Cow<'_, str> {
+        (*self).to_xml_value()
+    }
+}
+
+macro_rules! impl_to_string {
+    ($($ty:ty),*) => {
+        $(
+            impl ToXmlValue for $ty {
+                fn to_xml_value(&self) -> Cow<'_, str> {
+                    Cow::Owned(self.to_string())
+                }
+            }
+        )*
+    };
+}
+impl_to_string!(
+    i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, char
+);
+
+/// Adds an attribute to `tag`, named as `local` or `prefix:local`.
+pub fn push_attribute<T: ToXmlValue + ?Sized>(tag: &mut OwnedTagNode, name: &str, value: &T) {
+    tag.attributes
+        .push(OwnedNodeAttribute::new(name, value.to_xml_value()));
+}
+
+/// Adds a text child to `tag`, unless the text is empty.
+pub fn push_text<T: ToXmlValue + ?Sized>(tag: &mut OwnedTagNode, value: &T) {
+    let text = value.to_xml_value();
+    if !text.is_empty() {
+        tag.children
+            .push(OwnedNode::Text(OwnedTextNode::new(text.into_owned())));
+    }
+}
+
+/// Adds a child tag to `tag`, named as `local` or `prefix:local`.
+pub fn push_child<T: ToXml + ?Sized>(tag: &mut OwnedTagNode, name: &str, value: &T) {
+    tag.children.push(OwnedNode::Tag(value.to_xml_tag(name)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Document, from_xml};
+
+    struct Item {
+        id: u32,
+        label: Option<String>,
+        tags: Vec<&'static str>,
+    }
+    impl ToXml for Item {
+        fn to_xml_tag(&self, name: &str) -> OwnedTagNode {
+            let mut tag = OwnedTagNode::new(name);
+            push_attribute(&mut tag, "x:id", &self.id);
+            if let Some(label) = &self.label {
+                push_child(&mut tag, "label", label);
+            }
+            for value in &self.tags {
+                push_child(&mut tag, "tag", value);
+            }
+            tag
+        }
+    }
+
+    #[test]
+    fn test_to_xml() {
+        let item = Item {
+            id: 3,
+            label: Some("a < b".to_string()),
+            tags: vec!["", "t"],
+        };
+        let xml = item.to_xml_document("item").to_xml(None).unwrap();
+        let doc = Document::parse_str(&xml).unwrap();
+        let root = doc.root();
+        let src = doc.source().unwrap();
+
+        assert_eq!(
+            root.get_attribute(Some("x"), "id").unwrap().value().text(),
+            "3"
+        );
+        assert_eq!(
+            from_xml::required_child::<String>(root, src, "label").unwrap(),
+            "a < b"
+        );
+        assert_eq!(
+            from_xml::children::<String>(root, src, "tag").unwrap(),
+            ["", "t"]
+        );
+
+        let flag = true.to_xml_tag("flag");
+        assert!(matches!(&flag.children[..], [OwnedNode::Text(t)] if t.text == "true"));
+        assert!("".to_xml_tag("empty").children.is_empty());
+        assert_eq!(0.5f32.to_xml_value(), "5.0E-1");
+        assert_eq!('x'.to_xml_value(), "x");
+    }
+}
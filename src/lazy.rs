@@ -0,0 +1,492 @@
+//! Lazily parsed documents, which only build the tags that are accessed.
+use crate::{
+    Document, NamedElement, StrSpan,
+    document::maybe_empty,
+    error::{ErrorContext, XmlError, XmlErrorKind, XmlResult},
+    node::{
+        DtdNode, NodeName, TagNode,
+        subset::{SubsetCursor, SubsetItem},
+    },
+    scan,
+};
+use std::sync::OnceLock;
+use xmlparser::{ElementEnd, Token};
+
+/// A document where only the outline of the tags is parsed up front.
+///
+/// The first pass records the name and span of every tag, without building attributes, text or other nodes.
+/// Full [`TagNode`] subtrees are parsed from the source the first time they are accessed, and kept.
+///
+/// Reading a few values from a large document is much faster this way than with [`Document::parse_str`].
+/// Prolog and epilog nodes are not kept; use [`LazyDocument::to_document`] to parse the whole document.
+///
+/// # Example
+/// ```rust
+/// use xmltree::LazyDocument;
+///
+/// let src = r#"<library><shelf><book id="1">Dune</book></shelf><shelf /></library>"#;
+/// let doc = LazyDocument::parse_str(src).unwrap();
+///
+/// let shelf = doc.root().children().next().unwrap();
+/// assert_eq!(shelf.name(), "shelf");
+///
+/// // Only this subtree is fully parsed
+/// let shelf = shelf.materialize().unwrap();
+/// assert_eq!(shelf.children().len(), 1);
+/// ```
+#[derive(Debug)]
+pub struct LazyDocument<'src> {
+    src: &'src str,
+
+    /// Outline of every tag, in document order; the root is first
+    tags: Vec<LazyEntry<'src>>,
+}
+
+/// The outline of a tag, and its materialized node once it has been accessed
+#[derive(Debug)]
+struct LazyEntry<'src> {
+    name: NodeName<'src>,
+    span: StrSpan<'src>,
+    children: Vec<usize>,
+    node: OnceLock<TagNode<'src>>,
+}
+
+impl<'src> LazyDocument<'src> {
+    /// Records the outline of the tags in the given source string.
+    ///
+    /// The whole source is still checked to be well-formed.
+    ///
+    /// # Errors
+    /// Returns errors if the XML is invalid
+    pub fn parse_str(src: &'src str) -> XmlResult<Self> {
+        let mut tags: Vec<LazyEntry<'src>> = vec![];
+        let mut stack: Vec<usize> = vec![];
+
+        let mut tokenizer = xmlparser::Tokenizer::from(src);
+        while let Some(token) = tokenizer.next() {
+            let token = match token {
+                Ok(token) => token,
+                Err(e) => bail!(src, XmlErrorKind::Xml(e)),
+            };
+
+            match token {
+                // Read as a node, so that internal subsets the tokenizer rejects are skipped
+                Token::DtdStart { .. } => {
+                    DtdNode::parse(token, &mut tokenizer, src)?;
+                }
+
+                Token::ElementStart {
+                    prefix,
+                    local,
+                    span,
+                } => {
+                    if stack.is_empty() && !tags.is_empty() {
+                        bail!(src, span, msg = "Unexpected {} in after root", token.name());
+                    }
+
+                    let index = tags.len();
+                    if let Some(parent) = stack.last() {
+                        tags[*parent].children.push(index);
+                    }
+
+                    stack.push(index);
+                    tags.push(LazyEntry {
+                        name: NodeName::new(maybe_empty(prefix), local),
+                        span: span.into(),
+                        children: vec![],
+                        node: OnceLock::new(),
+                    });
+                }
+
+                Token::ElementEnd { end, span } => {
+                    let close = match end {
+                        ElementEnd::Open => continue,
+                        ElementEnd::Empty => None,
+                        ElementEnd::Close(prefix, local) => {
+                            Some(NodeName::new(maybe_empty(prefix), local))
+                        }
+                    };
+
+                    let Some(index) = stack.pop() else {
+                        bail!(src, span, msg = "Unexpected {}", token.name());
+                    };
+
+                    let tag = &mut tags[index];
+                    if close.is_some_and(|name| name != tag.name) {
+                        return Err(mismatch(src, span.into(), tag));
+                    }
+
+                    tag.span.extend(&span.into(), src);
+                }
+
+                _ => {}
+            }
+        }
+
+        if let Some(index) = stack.pop() {
+            let tag = &tags[index];
+            bail!(
+                src,
+                &tag.span,
+                XmlErrorKind::UnclosedTag(tag.name.to_string())
+            );
+        }
+
+        if tags.is_empty() {
+            bail!(src, XmlErrorKind::UnexpectedEof);
+        }
+
+        Ok(Self { src, tags })
+    }
+
+    /// Records the outline of the tags in the given source string, without checking the rest of the syntax.
+    ///
+    /// Only tag names and nesting are checked; markup inside a tag is checked when it is materialized,
+    /// and the prolog and epilog are skipped over.
+    /// On the example document of the `lazy_scan` benchmark, this is about three times faster than [`Document::parse_str`],
+    /// and a quarter faster again with the `simd` feature, which scans for markup with `memchr`.
+    ///
+    /// # Errors
+    /// Returns errors if tags are not nested properly, or markup is not terminated
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::LazyDocument;
+    ///
+    /// let doc = LazyDocument::scan_str("<a><!-- <b> --><c x='>'/></a>").unwrap();
+    /// assert_eq!(doc.tag_count(), 2);
+    /// assert_eq!(doc.root().children().next().unwrap().name(), "c");
+    /// ```
+    pub fn scan_str(src: &'src str) -> XmlResult<Self> {
+        let mut tags: Vec<LazyEntry<'src>> = vec![];
+        let mut stack: Vec<usize> = vec![];
+
+        let bytes = src.as_bytes();
+        let mut pos = 0;
+        while let Some(offset) = scan::find_byte(b'<', &bytes[pos..]) {
+            let start = pos + offset;
+            let rest = &src[start..];
+
+            // Markup that cannot contain tags is skipped whole
+            let skip = [("<!--", "-->"), ("<![CDATA[", "]]>"), ("<?", "?>")]
+                .into_iter()
+                .find(|(open, _)| rest.starts_with(open));
+            if let Some((open, close)) = skip {
+                let Some(end) = scan::find_str(close, &rest[open.len()..]) else {
+                    bail!(src, StrSpan::new(rest, start), msg = "Unterminated {open}");
+                };
+                pos = start + open.len() + end + close.len();
+                continue;
+            }
+
+            if rest.starts_with("<!") {
+                pos = start + skip_dtd(src, start)?;
+                continue;
+            }
+
+            let Some(end) = find_tag_end(&bytes[start..]) else {
+                bail!(src, StrSpan::new(rest, start), msg = "Unterminated tag");
+            };
+            let markup = &src[start..start + end];
+            pos = start + end;
+
+            if let Some(name) = markup.strip_prefix("</") {
+                let name = name.trim_end_matches('>').trim();
+                let Some(index) = stack.pop() else {
+                    bail!(
+                        src,
+                        StrSpan::new(markup, start),
+                        msg = "Unexpected closing tag"
+                    );
+                };
+
+                let tag = &mut tags[index];
+                if tag.name != name {
+                    return Err(mismatch(src, StrSpan::new(markup, start), tag));
+                }
+
+                tag.span.extend(&StrSpan::new(markup, start), src);
+                continue;
+            }
+
+            let name_len = markup[1..]
+                .find(|c: char| c.is_whitespace() || c == '/' || c == '>')
+                .unwrap_or(markup.len() - 1);
+            let name = &markup[1..=name_len];
+            if stack.is_empty() && !tags.is_empty() {
+                bail!(
+                    src,
+                    StrSpan::new(markup, start),
+                    msg = "Unexpected element in after root"
+                );
+            }
+
+            let index = tags.len();
+            if let Some(parent) = stack.last() {
+                tags[*parent].children.push(index);
+            }
+
+            let name = match name.split_once(':') {
+                Some((prefix, local)) => NodeName::new(Some(prefix), local),
+                None => NodeName::new(None, name),
+            };
+            tags.push(LazyEntry {
+                name,
+                span: StrSpan::new(markup, start),
+                children: vec![],
+                node: OnceLock::new(),
+            });
+
+            if !markup.ends_with("/>") {
+                stack.push(index);
+            }
+        }
+
+        if let Some(index) = stack.pop() {
+            let tag = &tags[index];
+            bail!(
+                src,
+                &tag.span,
+                XmlErrorKind::UnclosedTag(tag.name.to_string())
+            );
+        }
+
+        if tags.is_empty() {
+            bail!(src, XmlErrorKind::UnexpectedEof);
+        }
+
+        Ok(Self { src, tags })
+    }
+
+    /// Returns the source string of the document.
+    #[must_use]
+    pub fn source(&self) -> &'src str {
+        self.src
+    }
+
+    /// Returns the root tag.
+    #[must_use]
+    pub fn root(&self) -> LazyTag<'_, 'src> {
+        LazyTag {
+            document: self,
+            index: 0,
+        }
+    }
+
+    /// Returns the number of tags in the document.
+    #[must_use]
+    pub fn tag_count(&self) -> usize {
+        self.tags.len()
+    }
+
+    /// Parses the whole document, including the prolog and epilog.
+    ///
+    /// # Errors
+    /// Returns errors if the XML is invalid
+    pub fn to_document(&self) -> XmlResult<Document<'src>> {
+        Document::parse_str(self.src)
+    }
+}
+
+/// Builds the error for a closing tag that does not match the open tag, pointing back at the open tag.
+fn mismatch<'src>(src: &'src str, close: StrSpan<'src>, open: &LazyEntry<'src>) -> XmlError {
+    let error = XmlError::new(
+        XmlErrorKind::UnclosedTag(open.name.to_string()),
+        ErrorContext::new(src, close),
+    );
+    error.with_related(src, open.span, "Opening tag is here")
+}
+
+/// Returns the length of the start or end tag at the start of `bytes`, including the closing `>`.
+///
+/// Quoted attribute values may contain `>`, so they are skipped.
+fn find_tag_end(bytes: &[u8]) -> Option<usize> {
+    let mut pos = 0;
+    loop {
+        pos += scan::find_any3(b'>', b'"', b'\'', &bytes[pos..])?;
+        let quote = bytes[pos];
+        if quote == b'>' {
+            return Some(pos + 1);
+        }
+
+        pos += 1;
+        pos += scan::find_byte(quote, &bytes[pos..])? + 1;
+    }
+}
+
+/// Returns the length of the DTD or other `<!` markup starting at `start`, including an internal subset.
+///
+/// Quoted literals, comments and processing instructions can contain `]` or `>`, so the internal subset
+/// is read declaration by declaration.
+fn skip_dtd(src: &str, start: usize) -> XmlResult<usize> {
+    let text = StrSpan::new(&src[start..], start);
+
+    // The literals of the external ID can contain either delimiter
+    let mut quote = None;
+    let mut subset = false;
+    for (i, b) in text.text().bytes().enumerate() {
+        match (quote, b) {
+            (Some(q), b) if b == q => quote = None,
+            (None, b'"' | b'\'') => quote = Some(b),
+            (None, b'>') => return Ok(i + 1),
+            (None, b'[') => {
+                subset = true;
+                break;
+            }
+            _ => {}
+        }
+    }
+    if !subset {
+        bail!(src, text, msg = "Unterminated DTD");
+    }
+
+    let mut cursor = SubsetCursor::new(text);
+    loop {
+        match cursor.next_item() {
+            Some(SubsetItem::Declaration(..)) => cursor.skip_declaration(),
+            Some(SubsetItem::End) => return Ok(cursor.offset() - start),
+            Some(SubsetItem::Reference | SubsetItem::Conditional(_)) => {}
+            None if cursor.offset() == src.len() => bail!(src, text, msg = "Unterminated DTD"),
+            None => {
+                let span = StrSpan::new(&src[cursor.offset()..], cursor.offset());
+                bail!(src, span, msg = "Invalid internal subset");
+            }
+        }
+    }
+}
+
+/// A tag in a [`LazyDocument`], which may not have been fully parsed yet.
+#[derive(Debug, Clone, Copy)]
+pub struct LazyTag<'a, 'src> {
+    document: &'a LazyDocument<'src>,
+    index: usize,
+}
+impl<'a, 'src> LazyTag<'a, 'src> {
+    fn entry(&self) -> &'a LazyEntry<'src> {
+        &self.document.tags[self.index]
+    }
+
+    /// Returns the name of the tag.
+    #[must_use]
+    pub fn name(&self) -> &'a NodeName<'src> {
+        &self.entry().name
+    }
+
+    /// Returns the span of the whole tag, from `<` to the end of its closing tag.
+    #[must_use]
+    pub fn span(&self) -> &'a StrSpan<'src> {
+        &self.entry().span
+    }
+
+    /// Returns the child tags, in document order.
+    pub fn children(&self) -> impl Iterator<Item = LazyTag<'a, 'src>> + use<'a, 'src> {
+        let document = self.document;
+        self.entry().children.iter().map(move |index| LazyTag {
+            document,
+            index: *index,
+        })
+    }
+
+    /// Returns the first child tag with the given prefix and local name.
+    #[must_use]
+    pub fn child(&self, prefix: Option<&str>, local: &str) -> Option<LazyTag<'a, 'src>> {
+        self.children().find(|tag| tag.name().equals(prefix, local))
+    }
+
+    /// Returns true if the subtree of this tag has already been parsed.
+    #[must_use]
+    pub fn is_materialized(&self) -> bool {
+        self.entry().node.get().is_some()
+    }
+
+    /// Returns the fully parsed subtree of this tag, parsing it on first access.
+    ///
+    /// Spans in the subtree are offsets into the whole source, as with [`Document::parse_str`].
+    ///
+    /// # Errors
+    /// Returns errors if the XML of the subtree is invalid
+    pub fn materialize(&self) -> XmlResult<&'a TagNode<'src>> {
+        let entry = self.entry();
+        if let Some(node) = entry.node.get() {
+            return Ok(node);
+        }
+
+        let range = entry.span.start()..entry.span.start() + entry.span.len();
+        let node = Document::parse_element(self.document.src, range)?;
+        Ok(entry.node.get_or_init(|| node))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lazy_document() {
+        let src = "<?xml version=\"1.0\"?>\n<!DOCTYPE a [<!ENTITY e \"v\">]>\n<!-- <c> -->\n<a x=\"1\">\n  <b><c>one</c><![CDATA[<z>]]></b>\n  <d y='/>' />\n  <b>two<?pi <q>?></b>\n</a>\n";
+        let lazy = LazyDocument::parse_str(src).unwrap();
+        let doc = Document::parse_str(src).unwrap();
+        assert_eq!(lazy.tag_count(), 5);
+
+        let root = lazy.root();
+        let names: Vec<_> = root.children().map(|t| t.name().to_string()).collect();
+        assert_eq!(names, ["b", "d", "b"]);
+
+        let b = root.child(None, "b").unwrap();
+        assert!(!b.is_materialized());
+        let node = b.materialize().unwrap();
+        assert!(b.is_materialized());
+        assert!(!root.is_materialized());
+
+        let crate::node::Node::Child(expected) = &doc.root().children()[0] else {
+            panic!("Expected a tag");
+        };
+        assert_eq!(node, expected);
+        assert_eq!(root.materialize().unwrap(), doc.root());
+
+        let scanned = LazyDocument::scan_str(src).unwrap();
+        assert_eq!(scanned.tag_count(), lazy.tag_count());
+        for (a, b) in scanned.tags.iter().zip(&lazy.tags) {
+            assert_eq!(a.name, b.name);
+            assert_eq!(a.children, b.children);
+        }
+        let b = scanned.root().child(None, "b").unwrap();
+        assert_eq!(b.materialize().unwrap(), expected);
+
+        assert!(LazyDocument::parse_str("<a><b></a>").is_err());
+        for err in [
+            LazyDocument::parse_str("<a><b x='1'></a>").unwrap_err(),
+            LazyDocument::scan_str("<a><b x='1'></a>").unwrap_err(),
+        ] {
+            assert_eq!(err.context.span.start(), 12);
+            assert_eq!(err.context.related.len(), 1);
+            assert_eq!(err.context.related[0].span.start(), 3);
+        }
+        assert!(LazyDocument::parse_str("<a></a><b />").is_err());
+        assert!(LazyDocument::parse_str("<a>").is_err());
+
+        let lazy = LazyDocument::parse_str("<!DOCTYPE a [%p;]><a><b /></a>").unwrap();
+        assert_eq!(lazy.tag_count(), 2);
+    }
+
+    #[test]
+    fn test_scan_internal_subset() {
+        // Literals, comments, processing instructions and conditional sections can all contain `]` or `>`
+        for dtd in [
+            r#"<!DOCTYPE a [<!ENTITY e "]"><!ENTITY f "a>b<c>">]>"#,
+            "<!DOCTYPE a SYSTEM 'x]>' [<!-- ]> --><?pi ]>?>]>",
+            "<!DOCTYPE a [<![INCLUDE[<!ENTITY e 'v'>]]><![IGNORE[<!ENTITY f '<c>'>]]>]>",
+            "<!DOCTYPE a [ %p; ] >",
+            "<!DOCTYPE a PUBLIC \"-//x>\" \"y\">",
+        ] {
+            let src = format!("{dtd}<a><b /></a>");
+            let doc = LazyDocument::scan_str(&src).unwrap();
+            assert_eq!(doc.tag_count(), 2, "{dtd}");
+            assert_eq!(doc.root().name(), "a");
+        }
+
+        assert!(LazyDocument::scan_str("<!DOCTYPE a [<!ENTITY e \"]>\">").is_err());
+        assert!(LazyDocument::scan_str("<!DOCTYPE a [ x ]><a />").is_err());
+        assert!(LazyDocument::scan_str("<!DOCTYPE a SYSTEM \"x").is_err());
+    }
+}
@@ -83,6 +83,9 @@
 //! println!("{formatted_xml}");
 //! ```
 //!
+//! Documents too large to build in memory can be written one node at a time with [`XmlWriter`] instead.
+//! Streams of documents written back to back, like message dumps, can be read with [`split_documents`].
+//!
 #![warn(missing_docs)]
 #![warn(clippy::pedantic)]
 #![allow(clippy::needless_range_loop)]
@@ -91,9 +94,27 @@ mod strspan;
 pub use strspan::*;
 
 pub mod error;
+pub mod resolve;
 pub mod to_bin;
+pub mod validate;
+
+pub mod from_xml;
+pub mod into_xml;
 
 mod to_xml;
+pub use to_xml::{AttrSort, CommentPolicy, ControlCharPolicy, IndentFn, XmlWriteOptions};
+
+mod writer;
+pub use writer::XmlWriter;
+
+mod append;
+pub use append::DocumentAppender;
+
+mod split;
+pub use split::{DocumentSplitter, split_documents};
+
+mod source_map;
+pub use source_map::{SourceMap, SourceMapping};
 
 macro_rules! bail {
     ($src:expr, $kind:expr) => {
@@ -147,4 +168,109 @@ impl NamedElement for xmlparser::Token<'_> {
 mod document;
 pub use document::*;
 
+mod parser;
+pub use parser::{ParseControl, Parser, PushEvent};
+
+mod lazy;
+pub use lazy::{LazyDocument, LazyTag};
+
+mod scan;
+
+mod path;
+pub use path::*;
+
+mod metadata;
+pub use metadata::*;
+
+mod compare;
+pub use compare::CompareOptions;
+
+mod normalize;
+pub use normalize::NormalizeOptions;
+
+mod cow;
+pub use cow::{CowDocument, CowNode, CowTag};
+
+mod tracked;
+pub use tracked::{NodeChange, TrackedDocument};
+
+mod arc;
+pub use arc::ArcDocument;
+
+mod shared;
+pub use shared::{SharedDocument, SharedTag};
+
+mod transform;
+pub use transform::TransformAction;
+
+mod substitute;
+pub use substitute::SubstituteOptions;
+
+mod sanitize;
+pub use sanitize::Sanitizer;
+
+mod entities;
+pub use entities::EntityLimits;
+
+mod namespaces;
+
+mod matcher;
+pub use matcher::Matcher;
+
+mod destructure;
+pub use destructure::Destructured;
+
+mod table;
+pub use table::TableExtractor;
+
+mod hints;
+pub use hints::{SchemaHint, Stylesheet};
+
+mod digest;
+
+mod encoding;
+
+#[cfg(feature = "tokio")]
+mod async_io;
+
+#[cfg(feature = "rayon")]
+mod par;
+
+#[cfg(feature = "mmap")]
+mod mmap;
+#[cfg(feature = "mmap")]
+pub use mmap::BinMapping;
+
+#[cfg(feature = "roxmltree")]
+mod roxml;
+
+#[cfg(feature = "regex")]
+mod grep;
+#[cfg(feature = "regex")]
+pub use grep::Match;
+
+#[cfg(any(feature = "yaml", feature = "toml"))]
+mod structure;
+
+#[cfg(feature = "miette")]
+mod diagnostic;
+
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(feature = "capi")]
+pub mod capi;
+
+#[cfg(feature = "proptest")]
+mod arbitrary;
+#[cfg(feature = "proptest")]
+pub use arbitrary::TreeLimits;
+
+#[cfg(feature = "snapshot")]
+pub mod snapshot;
+
+pub mod testing;
+
 pub mod node;
+
+pub mod compat;
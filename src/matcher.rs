@@ -0,0 +1,233 @@
+//! Compiled search criteria for finding tags in a tree.
+use crate::node::{Node, TagNode};
+
+/// A predicate on the text of a tag.
+type TextPredicate = Box<dyn Fn(&str) -> bool + Send + Sync>;
+
+/// A set of criteria for finding tags, built once and reused across searches.
+///
+/// A tag matches if it meets every criterion that was set; a matcher with no criteria matches every tag.
+///
+/// # Example
+/// ```rust
+/// use xmltree::{Document, Matcher};
+///
+/// let doc = Document::parse_str(r#"<list><item id="1">a</item><item>b</item><item id="3">c</item></list>"#).unwrap();
+///
+/// let matcher = Matcher::new().with_name(None, "item").with_attribute(None, "id");
+/// let found = doc.root().find_all(&matcher);
+/// assert_eq!(found.len(), 2);
+///
+/// let matcher = Matcher::new().with_text(|text| text == "b");
+/// assert!(doc.root().find_first(&matcher).unwrap().get_attribute(None, "id").is_none());
+/// ```
+#[derive(Default)]
+pub struct Matcher {
+    name: Option<(Option<String>, String)>,
+    namespace: Option<String>,
+    attributes: Vec<(Option<String>, String, Option<String>)>,
+    text: Option<TextPredicate>,
+}
+impl Matcher {
+    /// Creates a matcher with no criteria.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Matches tags with the given prefix and local name.
+    #[must_use]
+    pub fn with_name(mut self, prefix: Option<&str>, local: &str) -> Self {
+        self.name = Some((prefix.map(str::to_string), local.to_string()));
+        self
+    }
+
+    /// Matches tags whose name is in the namespace with the given URI.
+    ///
+    /// Prefixes are resolved using the `xmlns` declarations in scope, starting from the tag the search begins at;
+    /// declarations made by its ancestors are not visible.
+    #[must_use]
+    pub fn with_namespace(mut self, uri: &str) -> Self {
+        self.namespace = Some(uri.to_string());
+        self
+    }
+
+    /// Matches tags that have the given attribute, with any value.
+    ///
+    /// Can be called more than once; all attributes must be present.
+    #[must_use]
+    pub fn with_attribute(mut self, prefix: Option<&str>, name: &str) -> Self {
+        self.attributes
+            .push((prefix.map(str::to_string), name.to_string(), None));
+        self
+    }
+
+    /// Matches tags that have the given attribute, with exactly the given value.
+    ///
+    /// Can be called more than once; all attributes must be present.
+    #[must_use]
+    pub fn with_attribute_value(mut self, prefix: Option<&str>, name: &str, value: &str) -> Self {
+        self.attributes.push((
+            prefix.map(str::to_string),
+            name.to_string(),
+            Some(value.to_string()),
+        ));
+        self
+    }
+
+    /// Matches tags with at least one direct text or CDATA child for which the predicate returns true.
+    #[must_use]
+    pub fn with_text(mut self, predicate: impl Fn(&str) -> bool + Send + Sync + 'static) -> Self {
+        self.text = Some(Box::new(predicate));
+        self
+    }
+
+    /// Returns true if the tag meets every criterion except the namespace, which needs the tag's scope.
+    fn matches_local(&self, tag: &TagNode<'_>) -> bool {
+        if let Some((prefix, local)) = &self.name
+            && !tag.name().equals(prefix.as_deref(), local)
+        {
+            return false;
+        }
+
+        let has_attributes = self.attributes.iter().all(|(prefix, name, value)| {
+            tag.get_attribute(prefix.as_deref(), name)
+                .is_some_and(|attr| value.as_deref().is_none_or(|v| attr.value().text() == v))
+        });
+        if !has_attributes {
+            return false;
+        }
+
+        match &self.text {
+            Some(predicate) => tag.children().iter().any(|child| match child {
+                Node::Text(text) => predicate(text.text().text()),
+                Node::Cdata(cdata) => predicate(cdata.content().text()),
+                _ => false,
+            }),
+            None => true,
+        }
+    }
+
+    /// Calls `f` on each matching tag at or below `tag`, in document order, until it returns false.
+    fn walk<'a, 'src>(&self, tag: &'a TagNode<'src>, mut f: impl FnMut(&'a TagNode<'src>) -> bool) {
+        // Declarations in scope, as (depth of declaring tag, prefix, uri); "" is the default namespace
+        let mut scope: Vec<(usize, &'a str, &'a str)> = vec![];
+
+        let mut stack = vec![(tag, 0)];
+        while let Some((tag, depth)) = stack.pop() {
+            stack.extend(tag.children().iter().rev().filter_map(|child| match child {
+                Node::Child(child) => Some((child, depth + 1)),
+                _ => None,
+            }));
+
+            let in_namespace = match &self.namespace {
+                Some(uri) => {
+                    scope.retain(|(d, ..)| *d < depth);
+                    for attr in tag.attributes() {
+                        let name = attr.name();
+                        match name.prefix() {
+                            Some(p) if p.text() == "xmlns" => {
+                                scope.push((depth, name.local().text(), attr.value().text()));
+                            }
+                            None if name.local().text() == "xmlns" => {
+                                scope.push((depth, "", attr.value().text()));
+                            }
+                            _ => {}
+                        }
+                    }
+
+                    let prefix = tag.name().prefix().map_or("", |p| p.text());
+                    scope
+                        .iter()
+                        .rev()
+                        .find(|(_, p, _)| *p == prefix)
+                        .is_some_and(|(.., u)| u == uri)
+                }
+                None => true,
+            };
+
+            if in_namespace && self.matches_local(tag) && !f(tag) {
+                return;
+            }
+        }
+    }
+}
+impl std::fmt::Debug for Matcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Matcher")
+            .field("name", &self.name)
+            .field("namespace", &self.namespace)
+            .field("attributes", &self.attributes)
+            .field("text", &self.text.as_ref().map(|_| ".."))
+            .finish()
+    }
+}
+
+impl<'src> TagNode<'src> {
+    /// Returns every tag at or below this one that matches, in document order.
+    ///
+    /// See [`Matcher`].
+    #[must_use]
+    pub fn find_all(&self, matcher: &Matcher) -> Vec<&TagNode<'src>> {
+        let mut found = vec![];
+        matcher.walk(self, |tag| {
+            found.push(tag);
+            true
+        });
+        found
+    }
+
+    /// Returns the first tag at or below this one that matches, in document order.
+    ///
+    /// See [`Matcher`].
+    #[must_use]
+    pub fn find_first(&self, matcher: &Matcher) -> Option<&TagNode<'src>> {
+        let mut found = None;
+        matcher.walk(self, |tag| {
+            found = Some(tag);
+            false
+        });
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Document;
+
+    #[test]
+    fn test_find_namespace() {
+        let src = r#"<root xmlns="urn:default" xmlns:x="urn:x">
+            <item id="1" />
+            <x:item id="2" />
+            <group xmlns="urn:other"><item id="3" /></group>
+            <item id="4" />
+        </root>"#;
+        let doc = Document::parse_str(src).unwrap();
+        let ids = |matcher: &Matcher| {
+            doc.root()
+                .find_all(matcher)
+                .iter()
+                .filter_map(|t| t.get_attribute(None, "id").map(|a| a.value().text()))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            ids(&Matcher::new().with_namespace("urn:default")),
+            ["1", "4"]
+        );
+        assert_eq!(ids(&Matcher::new().with_namespace("urn:x")), ["2"]);
+        assert_eq!(ids(&Matcher::new().with_namespace("urn:other")), ["3"]);
+        assert_eq!(
+            ids(&Matcher::new().with_attribute_value(None, "id", "3")),
+            ["3"]
+        );
+        assert_eq!(doc.root().find_all(&Matcher::new()).len(), 6);
+        assert!(
+            doc.root()
+                .find_first(&Matcher::new().with_name(Some("x"), "nope"))
+                .is_none()
+        );
+    }
+}
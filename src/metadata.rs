@@ -0,0 +1,98 @@
+use crate::to_bin::{BinDecodeError, Decoder, Encoder, ToBinHandler};
+use std::collections::BTreeMap;
+
+/// A key-value side table attached to a document, such as the source URL, fetch time or checksum of a parsed tree.
+///
+/// Metadata is never written as XML, but is carried through owned conversion and the binary format.
+///
+/// # Example
+/// ```rust
+/// use xmltree::Document;
+///
+/// let mut doc = Document::parse_str("<test />").unwrap();
+/// doc.metadata_mut().insert("source", "https://example.com/test.xml");
+///
+/// let owned = doc.to_owned();
+/// assert_eq!(owned.metadata.get("source"), Some("https://example.com/test.xml"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Metadata {
+    entries: BTreeMap<String, String>,
+}
+impl Metadata {
+    /// Creates an empty metadata table.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the value for the given key, if present.
+    #[must_use]
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries.get(key).map(String::as_str)
+    }
+
+    /// Sets the value for the given key, returning the previous value if there was one.
+    pub fn insert(&mut self, key: impl Into<String>, value: impl Into<String>) -> Option<String> {
+        self.entries.insert(key.into(), value.into())
+    }
+
+    /// Removes the given key, returning its value if it was present.
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        self.entries.remove(key)
+    }
+
+    /// Returns true if the table contains the given key.
+    #[must_use]
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// Returns an iterator over the entries of the table, sorted by key.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// Returns the number of entries in the table.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if the table has no entries.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for Metadata {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self {
+            entries: iter
+                .into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+        }
+    }
+}
+impl<'src> ToBinHandler<'src> for Metadata {
+    fn write(&self, encoder: &mut Encoder) -> std::io::Result<()> {
+        self.entries.len().write(encoder)?;
+        for (key, value) in &self.entries {
+            key.write(encoder)?;
+            value.write(encoder)?;
+        }
+        Ok(())
+    }
+
+    fn read(decoder: &mut Decoder<'src>) -> Result<Self, BinDecodeError> {
+        let len = usize::read(decoder)?;
+        let mut entries = BTreeMap::new();
+        for _ in 0..len {
+            let key = String::read(decoder)?;
+            let value = String::read(decoder)?;
+            entries.insert(key, value);
+        }
+        Ok(Self { entries })
+    }
+}
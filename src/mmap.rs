@@ -0,0 +1,86 @@
+//! Zero-copy decoding of binary documents from memory-mapped files.
+use crate::{Document, to_bin::BinDecodeError};
+use std::path::Path;
+
+/// Holds the memory mapping of a binary document file, which a [`Document`] borrows from.
+///
+/// See [`Document::from_bin_mmap`].
+#[derive(Debug, Default)]
+pub struct BinMapping {
+    map: Option<memmap2::Mmap>,
+}
+impl BinMapping {
+    /// Creates an empty mapping, to be filled by [`Document::from_bin_mmap`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the mapped bytes, or an empty slice if nothing is mapped.
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        self.map.as_deref().unwrap_or_default()
+    }
+}
+
+impl<'src> Document<'src> {
+    /// Maps a binary document file into memory, and decodes it without copying any strings.
+    ///
+    /// Every string in the document borrows from the mapping, which is stored in `mapping`,
+    /// so decoding only builds the tree itself. This works best for documents written with a source,
+    /// where the source text is stored once and every span is an offset into it.
+    ///
+    /// # Safety
+    /// The file must not be modified or truncated while the document or mapping are alive.
+    /// Changes made through the mapping by another process would be visible through strings in the
+    /// document, which are assumed to be immutable and valid UTF-8.
+    ///
+    /// # Errors
+    /// Returns errors if the file cannot be opened or mapped, or if the decoding fails
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{BinMapping, Document};
+    ///
+    /// let mut mapping = BinMapping::new();
+    /// let doc = unsafe { Document::from_bin_mmap("examples/example.bin", &mut mapping) }.unwrap();
+    /// assert_eq!(doc.root().name(), "bookstore");
+    /// ```
+    pub unsafe fn from_bin_mmap(
+        path: impl AsRef<Path>,
+        mapping: &'src mut BinMapping,
+    ) -> Result<Self, BinDecodeError> {
+        let file = std::fs::File::open(path)?;
+
+        // SAFETY: The caller guarantees the file is not modified while the mapping is in use
+        let map = unsafe { memmap2::Mmap::map(&file)? };
+        let map = mapping.map.insert(map);
+        Self::from_bin(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_bin_mmap() {
+        let src = "<root><a b='c'>text</a></root>";
+        let doc = Document::parse_str(src).unwrap();
+        let path = std::env::temp_dir().join(format!("xmltree-mmap-{}.bin", std::process::id()));
+        std::fs::write(&path, doc.to_bin().unwrap()).unwrap();
+
+        let mut mapping = BinMapping::new();
+        let mapped = unsafe { Document::from_bin_mmap(&path, &mut mapping) }.unwrap();
+        assert_eq!(mapped.source(), Some(src));
+        assert_eq!(mapped.to_owned(), doc.to_owned());
+
+        // Strings point into the mapping, rather than into copies
+        let text = mapped.root().span().text().as_ptr();
+        drop(mapped);
+        assert!(mapping.as_bytes().as_ptr_range().contains(&text));
+
+        drop(mapping);
+        std::fs::remove_file(path).unwrap();
+    }
+}
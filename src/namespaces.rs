@@ -0,0 +1,400 @@
+//! Document-wide rebinding of namespace prefixes, and namespace-aware renames.
+use crate::{
+    OwnedDocument,
+    error::{ErrorContext, XmlError, XmlErrorKind, XmlResult},
+    node::{OwnedNode, OwnedNodeAttribute, OwnedNodeName, OwnedTagNode},
+};
+use std::collections::HashMap;
+
+/// The namespace bound to the `xml` prefix, which cannot be declared or rebound.
+const XML_NAMESPACE: &str = "http://www.w3.org/XML/1998/namespace";
+
+fn conflict(message: String) -> XmlError {
+    XmlError::new(
+        XmlErrorKind::NamespaceConflict(message),
+        ErrorContext::new("", crate::StrSpan::default()),
+    )
+}
+
+/// Returns the prefix declared by an attribute; `""` for the default namespace, or `None` if it is not a declaration.
+fn declared_prefix(name: &OwnedNodeName) -> Option<&str> {
+    match name.prefix.as_deref() {
+        Some("xmlns") => Some(&name.local),
+        None if &*name.local == "xmlns" => Some(""),
+        _ => None,
+    }
+}
+
+/// The namespace declarations in scope during a walk of an owned tree.
+#[derive(Default)]
+struct Scope {
+    /// Declarations as (depth of declaring tag, prefix, uri); "" is the default namespace
+    declarations: Vec<(usize, String, String)>,
+}
+impl Scope {
+    /// Drops the declarations of tags that are no longer open, then adds those of a tag at `depth`.
+    fn enter(&mut self, tag: &OwnedTagNode, depth: usize) {
+        self.declarations.retain(|(d, ..)| *d < depth);
+        for attr in &tag.attributes {
+            if let Some(prefix) = declared_prefix(&attr.name) {
+                self.declarations
+                    .push((depth, prefix.to_string(), attr.value.clone()));
+            }
+        }
+    }
+
+    /// Returns the namespace bound to a prefix, or `None` if it is not declared.
+    fn resolve(&self, prefix: &str) -> Option<&str> {
+        match prefix {
+            "xml" => Some(XML_NAMESPACE),
+            _ => self
+                .declarations
+                .iter()
+                .rev()
+                .find(|(_, p, _)| p == prefix)
+                .map(|(.., uri)| uri.as_str()),
+        }
+    }
+
+    /// Returns the namespace of an element name, or `None` if it is in no namespace or its prefix is undeclared.
+    ///
+    /// Unprefixed elements are in the default namespace, if there is one.
+    fn element_namespace(&self, name: &OwnedNodeName) -> Option<&str> {
+        self.resolve(name.prefix.as_deref().unwrap_or(""))
+            .filter(|uri| !uri.is_empty())
+    }
+
+    /// Returns true if a name is in the given namespace, or in no namespace for `None`.
+    ///
+    /// Unprefixed attributes are always in no namespace, and names with undeclared prefixes are in none of them.
+    fn is_in(&self, name: &OwnedNodeName, namespace: Option<&str>, is_attribute: bool) -> bool {
+        match name.prefix.as_deref() {
+            Some(prefix) => self
+                .resolve(prefix)
+                .is_some_and(|uri| Some(uri) == namespace),
+            None if is_attribute => namespace.is_none(),
+            None => self.element_namespace(name) == namespace,
+        }
+    }
+}
+
+/// Calls `f` on every tag at or below `root`, in document order, with the declarations in scope.
+fn walk_scoped(root: &mut OwnedTagNode, mut f: impl FnMut(&mut OwnedTagNode, &Scope)) {
+    let mut scope = Scope::default();
+    let mut stack = vec![(root, 0)];
+    while let Some((tag, depth)) = stack.pop() {
+        scope.enter(tag, depth);
+        f(tag, &scope);
+        stack.extend(
+            tag.children
+                .iter_mut()
+                .rev()
+                .filter_map(|child| match child {
+                    OwnedNode::Tag(child) => Some((child, depth + 1)),
+                    _ => None,
+                }),
+        );
+    }
+}
+
+/// The state of a namespace rewrite; see [`OwnedDocument::rewrite_namespaces`].
+struct Rewriter<'m> {
+    map: &'m HashMap<String, String>,
+
+    /// The new prefixes, and the namespace each is mapped to.
+    targets: HashMap<&'m str, &'m str>,
+
+    /// The mapped namespaces that have been used, in order of first use.
+    used: Vec<&'m str>,
+}
+impl<'m> Rewriter<'m> {
+    fn new(map: &'m HashMap<String, String>) -> XmlResult<Self> {
+        if map.contains_key(XML_NAMESPACE) {
+            return Err(conflict("The xml namespace cannot be rebound".to_string()));
+        }
+
+        let mut targets = HashMap::new();
+        for (uri, prefix) in map {
+            if prefix == "xml" || prefix == "xmlns" {
+                return Err(conflict(format!("The {prefix} prefix is reserved")));
+            }
+            if let Some(other) = targets.insert(prefix.as_str(), uri.as_str()) {
+                return Err(conflict(format!(
+                    "Both {other} and {uri} are mapped to the prefix '{prefix}'"
+                )));
+            }
+        }
+
+        Ok(Self {
+            map,
+            targets,
+            used: vec![],
+        })
+    }
+
+    /// Renames an element or attribute in the namespace `uri`, or checks that its prefix can be left alone.
+    fn rename(&mut self, name: &mut OwnedNodeName, uri: Option<&str>, kind: &str) -> XmlResult<()> {
+        match uri.and_then(|uri| self.map.get_key_value(uri)) {
+            Some((_, new)) if new.is_empty() && kind == "attribute" => Err(conflict(format!(
+                "The attribute {name} cannot use the default namespace"
+            ))),
+            Some((uri, new)) => {
+                name.prefix = (!new.is_empty()).then(|| new.as_str().into());
+                if !self.used.contains(&uri.as_str()) {
+                    self.used.push(uri);
+                }
+                Ok(())
+            }
+            None => match self.targets.get(name.prefix.as_deref().unwrap_or("")) {
+                Some(target) => Err(conflict(format!(
+                    "The {kind} {name} would be moved into the namespace {target}"
+                ))),
+                None => Ok(()),
+            },
+        }
+    }
+    /// Renames a tag and its attributes, and drops the declarations of mapped namespaces.
+    fn rewrite_tag(&mut self, tag: &mut OwnedTagNode, scope: &Scope) -> XmlResult<()> {
+        let uri = scope.element_namespace(&tag.name);
+        self.rename(&mut tag.name, uri, "element")?;
+
+        let mut attributes = Vec::with_capacity(tag.attributes.len());
+        for mut attr in std::mem::take(&mut tag.attributes) {
+            if let Some(prefix) = declared_prefix(&attr.name) {
+                if self.map.contains_key(&attr.value) {
+                    continue;
+                }
+                if self.targets.contains_key(prefix) {
+                    return Err(conflict(format!(
+                        "The prefix '{prefix}' is already declared for {}",
+                        attr.value
+                    )));
+                }
+            } else if let Some(prefix) = attr.name.prefix.as_deref() {
+                // Unprefixed attributes are in no namespace
+                let uri = scope.resolve(prefix);
+                self.rename(&mut attr.name, uri, "attribute")?;
+            }
+            attributes.push(attr);
+        }
+        tag.attributes = attributes;
+        Ok(())
+    }
+}
+
+impl OwnedDocument {
+    /// Rebinds namespace prefixes across the whole document, using a map of namespace URIs to the prefix each should use.
+    ///
+    /// Every element and attribute in a mapped namespace is renamed to use the new prefix, and the old declarations of
+    /// mapped namespaces are removed. The mapped namespaces that are used are then declared once, on the root.
+    /// An empty prefix makes a namespace the default namespace, so its elements become unprefixed.
+    ///
+    /// Names in namespaces that are not in the map are left unchanged, as are their declarations.
+    ///
+    /// # Errors
+    /// Returns an [`XmlErrorKind::NamespaceConflict`] error, leaving the document unchanged, if:
+    /// - Two namespaces are mapped to the same prefix, or a namespace is mapped to the reserved `xml` or `xmlns` prefixes
+    /// - The `xml` namespace is in the map
+    /// - A new prefix is already used or declared for a namespace that is not mapped to it
+    /// - An attribute would need to use the default namespace, which attributes cannot do
+    ///
+    /// # Example
+    /// ```rust
+    /// use std::collections::HashMap;
+    /// use xmltree::{Document, XmlWriteOptions};
+    ///
+    /// let src = r#"<a:feed xmlns:a="urn:atom"><entry xmlns="urn:atom" xmlns:m="urn:meta" m:id="1" /></a:feed>"#;
+    /// let mut doc = Document::parse_str(src).unwrap().to_owned();
+    ///
+    /// let map = HashMap::from([("urn:atom".to_string(), "atom".to_string())]);
+    /// doc.rewrite_namespaces(&map).unwrap();
+    ///
+    /// let xml = doc.to_xml_with_options(&XmlWriteOptions::default().with_minify(true)).unwrap();
+    /// assert_eq!(xml, "<atom:feed xmlns:atom=\"urn:atom\"><atom:entry xmlns:m=\"urn:meta\" m:id=\"1\" /></atom:feed>\n");
+    /// ```
+    pub fn rewrite_namespaces(&mut self, map: &HashMap<String, String>) -> XmlResult<()> {
+        let mut rewriter = Rewriter::new(map)?;
+
+        // Rewrite a copy, so that the document is untouched if a conflict is found part way through
+        let mut root = self.root.clone();
+
+        let mut result = Ok(());
+        walk_scoped(&mut root, |tag, scope| {
+            if result.is_ok() {
+                result = rewriter.rewrite_tag(tag, scope);
+            }
+        });
+        result?;
+
+        let declarations = rewriter.used.into_iter().map(|uri| {
+            let name = match map[uri].as_str() {
+                "" => OwnedNodeName::new(None, "xmlns"),
+                prefix => OwnedNodeName::new(Some("xmlns"), prefix),
+            };
+            OwnedNodeAttribute::new(name, uri)
+        });
+        root.attributes.splice(0..0, declarations);
+
+        self.root = root;
+        Ok(())
+    }
+
+    /// Renames every element with the local name `old` in the given namespace to `new`, returning how many were renamed.
+    ///
+    /// Prefixes are resolved using the declarations in scope, so an element matches however its namespace is bound;
+    /// `None` matches elements in no namespace. Renamed elements keep their prefix, and so stay in the same namespace.
+    /// Elements with undeclared prefixes never match.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{Document, XmlWriteOptions};
+    ///
+    /// let src = r#"<v:order xmlns:v="urn:v1"><item xmlns="urn:v1" /><v:item /><item /></v:order>"#;
+    /// let mut doc = Document::parse_str(src).unwrap().to_owned();
+    ///
+    /// assert_eq!(doc.rename_elements(Some("urn:v1"), "item", "line"), 2);
+    ///
+    /// let xml = doc.to_xml_with_options(&XmlWriteOptions::default().with_minify(true)).unwrap();
+    /// assert_eq!(xml, "<v:order xmlns:v=\"urn:v1\"><line xmlns=\"urn:v1\" /><v:line /><item /></v:order>\n");
+    /// ```
+    pub fn rename_elements(&mut self, namespace: Option<&str>, old: &str, new: &str) -> usize {
+        let new: std::sync::Arc<str> = new.into();
+        let mut count = 0;
+        walk_scoped(&mut self.root, |tag, scope| {
+            if &*tag.name.local == old && scope.is_in(&tag.name, namespace, false) {
+                tag.name.local = new.clone();
+                count += 1;
+            }
+        });
+        count
+    }
+
+    /// Renames every attribute with the local name `old` in the given namespace to `new`, returning how many were renamed.
+    ///
+    /// Prefixes are resolved as in [`OwnedDocument::rename_elements`], but unprefixed attributes are always in no namespace,
+    /// and `xmlns` declarations are never renamed. An attribute is left alone if its tag already has one with the new name.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{Document, XmlWriteOptions};
+    ///
+    /// let src = r#"<a xmlns:m="urn:meta" m:ref="1"><b m:ref="2" ref="3" /></a>"#;
+    /// let mut doc = Document::parse_str(src).unwrap().to_owned();
+    ///
+    /// assert_eq!(doc.rename_attributes(Some("urn:meta"), "ref", "id"), 2);
+    /// assert_eq!(doc.rename_attributes(None, "ref", "href"), 1);
+    ///
+    /// let xml = doc.to_xml_with_options(&XmlWriteOptions::default().with_minify(true)).unwrap();
+    /// assert_eq!(xml, "<a xmlns:m=\"urn:meta\" m:id=\"1\"><b m:id=\"2\" href=\"3\" /></a>\n");
+    /// ```
+    pub fn rename_attributes(&mut self, namespace: Option<&str>, old: &str, new: &str) -> usize {
+        let new: std::sync::Arc<str> = new.into();
+        let mut count = 0;
+        walk_scoped(&mut self.root, |tag, scope| {
+            for i in 0..tag.attributes.len() {
+                let name = &tag.attributes[i].name;
+                let matches = &*name.local == old
+                    && declared_prefix(name).is_none()
+                    && scope.is_in(name, namespace, true);
+                let taken = tag
+                    .attributes
+                    .iter()
+                    .any(|attr| attr.name.equals(name.prefix.as_deref(), &new));
+                if matches && !taken {
+                    tag.attributes[i].name.local = new.clone();
+                    count += 1;
+                }
+            }
+        });
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Document, XmlWriteOptions};
+
+    fn rewrite(src: &str, map: &[(&str, &str)]) -> XmlResult<String> {
+        let mut doc = Document::parse_str(src).unwrap().to_owned();
+        let map = map
+            .iter()
+            .map(|(uri, prefix)| ((*uri).to_string(), (*prefix).to_string()))
+            .collect();
+        doc.rewrite_namespaces(&map)?;
+        Ok(doc
+            .to_xml_with_options(&XmlWriteOptions::default().with_minify(true))
+            .unwrap())
+    }
+
+    #[test]
+    fn test_rewrite_namespaces() {
+        let src = r#"<x:a xmlns:x="urn:one" xmlns:y="urn:two"><y:b y:k="v" k="w"><x:c xml:lang="en" /></y:b></x:a>"#;
+
+        let xml = rewrite(src, &[("urn:one", ""), ("urn:two", "two")]).unwrap();
+        assert_eq!(
+            xml,
+            r#"<a xmlns="urn:one" xmlns:two="urn:two"><two:b two:k="v" k="w"><c xml:lang="en" /></two:b></a>"#
+                .to_string()
+                + "\n"
+        );
+
+        // Swapping prefixes between namespaces is allowed
+        let xml = rewrite(src, &[("urn:one", "y"), ("urn:two", "x")]).unwrap();
+        assert_eq!(
+            xml,
+            r#"<y:a xmlns:y="urn:one" xmlns:x="urn:two"><x:b x:k="v" k="w"><y:c xml:lang="en" /></x:b></y:a>"#
+                .to_string()
+                + "\n"
+        );
+    }
+
+    #[test]
+    fn test_rewrite_namespaces_conflicts() {
+        let src = r#"<x:a xmlns:x="urn:one" xmlns:y="urn:two"><y:b y:k="v" /><c /></x:a>"#;
+        let is_conflict = |result: XmlResult<String>| {
+            matches!(result.unwrap_err().kind, XmlErrorKind::NamespaceConflict(_))
+        };
+
+        assert!(is_conflict(rewrite(src, &[("urn:one", "y")])));
+        assert!(is_conflict(rewrite(src, &[("urn:one", "")])));
+        assert!(is_conflict(rewrite(src, &[("urn:two", "")])));
+        assert!(is_conflict(rewrite(
+            src,
+            &[("urn:one", "p"), ("urn:two", "p")]
+        )));
+        assert!(is_conflict(rewrite(src, &[("urn:one", "xml")])));
+        assert!(is_conflict(rewrite(src, &[(XML_NAMESPACE, "x")])));
+
+        let mut doc = Document::parse_str(src).unwrap().to_owned();
+        let before = doc.clone();
+        let map = HashMap::from([("urn:one".to_string(), "y".to_string())]);
+        assert!(doc.rewrite_namespaces(&map).is_err());
+        assert_eq!(doc, before);
+    }
+
+    #[test]
+    fn test_renames() {
+        let src = r#"<r xmlns="urn:a" xmlns:p="urn:b"><x p:k="1" k="2" /><p:x p:k="3" p:id="4" /><x xmlns="" p:k="5" /><q:x /></r>"#;
+        let mut doc = Document::parse_str(src).unwrap().to_owned();
+
+        // Unprefixed elements follow the default namespace, which is undone by xmlns=""
+        assert_eq!(doc.rename_elements(Some("urn:a"), "x", "y"), 1);
+        assert_eq!(doc.rename_elements(None, "x", "z"), 1);
+        assert_eq!(doc.rename_elements(Some("urn:b"), "x", "w"), 1);
+
+        // The second p:k is left alone, as p:id is taken; declarations are never attributes to rename
+        assert_eq!(doc.rename_attributes(Some("urn:b"), "k", "id"), 2);
+        assert_eq!(doc.rename_attributes(None, "xmlns", "ns"), 0);
+
+        let xml = doc
+            .to_xml_with_options(&XmlWriteOptions::default().with_minify(true))
+            .unwrap();
+        assert_eq!(
+            xml,
+            r#"<r xmlns="urn:a" xmlns:p="urn:b"><y p:id="1" k="2" /><p:w p:k="3" p:id="4" /><z xmlns="" p:id="5" /><q:x /></r>"#
+                .to_string()
+                + "\n"
+        );
+    }
+}
@@ -11,15 +11,26 @@ pub use text::*;
 mod tag;
 pub use tag::*;
 
+mod attributes;
+pub use attributes::*;
+
 mod pi;
 pub use pi::*;
 
 mod cdata;
 pub use cdata::*;
 
+mod comment;
+pub use comment::*;
+
 mod dtd;
 pub use dtd::*;
 
+mod iter;
+pub use iter::*;
+
+pub(crate) mod subset;
+
 /// A node in the document tree. Can be any of:
 /// - `Child` - a tag node
 /// - `Text` - a text node
@@ -38,7 +49,7 @@ pub enum Node<'src> {
     Text(TextNode<'src>),
 
     /// A comment node.
-    Comment(StrSpan<'src>),
+    Comment(CommentNode<'src>),
 
     /// A processing instruction node.
     ProcessingInstruction(ProcessingInstructionNode<'src>),
@@ -49,14 +60,120 @@ pub enum Node<'src> {
     /// A CDATA node.
     Cdata(CdataNode<'src>),
 }
-impl Node<'_> {
+impl<'src> Node<'src> {
+    /// Returns the span of the node in the original source.
+    #[must_use]
+    pub fn span(&self) -> &StrSpan<'src> {
+        match self {
+            Self::Child(node) => node.span(),
+            Self::Text(node) => node.span(),
+            Self::Comment(node) => node.span(),
+            Self::ProcessingInstruction(node) => node.span(),
+            Self::DocumentType(node) => node.span(),
+            Self::Cdata(node) => node.span(),
+        }
+    }
+
+    /// Converts a comment into a processing instruction, in place.
+    ///
+    /// The first word of the comment becomes the target, and the rest becomes the content:
+    /// `<!--target content-->` becomes `<?target content?>`.
+    /// The new node's spans point into the comment's text.
+    ///
+    /// Returns false, leaving the node unchanged, if it is not a comment or the comment is blank.
+    pub fn comment_to_processing_instruction(&mut self) -> bool {
+        let Self::Comment(comment) = self else {
+            return false;
+        };
+
+        let text = comment.text();
+        let Some((target, content)) = split_instruction(text.text()) else {
+            return false;
+        };
+
+        let target = text.slice(target);
+        let content = content.map(|range| text.slice(range));
+        *self = Self::ProcessingInstruction(ProcessingInstructionNode::new(*text, target, content));
+        true
+    }
+
+    /// Converts a processing instruction into a comment, in place.
+    ///
+    /// The comment's text is the target followed by the content:
+    /// `<?target content?>` becomes `<!--target content-->`.
+    /// If the node was parsed from a source, the comment's span points to the text between `<?` and `?>`.
+    ///
+    /// Returns false, leaving the node unchanged, if it is not a processing instruction,
+    /// or is a detached node with content, whose text cannot be joined without allocating.
+    pub fn processing_instruction_to_comment(&mut self) -> bool {
+        let Self::ProcessingInstruction(pi) = self else {
+            return false;
+        };
+
+        let span = pi.span();
+        let text = if span.text().starts_with("<?") && span.text().ends_with("?>") {
+            let inner = span.slice(2..span.len() - 2);
+            inner.slice(0..inner.text().trim_end().len())
+        } else if pi.content().is_none() {
+            *pi.target()
+        } else {
+            return false;
+        };
+
+        *self = Self::Comment(CommentNode::from(text));
+        true
+    }
+
+    /// Converts a text node into a CDATA section, in place, keeping its spans.
+    ///
+    /// Returns false, leaving the node unchanged, if it is not a text node.
+    pub fn text_to_cdata(&mut self) -> bool {
+        let Self::Text(text) = self else {
+            return false;
+        };
+
+        *self = Self::Cdata(CdataNode::new(*text.span(), *text.text()));
+        true
+    }
+
+    /// Converts a CDATA section into a text node, in place, keeping its spans.
+    ///
+    /// Returns false, leaving the node unchanged, if it is not a CDATA section.
+    pub fn cdata_to_text(&mut self) -> bool {
+        let Self::Cdata(cdata) = self else {
+            return false;
+        };
+
+        *self = Self::Text(TextNode::new(*cdata.span(), *cdata.content()));
+        true
+    }
+
+    /// Returns a human-readable name for the kind of node, such as "CDATA section".
+    #[must_use]
+    pub fn kind_name(&self) -> &'static str {
+        match self {
+            Self::Child(_) => "Element",
+            Self::Text(_) => "Text",
+            Self::Comment(_) => "Comment",
+            Self::ProcessingInstruction(_) => "Processing instruction",
+            Self::DocumentType(_) => "DTD",
+            Self::Cdata(_) => "CDATA section",
+        }
+    }
+
     /// Returns an owned version of the node, with no span metadata.
     #[must_use]
     pub fn to_owned(&self) -> OwnedNode {
+        self.to_owned_in(&mut NameInterner::new())
+    }
+
+    /// Returns an owned version of the node, sharing element and attribute names through the given interner.
+    #[must_use]
+    pub fn to_owned_in(&self, interner: &mut NameInterner) -> OwnedNode {
         match self {
-            Self::Child(node) => OwnedNode::Tag(node.to_owned()),
+            Self::Child(node) => OwnedNode::Tag(node.to_owned_in(interner)),
             Self::Text(node) => OwnedNode::Text(node.to_owned()),
-            Self::Comment(span) => OwnedNode::Comment(span.text().to_string()),
+            Self::Comment(node) => OwnedNode::Comment(node.to_owned()),
             Self::ProcessingInstruction(node) => OwnedNode::ProcessingInstruction(node.to_owned()),
             Self::DocumentType(node) => OwnedNode::DocumentType(node.to_owned()),
             Self::Cdata(node) => OwnedNode::Cdata(node.to_owned()),
@@ -69,7 +186,8 @@ impl<'src> ToBinHandler<'src> for Node<'src> {
         let kind: u8 = match self {
             Self::Child(_) => 0,
             Self::Text(_) => 1,
-            Self::Comment(_) => 2,
+            Self::Comment(_) if encoder.format_version() < 3 => 2,
+            Self::Comment(_) => 6,
             Self::ProcessingInstruction(_) => 3,
             Self::DocumentType(_) => 4,
             Self::Cdata(_) => 5,
@@ -78,7 +196,8 @@ impl<'src> ToBinHandler<'src> for Node<'src> {
         match self {
             Self::Child(node) => node.write(encoder)?,
             Self::Text(node) => node.write(encoder)?,
-            Self::Comment(span) => span.write(encoder)?,
+            Self::Comment(node) if encoder.format_version() < 3 => node.text().write(encoder)?,
+            Self::Comment(node) => node.write(encoder)?,
             Self::ProcessingInstruction(node) => node.write(encoder)?,
             Self::DocumentType(node) => node.write(encoder)?,
             Self::Cdata(node) => node.write(encoder)?,
@@ -91,10 +210,12 @@ impl<'src> ToBinHandler<'src> for Node<'src> {
         let node = match kind {
             0 => Node::Child(TagNode::read(decoder)?),
             1 => Node::Text(TextNode::read(decoder)?),
-            2 => Node::Comment(StrSpan::read(decoder)?),
+            // Comments were written as only their text before they had a span of their own
+            2 => Node::Comment(CommentNode::from(StrSpan::read(decoder)?)),
             3 => Node::ProcessingInstruction(ProcessingInstructionNode::read(decoder)?),
             4 => Node::DocumentType(DtdNode::read(decoder)?),
             5 => Node::Cdata(CdataNode::read(decoder)?),
+            6 => Node::Comment(CommentNode::read(decoder)?),
             _ => return Err(BinDecodeError::InvalidEnumVariant),
         };
 
@@ -102,8 +223,37 @@ impl<'src> ToBinHandler<'src> for Node<'src> {
     }
 }
 
+impl<'src> Node<'src> {
+    /// Moves past an encoded node. Tags are skipped without collecting their descendants.
+    pub(crate) fn skip(decoder: &mut Decoder<'src>) -> Result<(), BinDecodeError> {
+        match u8::read(decoder)? {
+            0 => TagNode::skip(decoder)?,
+            1 => {
+                TextNode::read(decoder)?;
+            }
+            2 => {
+                StrSpan::read(decoder)?;
+            }
+            3 => {
+                ProcessingInstructionNode::read(decoder)?;
+            }
+            4 => {
+                DtdNode::read(decoder)?;
+            }
+            5 => {
+                CdataNode::read(decoder)?;
+            }
+            6 => {
+                CommentNode::read(decoder)?;
+            }
+            _ => return Err(BinDecodeError::InvalidEnumVariant),
+        }
+        Ok(())
+    }
+}
+
 /// An owned version of a node in the document tree. See [`Node`].
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum OwnedNode {
     /// A tag node.
     Tag(OwnedTagNode),
@@ -112,7 +262,7 @@ pub enum OwnedNode {
     Text(OwnedTextNode),
 
     /// A comment node.
-    Comment(String),
+    Comment(OwnedCommentNode),
 
     /// A processing instruction node.
     ProcessingInstruction(OwnedProcessingInstructionNode),
@@ -128,13 +278,133 @@ impl OwnedNode {
         match self {
             Self::Tag(node) => Node::Child(node.borrowed()),
             Self::Text(node) => Node::Text(node.borrowed()),
-            Self::Comment(text) => Node::Comment(StrSpan::from(text.as_str())),
+            Self::Comment(node) => Node::Comment(node.borrowed()),
             Self::ProcessingInstruction(node) => Node::ProcessingInstruction(node.borrowed()),
             Self::DocumentType(node) => Node::DocumentType(node.borrowed()),
             Self::Cdata(node) => Node::Cdata(node.borrowed()),
         }
     }
 }
+impl OwnedNode {
+    /// Converts a slice of nodes into owned nodes in one pass, sharing names between them.
+    ///
+    /// This is faster than calling [`Node::to_owned`] on each node, which allocates every name separately.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{Document, node::OwnedNode};
+    ///
+    /// let doc = Document::parse_str("<list><item>a</item><item>b</item></list>").unwrap();
+    /// let items = OwnedNode::from_nodes(doc.root().children());
+    /// assert_eq!(items.len(), 2);
+    /// ```
+    #[must_use]
+    pub fn from_nodes(nodes: &[Node<'_>]) -> Vec<OwnedNode> {
+        Self::from_nodes_in(nodes, &mut NameInterner::new())
+    }
+
+    /// Converts a slice of nodes into owned nodes in one pass, sharing names through the given interner.
+    #[must_use]
+    pub fn from_nodes_in(nodes: &[Node<'_>], interner: &mut NameInterner) -> Vec<OwnedNode> {
+        let mut owned = Vec::with_capacity(nodes.len());
+        owned.extend(nodes.iter().map(|node| node.to_owned_in(interner)));
+        owned
+    }
+
+    /// Replaces the element and attribute names in this node with the interner's shared copies.
+    ///
+    /// See [`OwnedTagNode::intern_names`].
+    pub fn intern_names(&mut self, interner: &mut NameInterner) {
+        if let Self::Tag(node) = self {
+            node.intern_names(interner);
+        }
+    }
+
+    /// Converts a comment into a processing instruction, in place.
+    ///
+    /// See [`Node::comment_to_processing_instruction`] for details.
+    ///
+    /// Returns false, leaving the node unchanged, if it is not a comment or the comment is blank.
+    pub fn comment_to_processing_instruction(&mut self) -> bool {
+        let Self::Comment(OwnedCommentNode { text, .. }) = self else {
+            return false;
+        };
+
+        let Some((target, content)) = split_instruction(text) else {
+            return false;
+        };
+
+        let target = text[target].to_string();
+        let content = content.map(|range| text[range].to_string());
+        *self = Self::ProcessingInstruction(OwnedProcessingInstructionNode::new(target, content));
+        true
+    }
+
+    /// Converts a processing instruction into a comment, in place.
+    ///
+    /// See [`Node::processing_instruction_to_comment`] for details.
+    ///
+    /// Returns false, leaving the node unchanged, if it is not a processing instruction.
+    pub fn processing_instruction_to_comment(&mut self) -> bool {
+        let Self::ProcessingInstruction(pi) = self else {
+            return false;
+        };
+
+        let text = match &pi.content {
+            Some(content) => format!("{} {content}", pi.target),
+            None => pi.target.clone(),
+        };
+        *self = Self::Comment(OwnedCommentNode::new(text));
+        true
+    }
+
+    /// Converts a text node into a CDATA section, in place.
+    ///
+    /// Returns false, leaving the node unchanged, if it is not a text node.
+    pub fn text_to_cdata(&mut self) -> bool {
+        let Self::Text(text) = self else {
+            return false;
+        };
+
+        *self = Self::Cdata(OwnedCdataNode::new(std::mem::take(&mut text.text)));
+        true
+    }
+
+    /// Converts a CDATA section into a text node, in place.
+    ///
+    /// Returns false, leaving the node unchanged, if it is not a CDATA section.
+    pub fn cdata_to_text(&mut self) -> bool {
+        let Self::Cdata(cdata) = self else {
+            return false;
+        };
+
+        *self = Self::Text(OwnedTextNode::new(std::mem::take(&mut cdata.content)));
+        true
+    }
+}
+
+/// Splits the text of a comment into the ranges of a processing instruction's target and content.
+///
+/// Returns `None` if the text is blank.
+fn split_instruction(
+    text: &str,
+) -> Option<(std::ops::Range<usize>, Option<std::ops::Range<usize>>)> {
+    let start = text.len() - text.trim_start().len();
+    let end = text.trim_end().len();
+    if start >= end {
+        return None;
+    }
+
+    let Some(target_len) = text[start..end].find(char::is_whitespace) else {
+        return Some((start..end, None));
+    };
+
+    let target = start..start + target_len;
+    let rest = &text[target.end..end];
+    let content_start = target.end + (rest.len() - rest.trim_start().len());
+    Some((target, Some(content_start..end)))
+}
+
 impl<'src> ToBinHandler<'src> for OwnedNode {
     fn write(&self, encoder: &mut Encoder) -> std::io::Result<()> {
         self.borrowed().write(encoder)
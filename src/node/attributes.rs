@@ -0,0 +1,308 @@
+use super::NodeAttribute;
+use crate::to_bin::{BinDecodeError, Decoder, Encoder, ToBinHandler};
+/// Number of attributes stored without a heap allocation.
+///
+/// Every tag pays for the inline slots, including tags without attributes, so only one is kept.
+const INLINE: usize = 1;
+
+// Fails to compile if the inline slots grow tags by more than a single attribute
+const _: () = assert!(size_of::<Attributes>() <= size_of::<NodeAttribute>() + size_of::<usize>());
+
+/// The attributes of a [`TagNode`](super::TagNode), in document order.
+///
+/// Tags with a single attribute are common, so one is stored inline, without a heap allocation.
+/// Derefs to a slice, and has the common mutation methods of `Vec`.
+#[derive(Clone)]
+pub struct Attributes<'src>(Storage<'src>);
+
+/// Attributes are plain spans with no drop logic, so unused inline slots hold empty placeholders
+/// instead of uninitialized memory. This keeps the type covariant and free of a `Drop` impl,
+/// so a tree may still borrow strings that are dropped before it.
+#[derive(Clone)]
+enum Storage<'src> {
+    Inline {
+        slots: [NodeAttribute<'src>; INLINE],
+        len: usize,
+    },
+    Heap(Vec<NodeAttribute<'src>>),
+}
+
+fn placeholder<'src>() -> NodeAttribute<'src> {
+    NodeAttribute::new(None, "", "")
+}
+
+impl<'src> Attributes<'src> {
+    /// Creates an empty list of attributes.
+    #[must_use]
+    pub fn new() -> Self {
+        Self(Storage::Inline {
+            slots: std::array::from_fn(|_| placeholder()),
+            len: 0,
+        })
+    }
+
+    /// Creates an empty list of attributes with room for at least `capacity` attributes.
+    #[must_use]
+    pub fn with_capacity(capacity: usize) -> Self {
+        if capacity <= INLINE {
+            Self::new()
+        } else {
+            Self(Storage::Heap(Vec::with_capacity(capacity)))
+        }
+    }
+
+    /// Moves the list to the heap, if needed, and returns it as a vector.
+    fn spill(&mut self) -> &mut Vec<NodeAttribute<'src>> {
+        if let Storage::Inline { slots, len } = &mut self.0 {
+            let mut heap = Vec::with_capacity(INLINE * 2);
+            heap.extend(
+                slots[..*len]
+                    .iter_mut()
+                    .map(|slot| std::mem::replace(slot, placeholder())),
+            );
+            self.0 = Storage::Heap(heap);
+        }
+
+        match &mut self.0 {
+            Storage::Heap(heap) => heap,
+            Storage::Inline { .. } => unreachable!("Attributes were just moved to the heap"),
+        }
+    }
+
+    /// Adds an attribute to the end of the list.
+    pub fn push(&mut self, attribute: NodeAttribute<'src>) {
+        match &mut self.0 {
+            Storage::Inline { slots, len } if *len < INLINE => {
+                slots[*len] = attribute;
+                *len += 1;
+            }
+            _ => self.spill().push(attribute),
+        }
+    }
+
+    /// Inserts an attribute at the given index, shifting the ones after it.
+    ///
+    /// # Panics
+    /// Panics if `index` is greater than the length of the list.
+    pub fn insert(&mut self, index: usize, attribute: NodeAttribute<'src>) {
+        match &mut self.0 {
+            Storage::Inline { slots, len } if *len < INLINE => {
+                assert!(index <= *len, "insertion index {index} is out of bounds");
+                slots[*len] = attribute;
+                slots[index..=*len].rotate_right(1);
+                *len += 1;
+            }
+            _ => self.spill().insert(index, attribute),
+        }
+    }
+
+    /// Removes and returns the attribute at the given index, shifting the ones after it.
+    ///
+    /// # Panics
+    /// Panics if `index` is out of bounds.
+    pub fn remove(&mut self, index: usize) -> NodeAttribute<'src> {
+        match &mut self.0 {
+            Storage::Inline { slots, len } => {
+                slots[index..*len].rotate_left(1);
+                *len -= 1;
+                std::mem::replace(&mut slots[*len], placeholder())
+            }
+            Storage::Heap(heap) => heap.remove(index),
+        }
+    }
+
+    /// Removes and returns the last attribute, if any.
+    pub fn pop(&mut self) -> Option<NodeAttribute<'src>> {
+        match self.len() {
+            0 => None,
+            len => Some(self.remove(len - 1)),
+        }
+    }
+
+    /// Keeps only the attributes for which the predicate returns true.
+    pub fn retain(&mut self, mut predicate: impl FnMut(&NodeAttribute<'src>) -> bool) {
+        match &mut self.0 {
+            Storage::Inline { slots, len } => {
+                let mut kept = 0;
+                for i in 0..*len {
+                    if predicate(&slots[i]) {
+                        slots.swap(kept, i);
+                        kept += 1;
+                    }
+                }
+                self.truncate(kept);
+            }
+            Storage::Heap(heap) => heap.retain(predicate),
+        }
+    }
+
+    /// Shortens the list to `len` attributes, dropping the rest.
+    pub fn truncate(&mut self, new_len: usize) {
+        match &mut self.0 {
+            Storage::Inline { slots, len } => {
+                if new_len < *len {
+                    slots[new_len..*len].fill_with(placeholder);
+                    *len = new_len;
+                }
+            }
+            Storage::Heap(heap) => heap.truncate(new_len),
+        }
+    }
+
+    /// Removes all attributes.
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    /// Returns true if the attributes are stored inline, without a heap allocation.
+    #[must_use]
+    pub fn is_inline(&self) -> bool {
+        matches!(self.0, Storage::Inline { .. })
+    }
+
+    /// Converts the list into a vector.
+    #[must_use]
+    pub fn into_vec(self) -> Vec<NodeAttribute<'src>> {
+        match self.0 {
+            Storage::Inline { slots, len } => slots.into_iter().take(len).collect(),
+            Storage::Heap(heap) => heap,
+        }
+    }
+}
+
+impl Default for Attributes<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'src> std::ops::Deref for Attributes<'src> {
+    type Target = [NodeAttribute<'src>];
+
+    fn deref(&self) -> &Self::Target {
+        match &self.0 {
+            Storage::Inline { slots, len } => &slots[..*len],
+            Storage::Heap(heap) => heap,
+        }
+    }
+}
+
+impl std::ops::DerefMut for Attributes<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match &mut self.0 {
+            Storage::Inline { slots, len } => &mut slots[..*len],
+            Storage::Heap(heap) => heap,
+        }
+    }
+}
+
+impl std::fmt::Debug for Attributes<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl PartialEq for Attributes<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl<'src> From<Vec<NodeAttribute<'src>>> for Attributes<'src> {
+    fn from(heap: Vec<NodeAttribute<'src>>) -> Self {
+        Self(Storage::Heap(heap))
+    }
+}
+
+impl<'src> FromIterator<NodeAttribute<'src>> for Attributes<'src> {
+    fn from_iter<I: IntoIterator<Item = NodeAttribute<'src>>>(iter: I) -> Self {
+        let mut attributes = Self::new();
+        attributes.extend(iter);
+        attributes
+    }
+}
+
+impl<'src> Extend<NodeAttribute<'src>> for Attributes<'src> {
+    fn extend<I: IntoIterator<Item = NodeAttribute<'src>>>(&mut self, iter: I) {
+        for attribute in iter {
+            self.push(attribute);
+        }
+    }
+}
+
+impl<'a, 'src> IntoIterator for &'a Attributes<'src> {
+    type Item = &'a NodeAttribute<'src>;
+    type IntoIter = std::slice::Iter<'a, NodeAttribute<'src>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, 'src> IntoIterator for &'a mut Attributes<'src> {
+    type Item = &'a mut NodeAttribute<'src>;
+    type IntoIter = std::slice::IterMut<'a, NodeAttribute<'src>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<'src> ToBinHandler<'src> for Attributes<'src> {
+    /// Same layout as a `Vec`, so older binary documents still decode
+    fn write(&self, encoder: &mut Encoder) -> std::io::Result<()> {
+        self.len().write(encoder)?;
+        for attribute in self {
+            attribute.write(encoder)?;
+        }
+        Ok(())
+    }
+
+    fn read(decoder: &mut Decoder<'src>) -> Result<Self, BinDecodeError> {
+        let len = usize::read(decoder)?;
+        let mut attributes = Self::new();
+        if len > INLINE {
+            let mut heap = vec![];
+            heap.try_reserve(len)?;
+            attributes = Self::from(heap);
+        }
+
+        for _ in 0..len {
+            attributes.push(NodeAttribute::read(decoder)?);
+        }
+        Ok(attributes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attributes_spill() {
+        let mut attributes = Attributes::new();
+        attributes.push(NodeAttribute::detached(None, "b", "1"));
+        attributes.insert(0, NodeAttribute::detached(None, "a", "1"));
+        assert!(!attributes.is_inline());
+        attributes.push(NodeAttribute::detached(None, "c", "1"));
+        assert_eq!(attributes.remove(0).name(), "a");
+        attributes.insert(0, NodeAttribute::detached(None, "a", "1"));
+        attributes.insert(0, NodeAttribute::detached(None, "z", "2"));
+
+        let names: Vec<_> = attributes.iter().map(|a| a.name().to_string()).collect();
+        assert_eq!(names, ["z", "a", "b", "c"]);
+
+        attributes.retain(|a| a.value().text() == "1");
+        assert_eq!(attributes.len(), 3);
+        assert_eq!(
+            attributes,
+            attributes.iter().cloned().collect::<Attributes>()
+        );
+
+        let mut single = Attributes::new();
+        single.push(NodeAttribute::detached(None, "a", "1"));
+        assert!(single.is_inline());
+        assert_eq!(single.pop().unwrap().name(), "a");
+        assert!(single.is_empty());
+    }
+}
@@ -1,5 +1,5 @@
 use crate::{
-    StrSpan,
+    Origin, StrSpan,
     to_bin::{BinDecodeError, Decoder, Encoder, ToBinHandler},
 };
 
@@ -19,6 +19,14 @@ impl<'src> CdataNode<'src> {
         }
     }
 
+    /// Create a new CDATA node that is not part of the original source.
+    ///
+    /// See [`TagNode::detached`](super::TagNode::detached) for the lifetime requirements of the string.
+    #[must_use]
+    pub fn detached(content: &'src str) -> Self {
+        Self::new("", content)
+    }
+
     /// Returns the span of the node in the original source.
     #[must_use]
     pub fn span(&self) -> &StrSpan<'src> {
@@ -31,11 +39,12 @@ impl<'src> CdataNode<'src> {
         &self.content
     }
 
-    /// Returns an owned version of the CDATA node, with no span metadata.
+    /// Returns an owned version of the CDATA node; only its origin is kept from the span metadata.
     #[must_use]
     pub fn to_owned(&self) -> OwnedCdataNode {
         OwnedCdataNode {
             content: self.content.text().to_string(),
+            origin: Origin::from(&self.span),
         }
     }
 }
@@ -55,16 +64,20 @@ impl<'src> ToBinHandler<'src> for CdataNode<'src> {
 }
 
 /// An owned version of a CDATA node, with no span metadata. See [`CdataNode`].
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct OwnedCdataNode {
     /// The inner content of the CDATA node.
     pub content: String,
+
+    /// Where the node was converted from, if it was parsed. See [`Origin`].
+    pub origin: Origin,
 }
 impl OwnedCdataNode {
     /// Create a new CDATA node.
     pub fn new(content: impl Into<String>) -> Self {
         Self {
             content: content.into(),
+            origin: Origin::default(),
         }
     }
 
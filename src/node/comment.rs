@@ -0,0 +1,139 @@
+use crate::{
+    Origin, StrSpan,
+    to_bin::{BinDecodeError, Decoder, Encoder, ToBinHandler},
+};
+
+/// A comment node:
+/// `<!--text-->`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommentNode<'src> {
+    span: StrSpan<'src>,
+    text: StrSpan<'src>,
+}
+impl<'src> CommentNode<'src> {
+    /// Create a new comment node.
+    pub(crate) fn new<T: Into<StrSpan<'src>>>(span: T, text: T) -> Self {
+        Self {
+            span: span.into(),
+            text: text.into(),
+        }
+    }
+
+    /// Create a new comment node that is not part of the original source.
+    ///
+    /// See [`TagNode::detached`](super::TagNode::detached) for the lifetime requirements of the string.
+    #[must_use]
+    pub fn detached(text: &'src str) -> Self {
+        Self::new("", text)
+    }
+
+    /// Returns the span of the node in the original source, including the `<!--` and `-->` delimiters.
+    #[must_use]
+    pub fn span(&self) -> &StrSpan<'src> {
+        &self.span
+    }
+
+    /// Returns the text of the comment, exactly as written between the delimiters.
+    #[must_use]
+    pub fn text(&self) -> &StrSpan<'src> {
+        &self.text
+    }
+
+    /// Returns the text of the comment, without leading and trailing whitespace.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{Document, node::Node};
+    ///
+    /// let doc = Document::parse_str("<!--  note  --><root />").unwrap();
+    /// let Node::Comment(comment) = &doc.prolog()[0] else { unreachable!() };
+    /// assert_eq!(comment.text().text(), "  note  ");
+    /// assert_eq!(comment.trimmed().text(), "note");
+    /// assert_eq!(comment.trimmed().start(), 6);
+    /// ```
+    #[must_use]
+    pub fn trimmed(&self) -> StrSpan<'src> {
+        let text = self.text.text();
+        let start = text.len() - text.trim_start().len();
+        let end = text.trim_end().len().max(start);
+        self.text.slice(start..end)
+    }
+
+    /// Returns an owned version of the comment node; only its origin is kept from the span metadata.
+    #[must_use]
+    pub fn to_owned(&self) -> OwnedCommentNode {
+        OwnedCommentNode {
+            text: self.text.text().to_string(),
+            origin: Origin::from(&self.span),
+        }
+    }
+}
+impl<'src> From<StrSpan<'src>> for CommentNode<'src> {
+    /// Creates a comment from the span of its text, which is also used as the span of the node.
+    fn from(text: StrSpan<'src>) -> Self {
+        Self::new(text, text)
+    }
+}
+impl<'src> ToBinHandler<'src> for CommentNode<'src> {
+    fn write(&self, encoder: &mut Encoder) -> std::io::Result<()> {
+        self.span.write(encoder)?;
+        self.text.write(encoder)?;
+        Ok(())
+    }
+
+    fn read(decoder: &mut Decoder<'src>) -> Result<Self, BinDecodeError> {
+        let span = StrSpan::read(decoder)?;
+        let text = StrSpan::read(decoder)?;
+
+        Ok(CommentNode { span, text })
+    }
+}
+
+/// An owned version of a comment node, with no span metadata. See [`CommentNode`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OwnedCommentNode {
+    /// The text of the comment, without the delimiters.
+    pub text: String,
+
+    /// Where the node was converted from, if it was parsed. See [`Origin`].
+    pub origin: Origin,
+}
+impl OwnedCommentNode {
+    /// Create a new comment node.
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            origin: Origin::default(),
+        }
+    }
+
+    /// Returns the text of the comment, without leading and trailing whitespace.
+    #[must_use]
+    pub fn trimmed(&self) -> &str {
+        self.text.trim()
+    }
+
+    pub(crate) fn borrowed(&self) -> CommentNode<'_> {
+        CommentNode::new("", self.text.as_str())
+    }
+}
+impl From<String> for OwnedCommentNode {
+    fn from(text: String) -> Self {
+        Self::new(text)
+    }
+}
+impl From<&str> for OwnedCommentNode {
+    fn from(text: &str) -> Self {
+        Self::new(text)
+    }
+}
+impl<'src> ToBinHandler<'src> for OwnedCommentNode {
+    fn write(&self, encoder: &mut Encoder) -> std::io::Result<()> {
+        self.borrowed().write(encoder)
+    }
+
+    fn read(decoder: &mut Decoder<'src>) -> Result<Self, BinDecodeError> {
+        let node = CommentNode::read(decoder)?;
+        Ok(Self::new(node.text.text()))
+    }
+}
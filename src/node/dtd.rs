@@ -1,6 +1,8 @@
+use super::subset::{SubsetCursor, SubsetItem};
 use crate::{
-    StrSpan,
+    Origin, StrSpan,
     error::{ErrorContext, XmlError, XmlErrorKind, XmlResult},
+    resolve::UriResolver,
     to_bin::{BinDecodeError, Decoder, Encoder, ToBinHandler},
 };
 use xmlparser::{Token, Tokenizer};
@@ -23,6 +25,24 @@ impl<'src> ExternalId<'src> {
         ExternalId::Public(p.into(), s.into())
     }
 
+    /// Returns the system identifier (the URI) of the external ID.
+    #[must_use]
+    pub fn system(&self) -> &StrSpan<'src> {
+        match self {
+            ExternalId::System(system) | ExternalId::Public(_, system) => system,
+        }
+    }
+
+    /// Fetches the resource referenced by the system identifier through the given resolver.
+    ///
+    /// `base` is the URI of the referencing document, used to resolve relative identifiers.
+    ///
+    /// # Errors
+    /// Returns an error if the resolver refuses or fails to load the resource
+    pub fn load(&self, resolver: &dyn UriResolver, base: Option<&str>) -> XmlResult<Vec<u8>> {
+        resolver.resolve(self.system().text(), base)
+    }
+
     /// Returns an owned version of the external ID, with no span metadata.
     #[must_use]
     pub fn to_owned(&self) -> OwnedExternalId {
@@ -308,6 +328,34 @@ impl<'src> ToBinHandler<'src> for OwnedDtdEntity {
     }
 }
 
+/// A default value for an attribute, declared by an `<!ATTLIST>` in the internal subset of a DTD.
+///
+/// See [`DtdNode::attribute_defaults`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct AttributeDefault<'src> {
+    /// The span of the attribute definition in the source XML, from the attribute name to the closing quote.
+    pub span: StrSpan<'src>,
+
+    /// The name of the element the attribute belongs to, including any prefix.
+    pub element: StrSpan<'src>,
+
+    /// The name of the attribute, including any prefix.
+    pub name: StrSpan<'src>,
+
+    /// The default value, exactly as written between the quotes.
+    pub value: StrSpan<'src>,
+
+    /// True if the default is `#FIXED`, so the attribute must always have this value.
+    pub fixed: bool,
+}
+
+/// Returns true if the text of an entity declaration declares a parameter entity, as in `<!ENTITY % name ...>`.
+fn is_parameter_entity(declaration: &str) -> bool {
+    declaration
+        .strip_prefix("<!ENTITY")
+        .is_some_and(|rest| rest.trim_start().starts_with('%'))
+}
+
 /// The DTD node in the XML document.
 #[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub struct DtdNode<'src> {
@@ -315,6 +363,7 @@ pub struct DtdNode<'src> {
     name: StrSpan<'src>,
     external_id: Option<ExternalId<'src>>,
     entities: Vec<DtdEntity<'src>>,
+    internal_subset: Option<StrSpan<'src>>,
 }
 impl<'src> DtdNode<'src> {
     /// Returns the span of the DTD node in the original source.
@@ -341,13 +390,215 @@ impl<'src> DtdNode<'src> {
         &self.entities
     }
 
-    /// Returns an owned version of the DTD node, with no span metadata.
+    /// Returns the text of the internal subset, between its brackets, if the DTD has one.
+    ///
+    /// The subset is kept as written, including declarations, references and sections that are not interpreted,
+    /// and is written back unchanged. Declarations other than general entities are read from it on demand,
+    /// by methods such as [`DtdNode::attribute_defaults`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{Document, node::Node};
+    ///
+    /// let doc = Document::parse_str(r#"<!DOCTYPE doc [<!NOTATION n SYSTEM "x">]><doc />"#).unwrap();
+    /// let Node::DocumentType(dtd) = &doc.prolog()[0] else { unreachable!() };
+    /// assert_eq!(dtd.internal_subset().unwrap().text(), r#"<!NOTATION n SYSTEM "x">"#);
+    /// ```
+    #[must_use]
+    pub fn internal_subset(&self) -> Option<&StrSpan<'src>> {
+        self.internal_subset.as_ref()
+    }
+
+    /// Splits the internal subset into its markup, for writing one item per line.
+    ///
+    /// Each item keeps the comments and processing instructions before it; joined, they are the whole subset.
+    pub(crate) fn internal_subset_items(&self) -> Vec<&'src str> {
+        let Some(subset) = self.internal_subset else {
+            return vec![];
+        };
+
+        let mut items = vec![];
+        let mut cursor = self.subset_cursor();
+        let mut start = 0;
+        while let Some(item) = cursor.next_item() {
+            if let SubsetItem::Declaration(..) = item {
+                cursor.skip_declaration();
+            }
+            let end = cursor.offset() - subset.start();
+            items.push(subset.text()[start..end].trim());
+            start = end;
+        }
+
+        // Trailing comments, or text the cursor cannot read, are kept as written
+        let rest = subset.text()[start..].trim();
+        if !rest.is_empty() {
+            items.push(rest);
+        }
+        items
+    }
+
+    /// Returns a cursor at the start of the internal subset, which is empty if there is none.
+    fn subset_cursor(&self) -> SubsetCursor<'src> {
+        SubsetCursor::inside(self.internal_subset.unwrap_or_default())
+    }
+
+    /// Returns the attribute default values declared by `<!ATTLIST>` declarations in the internal subset, in order.
+    ///
+    /// Attributes that are `#REQUIRED` or `#IMPLIED` have no default, and are not included.
+    /// Declarations are read from the [internal subset](DtdNode::internal_subset), so nodes created
+    /// without one have no defaults. Declarations that use parameter entities are skipped.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{Document, node::Node};
+    ///
+    /// let doc = Document::parse_str(r#"<!DOCTYPE doc [
+    ///     <!ATTLIST item kind (a|b) "a" id ID #REQUIRED version CDATA #FIXED '1.0'>
+    /// ]><doc />"#).unwrap();
+    /// let Node::DocumentType(dtd) = &doc.prolog()[0] else { unreachable!() };
+    ///
+    /// let defaults = dtd.attribute_defaults();
+    /// assert_eq!(defaults.len(), 2);
+    /// assert_eq!(defaults[0].element.text(), "item");
+    /// assert_eq!(defaults[0].name.text(), "kind");
+    /// assert_eq!(defaults[0].value.text(), "a");
+    /// assert!(defaults[1].fixed);
+    /// ```
+    #[must_use]
+    pub fn attribute_defaults(&self) -> Vec<AttributeDefault<'src>> {
+        let mut defaults = vec![];
+        let mut cursor = self.subset_cursor();
+        while let Some(item) = cursor.next_item() {
+            match item {
+                SubsetItem::Declaration("ATTLIST", _) => {
+                    if cursor.attlist(&mut defaults).is_none() {
+                        cursor.skip_declaration();
+                    }
+                }
+                SubsetItem::Declaration(..) => cursor.skip_declaration(),
+                SubsetItem::End => break,
+                SubsetItem::Reference | SubsetItem::Conditional(_) => {}
+            }
+        }
+
+        defaults
+    }
+
+    /// Returns the parameter entities declared in the internal subset, such as `<!ENTITY % name "value">`, in order.
+    ///
+    /// Parameter entities can only be referenced inside the DTD, so they are kept apart from [`DtdNode::entities`].
+    /// Like [`DtdNode::attribute_defaults`], they are read from the internal subset, including from
+    /// `INCLUDE` sections; references to them are not expanded.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{Document, node::Node};
+    ///
+    /// let doc = Document::parse_str(r#"<!DOCTYPE doc [
+    ///     <!ENTITY % draft "INCLUDE">
+    ///     <![%draft;[ <!ENTITY status "draft"> ]]>
+    ///     <!ENTITY % common SYSTEM "common.ent">
+    ///     %common;
+    /// ]><doc>&status;</doc>"#).unwrap();
+    /// let Node::DocumentType(dtd) = &doc.prolog()[0] else { unreachable!() };
+    ///
+    /// let parameters = dtd.parameter_entities();
+    /// assert_eq!(parameters[0].name.text(), "draft");
+    /// assert_eq!(parameters[1].name.text(), "common");
+    /// assert_eq!(dtd.conditional_sections()[0].text(), "<![%draft;[ <!ENTITY status \"draft\"> ]]>");
+    /// assert!(dtd.entities().is_empty());
+    /// ```
+    #[must_use]
+    pub fn parameter_entities(&self) -> Vec<DtdEntity<'src>> {
+        let mut entities = vec![];
+        let mut cursor = self.subset_cursor();
+        while let Some(item) = cursor.next_item() {
+            match item {
+                SubsetItem::Declaration("ENTITY", start) => match cursor.entity(start) {
+                    Some((entity, true)) => entities.push(entity),
+                    Some((_, false)) => {}
+                    None => cursor.skip_declaration(),
+                },
+                SubsetItem::Declaration(..) => cursor.skip_declaration(),
+                SubsetItem::End => break,
+                SubsetItem::Reference | SubsetItem::Conditional(_) => {}
+            }
+        }
+
+        entities
+    }
+
+    /// Returns the declarations in the internal subset other than entities, such as `<!ELEMENT`, `<!ATTLIST`,
+    /// and `<!NOTATION`, in order.
+    ///
+    /// Declarations are not interpreted; each span covers the whole declaration, from `<!` to the closing `>`.
+    /// Unrecognized declarations are included, as are those in `INCLUDE` sections.
+    /// They are read from the internal subset, like [`DtdNode::attribute_defaults`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{Document, node::Node};
+    ///
+    /// let doc = Document::parse_str(r#"<!DOCTYPE doc [
+    ///     <!NOTATION gif PUBLIC "image/gif">
+    ///     <!ENTITY logo SYSTEM "logo.gif" NDATA gif>
+    ///     <!ELEMENT doc (#PCDATA)>
+    /// ]><doc />"#).unwrap();
+    /// let Node::DocumentType(dtd) = &doc.prolog()[0] else { unreachable!() };
+    ///
+    /// let other: Vec<_> = dtd.other_declarations().iter().map(|d| d.text()).collect();
+    /// assert_eq!(other, [r#"<!NOTATION gif PUBLIC "image/gif">"#, "<!ELEMENT doc (#PCDATA)>"]);
+    /// assert_eq!(dtd.entities()[0].name.text(), "logo");
+    /// ```
+    #[must_use]
+    pub fn other_declarations(&self) -> Vec<StrSpan<'src>> {
+        let mut declarations = vec![];
+        let mut cursor = self.subset_cursor();
+        while let Some(item) = cursor.next_item() {
+            match item {
+                SubsetItem::Declaration("ENTITY", _) => cursor.skip_declaration(),
+                SubsetItem::Declaration(_, start) => {
+                    cursor.skip_declaration();
+                    declarations.push(cursor.span_from(start));
+                }
+                SubsetItem::End => break,
+                SubsetItem::Reference | SubsetItem::Conditional(_) => {}
+            }
+        }
+
+        declarations
+    }
+
+    /// Returns the conditional sections in the internal subset, such as `<![IGNORE[ ... ]]>`, in order.
+    ///
+    /// Sections are read from the internal subset, like [`DtdNode::attribute_defaults`].
+    /// Declarations in sections marked `INCLUDE` are read, but those in other sections are not, since
+    /// references such as `<![%draft;[` are not expanded. The spans include the delimiters.
+    #[must_use]
+    pub fn conditional_sections(&self) -> Vec<StrSpan<'src>> {
+        let mut sections = vec![];
+        let mut cursor = self.subset_cursor();
+        while let Some(item) = cursor.next_item() {
+            match item {
+                SubsetItem::Conditional(span) => sections.push(span),
+                SubsetItem::Declaration(..) => cursor.skip_declaration(),
+                SubsetItem::End => break,
+                SubsetItem::Reference => {}
+            }
+        }
+
+        sections
+    }
+
+    /// Returns an owned version of the DTD node; only its origin is kept from the span metadata.
     #[must_use]
     pub fn to_owned(&self) -> OwnedDtdNode {
         OwnedDtdNode {
             name: self.name.text().to_string(),
             external_id: self.external_id.as_ref().map(ExternalId::to_owned),
             entities: self.entities.iter().map(DtdEntity::to_owned).collect(),
+            internal_subset: self.internal_subset.map(|s| s.text().to_string()),
+            origin: Origin::from(&self.span),
         }
     }
 
@@ -361,9 +612,77 @@ impl<'src> DtdNode<'src> {
             name: name.into(),
             external_id,
             entities: Vec::new(),
+            internal_subset: None,
         }
     }
 
+    pub(crate) fn with_entities(mut self, entities: Vec<DtdEntity<'src>>) -> Self {
+        self.entities = entities;
+        self
+    }
+
+    pub(crate) fn with_internal_subset(mut self, subset: Option<StrSpan<'src>>) -> Self {
+        self.internal_subset = subset;
+        self
+    }
+
+    /// Reads the general entities of the internal subset directly from the source, if the subset uses
+    /// constructs that the tokenizer rejects.
+    ///
+    /// Returns the offset of the end of the DTD, or `None` if the tokenizer can read the internal subset.
+    fn read_internal_subset(&mut self, src: &'src str) -> XmlResult<Option<usize>> {
+        let text = StrSpan::new(&src[self.span.start()..], self.span.start());
+
+        // Checked first, so that subsets the tokenizer can read keep its error reporting
+        let mut cursor = SubsetCursor::new(text);
+        let mut unsupported = false;
+        loop {
+            match cursor.next_item() {
+                // Constructs the tokenizer rejects are the only reason to read the subset here
+                None if unsupported => {
+                    let pos = cursor.offset() - text.start();
+                    return Err(XmlError::new(
+                        XmlErrorKind::Custom("Invalid internal subset".to_string()),
+                        ErrorContext::new(src, text.slice(pos..pos)),
+                    ));
+                }
+                None => return Ok(None),
+                Some(SubsetItem::End) => break,
+                Some(SubsetItem::Reference | SubsetItem::Conditional(_)) => unsupported = true,
+                Some(SubsetItem::Declaration(keyword, _)) => {
+                    unsupported |=
+                        !matches!(keyword, "ENTITY" | "ELEMENT" | "ATTLIST" | "NOTATION");
+                    cursor.skip_declaration();
+                }
+            }
+        }
+        if !unsupported {
+            return Ok(None);
+        }
+
+        let mut cursor = SubsetCursor::new(text);
+        while let Some(item) = cursor.next_item() {
+            match item {
+                SubsetItem::Declaration("ENTITY", start) => match cursor.entity(start) {
+                    Some((entity, false)) => self.entities.push(entity),
+                    Some((_, true)) => {}
+                    None => {
+                        let span = text.slice(start..cursor.offset() - text.start());
+                        return Err(XmlError::new(
+                            XmlErrorKind::Custom("Invalid entity declaration".to_string()),
+                            ErrorContext::new(src, span),
+                        ));
+                    }
+                },
+                SubsetItem::Declaration(..) => cursor.skip_declaration(),
+                SubsetItem::End => return Ok(Some(cursor.offset())),
+                SubsetItem::Reference | SubsetItem::Conditional(_) => {}
+            }
+        }
+
+        Ok(None)
+    }
+
     pub(crate) fn parse(
         start: Token<'src>,
         tokenizer: &mut Tokenizer<'src>,
@@ -374,24 +693,14 @@ impl<'src> DtdNode<'src> {
                 span,
                 name,
                 external_id,
-            } => DtdNode {
-                span: StrSpan::from(span),
-                name: StrSpan::from(name),
-                external_id: external_id.map(Into::into),
-                entities: Vec::new(),
-            },
+            } => DtdNode::new(span, name, external_id.map(Into::into)),
 
             Token::EmptyDtd {
                 name,
                 external_id,
                 span,
             } => {
-                return Ok(DtdNode {
-                    span: StrSpan::from(span),
-                    name: StrSpan::from(name),
-                    external_id: external_id.map(Into::into),
-                    entities: Vec::new(),
-                });
+                return Ok(DtdNode::new(span, name, external_id.map(Into::into)));
             }
 
             _ => {
@@ -402,6 +711,16 @@ impl<'src> DtdNode<'src> {
             }
         };
 
+        // The tokenizer rejects parameter entity references, conditional sections, and unknown declarations,
+        // so internal subsets that use them are read here, and the tokenizer resumes after the DTD
+        if let Some(end) = node.read_internal_subset(src)? {
+            let start = node.span.start();
+            node.span = StrSpan::new(&src[start..end], start);
+            node.internal_subset = find_internal_subset(node.span);
+            *tokenizer = Tokenizer::from_fragment(src, end..src.len());
+            return Ok(node);
+        }
+
         loop {
             let token = match tokenizer.next() {
                 None => {
@@ -424,9 +743,13 @@ impl<'src> DtdNode<'src> {
             match token {
                 Token::DtdEnd { span } => {
                     node.span.extend(&span.into(), src);
+                    node.internal_subset = find_internal_subset(node.span);
                     return Ok(node);
                 }
 
+                // Parameter entities are read from the source on demand; see `parameter_entities`
+                Token::EntityDeclaration { span, .. } if is_parameter_entity(span.as_str()) => {}
+
                 Token::EntityDeclaration {
                     name,
                     definition,
@@ -440,6 +763,8 @@ impl<'src> DtdNode<'src> {
                     node.entities.push(entity);
                 }
 
+                Token::Comment { .. } | Token::ProcessingInstruction { .. } => {}
+
                 _ => {
                     return Err(XmlError::new(
                         XmlErrorKind::Custom("Expected Entity or DTD end".to_string()),
@@ -456,6 +781,9 @@ impl<'src> ToBinHandler<'src> for DtdNode<'src> {
         self.name.write(encoder)?;
         self.external_id.write(encoder)?;
         self.entities.write(encoder)?;
+        if encoder.format_version() >= 4 {
+            self.internal_subset.write(encoder)?;
+        }
         Ok(())
     }
 
@@ -465,15 +793,41 @@ impl<'src> ToBinHandler<'src> for DtdNode<'src> {
         let external_id = Option::<ExternalId>::read(decoder)?;
         let entities = Vec::<DtdEntity>::read(decoder)?;
 
+        // Older versions only have the subset as part of the span, so nodes without a source lose it
+        let internal_subset = if decoder.format_version() >= 4 {
+            Option::<StrSpan>::read(decoder)?
+        } else {
+            find_internal_subset(span)
+        };
+
         Ok(DtdNode {
             span,
             name,
             external_id,
             entities,
+            internal_subset,
         })
     }
 }
 
+/// Returns the text between the brackets of the internal subset of the DTD at `span`, if it has one.
+fn find_internal_subset(span: StrSpan<'_>) -> Option<StrSpan<'_>> {
+    let mut cursor = SubsetCursor::new(span);
+    let start = cursor.offset() - span.start();
+    loop {
+        match cursor.next_item()? {
+            SubsetItem::Declaration(..) => cursor.skip_declaration(),
+            SubsetItem::End => break,
+            SubsetItem::Reference | SubsetItem::Conditional(_) => {}
+        }
+    }
+
+    let end = cursor.offset() - span.start();
+    let closed = span.text()[start..end].strip_suffix('>')?.trim_end();
+    let len = closed.strip_suffix(']')?.len();
+    Some(span.slice(start..start + len))
+}
+
 /// An owned version of the DTD node, with no span metadata. See [`DtdNode`].
 #[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub struct OwnedDtdNode {
@@ -483,8 +837,16 @@ pub struct OwnedDtdNode {
     /// The external ID of the DTD node, if any.
     pub external_id: Option<OwnedExternalId>,
 
-    /// The entities declared in the DTD node.
+    /// The general entities declared in the DTD node.
     pub entities: Vec<OwnedDtdEntity>,
+
+    /// The text of the internal subset, between its brackets, as written. See [`DtdNode::internal_subset`].
+    ///
+    /// If set, it is written in place of `entities`, so clear it to write edited entities instead.
+    pub internal_subset: Option<String>,
+
+    /// Where the node was converted from, if it was parsed. See [`Origin`].
+    pub origin: Origin,
 }
 impl OwnedDtdNode {
     /// Create a new DTD node.
@@ -493,6 +855,8 @@ impl OwnedDtdNode {
             name: name.into(),
             external_id,
             entities: Vec::new(),
+            internal_subset: None,
+            origin: Origin::default(),
         }
     }
 
@@ -502,5 +866,7 @@ impl OwnedDtdNode {
             self.name.as_str(),
             self.external_id.as_ref().map(|e| e.borrowed()),
         )
+        .with_entities(self.entities.iter().map(OwnedDtdEntity::borrowed).collect())
+        .with_internal_subset(self.internal_subset.as_deref().map(StrSpan::from))
     }
 }
@@ -0,0 +1,227 @@
+use super::{Node, TagNode};
+use crate::Document;
+use std::collections::VecDeque;
+
+/// Depth-first iterator over nodes, in document order, with the depth of each node.
+///
+/// Returned by [`TagNode::descendants`] and [`Document::iter`](crate::Document::iter).
+/// The children of a tag are one level deeper than the tag; the root element is at depth 0.
+#[derive(Debug, Clone)]
+pub struct Descendants<'a, 'src> {
+    /// Sibling lists still being walked, with the depth of their nodes; the innermost is last
+    stack: Vec<(std::slice::Iter<'a, Node<'src>>, usize)>,
+}
+impl<'a, 'src> Descendants<'a, 'src> {
+    pub(crate) fn new() -> Self {
+        Self { stack: vec![] }
+    }
+
+    /// Queues a list of siblings to be walked before anything queued earlier.
+    pub(crate) fn push(&mut self, nodes: &'a [Node<'src>], depth: usize) {
+        self.stack.push((nodes.iter(), depth));
+    }
+}
+impl<'a, 'src> Iterator for Descendants<'a, 'src> {
+    type Item = (usize, &'a Node<'src>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (siblings, depth) = self.stack.last_mut()?;
+            let depth = *depth;
+            let Some(node) = siblings.next() else {
+                self.stack.pop();
+                continue;
+            };
+
+            if let Node::Child(tag) = node {
+                self.stack.push((tag.children().iter(), depth + 1));
+            }
+            return Some((depth, node));
+        }
+    }
+}
+
+/// Breadth-first iterator over nodes, level by level, with the depth of each node.
+///
+/// Returned by [`TagNode::breadth_first`] and [`Document::iter_breadth_first`](crate::Document::iter_breadth_first).
+/// Nodes of the same depth are in document order.
+#[derive(Debug, Clone)]
+pub struct BreadthFirst<'a, 'src> {
+    /// Sibling lists waiting to be visited, with the depth of their nodes
+    queue: VecDeque<(&'a [Node<'src>], usize)>,
+
+    /// The sibling list being visited
+    current: Option<(std::slice::Iter<'a, Node<'src>>, usize)>,
+}
+impl<'a, 'src> BreadthFirst<'a, 'src> {
+    pub(crate) fn new() -> Self {
+        Self {
+            queue: VecDeque::new(),
+            current: None,
+        }
+    }
+
+    /// Queues a list of siblings to be visited after anything queued earlier.
+    pub(crate) fn push(&mut self, nodes: &'a [Node<'src>], depth: usize) {
+        self.queue.push_back((nodes, depth));
+    }
+}
+impl<'a, 'src> Iterator for BreadthFirst<'a, 'src> {
+    type Item = (usize, &'a Node<'src>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((siblings, depth)) = &mut self.current
+                && let Some(node) = siblings.next()
+            {
+                let depth = *depth;
+                if let Node::Child(tag) = node {
+                    self.queue.push_back((tag.children(), depth + 1));
+                }
+                return Some((depth, node));
+            }
+
+            let (nodes, depth) = self.queue.pop_front()?;
+            self.current = Some((nodes.iter(), depth));
+        }
+    }
+}
+
+impl<'src> TagNode<'src> {
+    /// Returns every node below this tag in document order, with its depth below this tag.
+    ///
+    /// Children are at depth 1, their children at depth 2, and so on. The walk does not recurse,
+    /// so it is safe on deeply nested documents.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{Document, node::Node};
+    ///
+    /// let doc = Document::parse_str("<a><b><c /></b><d /></a>").unwrap();
+    /// let names: Vec<_> = doc
+    ///     .root()
+    ///     .descendants()
+    ///     .filter_map(|(depth, node)| match node {
+    ///         Node::Child(tag) => Some(format!("{depth}:{}", tag.name())),
+    ///         _ => None,
+    ///     })
+    ///     .collect();
+    /// assert_eq!(names, ["1:b", "2:c", "1:d"]);
+    /// ```
+    #[must_use]
+    pub fn descendants(&self) -> Descendants<'_, 'src> {
+        let mut iter = Descendants::new();
+        iter.push(self.children(), 1);
+        iter
+    }
+
+    /// Returns every node below this tag level by level, with its depth below this tag.
+    ///
+    /// See [`TagNode::descendants`] for the depths.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{Document, node::Node};
+    ///
+    /// let doc = Document::parse_str("<a><b><c /></b><d /></a>").unwrap();
+    /// let names: Vec<_> = doc
+    ///     .root()
+    ///     .breadth_first()
+    ///     .filter_map(|(depth, node)| match node {
+    ///         Node::Child(tag) => Some(format!("{depth}:{}", tag.name())),
+    ///         _ => None,
+    ///     })
+    ///     .collect();
+    /// assert_eq!(names, ["1:b", "1:d", "2:c"]);
+    /// ```
+    #[must_use]
+    pub fn breadth_first(&self) -> BreadthFirst<'_, 'src> {
+        let mut iter = BreadthFirst::new();
+        iter.push(self.children(), 1);
+        iter
+    }
+}
+
+impl<'src> Document<'src> {
+    /// Returns every node of the document in document order, with its depth.
+    ///
+    /// The root element is at depth 0 but is not a [`Node`], so it is not returned; see [`Document::root`].
+    /// Its children are at depth 1, and the nodes of the prolog and epilog are at depth 0.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::Document;
+    ///
+    /// let doc = Document::parse_str("<!-- a --><r><b>text</b></r><!-- z -->").unwrap();
+    /// let depths: Vec<_> = doc.iter().map(|(depth, _)| depth).collect();
+    /// assert_eq!(depths, [0, 1, 2, 0]);
+    ///
+    /// for (depth, node) in &doc {
+    ///     println!("{}{node}", "  ".repeat(depth));
+    /// }
+    /// ```
+    #[must_use]
+    pub fn iter(&self) -> Descendants<'_, 'src> {
+        let mut iter = Descendants::new();
+        iter.push(self.epilog(), 0);
+        iter.push(self.root().children(), 1);
+        iter.push(self.prolog(), 0);
+        iter
+    }
+
+    /// Returns every node of the document level by level, with its depth.
+    ///
+    /// Depths are the same as for [`Document::iter`]; the prolog comes first, then the epilog,
+    /// then the children of the root and each level below them.
+    #[must_use]
+    pub fn iter_breadth_first(&self) -> BreadthFirst<'_, 'src> {
+        let mut iter = BreadthFirst::new();
+        iter.push(self.prolog(), 0);
+        iter.push(self.epilog(), 0);
+        iter.push(self.root().children(), 1);
+        iter
+    }
+}
+impl<'a, 'src> IntoIterator for &'a Document<'src> {
+    type Item = (usize, &'a Node<'src>);
+    type IntoIter = Descendants<'a, 'src>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document_iterators() {
+        let doc = Document::parse_str("<!-- a --><r><b><c /></b><d>t</d></r><?z?>").unwrap();
+        let describe = |(depth, node): (usize, &Node)| {
+            let name = match node {
+                Node::Child(tag) => tag.name().to_string(),
+                Node::Text(_) => "#text".to_string(),
+                Node::Comment(_) => "#comment".to_string(),
+                Node::ProcessingInstruction(_) => "#pi".to_string(),
+                _ => "#other".to_string(),
+            };
+            format!("{depth}:{name}")
+        };
+
+        let depth_first: Vec<_> = doc.iter().map(describe).collect();
+        assert_eq!(
+            depth_first,
+            ["0:#comment", "1:b", "2:c", "1:d", "2:#text", "0:#pi"]
+        );
+
+        let breadth_first: Vec<_> = doc.iter_breadth_first().map(describe).collect();
+        assert_eq!(
+            breadth_first,
+            ["0:#comment", "0:#pi", "1:b", "1:d", "2:c", "2:#text"]
+        );
+
+        assert_eq!(doc.root().descendants().count(), 4);
+        assert_eq!(Document::parse_str("<r />").unwrap().iter().count(), 0);
+    }
+}
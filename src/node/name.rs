@@ -2,6 +2,7 @@ use crate::{
     StrSpan,
     to_bin::{BinDecodeError, Decoder, Encoder, ToBinHandler},
 };
+use std::{collections::HashSet, sync::Arc};
 
 /// The name of a node, with an optional prefix:  
 /// `prefix:local`
@@ -39,26 +40,58 @@ impl<'src> NodeName<'src> {
     /// Get an owned version of the node name, with no span metadata.
     #[must_use]
     pub fn to_owned(&self) -> OwnedNodeName {
+        self.to_owned_in(&mut NameInterner::new())
+    }
+
+    /// Get an owned version of the node name, sharing its strings with other names in the interner.
+    #[must_use]
+    pub fn to_owned_in(&self, interner: &mut NameInterner) -> OwnedNodeName {
         OwnedNodeName {
-            prefix: self.prefix.as_ref().map(|s| s.text().to_string()),
-            local: self.local.text().to_string(),
+            prefix: self.prefix.map(|s| interner.intern(s.text())),
+            local: interner.intern(self.local.text()),
         }
     }
 }
 impl<'src> ToBinHandler<'src> for NodeName<'src> {
     fn write(&self, encoder: &mut Encoder) -> std::io::Result<()> {
+        // Names repeat throughout a document, so they are deduplicated when a string table is in use
+        if encoder.has_string_table() {
+            u8::from(self.prefix.is_some()).write(encoder)?;
+            if let Some(prefix) = self.prefix {
+                encoder.write_shared_str(prefix.text())?;
+            }
+            return encoder.write_shared_str(self.local.text());
+        }
+
         self.prefix.write(encoder)?;
         self.local.write(encoder)?;
         Ok(())
     }
 
     fn read(decoder: &mut Decoder<'src>) -> Result<Self, BinDecodeError> {
+        if decoder.has_string_table() {
+            let prefix = match u8::read(decoder)? {
+                0 => None,
+                _ => Some(read_shared_span(decoder)?),
+            };
+            let local = read_shared_span(decoder)?;
+            return Ok(NodeName { prefix, local });
+        }
+
         let prefix = Option::<StrSpan>::read(decoder)?;
         let local = StrSpan::read(decoder)?;
 
         Ok(NodeName { prefix, local })
     }
 }
+
+/// Reads a string written through the encoder's string table, as a span.
+fn read_shared_span<'src>(decoder: &mut Decoder<'src>) -> Result<StrSpan<'src>, BinDecodeError> {
+    let start = decoder.cursor();
+    let text = decoder.read_shared_str()?;
+    Ok(StrSpan::new(text, start))
+}
+
 impl std::fmt::Display for NodeName<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         if let Some(prefix) = self.prefix {
@@ -86,17 +119,20 @@ impl PartialEq<str> for NodeName<'_> {
 }
 
 /// Owned version of a node name, with no span metadata. See [`NodeName`].
+///
+/// The parts are reference-counted, so names converted through the same [`NameInterner`]
+/// share a single allocation per distinct string.
 #[derive(Debug, Clone)]
 pub struct OwnedNodeName {
     /// The prefix of the node name.
-    pub prefix: Option<String>,
+    pub prefix: Option<Arc<str>>,
 
     /// The local portion of the node name.
-    pub local: String,
+    pub local: Arc<str>,
 }
 impl OwnedNodeName {
     /// Create a new node name.
-    pub fn new<T: Into<String>>(prefix: Option<T>, local: T) -> Self {
+    pub fn new<T: Into<Arc<str>>>(prefix: Option<T>, local: T) -> Self {
         Self {
             prefix: prefix.map(Into::into),
             local: local.into(),
@@ -106,11 +142,19 @@ impl OwnedNodeName {
     /// Compare the name with a prefix and local name.
     #[must_use]
     pub fn equals(&self, prefix: Option<&str>, local: &str) -> bool {
-        self.prefix.as_deref() == prefix && self.local.as_str() == local
+        self.prefix.as_deref() == prefix && &*self.local == local
+    }
+
+    /// Replaces the parts of the name with the interner's shared copies.
+    pub fn intern(&mut self, interner: &mut NameInterner) {
+        if let Some(prefix) = &mut self.prefix {
+            *prefix = interner.intern(prefix);
+        }
+        self.local = interner.intern(&self.local);
     }
 
     pub(crate) fn borrowed(&self) -> NodeName<'_> {
-        NodeName::new(self.prefix.as_deref(), self.local.as_str())
+        NodeName::new(self.prefix.as_deref(), &*self.local)
     }
 }
 
@@ -135,8 +179,14 @@ impl std::fmt::Display for OwnedNodeName {
 }
 impl PartialEq for OwnedNodeName {
     fn eq(&self, other: &Self) -> bool {
-        self.prefix.as_deref() == other.prefix.as_deref()
-            && self.local.as_str() == other.local.as_str()
+        self.prefix.as_deref() == other.prefix.as_deref() && self.local == other.local
+    }
+}
+impl Eq for OwnedNodeName {}
+impl std::hash::Hash for OwnedNodeName {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.prefix.hash(state);
+        self.local.hash(state);
     }
 }
 impl PartialEq<&str> for OwnedNodeName {
@@ -152,7 +202,7 @@ impl PartialEq<str> for OwnedNodeName {
 impl PartialEq<NodeName<'_>> for OwnedNodeName {
     fn eq(&self, other: &NodeName<'_>) -> bool {
         self.prefix.as_deref() == other.prefix.map(|s| s.text())
-            && self.local.as_str() == other.local.text()
+            && &*self.local == other.local.text()
     }
 }
 
@@ -171,3 +221,55 @@ impl From<String> for OwnedNodeName {
         OwnedNodeName::from(name.as_str())
     }
 }
+
+/// A set of shared strings used to deduplicate the names of owned nodes.
+///
+/// Wide documents repeat the same few element and attribute names many times;
+/// converting them through one interner stores each distinct name once.
+///
+/// # Example
+/// ```rust
+/// use xmltree::{Document, node::NameInterner};
+///
+/// let doc = Document::parse_str("<table><row /><row /><row /></table>").unwrap();
+/// let mut interner = NameInterner::new();
+/// let owned = doc.to_owned_in(&mut interner);
+/// assert_eq!(interner.len(), 2);
+///
+/// let rows = &owned.root.children;
+/// assert_eq!(rows.len(), 3);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct NameInterner {
+    names: HashSet<Arc<str>>,
+}
+impl NameInterner {
+    /// Creates an empty interner.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the shared copy of the string, adding it if it is not yet present.
+    pub fn intern(&mut self, name: &str) -> Arc<str> {
+        if let Some(shared) = self.names.get(name) {
+            return Arc::clone(shared);
+        }
+
+        let shared: Arc<str> = Arc::from(name);
+        self.names.insert(Arc::clone(&shared));
+        shared
+    }
+
+    /// Returns the number of distinct strings in the interner.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Returns true if the interner holds no strings.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
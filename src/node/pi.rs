@@ -1,7 +1,8 @@
 use crate::{
-    StrSpan,
+    Origin, StrSpan,
     to_bin::{BinDecodeError, Decoder, Encoder, ToBinHandler},
 };
+use std::fmt::Write;
 
 /// A processing instruction node:  
 /// `<?target content?>`
@@ -21,6 +22,14 @@ impl<'src> ProcessingInstructionNode<'src> {
         }
     }
 
+    /// Create a new processing instruction node that is not part of the original source.
+    ///
+    /// See [`TagNode::detached`](super::TagNode::detached) for the lifetime requirements of the strings.
+    #[must_use]
+    pub fn detached(target: &'src str, content: Option<&'src str>) -> Self {
+        Self::new("", target, content)
+    }
+
     /// Returns the span of the node in the original source.
     #[must_use]
     pub fn span(&self) -> &StrSpan<'src> {
@@ -39,12 +48,58 @@ impl<'src> ProcessingInstructionNode<'src> {
         self.content.as_ref()
     }
 
-    /// Returns an owned version of the processing instruction node, with no span metadata.
+    /// Parses the content as a list of pseudo-attributes, such as `type="text/xsl" href="style.xsl"`.
+    ///
+    /// Returns the name and value of each pseudo-attribute, in order, with the quotes removed from the values.
+    /// Returns an empty list if there is no content, or `None` if the content is not a list of pseudo-attributes.
+    /// As with attributes, entities in the values are not decoded.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{Document, node::Node};
+    ///
+    /// let doc = Document::parse_str(r#"<?xml-stylesheet type="text/xsl" href='style.xsl'?><root />"#).unwrap();
+    /// let Node::ProcessingInstruction(pi) = &doc.prolog()[0] else { unreachable!() };
+    ///
+    /// let pairs = pi.pseudo_attributes().unwrap();
+    /// let pairs: Vec<_> = pairs.iter().map(|(name, value)| (name.text(), value.text())).collect();
+    /// assert_eq!(pairs, [("type", "text/xsl"), ("href", "style.xsl")]);
+    /// assert_eq!(pi.pseudo_attribute("href").unwrap().text(), "style.xsl");
+    /// ```
+    #[must_use]
+    pub fn pseudo_attributes(&self) -> Option<Vec<(StrSpan<'src>, StrSpan<'src>)>> {
+        let Some(content) = &self.content else {
+            return Some(vec![]);
+        };
+
+        let pairs = parse_pseudo_attributes(content.text())?;
+        Some(
+            pairs
+                .into_iter()
+                .map(|(name, value)| (content.slice(name), content.slice(value)))
+                .collect(),
+        )
+    }
+
+    /// Returns the value of the first pseudo-attribute with the given name.
+    ///
+    /// Returns `None` if there is no such pseudo-attribute, or the content is not a list of them.
+    /// See [`ProcessingInstructionNode::pseudo_attributes`].
+    #[must_use]
+    pub fn pseudo_attribute(&self, name: &str) -> Option<StrSpan<'src>> {
+        self.pseudo_attributes()?
+            .into_iter()
+            .find(|(n, _)| n.text() == name)
+            .map(|(_, value)| value)
+    }
+
+    /// Returns an owned version of the processing instruction node; only its origin is kept from the span metadata.
     #[must_use]
     pub fn to_owned(&self) -> OwnedProcessingInstructionNode {
         OwnedProcessingInstructionNode {
             target: self.target.text().to_string(),
             content: self.content.as_ref().map(|s| s.text().to_string()),
+            origin: Origin::from(&self.span),
         }
     }
 }
@@ -69,20 +124,114 @@ impl<'src> ToBinHandler<'src> for ProcessingInstructionNode<'src> {
     }
 }
 
+/// Parses `name="value"` pairs separated by whitespace, returning the byte ranges of each name and unquoted value.
+fn parse_pseudo_attributes(
+    content: &str,
+) -> Option<Vec<(std::ops::Range<usize>, std::ops::Range<usize>)>> {
+    let bytes = content.as_bytes();
+    let skip_whitespace = |mut i: usize| {
+        while bytes.get(i).is_some_and(u8::is_ascii_whitespace) {
+            i += 1;
+        }
+        i
+    };
+
+    let mut pairs = vec![];
+    let mut i = skip_whitespace(0);
+    while i < bytes.len() {
+        let name_start = i;
+        while bytes
+            .get(i)
+            .is_some_and(|b| *b != b'=' && !b.is_ascii_whitespace())
+        {
+            i += 1;
+        }
+        let name = name_start..i;
+
+        i = skip_whitespace(i);
+        if name.is_empty() || bytes.get(i) != Some(&b'=') {
+            return None;
+        }
+        i = skip_whitespace(i + 1);
+
+        let quote = *bytes.get(i).filter(|b| matches!(b, b'"' | b'\''))?;
+        let value_start = i + 1;
+        let value_end = value_start + content[value_start..].find(char::from(quote))?;
+        pairs.push((name, value_start..value_end));
+
+        // Pairs must be separated by whitespace
+        i = value_end + 1;
+        if i < bytes.len() && !bytes[i].is_ascii_whitespace() {
+            return None;
+        }
+        i = skip_whitespace(i);
+    }
+
+    Some(pairs)
+}
+
 /// An owned version of a processing instruction node, with no span metadata. See [`ProcessingInstructionNode`].
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct OwnedProcessingInstructionNode {
     /// The target of the processing instruction.
     pub target: String,
 
     /// The content of the processing instruction.
     pub content: Option<String>,
+
+    /// Where the node was converted from, if it was parsed. See [`Origin`].
+    pub origin: Origin,
 }
 impl OwnedProcessingInstructionNode {
     /// Create a new processing instruction node.
     #[must_use]
     pub fn new(target: String, content: Option<String>) -> Self {
-        Self { target, content }
+        Self {
+            target,
+            content,
+            origin: Origin::default(),
+        }
+    }
+
+    /// Appends a pseudo-attribute to the content, as `name="value"`.
+    ///
+    /// Quotes and ampersands in the value are written as entities.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::node::OwnedProcessingInstructionNode;
+    ///
+    /// let pi = OwnedProcessingInstructionNode::new("xml-stylesheet".to_string(), None)
+    ///     .with_pseudo_attribute("type", "text/xsl")
+    ///     .with_pseudo_attribute("href", "style.xsl");
+    /// assert_eq!(pi.content.as_deref(), Some(r#"type="text/xsl" href="style.xsl""#));
+    /// assert_eq!(pi.pseudo_attributes().unwrap(), [("type", "text/xsl"), ("href", "style.xsl")]);
+    /// ```
+    #[must_use]
+    pub fn with_pseudo_attribute(mut self, name: &str, value: &str) -> Self {
+        let value = value.replace('&', "&amp;").replace('"', "&quot;");
+        let content = self.content.get_or_insert_default();
+        if !content.is_empty() {
+            content.push(' ');
+        }
+        let _ = write!(content, "{name}=\"{value}\"");
+        self
+    }
+
+    /// Parses the content as a list of pseudo-attributes. See [`ProcessingInstructionNode::pseudo_attributes`].
+    #[must_use]
+    pub fn pseudo_attributes(&self) -> Option<Vec<(&str, &str)>> {
+        let Some(content) = &self.content else {
+            return Some(vec![]);
+        };
+
+        let pairs = parse_pseudo_attributes(content)?;
+        Some(
+            pairs
+                .into_iter()
+                .map(|(name, value)| (&content[name], &content[value]))
+                .collect(),
+        )
     }
 
     pub(crate) fn borrowed(&self) -> ProcessingInstructionNode<'_> {
@@ -0,0 +1,336 @@
+//! Reading of DTD internal subsets, for the declarations and constructs that the tokenizer skips or rejects.
+use super::{AttributeDefault, DtdEntity, EntityDefinition, ExternalId};
+use crate::StrSpan;
+use xmlparser::XmlCharExt;
+
+/// An item in the internal subset of a DTD. See [`SubsetCursor::next_item`].
+pub(crate) enum SubsetItem<'src> {
+    /// A markup declaration, such as `<!ENTITY`, and its start; the cursor is after the keyword.
+    Declaration(&'src str, usize),
+
+    /// A parameter entity reference, such as `%name;`.
+    Reference,
+
+    /// A conditional section, such as `<![INCLUDE[ ... ]]>`; the cursor is inside it if it is included.
+    Conditional(StrSpan<'src>),
+
+    /// The `]>` that ends the internal subset; the cursor is after it.
+    End,
+}
+
+/// A cursor over the text of a DTD.
+pub(crate) struct SubsetCursor<'src> {
+    span: StrSpan<'src>,
+    pos: usize,
+
+    /// The number of `INCLUDE` sections the cursor is inside.
+    include_depth: usize,
+}
+impl<'src> SubsetCursor<'src> {
+    /// Creates a cursor at the start of the internal subset of the DTD starting at `span`, after the opening `[`.
+    ///
+    /// The cursor is at the end of the span if the DTD has no internal subset.
+    pub(crate) fn new(span: StrSpan<'src>) -> Self {
+        let mut cursor = Self {
+            span,
+            pos: 0,
+            include_depth: 0,
+        };
+
+        // The external ID can contain any character, so its literals are skipped
+        loop {
+            match cursor.rest().chars().next() {
+                None => break,
+                Some('[') => {
+                    cursor.pos += 1;
+                    break;
+                }
+                Some('"' | '\'') => {
+                    if cursor.literal().is_none() {
+                        cursor.pos = span.len();
+                    }
+                }
+                Some(c) => cursor.pos += c.len_utf8(),
+            }
+        }
+        cursor
+    }
+
+    /// Creates a cursor at the start of the text of an internal subset, without its brackets.
+    pub(crate) fn inside(span: StrSpan<'src>) -> Self {
+        Self {
+            span,
+            pos: 0,
+            include_depth: 0,
+        }
+    }
+
+    /// Returns the offset of the cursor in the source.
+    pub(crate) fn offset(&self) -> usize {
+        self.span.start() + self.pos
+    }
+
+    /// Returns the span from `start` to the cursor.
+    pub(crate) fn span_from(&self, start: usize) -> StrSpan<'src> {
+        self.span.slice(start..self.pos)
+    }
+
+    fn rest(&self) -> &'src str {
+        &self.span.text()[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Moves past the next occurrence of `end`, or to the end of the text.
+    fn skip_past(&mut self, end: &str) {
+        self.pos = match self.rest().find(end) {
+            Some(i) => self.pos + i + end.len(),
+            None => self.span.len(),
+        };
+    }
+
+    /// Reads a name or keyword, up to whitespace or a delimiter.
+    fn word(&mut self) -> StrSpan<'src> {
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| c.is_whitespace() || matches!(c, '>' | '(' | '[' | '"' | '\''))
+            .unwrap_or(rest.len());
+        let word = self.span.slice(self.pos..self.pos + len);
+        self.pos += len;
+        word
+    }
+
+    /// Reads a quoted literal, returning the text between the quotes.
+    fn literal(&mut self) -> Option<StrSpan<'src>> {
+        let quote = self
+            .rest()
+            .chars()
+            .next()
+            .filter(|c| matches!(c, '"' | '\''))?;
+        let len = self.rest()[1..].find(quote)?;
+        let value = self.span.slice(self.pos + 1..self.pos + 1 + len);
+        self.pos += len + 2;
+        Some(value)
+    }
+
+    /// Returns the position just after the `]]>` that closes the conditional section at the cursor.
+    fn section_end(&self) -> Option<usize> {
+        let bytes = self.rest().as_bytes();
+        let mut depth = 0;
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i..].starts_with(b"<![") {
+                depth += 1;
+                i += 3;
+            } else if bytes[i..].starts_with(b"]]>") {
+                depth -= 1;
+                i += 3;
+                if depth == 0 {
+                    return Some(self.pos + i);
+                }
+            } else {
+                i += 1;
+            }
+        }
+        None
+    }
+
+    /// Moves to the next item in the internal subset, skipping whitespace, comments, and processing instructions.
+    ///
+    /// Included conditional sections are entered, and ignored ones are skipped.
+    /// Returns `None` if the text ends before the internal subset does, or if the cursor is at a character
+    /// that cannot start an item; the cursor is left at that character.
+    pub(crate) fn next_item(&mut self) -> Option<SubsetItem<'src>> {
+        loop {
+            self.skip_whitespace();
+            let rest = self.rest();
+            if rest.is_empty() {
+                return None;
+            } else if rest.starts_with("<!--") {
+                self.skip_past("-->");
+            } else if rest.starts_with("<?") {
+                self.skip_past("?>");
+            } else if rest.starts_with("<![") {
+                let start = self.pos;
+                let end = self.section_end()?;
+                self.pos += 3;
+                self.skip_whitespace();
+                if self.word().text() == "INCLUDE" {
+                    self.skip_whitespace();
+                    if self.rest().starts_with('[') {
+                        self.pos += 1;
+                        self.include_depth += 1;
+                    } else {
+                        self.pos = end;
+                    }
+                } else {
+                    self.pos = end;
+                }
+                return Some(SubsetItem::Conditional(self.span.slice(start..end)));
+            } else if self.include_depth > 0 && rest.starts_with("]]>") {
+                self.pos += 3;
+                self.include_depth -= 1;
+            } else if rest.starts_with(']') {
+                self.pos += 1;
+                self.skip_whitespace();
+                if !self.rest().starts_with('>') {
+                    return None;
+                }
+                self.pos += 1;
+                return Some(SubsetItem::End);
+            } else if rest.starts_with("<!") {
+                let start = self.pos;
+                self.pos += 2;
+                let keyword = self.word();
+                return Some(SubsetItem::Declaration(keyword.text(), start));
+            } else if rest.starts_with('%') {
+                let end = rest.find(';')?;
+                if !is_name(&rest[1..end]) {
+                    return None;
+                }
+                self.pos += end + 1;
+                return Some(SubsetItem::Reference);
+            } else {
+                return None;
+            }
+        }
+    }
+
+    /// Moves past the closing `>` of the declaration at the cursor.
+    pub(crate) fn skip_declaration(&mut self) {
+        loop {
+            match self.rest().chars().next() {
+                None => return,
+                Some('>') => {
+                    self.pos += 1;
+                    return;
+                }
+                Some('"' | '\'') => {
+                    if self.literal().is_none() {
+                        self.pos = self.span.len();
+                    }
+                }
+                Some(c) => self.pos += c.len_utf8(),
+            }
+        }
+    }
+
+    /// Reads an `<!ENTITY` declaration that starts at `start`, from after the keyword up to the closing `>`.
+    ///
+    /// Returns the entity, and true if it is a parameter entity, or `None` if the declaration cannot be read.
+    pub(crate) fn entity(&mut self, start: usize) -> Option<(DtdEntity<'src>, bool)> {
+        self.skip_whitespace();
+        let parameter = self.rest().starts_with('%');
+        if parameter {
+            self.pos += 1;
+            self.skip_whitespace();
+        }
+
+        let name = self.word();
+        if !is_name(name.text()) {
+            return None;
+        }
+
+        self.skip_whitespace();
+        let definition = if let Some(value) = self.literal() {
+            EntityDefinition::EntityValue(value)
+        } else {
+            let external_id = match self.word().text() {
+                "SYSTEM" => {
+                    self.skip_whitespace();
+                    ExternalId::System(self.literal()?)
+                }
+                "PUBLIC" => {
+                    self.skip_whitespace();
+                    let public = self.literal()?;
+                    self.skip_whitespace();
+                    ExternalId::Public(public, self.literal()?)
+                }
+                _ => return None,
+            };
+
+            // Unparsed entities name their notation, which is not kept
+            self.skip_whitespace();
+            if !parameter && self.rest().starts_with("NDATA") {
+                self.word();
+                self.skip_whitespace();
+                self.word();
+            }
+            EntityDefinition::ExternalId(external_id)
+        };
+
+        self.skip_whitespace();
+        if !self.rest().starts_with('>') {
+            return None;
+        }
+        self.pos += 1;
+
+        let entity = DtdEntity {
+            span: self.span.slice(start..self.pos),
+            name,
+            definition,
+        };
+        Some((entity, parameter))
+    }
+
+    /// Reads the attribute definitions of an `<!ATTLIST`, from after the keyword up to the closing `>`.
+    ///
+    /// Definitions without a default value are skipped. Returns `None` if the declaration cannot be read,
+    /// such as when it uses parameter entities.
+    pub(crate) fn attlist(&mut self, defaults: &mut Vec<AttributeDefault<'src>>) -> Option<()> {
+        self.skip_whitespace();
+        let element = self.word();
+        let mut found = vec![];
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with('>') {
+                self.pos += 1;
+                defaults.extend(found);
+                return Some(());
+            }
+
+            let start = self.pos;
+            let name = self.word();
+            if name.is_empty() || name.text().starts_with('%') {
+                return None;
+            }
+
+            // The attribute type, which may be an enumeration
+            self.skip_whitespace();
+            let kind = self.word();
+            self.skip_whitespace();
+            if kind.is_empty() || kind.text() == "NOTATION" {
+                if !self.rest().starts_with('(') {
+                    return None;
+                }
+                self.skip_past(")");
+                self.skip_whitespace();
+            }
+
+            let fixed = match self.word().text() {
+                "#REQUIRED" | "#IMPLIED" => continue,
+                "#FIXED" => true,
+                "" => false,
+                _ => return None,
+            };
+            self.skip_whitespace();
+            let value = self.literal()?;
+            found.push(AttributeDefault {
+                span: self.span.slice(start..self.pos),
+                element,
+                name,
+                value,
+                fixed,
+            });
+        }
+    }
+}
+
+/// Returns true if the text is an XML [Name](https://www.w3.org/TR/xml/#NT-Name).
+fn is_name(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars.next().is_some_and(|c| c.is_xml_name_start()) && chars.all(|c| c.is_xml_name())
+}
@@ -1,18 +1,31 @@
-use super::{Node, NodeName, OwnedNode, OwnedNodeName};
+use super::{
+    Attributes, NameInterner, Node, NodeName, OwnedCommentNode, OwnedNode, OwnedNodeName,
+    OwnedTextNode,
+};
 use crate::{
-    StrSpan,
+    Origin, OwnedDocument, StrSpan, XmlWriteOptions,
+    entities::resolve_builtin_reference,
     to_bin::{BinDecodeError, Decoder, Encoder, ToBinHandler},
 };
+use std::borrow::Cow;
+
+/// Header of the binary format for a single subtree.
+const HEADER_TAG: &[u8] = b"XMLT";
 
 /// A node in the document tree, with a name, attributes, and children:
 /// `<name attr="value">...</name>`
 ///
 /// Duplicate attributes are allowed (searches use the last attribute with the same name).
-#[derive(Debug, Clone, PartialEq)]
+///
+/// Formatting with `{:#?}` prints a compact tree of the node and its descendants, one line per node.
+/// `{}` renders the node as XML.
+#[derive(Clone, PartialEq)]
 pub struct TagNode<'src> {
     span: StrSpan<'src>,
     name: NodeName<'src>,
-    attributes: Vec<NodeAttribute<'src>>,
+    attributes: Attributes<'src>,
+
+    /// Not stored inline like attributes; a child tag is held by value, so an inline slot would make the type infinitely large.
     children: Vec<Node<'src>>,
 }
 impl<'src> TagNode<'src> {
@@ -20,7 +33,7 @@ impl<'src> TagNode<'src> {
         Self {
             span: StrSpan::default(),
             name: NodeName::new(prefix, local),
-            attributes: vec![],
+            attributes: Attributes::new(),
             children: vec![],
         }
     }
@@ -30,14 +43,50 @@ impl<'src> TagNode<'src> {
         self
     }
 
-    pub(crate) fn push_child(&mut self, child: Node<'src>) {
+    /// Create a new tag node that is not part of the original source.
+    ///
+    /// Strings must outlive the document the node is added to; they can be slices of the source,
+    /// or be allocated in an arena created before the document (such as a `bumpalo::Bump`).
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{Document, node::{Node, NodeAttribute, TagNode}};
+    ///
+    /// let mut doc = Document::parse_str("<root />").unwrap();
+    ///
+    /// let mut child = TagNode::detached(Some("ns"), "child");
+    /// child.push_attribute(NodeAttribute::detached(None, "id", "1"));
+    /// doc.root_mut().push_child(Node::Child(child));
+    ///
+    /// assert_eq!(doc.root().children().len(), 1);
+    /// ```
+    #[must_use]
+    pub fn detached(prefix: Option<&'src str>, local: &'src str) -> Self {
+        Self::new(prefix, local)
+    }
+
+    /// Add a child to the end of this node's children.
+    pub fn push_child(&mut self, child: Node<'src>) {
         self.children.push(child);
     }
 
-    pub(crate) fn push_attribute(&mut self, attribute: NodeAttribute<'src>) {
+    /// Add an attribute to the end of this node's attributes.
+    pub fn push_attribute(&mut self, attribute: NodeAttribute<'src>) {
         self.attributes.push(attribute);
     }
 
+    /// Get a mutable reference to the children of the node.
+    #[must_use]
+    pub fn children_mut(&mut self) -> &mut Vec<Node<'src>> {
+        &mut self.children
+    }
+
+    /// Get a mutable reference to the attributes of the node.
+    #[must_use]
+    pub fn attributes_mut(&mut self) -> &mut Attributes<'src> {
+        &mut self.attributes
+    }
+
     pub(crate) fn extend_span(&mut self, span: &StrSpan<'src>, src: &'src str) {
         self.span.extend(span, src);
     }
@@ -53,6 +102,111 @@ impl<'src> TagNode<'src> {
             .find(|a| a.name.equals(prefix, name))
     }
 
+    /// Get the attributes with the given local name, whatever their prefix, in the order they were written.
+    ///
+    /// Useful when documents are inconsistent about prefixes; more than one attribute is returned if the name is ambiguous.
+    /// Namespace declarations such as `xmlns:href` are not attributes for this purpose, and are skipped.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::Document;
+    ///
+    /// let doc = Document::parse_str(r#"<a xmlns:xlink="urn:link" xlink:href="one" href="two" />"#).unwrap();
+    /// let values: Vec<_> = doc.root().get_attribute_local("href").iter().map(|a| a.value().text()).collect();
+    /// assert_eq!(values, ["one", "two"]);
+    /// assert!(doc.root().get_attribute_local("xlink").is_empty());
+    /// ```
+    #[must_use]
+    pub fn get_attribute_local(&self, local: &str) -> Vec<&NodeAttribute<'src>> {
+        self.attributes
+            .iter()
+            .filter(|a| {
+                a.name.local().text() == local
+                    && a.name.prefix().is_none_or(|p| p.text() != "xmlns")
+            })
+            .collect()
+    }
+
+    /// Returns the namespace URI bound to `prefix` by the declarations on this tag, where `""` is the default namespace.
+    ///
+    /// An empty URI, as in `xmlns=""`, means the prefix is unbound.
+    pub(crate) fn declared_namespace(&self, prefix: &str) -> Option<&'src str> {
+        if prefix == "xml" {
+            return Some("http://www.w3.org/XML/1998/namespace");
+        }
+
+        let declaration = match prefix {
+            "" => self.get_attribute(None, "xmlns"),
+            _ => self.get_attribute(Some("xmlns"), prefix),
+        };
+        declaration.map(|attr| attr.value().text())
+    }
+
+    /// Get an attribute by namespace URI and local name, instead of by prefix.
+    ///
+    /// Prefixes are resolved using the `xmlns` declarations on this tag; declarations made by its ancestors are not visible.
+    /// Use [`Document::get_attribute_ns`](crate::Document::get_attribute_ns) to resolve them from every declaration in scope.
+    /// Unprefixed attributes are in no namespace, and are found with an empty `uri`.
+    ///
+    /// Like [`TagNode::get_attribute`], the last matching attribute is returned.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::Document;
+    ///
+    /// let doc = Document::parse_str(r#"<a xmlns:x="urn:link" x:href="one" href="two" />"#).unwrap();
+    /// let root = doc.root();
+    /// assert_eq!(root.get_attribute_ns("urn:link", "href").unwrap().value().text(), "one");
+    /// assert_eq!(root.get_attribute_ns("", "href").unwrap().value().text(), "two");
+    /// ```
+    #[must_use]
+    pub fn get_attribute_ns(&self, uri: &str, local: &str) -> Option<&NodeAttribute<'src>> {
+        self.attributes.iter().rev().find(|a| {
+            let name = a.name();
+            name.local().text() == local
+                && match name.prefix() {
+                    Some(prefix) => self.declared_namespace(prefix.text()) == Some(uri),
+                    None => uri.is_empty(),
+                }
+        })
+    }
+
+    /// Returns the child tags with the given namespace URI and local name, in order.
+    ///
+    /// A child's prefix is resolved using the `xmlns` declarations on the child, then on this tag; declarations made by
+    /// ancestors of this tag are not visible, so children with a prefix declared elsewhere are never found;
+    /// see [`Document::children_ns`](crate::Document::children_ns) for that. Unprefixed children without a default namespace are found with an empty `uri`.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::Document;
+    ///
+    /// let doc = Document::parse_str(r#"<feed xmlns="urn:atom" xmlns:m="urn:meta">
+    ///     <entry /><m:entry /><entry xmlns="" /><e:entry xmlns:e="urn:atom" />
+    /// </feed>"#).unwrap();
+    ///
+    /// assert_eq!(doc.root().children_ns("urn:atom", "entry").count(), 2);
+    /// assert_eq!(doc.root().children_ns("urn:meta", "entry").count(), 1);
+    /// assert_eq!(doc.root().children_ns("", "entry").count(), 1);
+    /// ```
+    pub fn children_ns<'a>(
+        &'a self,
+        uri: &'a str,
+        local: &'a str,
+    ) -> impl Iterator<Item = &'a TagNode<'src>> + 'a {
+        self.children.iter().filter_map(move |child| match child {
+            Node::Child(child) if child.name().local().text() == local => {
+                let prefix = child.name().prefix().map_or("", |p| p.text());
+                let namespace = child
+                    .declared_namespace(prefix)
+                    .or_else(|| self.declared_namespace(prefix))
+                    .or(prefix.is_empty().then_some(""));
+                (namespace == Some(uri)).then_some(child)
+            }
+            _ => None,
+        })
+    }
+
     /// Get the span of the node in the original source.
     #[must_use]
     pub fn span(&self) -> &StrSpan<'src> {
@@ -77,34 +231,236 @@ impl<'src> TagNode<'src> {
         &self.children
     }
 
-    /// Get an owned version of the tag node, with no span metadata.
+    /// Get the first child tag, skipping any text, comments or other nodes before it.
+    #[must_use]
+    pub fn first_child_tag(&self) -> Option<&TagNode<'src>> {
+        self.children.iter().find_map(|child| match child {
+            Node::Child(child) => Some(child),
+            _ => None,
+        })
+    }
+
+    /// Get the only child tag with the given name.
+    ///
+    /// # Errors
+    /// Returns an [`Ambiguity`] if there is no child with the name, or more than one.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{Document, node::Ambiguity};
+    ///
+    /// let doc = Document::parse_str("<book><title>Dune</title><author /><author /></book>").unwrap();
+    /// let root = doc.root();
+    /// assert_eq!(root.only_child_named(None, "title").unwrap().name(), "title");
+    /// assert_eq!(root.only_child_named(None, "author").unwrap_err(), Ambiguity::Multiple(2));
+    /// assert_eq!(root.only_child_named(None, "isbn").unwrap_err(), Ambiguity::Missing);
+    /// ```
+    pub fn only_child_named(
+        &self,
+        prefix: Option<&str>,
+        local: &str,
+    ) -> Result<&TagNode<'src>, Ambiguity> {
+        let mut found = self.children.iter().filter_map(|child| match child {
+            Node::Child(child) if child.name.equals(prefix, local) => Some(child),
+            _ => None,
+        });
+
+        match (found.next(), found.count()) {
+            (None, _) => Err(Ambiguity::Missing),
+            (Some(child), 0) => Ok(child),
+            (Some(_), rest) => Err(Ambiguity::Multiple(rest + 1)),
+        }
+    }
+
+    /// Get the text of the first child tag with the given name, from its first text or CDATA node.
+    ///
+    /// The text is raw, as written in the source; see [`StrSpan::decoded`].
+    /// Returns `None` if there is no such child, or if it has no text.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::Document;
+    ///
+    /// let doc = Document::parse_str("<book><title> Dune </title><notes><![CDATA[<none>]]></notes><blank /></book>").unwrap();
+    /// assert_eq!(doc.root().child_text(None, "title"), Some("Dune"));
+    /// assert_eq!(doc.root().child_text(None, "notes"), Some("<none>"));
+    /// assert_eq!(doc.root().child_text(None, "blank"), None);
+    /// ```
+    #[must_use]
+    pub fn child_text(&self, prefix: Option<&str>, local: &str) -> Option<&'src str> {
+        let child = self.children.iter().find_map(|child| match child {
+            Node::Child(child) if child.name.equals(prefix, local) => Some(child),
+            _ => None,
+        })?;
+
+        child.children.iter().find_map(|node| match node {
+            Node::Text(text) => Some(text.text().text()),
+            Node::Cdata(cdata) => Some(cdata.content().text()),
+            _ => None,
+        })
+    }
+
+    /// Get an owned version of the tag node; only origins are kept from the span metadata.
+    ///
+    /// Repeated names within the subtree share one allocation. See [`NameInterner`].
     #[must_use]
     pub fn to_owned(&self) -> OwnedTagNode {
+        self.to_owned_in(&mut NameInterner::new())
+    }
+
+    /// Get an owned version of the tag node, sharing names through the given interner.
+    #[must_use]
+    pub fn to_owned_in(&self, interner: &mut NameInterner) -> OwnedTagNode {
         OwnedTagNode {
-            name: self.name.to_owned(),
+            name: self.name.to_owned_in(interner),
             attributes: self
                 .attributes
                 .iter()
-                .map(NodeAttribute::to_owned)
+                .map(|attr| attr.to_owned_in(interner))
                 .collect(),
-            children: self.children.iter().map(Node::to_owned).collect(),
+            children: OwnedNode::from_nodes_in(&self.children, interner),
+            origin: Origin::from(&self.span),
+        }
+    }
+
+    /// Clones this node and its descendants into a new document rooted at this node.
+    ///
+    /// Namespace declarations made by ancestors of the node are not included;
+    /// use [`crate::Document::extract_owned`] to carry them over.
+    #[must_use]
+    pub fn extract_owned(&self) -> OwnedDocument {
+        OwnedDocument::new(self.to_owned())
+    }
+
+    /// Create a formatted XML string from this node and its descendants.
+    ///
+    /// `tab_char` is used to indent the XML. If `None`, a tab is used.
+    ///
+    /// # Errors
+    /// Can fail if a string in the node cannot be entity encoded.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{Document, node::Node};
+    ///
+    /// let doc = Document::parse_str("<root><item id=\"1\">text</item></root>").unwrap();
+    /// let Node::Child(item) = &doc.root().children()[0] else { unreachable!() };
+    ///
+    /// let xml = item.to_xml(Some("  ")).unwrap();
+    /// assert_eq!(xml, "<item id=\"1\">\n  text\n</item>\n");
+    /// ```
+    pub fn to_xml(&self, tab_char: Option<&str>) -> std::io::Result<String> {
+        self.to_xml_with_options(&XmlWriteOptions::from_tab_char(tab_char))
+    }
+
+    /// Create a formatted XML string from this node and its descendants, using the given options.
+    ///
+    /// See [`XmlWriteOptions`] for the available options.
+    ///
+    /// # Errors
+    /// Can fail if a string in the node cannot be entity encoded,
+    /// or contains characters rejected by [`XmlWriteOptions::control_chars`].
+    pub fn to_xml_with_options(&self, options: &XmlWriteOptions) -> std::io::Result<String> {
+        let mut buffer = vec![];
+        crate::to_xml::write_tag(
+            &mut buffer,
+            self,
+            options,
+            &crate::to_xml::Ancestors::default(),
+        )?;
+
+        let buffer = String::from_utf8(buffer).map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Failed to convert to UTF-8: {e}"),
+            )
+        })?;
+        Ok(buffer)
+    }
+
+    /// Write this node and its descendants as a flat binary format.
+    ///
+    /// Strings are stored inline, so the result can be read back without the original source.
+    ///
+    /// # Errors
+    /// Returns errors if the encoding fails
+    pub fn to_bin(&self) -> std::io::Result<Vec<u8>> {
+        let mut encoder = Encoder::new();
+        encoder.write_all(HEADER_TAG)?;
+        self.write(&mut encoder)?;
+        Ok(encoder.into_inner())
+    }
+
+    /// Read a node from the flat binary format written by [`TagNode::to_bin`].
+    ///
+    /// # Errors
+    /// Returns errors if the decoding fails
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{Document, node::{Node, TagNode}};
+    ///
+    /// let doc = Document::parse_str("<root><item id=\"1\">text</item></root>").unwrap();
+    /// let Node::Child(item) = &doc.root().children()[0] else { unreachable!() };
+    ///
+    /// let bin = item.to_bin().unwrap();
+    /// let item2 = TagNode::from_bin(&bin).unwrap();
+    /// assert_eq!(item2.to_owned(), item.to_owned());
+    /// ```
+    pub fn from_bin(data: &'src [u8]) -> Result<Self, BinDecodeError> {
+        let mut decoder = Decoder::new(data);
+        if decoder.read_all(HEADER_TAG.len())? != HEADER_TAG {
+            return Err(BinDecodeError::InvalidHeader);
+        }
+        Self::read(&mut decoder)
+    }
+}
+impl<'src> TagNode<'src> {
+    /// Reads the span, name and attributes of an encoded tag, leaving its children unread.
+    pub(crate) fn read_shallow(decoder: &mut Decoder<'src>) -> Result<Self, BinDecodeError> {
+        let span = StrSpan::read(decoder)?;
+        let name = NodeName::read(decoder)?;
+        let attributes = Attributes::read(decoder)?;
+
+        Ok(TagNode {
+            span,
+            name,
+            attributes,
+            children: vec![],
+        })
+    }
+
+    /// Moves past an encoded tag and its descendants, without collecting them.
+    pub(crate) fn skip(decoder: &mut Decoder<'src>) -> Result<(), BinDecodeError> {
+        decoder.enter_tag()?;
+        Self::read_shallow(decoder)?;
+        let children = usize::read(decoder)?;
+        for _ in 0..children {
+            decoder.skip_node()?;
         }
+        decoder.exit_tag();
+        Ok(())
     }
 }
+
 impl<'src> ToBinHandler<'src> for TagNode<'src> {
     fn write(&self, encoder: &mut Encoder) -> std::io::Result<()> {
+        encoder.enter_tag()?;
         self.span.write(encoder)?;
         self.name.write(encoder)?;
         self.attributes.write(encoder)?;
         self.children.write(encoder)?;
+        encoder.exit_tag();
         Ok(())
     }
 
     fn read(decoder: &mut Decoder<'src>) -> Result<Self, BinDecodeError> {
+        decoder.enter_tag()?;
         let span = StrSpan::read(decoder)?;
         let name = NodeName::read(decoder)?;
-        let attributes = Vec::<NodeAttribute>::read(decoder)?;
+        let attributes = Attributes::read(decoder)?;
         let children = Vec::<Node>::read(decoder)?;
+        decoder.exit_tag();
 
         Ok(TagNode {
             span,
@@ -115,8 +471,63 @@ impl<'src> ToBinHandler<'src> for TagNode<'src> {
     }
 }
 
+impl std::fmt::Debug for TagNode<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if f.alternate() {
+            return write_tree(f, self);
+        }
+
+        f.debug_struct("TagNode")
+            .field("span", &self.span)
+            .field("name", &self.name)
+            .field("attributes", &self.attributes)
+            .field("children", &self.children)
+            .finish()
+    }
+}
+
+/// Writes a compact tree of the node and its descendants, one line per node, indented by depth.
+fn write_tree(f: &mut std::fmt::Formatter<'_>, node: &TagNode<'_>) -> std::fmt::Result {
+    write_tree_tag(f, node, "")?;
+
+    let mut stack: Vec<_> = node.children.iter().rev().map(|c| (c, 1)).collect();
+    while let Some((node, depth)) = stack.pop() {
+        let indent = "  ".repeat(depth);
+        writeln!(f)?;
+
+        match node {
+            Node::Child(tag) => {
+                write_tree_tag(f, tag, &indent)?;
+                stack.extend(tag.children.iter().rev().map(|c| (c, depth + 1)));
+            }
+            Node::Text(text) => write!(f, "{indent}{:?}", text.text().text())?,
+            Node::Cdata(cdata) => write!(f, "{indent}cdata {:?}", cdata.content().text())?,
+            Node::Comment(comment) => write!(f, "{indent}comment {:?}", comment.text().text())?,
+            Node::ProcessingInstruction(pi) => write!(f, "{indent}pi {}", pi.target())?,
+            Node::DocumentType(dtd) => write!(f, "{indent}doctype {}", dtd.name())?,
+        }
+    }
+
+    Ok(())
+}
+
+fn write_tree_tag(
+    f: &mut std::fmt::Formatter<'_>,
+    node: &TagNode<'_>,
+    indent: &str,
+) -> std::fmt::Result {
+    write!(f, "{indent}{}", node.name)?;
+    for attr in &node.attributes {
+        write!(f, " {}={:?}", attr.name(), attr.value().text())?;
+    }
+    Ok(())
+}
+
 /// An owned version of a tag node, with no span metadata. See [`TagNode`].
-#[derive(Debug, Clone, PartialEq)]
+///
+/// Equality and hashing are structural: names, attributes and children are compared in document order.
+/// Use [`OwnedTagNode::semantic_eq`] to ignore attribute order or formatting.
+#[derive(Clone, PartialEq, Eq, Hash)]
 pub struct OwnedTagNode {
     /// The name of the node.
     pub name: OwnedNodeName,
@@ -126,6 +537,9 @@ pub struct OwnedTagNode {
 
     /// The children of the node.
     pub children: Vec<OwnedNode>,
+
+    /// Where the node was converted from, if it was parsed. See [`Origin`].
+    pub origin: Origin,
 }
 impl OwnedTagNode {
     /// Create a new tag node.
@@ -135,9 +549,106 @@ impl OwnedTagNode {
             name: name.into(),
             attributes: vec![],
             children: vec![],
+            origin: Origin::default(),
         }
     }
 
+    /// Adds an attribute with a boolean value, written as `true` or `false`.
+    #[must_use]
+    pub fn attr_bool(mut self, name: impl Into<OwnedNodeName>, value: bool) -> Self {
+        let value = if value { "true" } else { "false" };
+        self.attributes.push(OwnedNodeAttribute::new(name, value));
+        self
+    }
+
+    /// Adds an attribute with an integer value, written in decimal with no grouping separators.
+    #[must_use]
+    pub fn attr_int(mut self, name: impl Into<OwnedNodeName>, value: i64) -> Self {
+        self.attributes
+            .push(OwnedNodeAttribute::new(name, value.to_string()));
+        self
+    }
+
+    /// Adds an attribute with a floating point value, in the canonical form of an XML schema `double`.
+    ///
+    /// The form does not depend on locale; see [`canonical_double`] for examples.
+    #[must_use]
+    pub fn attr_float(mut self, name: impl Into<OwnedNodeName>, value: f64) -> Self {
+        self.attributes
+            .push(OwnedNodeAttribute::new(name, canonical_double(value)));
+        self
+    }
+
+    /// Adds a text child with a floating point value, in the canonical form of an XML schema `double`.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::node::OwnedTagNode;
+    ///
+    /// let tag = OwnedTagNode::new("reading")
+    ///     .attr_bool("valid", true)
+    ///     .attr_int("sensor", -12)
+    ///     .attr_float("scale", 0.5)
+    ///     .text_float(3.14);
+    /// let xml = tag.to_xml(None).unwrap();
+    /// assert!(xml.starts_with("<reading valid=\"true\" sensor=\"-12\" scale=\"5.0E-1\">"));
+    /// assert!(xml.contains("3.14E0"));
+    /// ```
+    #[must_use]
+    pub fn text_float(mut self, value: f64) -> Self {
+        self.children
+            .push(OwnedNode::Text(OwnedTextNode::new(canonical_double(value))));
+        self
+    }
+
+    /// Replaces the names of this node, its attributes, and its descendants with the interner's shared copies.
+    ///
+    /// Useful after building or editing a tree by hand, where each name has its own allocation.
+    pub fn intern_names(&mut self, interner: &mut NameInterner) {
+        self.name.intern(interner);
+        for attr in &mut self.attributes {
+            attr.name.intern(interner);
+        }
+        for child in &mut self.children {
+            child.intern_names(interner);
+        }
+    }
+
+    /// Removes every node nested more than `depth` levels below this node, where its children are at depth 1.
+    ///
+    /// Returns the number of nodes removed, including their descendants.
+    pub fn truncate_depth(&mut self, depth: usize) -> usize {
+        self.truncate_below(depth, false)
+    }
+
+    /// Like [`OwnedTagNode::truncate_depth`], but leaves a comment in place of the removed nodes,
+    /// such as `<!-- truncated 5 nodes (2 elements) -->`.
+    pub fn truncate_depth_with_marker(&mut self, depth: usize) -> usize {
+        self.truncate_below(depth, true)
+    }
+
+    fn truncate_below(&mut self, depth: usize, marker: bool) -> usize {
+        if depth > 0 {
+            return self
+                .children
+                .iter_mut()
+                .filter_map(|child| match child {
+                    OwnedNode::Tag(tag) => Some(tag.truncate_below(depth - 1, marker)),
+                    _ => None,
+                })
+                .sum();
+        }
+
+        let (total, elements) = count_nodes(&self.children);
+        self.children.clear();
+        if marker && total > 0 {
+            let text = format!(" truncated {total} nodes ({elements} elements) ");
+            self.children
+                .push(OwnedNode::Comment(OwnedCommentNode::new(text)));
+        }
+        total
+    }
+
     /// Get an attribute by name.
     ///
     /// Searches the attributes in reverse order, so the last attribute with the same name is returned.
@@ -164,6 +675,105 @@ impl OwnedTagNode {
             .find(|a| a.name.equals(prefix, name))
     }
 
+    /// Get the attributes with the given local name, whatever their prefix, in order.
+    ///
+    /// See [`TagNode::get_attribute_local`] for more details.
+    #[must_use]
+    pub fn get_attribute_local(&self, local: &str) -> Vec<&OwnedNodeAttribute> {
+        self.attributes
+            .iter()
+            .filter(|a| &*a.name.local == local && a.name.prefix.as_deref() != Some("xmlns"))
+            .collect()
+    }
+
+    /// Get the first child tag, skipping any text, comments or other nodes before it.
+    #[must_use]
+    pub fn first_child_tag(&self) -> Option<&OwnedTagNode> {
+        self.children.iter().find_map(|child| match child {
+            OwnedNode::Tag(child) => Some(child),
+            _ => None,
+        })
+    }
+
+    /// Get the only child tag with the given name.
+    ///
+    /// See [`TagNode::only_child_named`] for more details.
+    ///
+    /// # Errors
+    /// Returns an [`Ambiguity`] if there is no child with the name, or more than one.
+    pub fn only_child_named(
+        &self,
+        prefix: Option<&str>,
+        local: &str,
+    ) -> Result<&OwnedTagNode, Ambiguity> {
+        let mut found = self.children.iter().filter_map(|child| match child {
+            OwnedNode::Tag(child) if child.name.equals(prefix, local) => Some(child),
+            _ => None,
+        });
+
+        match (found.next(), found.count()) {
+            (None, _) => Err(Ambiguity::Missing),
+            (Some(child), 0) => Ok(child),
+            (Some(_), rest) => Err(Ambiguity::Multiple(rest + 1)),
+        }
+    }
+
+    /// Get the text of the first child tag with the given name, from its first text or CDATA node.
+    ///
+    /// See [`TagNode::child_text`] for more details.
+    #[must_use]
+    pub fn child_text(&self, prefix: Option<&str>, local: &str) -> Option<&str> {
+        let child = self.children.iter().find_map(|child| match child {
+            OwnedNode::Tag(child) if child.name.equals(prefix, local) => Some(child),
+            _ => None,
+        })?;
+
+        child.children.iter().find_map(|node| match node {
+            OwnedNode::Text(text) => Some(text.text.as_str()),
+            OwnedNode::Cdata(cdata) => Some(cdata.content.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Create a formatted XML string from this node and its descendants.
+    ///
+    /// See [`TagNode::to_xml`] for more details.
+    ///
+    /// # Errors
+    /// Can fail if a string in the node cannot be entity encoded.
+    pub fn to_xml(&self, tab_char: Option<&str>) -> std::io::Result<String> {
+        self.borrowed().to_xml(tab_char)
+    }
+
+    /// Create a formatted XML string from this node and its descendants, using the given options.
+    ///
+    /// See [`XmlWriteOptions`] for the available options.
+    ///
+    /// # Errors
+    /// Can fail if a string in the node cannot be entity encoded,
+    /// or contains characters rejected by [`XmlWriteOptions::control_chars`].
+    pub fn to_xml_with_options(&self, options: &XmlWriteOptions) -> std::io::Result<String> {
+        self.borrowed().to_xml_with_options(options)
+    }
+
+    /// Write this node and its descendants as a flat binary format.
+    ///
+    /// See [`TagNode::to_bin`] for more details.
+    ///
+    /// # Errors
+    /// Returns errors if the encoding fails
+    pub fn to_bin(&self) -> std::io::Result<Vec<u8>> {
+        self.borrowed().to_bin()
+    }
+
+    /// Read a node from the flat binary format written by [`TagNode::to_bin`] or [`OwnedTagNode::to_bin`].
+    ///
+    /// # Errors
+    /// Returns errors if the decoding fails
+    pub fn from_bin(data: &[u8]) -> Result<Self, BinDecodeError> {
+        Ok(TagNode::from_bin(data)?.to_owned())
+    }
+
     pub(crate) fn borrowed(&self) -> TagNode<'_> {
         TagNode {
             span: StrSpan::default(),
@@ -173,6 +783,66 @@ impl OwnedTagNode {
         }
     }
 }
+/// Formats a number in the canonical lexical form of an XML schema `double`.
+///
+/// One digit before the decimal point, at least one after it, and an exponent: `100.0` is `1.0E2`,
+/// and `0.25` is `2.5E-1`. Special values are `INF`, `-INF`, and `NaN`.
+///
+/// Uses the shortest digits that read back as the same value, and never a locale's separators.
+///
+/// # Example
+/// ```rust
+/// use xmltree::node::canonical_double;
+///
+/// assert_eq!(canonical_double(100.0), "1.0E2");
+/// assert_eq!(canonical_double(-0.001_5), "-1.5E-3");
+/// assert_eq!(canonical_double(f64::NEG_INFINITY), "-INF");
+/// assert_eq!(canonical_double(f64::NAN), "NaN");
+/// ```
+#[must_use]
+pub fn canonical_double(value: f64) -> String {
+    if value.is_nan() {
+        return "NaN".to_string();
+    } else if value.is_infinite() {
+        return if value > 0.0 { "INF" } else { "-INF" }.to_string();
+    }
+
+    let formatted = format!("{value:E}");
+    match formatted.split_once('E') {
+        Some((mantissa, exponent)) if !mantissa.contains('.') => {
+            format!("{mantissa}.0E{exponent}")
+        }
+        _ => formatted,
+    }
+}
+
+/// Counts the given nodes and all their descendants, returning the total and the number of elements.
+fn count_nodes(nodes: &[OwnedNode]) -> (usize, usize) {
+    nodes
+        .iter()
+        .fold((0, 0), |(total, elements), node| match node {
+            OwnedNode::Tag(tag) => {
+                let (inner_total, inner_elements) = count_nodes(&tag.children);
+                (total + 1 + inner_total, elements + 1 + inner_elements)
+            }
+            _ => (total + 1, elements),
+        })
+}
+
+impl std::fmt::Debug for OwnedTagNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if f.alternate() {
+            return write_tree(f, &self.borrowed());
+        }
+
+        f.debug_struct("OwnedTagNode")
+            .field("name", &self.name)
+            .field("attributes", &self.attributes)
+            .field("children", &self.children)
+            .field("origin", &self.origin)
+            .finish()
+    }
+}
 impl<'src> ToBinHandler<'src> for OwnedTagNode {
     fn write(&self, encoder: &mut Encoder) -> std::io::Result<()> {
         self.borrowed().write(encoder)
@@ -193,6 +863,7 @@ pub struct NodeAttribute<'src> {
     span: StrSpan<'src>,
     name: NodeName<'src>,
     value: StrSpan<'src>,
+    defaulted: bool,
 }
 impl<'src> NodeAttribute<'src> {
     pub(crate) fn new<T: Into<StrSpan<'src>>>(prefix: Option<T>, local: T, value: T) -> Self {
@@ -200,6 +871,7 @@ impl<'src> NodeAttribute<'src> {
             span: StrSpan::default(),
             name: NodeName::new(prefix, local),
             value: value.into(),
+            defaulted: false,
         }
     }
 
@@ -208,30 +880,96 @@ impl<'src> NodeAttribute<'src> {
         self
     }
 
+    pub(crate) fn with_defaulted(mut self) -> Self {
+        self.defaulted = true;
+        self
+    }
+
+    /// Create a new attribute that is not part of the original source.
+    ///
+    /// See [`TagNode::detached`] for the lifetime requirements of the strings.
+    #[must_use]
+    pub fn detached(prefix: Option<&'src str>, local: &'src str, value: &'src str) -> Self {
+        Self::new(prefix, local, value)
+    }
+
     /// Returns the name of the attribute.
     #[must_use]
     pub fn name(&self) -> &NodeName<'src> {
         &self.name
     }
 
-    /// Returns the value of the attribute.
+    /// Returns the value of the attribute, exactly as written between the quotes.
+    ///
+    /// Entity references are not decoded; see [`NodeAttribute::decoded_value`].
     #[must_use]
     pub fn value(&self) -> &StrSpan<'src> {
         &self.value
     }
 
-    /// Returns the span of the attribute in the original source
+    /// Returns the value of the attribute, as an XML processor would report it.
+    ///
+    /// Character references and the predefined entities (`&lt;`, `&gt;`, `&amp;`, `&apos;`, `&quot;`) are decoded,
+    /// and literal tabs and line breaks become spaces. References to other entities are kept as written.
+    /// Borrows from the source when there is nothing to decode.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::Document;
+    ///
+    /// let doc = Document::parse_str("<a title='Tom &amp; &#x4A;erry&#10;&custom;' />").unwrap();
+    /// let attr = doc.root().get_attribute(None, "title").unwrap();
+    /// assert_eq!(attr.value().text(), "Tom &amp; &#x4A;erry&#10;&custom;");
+    /// assert_eq!(attr.decoded_value(), "Tom & Jerry\n&custom;");
+    /// assert_eq!(attr.quote(), '\'');
+    /// ```
+    #[must_use]
+    pub fn decoded_value(&self) -> Cow<'src, str> {
+        decode_attribute_value(self.value.text())
+    }
+
+    /// Returns the quote character the value was written with in the source.
+    ///
+    /// Attributes that are not from a source, such as detached ones, report a double quote.
+    #[must_use]
+    pub fn quote(&self) -> char {
+        if self.span.text().ends_with('\'') {
+            '\''
+        } else {
+            '"'
+        }
+    }
+
+    /// Returns the span of the attribute in the original source, from the start of the name to the closing quote.
+    ///
+    /// For defaulted attributes, this is the span of the attribute's definition in the DTD.
     #[must_use]
     pub fn span(&self) -> &StrSpan<'src> {
         &self.span
     }
 
-    /// Returns an owned version of the attribute, with no span metadata.
+    /// Returns true if the attribute was not written on its tag, but added from a default in the DTD.
+    ///
+    /// See [`Document::apply_dtd_defaults`](crate::Document::apply_dtd_defaults).
+    /// The flag is not kept by the binary format, or in owned attributes.
+    #[must_use]
+    pub fn is_defaulted(&self) -> bool {
+        self.defaulted
+    }
+
+    /// Returns an owned version of the attribute; only its origin is kept from the span metadata.
     #[must_use]
     pub fn to_owned(&self) -> OwnedNodeAttribute {
+        self.to_owned_in(&mut NameInterner::new())
+    }
+
+    /// Returns an owned version of the attribute, sharing its name through the given interner.
+    #[must_use]
+    pub fn to_owned_in(&self, interner: &mut NameInterner) -> OwnedNodeAttribute {
         OwnedNodeAttribute {
-            name: self.name.to_owned(),
+            name: self.name.to_owned_in(interner),
             value: self.value.text().to_string(),
+            origin: Origin::from(&self.span),
         }
     }
 }
@@ -248,18 +986,76 @@ impl<'src> ToBinHandler<'src> for NodeAttribute<'src> {
         let name = NodeName::read(decoder)?;
         let value = StrSpan::read(decoder)?;
 
-        Ok(NodeAttribute { span, name, value })
+        Ok(NodeAttribute {
+            span,
+            name,
+            value,
+            defaulted: false,
+        })
     }
 }
 
+/// Decodes character references and predefined entities in an attribute value, and normalizes literal whitespace.
+pub(crate) fn decode_attribute_value(raw: &str) -> Cow<'_, str> {
+    if !raw.contains(['&', '\t', '\n', '\r']) {
+        return Cow::Borrowed(raw);
+    }
+
+    let mut decoded = String::with_capacity(raw.len());
+    let mut rest = raw;
+    while let Some(i) = rest.find(['&', '\t', '\n', '\r']) {
+        decoded.push_str(&rest[..i]);
+        rest = &rest[i..];
+
+        // A \r\n pair is a single line break
+        if let Some(after) = rest.strip_prefix("\r\n") {
+            decoded.push(' ');
+            rest = after;
+            continue;
+        }
+        if !rest.starts_with('&') {
+            decoded.push(' ');
+            rest = &rest[1..];
+            continue;
+        }
+
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+        match resolve_builtin_reference(&rest[1..end]) {
+            Some(c) => decoded.push(c),
+            None => decoded.push_str(&rest[..=end]),
+        }
+        rest = &rest[end + 1..];
+    }
+
+    decoded.push_str(rest);
+    Cow::Owned(decoded)
+}
+
+/// Why [`TagNode::only_child_named`] could not pick a single child.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, thiserror::Error)]
+pub enum Ambiguity {
+    /// No child has the name.
+    #[error("No child has the name")]
+    Missing,
+
+    /// More than one child has the name; holds how many.
+    #[error("{0} children have the name")]
+    Multiple(usize),
+}
+
 /// Owned version of a node attribute, with no span metadata. See [`NodeAttribute`].
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct OwnedNodeAttribute {
     /// The name of the attribute.
     pub name: OwnedNodeName,
 
     /// The value of the attribute.
     pub value: String,
+
+    /// Where the attribute was converted from, if it was parsed. See [`Origin`].
+    pub origin: Origin,
 }
 impl OwnedNodeAttribute {
     /// Create a new node attribute.
@@ -267,6 +1063,7 @@ impl OwnedNodeAttribute {
         Self {
             name: name.into(),
             value: value.into(),
+            origin: Origin::default(),
         }
     }
 
@@ -275,6 +1072,7 @@ impl OwnedNodeAttribute {
             span: StrSpan::default(),
             name: self.name.borrowed(),
             value: self.value.as_str().into(),
+            defaulted: false,
         }
     }
 }
@@ -1,5 +1,5 @@
 use crate::{
-    StrSpan,
+    Origin, StrSpan,
     to_bin::{BinDecodeError, Decoder, Encoder, ToBinHandler},
 };
 
@@ -21,6 +21,14 @@ impl<'src> TextNode<'src> {
         }
     }
 
+    /// Create a new text node that is not part of the original source.
+    ///
+    /// See [`TagNode::detached`](super::TagNode::detached) for the lifetime requirements of the string.
+    #[must_use]
+    pub fn detached(text: &'src str) -> Self {
+        Self::new(StrSpan::default(), text)
+    }
+
     /// Returns the span of the node in the original source.
     #[must_use]
     pub fn span(&self) -> &StrSpan<'src> {
@@ -34,11 +42,12 @@ impl<'src> TextNode<'src> {
         &self.text
     }
 
-    /// Returns an owned version of the text node, with no span metadata
+    /// Returns an owned version of the text node; only its origin is kept from the span metadata
     #[must_use]
     pub fn to_owned(&self) -> OwnedTextNode {
         OwnedTextNode {
             text: self.text.text().to_string(),
+            origin: Origin::from(&self.span),
         }
     }
 }
@@ -58,15 +67,21 @@ impl<'src> ToBinHandler<'src> for TextNode<'src> {
 }
 
 /// A non-empty span of text inside a node of the document tree.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct OwnedTextNode {
     /// The inner text of the node.
     pub text: String,
+
+    /// Where the node was converted from, if it was parsed. See [`Origin`].
+    pub origin: Origin,
 }
 impl OwnedTextNode {
     /// Create a new text node.
     pub fn new(text: impl Into<String>) -> Self {
-        Self { text: text.into() }
+        Self {
+            text: text.into(),
+            origin: Origin::default(),
+        }
     }
 
     pub(crate) fn borrowed(&self) -> TextNode<'_> {
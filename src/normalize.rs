@@ -0,0 +1,252 @@
+//! Canonical ordering and cleanup of owned trees, for deterministic output.
+use crate::{
+    OwnedDocument,
+    node::{OwnedNode, OwnedNodeName, OwnedTagNode, OwnedTextNode},
+};
+
+/// Options controlling the changes made by [`OwnedTagNode::normalize`].
+///
+/// By default, comments and blank text are removed and adjacent text is merged; nothing is reordered.
+///
+/// # Example
+/// ```rust
+/// use xmltree::{Document, NormalizeOptions};
+///
+/// let doc = Document::parse_str("<a z='1' b='2'><d>x<!-- note -->y</d><c /></a>").unwrap();
+/// let mut root = doc.root().to_owned();
+/// root.normalize(&NormalizeOptions::default().with_sort_attributes(true).with_sort_children(true));
+///
+/// let expected = Document::parse_str("<a b='2' z='1'><c /><d>x y</d></a>").unwrap();
+/// assert_eq!(root, expected.root().to_owned());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[expect(clippy::struct_excessive_bools, reason = "Independent switches")]
+pub struct NormalizeOptions {
+    /// Remove comments.
+    pub strip_comments: bool,
+
+    /// Remove text nodes that are empty or only whitespace.
+    pub drop_empty_text: bool,
+
+    /// Merge adjacent text nodes into one, separated by a space.
+    ///
+    /// Merging happens after comments are removed, so text on either side of a comment is merged.
+    pub merge_text: bool,
+
+    /// Sort the attributes of every tag. See [`OwnedTagNode::sort_attributes`].
+    pub sort_attributes: bool,
+
+    /// Sort the child tags of every tag. See [`OwnedTagNode::sort_children_by_name`].
+    pub sort_children: bool,
+}
+impl Default for NormalizeOptions {
+    fn default() -> Self {
+        Self {
+            strip_comments: true,
+            drop_empty_text: true,
+            merge_text: true,
+            sort_attributes: false,
+            sort_children: false,
+        }
+    }
+}
+impl NormalizeOptions {
+    /// Sets whether comments are removed.
+    #[must_use]
+    pub fn with_strip_comments(mut self, strip: bool) -> Self {
+        self.strip_comments = strip;
+        self
+    }
+
+    /// Sets whether text nodes that are empty or only whitespace are removed.
+    #[must_use]
+    pub fn with_drop_empty_text(mut self, drop: bool) -> Self {
+        self.drop_empty_text = drop;
+        self
+    }
+
+    /// Sets whether adjacent text nodes are merged.
+    #[must_use]
+    pub fn with_merge_text(mut self, merge: bool) -> Self {
+        self.merge_text = merge;
+        self
+    }
+
+    /// Sets whether the attributes of every tag are sorted.
+    #[must_use]
+    pub fn with_sort_attributes(mut self, sort: bool) -> Self {
+        self.sort_attributes = sort;
+        self
+    }
+
+    /// Sets whether the child tags of every tag are sorted.
+    #[must_use]
+    pub fn with_sort_children(mut self, sort: bool) -> Self {
+        self.sort_children = sort;
+        self
+    }
+
+    /// Applies the options to a list of sibling nodes, without descending into tags.
+    fn apply_to_siblings(self, nodes: &mut Vec<OwnedNode>) {
+        if self.strip_comments {
+            nodes.retain(|node| !matches!(node, OwnedNode::Comment(_)));
+        }
+
+        if self.drop_empty_text {
+            nodes.retain(
+                |node| !matches!(node, OwnedNode::Text(text) if text.text.trim().is_empty()),
+            );
+        }
+
+        if self.merge_text {
+            let mut merged: Vec<OwnedNode> = Vec::with_capacity(nodes.len());
+            for node in nodes.drain(..) {
+                match (merged.last_mut(), node) {
+                    (Some(OwnedNode::Text(last)), OwnedNode::Text(OwnedTextNode { text, .. })) => {
+                        if !last.text.is_empty() && !text.is_empty() {
+                            last.text.push(' ');
+                        }
+                        last.text.push_str(&text);
+                    }
+                    (_, node) => merged.push(node),
+                }
+            }
+            *nodes = merged;
+        }
+    }
+}
+
+/// Orders names by prefix, then local name, with unprefixed names first.
+fn name_key(name: &OwnedNodeName) -> (Option<&str>, &str) {
+    (name.prefix.as_deref(), &name.local)
+}
+
+impl OwnedTagNode {
+    /// Sorts the attributes of this tag by name; by prefix, then local name, with unprefixed names first.
+    ///
+    /// The sort is stable, so attributes with the same name keep their order, and the last one is still used for lookups.
+    pub fn sort_attributes(&mut self) {
+        self.attributes
+            .sort_by(|a, b| name_key(&a.name).cmp(&name_key(&b.name)));
+    }
+
+    /// Sorts the child tags of this tag by name, in the same order as [`OwnedTagNode::sort_attributes`].
+    ///
+    /// Only tags move; other children such as text stay where they are, and tags are placed in the slots tags occupied.
+    /// The sort is stable, and does not descend into the children.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::Document;
+    ///
+    /// let doc = Document::parse_str("<a><c />text<b /></a>").unwrap();
+    /// let mut root = doc.root().to_owned();
+    /// root.sort_children_by_name();
+    ///
+    /// let expected = Document::parse_str("<a><b />text<c /></a>").unwrap();
+    /// assert_eq!(root, expected.root().to_owned());
+    /// ```
+    pub fn sort_children_by_name(&mut self) {
+        let mut tags: Vec<OwnedTagNode> = vec![];
+        let mut slots = vec![];
+        for (i, child) in self.children.iter_mut().enumerate() {
+            if let OwnedNode::Tag(tag) = child {
+                tags.push(std::mem::replace(tag, OwnedTagNode::new("")));
+                slots.push(i);
+            }
+        }
+
+        tags.sort_by(|a, b| name_key(&a.name).cmp(&name_key(&b.name)));
+        for (slot, tag) in slots.into_iter().zip(tags) {
+            self.children[slot] = OwnedNode::Tag(tag);
+        }
+    }
+
+    /// Cleans up and reorders this tag and everything below it. See [`NormalizeOptions`].
+    pub fn normalize(&mut self, options: &NormalizeOptions) {
+        let mut stack = vec![self];
+        while let Some(tag) = stack.pop() {
+            if options.sort_attributes {
+                tag.sort_attributes();
+            }
+            options.apply_to_siblings(&mut tag.children);
+            if options.sort_children {
+                tag.sort_children_by_name();
+            }
+
+            stack.extend(tag.children.iter_mut().filter_map(|child| match child {
+                OwnedNode::Tag(tag) => Some(tag),
+                _ => None,
+            }));
+        }
+    }
+}
+
+impl OwnedDocument {
+    /// Cleans up and reorders the whole document. See [`NormalizeOptions`].
+    ///
+    /// The prolog and epilog are cleaned up as well, but never reordered.
+    pub fn normalize(&mut self, options: &NormalizeOptions) {
+        options.apply_to_siblings(&mut self.prolog);
+        options.apply_to_siblings(&mut self.epilog);
+        self.root.normalize(options);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::{OwnedCdataNode, OwnedCommentNode};
+
+    #[test]
+    fn test_normalize() {
+        let mut tag = OwnedTagNode::new("root");
+        tag.children = vec![
+            OwnedNode::Text(OwnedTextNode::new("a")),
+            OwnedNode::Comment(OwnedCommentNode::new("c")),
+            OwnedNode::Text(OwnedTextNode::new("  ")),
+            OwnedNode::Text(OwnedTextNode::new("b")),
+            OwnedNode::Cdata(OwnedCdataNode::new("d")),
+            OwnedNode::Text(OwnedTextNode::new("e")),
+        ];
+
+        let mut kept = tag.clone();
+        kept.normalize(&NormalizeOptions::default().with_strip_comments(false));
+        assert_eq!(kept.children.len(), 6 - 1);
+
+        tag.normalize(&NormalizeOptions::default());
+        assert_eq!(
+            tag.children,
+            vec![
+                OwnedNode::Text(OwnedTextNode::new("a b")),
+                OwnedNode::Cdata(OwnedCdataNode::new("d")),
+                OwnedNode::Text(OwnedTextNode::new("e")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sort_attributes() {
+        let mut tag = OwnedTagNode::new("root");
+        for (name, value) in [("z", "1"), ("x:a", "2"), ("a", "3"), ("z", "4")] {
+            tag.attributes
+                .push(crate::node::OwnedNodeAttribute::new(name, value));
+        }
+
+        tag.sort_attributes();
+        let order: Vec<_> = tag
+            .attributes
+            .iter()
+            .map(|attr| (attr.name.to_string(), attr.value.as_str()))
+            .collect();
+        assert_eq!(
+            order,
+            [
+                ("a".to_string(), "3"),
+                ("z".to_string(), "1"),
+                ("z".to_string(), "4"),
+                ("x:a".to_string(), "2")
+            ]
+        );
+    }
+}
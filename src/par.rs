@@ -0,0 +1,157 @@
+//! Parallel traversal of document trees, using `rayon`.
+//!
+//! Trees are immutable while being traversed, so any node can be visited from any thread.
+use crate::{
+    Document, OwnedDocument,
+    node::{NameInterner, Node, OwnedNode, OwnedTagNode, TagNode},
+};
+use rayon::iter::{
+    IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator, walk_tree_prefix,
+};
+
+impl<'src> TagNode<'src> {
+    /// Returns a parallel iterator over all descendants of this node.
+    ///
+    /// The order in which nodes are visited is not guaranteed.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rayon::iter::ParallelIterator;
+    /// use xmltree::{Document, node::Node};
+    ///
+    /// let doc = Document::parse_str("<a><b><c /></b><d>text</d></a>").unwrap();
+    /// let tags = doc.root().par_descendants().filter(|n| matches!(n, Node::Child(_))).count();
+    /// assert_eq!(tags, 3);
+    /// ```
+    #[must_use]
+    pub fn par_descendants(&self) -> impl ParallelIterator<Item = &Node<'src>> {
+        self.children()
+            .par_iter()
+            .flat_map(|child| walk_tree_prefix(child, |node| node_children(node).iter()))
+    }
+}
+
+impl OwnedTagNode {
+    /// Returns a parallel iterator over all descendants of this node.
+    ///
+    /// The order in which nodes are visited is not guaranteed.
+    #[must_use]
+    pub fn par_descendants(&self) -> impl ParallelIterator<Item = &OwnedNode> {
+        self.children
+            .par_iter()
+            .flat_map(|child| walk_tree_prefix(child, |node| owned_node_children(node).iter()))
+    }
+}
+
+impl OwnedNode {
+    /// Converts a slice of nodes into owned nodes, spreading the work across threads.
+    ///
+    /// Names are shared between nodes converted on the same thread. See [`OwnedNode::from_nodes`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{Document, node::OwnedNode};
+    ///
+    /// let doc = Document::parse_str("<list><item>a</item><item>b</item></list>").unwrap();
+    /// let items = OwnedNode::par_from_nodes(doc.root().children());
+    /// assert_eq!(items, OwnedNode::from_nodes(doc.root().children()));
+    /// ```
+    #[must_use]
+    pub fn par_from_nodes(nodes: &[Node<'_>]) -> Vec<OwnedNode> {
+        let mut owned = Vec::with_capacity(nodes.len());
+        nodes
+            .par_iter()
+            .map_init(NameInterner::new, |interner, node| {
+                node.to_owned_in(interner)
+            })
+            .collect_into_vec(&mut owned);
+        owned
+    }
+}
+
+impl<'src> Document<'src> {
+    /// Returns a parallel iterator over every node in the document; the prolog, the descendants of the root, and the epilog.
+    ///
+    /// The root node itself is not a [`Node`], and is available through [`Document::root`].
+    /// The order in which nodes are visited is not guaranteed.
+    #[must_use]
+    pub fn par_walk(&self) -> impl ParallelIterator<Item = &Node<'src>> {
+        self.prolog()
+            .par_iter()
+            .chain(self.root().par_descendants())
+            .chain(self.epilog().par_iter())
+    }
+}
+
+impl OwnedDocument {
+    /// Returns a parallel iterator over every node in the document; the prolog, the descendants of the root, and the epilog.
+    ///
+    /// The root node itself is not an [`OwnedNode`], and is available through [`OwnedDocument::root`].
+    /// The order in which nodes are visited is not guaranteed.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rayon::iter::ParallelIterator;
+    /// use xmltree::{Document, node::OwnedNode};
+    ///
+    /// let doc = Document::parse_str("<!-- a --><a><b>one</b><c>two</c></a>").unwrap().to_owned();
+    /// let text_len: usize = doc
+    ///     .par_walk()
+    ///     .filter_map(|n| match n {
+    ///         OwnedNode::Text(t) => Some(t.text.len()),
+    ///         _ => None,
+    ///     })
+    ///     .sum();
+    /// assert_eq!(text_len, 6);
+    /// ```
+    #[must_use]
+    pub fn par_walk(&self) -> impl ParallelIterator<Item = &OwnedNode> {
+        self.prolog
+            .par_iter()
+            .chain(self.root.par_descendants())
+            .chain(self.epilog.par_iter())
+    }
+}
+
+fn node_children<'a, 'src>(node: &'a Node<'src>) -> &'a [Node<'src>] {
+    match node {
+        Node::Child(tag) => tag.children(),
+        _ => &[],
+    }
+}
+
+fn owned_node_children(node: &OwnedNode) -> &[OwnedNode] {
+    match node {
+        OwnedNode::Tag(tag) => &tag.children,
+        _ => &[],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SRC: &str = "<?pi?><a><b><c /><c>x</c></b><!-- d --><e><f><g /></f></e></a><!-- end -->";
+
+    #[test]
+    fn test_par_descendants() {
+        let doc = Document::parse_str(SRC).unwrap();
+        assert_eq!(doc.root().par_descendants().count(), 8);
+
+        let owned = doc.to_owned();
+        assert_eq!(owned.root.par_descendants().count(), 8);
+    }
+
+    #[test]
+    fn test_par_walk() {
+        let doc = Document::parse_str(SRC).unwrap();
+        assert_eq!(doc.par_walk().count(), 10);
+
+        let owned = doc.to_owned();
+        let comments = owned
+            .par_walk()
+            .filter(|n| matches!(n, OwnedNode::Comment(_)))
+            .count();
+        assert_eq!(comments, 2);
+    }
+}
@@ -0,0 +1,536 @@
+use crate::{
+    Document, StrSpan,
+    error::{ErrorContext, XmlError, XmlErrorKind, XmlResult},
+    node::TagNode,
+    to_bin::{BinDecodeError, Decoder, Encoder, ToBinHandler},
+    to_xml::is_xml10_char,
+};
+
+mod push;
+pub use push::*;
+
+mod warnings;
+pub(crate) use warnings::{WarningSink, suspicious_references};
+
+/// A reusable parser, for parsing many documents in a row.
+///
+/// The parser keeps its working buffers between calls, so parsing a stream of small documents
+/// does not allocate them again for every document.
+///
+/// It can also parse a document pushed in chunks, producing events instead of a tree; see [`Parser::feed`].
+///
+/// [`Document::parse_str`] is equivalent to parsing with a new parser.
+///
+/// Limits set on the parser also apply to [`Parser::from_bin`] and [`Parser::to_bin`],
+/// so binary documents encoded elsewhere cannot get around them.
+///
+/// # Example
+/// ```rust
+/// use xmltree::Parser;
+///
+/// let mut parser = Parser::new();
+/// for src in ["<a><b /></a>", "<c />"] {
+///     let doc = parser.parse(src).unwrap();
+///     println!("{}", doc.root().name());
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct Parser {
+    /// Stack of open tags; always empty between calls, but keeps its capacity
+    stack: Vec<TagNode<'static>>,
+
+    config: ParseConfig,
+
+    /// State of the document being pushed with [`Parser::feed`]
+    push: PushState,
+
+    /// Receives non-fatal observations made by [`Parser::parse`]
+    warnings: WarningSink,
+}
+impl Parser {
+    /// Creates a new parser.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limits how deeply tags can be nested below the root.
+    ///
+    /// The root is at depth 0, so a limit of 0 only allows a root with no child tags.
+    /// Deeper documents fail with [`XmlErrorKind::DepthLimitExceeded`](crate::error::XmlErrorKind::DepthLimitExceeded).
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::Parser;
+    ///
+    /// let mut parser = Parser::new().with_max_depth(1);
+    /// assert!(parser.parse("<a><b /></a>").is_ok());
+    /// assert!(parser.parse("<a><b><c /></b></a>").is_err());
+    /// ```
+    #[must_use]
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.config.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Returns the nesting depth limit, if one was set.
+    #[must_use]
+    pub fn max_depth(&self) -> Option<usize> {
+        self.config.max_depth
+    }
+
+    /// Accepts documents that end before a root element, instead of failing with
+    /// [`XmlErrorKind::UnexpectedEof`](crate::error::XmlErrorKind::UnexpectedEof).
+    ///
+    /// Meant for recovery tools working on truncated files; the nodes found are all in the prolog,
+    /// and [`Document::has_root`] returns false.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::Parser;
+    ///
+    /// let mut parser = Parser::new().with_allow_missing_root(true);
+    /// let doc = parser.parse("<?xml version=\"1.0\"?><!-- header -->").unwrap();
+    /// assert!(!doc.has_root());
+    /// assert_eq!(doc.prolog().len(), 1);
+    /// ```
+    #[must_use]
+    pub fn with_allow_missing_root(mut self, allow: bool) -> Self {
+        self.config.allow_missing_root = allow;
+        self
+    }
+
+    /// Returns true if documents without a root element are accepted.
+    #[must_use]
+    pub fn allow_missing_root(&self) -> bool {
+        self.config.allow_missing_root
+    }
+
+    /// Rejects character references to characters outside the XML `Char` production, such as `&#x1;`,
+    /// failing with [`XmlErrorKind::InvalidCharacter`].
+    ///
+    /// Such characters written literally are always rejected; references to them are otherwise kept as they are,
+    /// since text is not decoded while parsing. Documents declared as version `1.1` may refer to restricted
+    /// characters, but not to `U+0000`, `U+FFFE` or `U+FFFF`.
+    ///
+    /// Text and attribute values are checked, by both [`Parser::parse`] and [`Parser::feed`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{Parser, error::XmlErrorKind};
+    ///
+    /// let mut parser = Parser::new().with_strict_chars(true);
+    /// assert!(parser.parse("<a>&#x41;</a>").is_ok());
+    ///
+    /// let err = parser.parse("<a>bell: &#7;</a>").unwrap_err();
+    /// assert!(matches!(err.kind, XmlErrorKind::InvalidCharacter(_)));
+    ///
+    /// assert!(parser.parse("<?xml version='1.1'?><a>bell: &#7;</a>").is_ok());
+    /// ```
+    #[must_use]
+    pub fn with_strict_chars(mut self, strict: bool) -> Self {
+        self.config.strict_chars = strict;
+        self
+    }
+
+    /// Returns true if character references to characters XML does not allow are rejected.
+    #[must_use]
+    pub fn strict_chars(&self) -> bool {
+        self.config.strict_chars
+    }
+
+    /// Checks documents against the well-formedness rules of the XML spec, for conformance checking
+    /// rather than lenient tooling.
+    ///
+    /// Problems that are otherwise only reported as warnings become errors:
+    /// - Attributes repeated on the same tag fail with [`XmlErrorKind::DuplicateAttribute`]
+    /// - Malformed entity references, and references to entities the DTD does not declare, fail with [`XmlErrorKind::SuspiciousEntity`]
+    ///
+    /// Markup in the wrong place around the root, such as a second root element, a DOCTYPE after the root,
+    /// or CDATA and text outside of it, fails with [`XmlErrorKind::InvalidStructure`] describing the problem,
+    /// rather than a generic tokenizer error.
+    ///
+    /// Only [`Parser::parse`] applies these checks.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{Parser, error::XmlErrorKind};
+    ///
+    /// let mut parser = Parser::new().with_strict(true);
+    /// assert!(parser.parse("<a x='1'>&amp;</a><!-- end -->").is_ok());
+    ///
+    /// let err = parser.parse("<a x='1' x='2' />").unwrap_err();
+    /// assert!(matches!(err.kind, XmlErrorKind::DuplicateAttribute(_)));
+    ///
+    /// let err = parser.parse("<a /><b />").unwrap_err();
+    /// assert_eq!(err.kind.to_string(), "Invalid document structure: A document can only have one root element");
+    /// ```
+    #[must_use]
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.config.strict = strict;
+        self
+    }
+
+    /// Returns true if documents are checked against the well-formedness rules of the XML spec.
+    #[must_use]
+    pub fn strict(&self) -> bool {
+        self.config.strict
+    }
+
+    /// Sends non-fatal observations made while parsing to the handler, as errors with [`Severity::Warning`](crate::error::Severity::Warning).
+    ///
+    /// The document is still produced; warnings cover legal but suspicious input:
+    /// - Attributes repeated on the same tag
+    /// - A DOCTYPE after comments or processing instructions
+    /// - Comments, processing instructions or CDATA after the root element
+    /// - Malformed entity references, and references to entities the DTD does not declare
+    ///
+    /// Only [`Parser::parse`] reports warnings.
+    ///
+    /// # Example
+    /// ```rust
+    /// use std::sync::{Arc, Mutex};
+    /// use xmltree::{Parser, error::ErrorCode};
+    ///
+    /// let codes = Arc::new(Mutex::new(vec![]));
+    /// let sink = Arc::clone(&codes);
+    /// let mut parser = Parser::new().with_warning_handler(move |w| sink.lock().unwrap().push(w.code()));
+    ///
+    /// let doc = parser.parse("<a x='1' x='2'>&nbsp;</a><!-- done -->").unwrap();
+    /// assert_eq!(doc.root().name(), "a");
+    /// assert_eq!(
+    ///     *codes.lock().unwrap(),
+    ///     [ErrorCode::DuplicateAttribute, ErrorCode::SuspiciousEntity, ErrorCode::EpilogContent]
+    /// );
+    /// ```
+    #[must_use]
+    pub fn with_warning_handler(mut self, handler: impl FnMut(XmlError) + Send + 'static) -> Self {
+        self.warnings = WarningSink::new(handler);
+        self
+    }
+
+    /// Parses a document from the given source string.
+    ///
+    /// Source string must live at least as long as the document.
+    ///
+    /// # Errors
+    /// Returns errors if the XML is invalid
+    pub fn parse<'src>(&mut self, src: &'src str) -> XmlResult<Document<'src>> {
+        let mut stack = recycle(std::mem::take(&mut self.stack));
+        let result = Document::parse_with(src, &mut stack, self.config, &mut self.warnings);
+        self.stack = recycle(stack);
+        result
+    }
+
+    /// Reads a document from the flat binary format, applying the parser's limits.
+    ///
+    /// See [`Document::from_bin`].
+    ///
+    /// # Errors
+    /// Returns errors if the decoding fails, or the document exceeds a limit
+    pub fn from_bin<'src>(&self, data: &'src [u8]) -> Result<Document<'src>, BinDecodeError> {
+        let mut decoder = Decoder::new(data);
+        if let Some(max_depth) = self.config.max_depth {
+            decoder.with_max_depth(max_depth);
+        }
+        Document::read(&mut decoder)
+    }
+
+    /// Writes a document to the flat binary format, applying the parser's limits.
+    ///
+    /// See [`Document::to_bin`].
+    ///
+    /// # Errors
+    /// Returns errors if the encoding fails, or the document exceeds a limit
+    pub fn to_bin(&self, document: &Document<'_>) -> std::io::Result<Vec<u8>> {
+        let mut encoder = Encoder::new();
+        if let Some(max_depth) = self.config.max_depth {
+            encoder.with_max_depth(max_depth);
+        }
+        document.write(&mut encoder)?;
+        Ok(encoder.into_inner())
+    }
+}
+
+/// Settings shared by the batch and push parsers.
+#[derive(Debug, Default, Clone, Copy)]
+#[expect(clippy::struct_excessive_bools, reason = "Independent switches")]
+pub(crate) struct ParseConfig {
+    /// Deepest allowed tag below the root, which is at depth 0
+    pub max_depth: Option<usize>,
+
+    /// Accept documents that end before a root element
+    pub allow_missing_root: bool,
+
+    /// Reject character references to characters outside the XML `Char` production
+    pub strict_chars: bool,
+
+    /// Treat well-formedness problems reported as warnings as errors, and explain misplaced markup
+    pub strict: bool,
+
+    /// Return as soon as the root element is closed, leaving the rest of the source unread
+    pub stop_after_root: bool,
+}
+
+/// Describes markup the tokenizer could not read around the root, starting after `offset`.
+///
+/// Returns `None` if the problem is not misplaced markup, so the tokenizer error should be kept.
+pub(crate) fn misplaced_markup(src: &str, offset: usize, after_root: bool) -> Option<XmlError> {
+    let rest = src.get(offset..)?.trim_start();
+    let start = src.len() - rest.len();
+    let markup_len = rest.find('>').map_or(rest.len(), |i| i + 1);
+    let where_ = if after_root { "after" } else { "before" };
+
+    let (message, len) = if rest.starts_with("<!DOCTYPE") {
+        let message = if after_root {
+            "A DOCTYPE must come before the root element".to_string()
+        } else {
+            "A document can only have one DTD".to_string()
+        };
+        (message, markup_len)
+    } else if rest.starts_with("<![CDATA[") {
+        let len = rest.find("]]>").map_or(rest.len(), |i| i + 3);
+        (
+            format!("CDATA is not allowed {where_} the root element"),
+            len,
+        )
+    } else if after_root
+        && rest.starts_with('<')
+        && rest[1..].starts_with(|c: char| c.is_alphabetic() || c == '_')
+    {
+        (
+            "A document can only have one root element".to_string(),
+            markup_len,
+        )
+    } else if !rest.is_empty() && !rest.starts_with('<') {
+        let len = rest.find('<').unwrap_or(rest.len());
+        (
+            format!("Text is not allowed {where_} the root element"),
+            rest[..len].trim_end().len(),
+        )
+    } else {
+        return None;
+    };
+
+    Some(XmlError::new(
+        XmlErrorKind::InvalidStructure(message),
+        ErrorContext::new(src, StrSpan::new(&rest[..len], start)),
+    ))
+}
+
+/// Fails on character references in raw text that refer to characters XML does not allow.
+///
+/// XML 1.1 allows references to restricted characters, but not to `U+0000`, `U+FFFE` or `U+FFFF`.
+/// Malformed references are left alone; they are reported as warnings instead.
+pub(crate) fn check_char_references(src: &str, text: StrSpan<'_>, xml11: bool) -> XmlResult<()> {
+    let raw = text.text();
+    let mut offset = 0;
+    while let Some(i) = raw[offset..].find("&#") {
+        let start = offset + i;
+        let Some(len) = raw[start..].find(';') else {
+            break;
+        };
+        offset = start + len + 1;
+
+        let reference = &raw[start..offset];
+        let digits = &reference[2..reference.len() - 1];
+        let code = match digits.strip_prefix('x') {
+            Some(hex) => u32::from_str_radix(hex, 16),
+            None => digits.parse(),
+        };
+        let Ok(code) = code else {
+            continue;
+        };
+
+        let allowed = char::from_u32(code).is_some_and(|c| {
+            if xml11 {
+                !matches!(c, '\0' | '\u{FFFE}' | '\u{FFFF}')
+            } else {
+                is_xml10_char(c)
+            }
+        });
+        if !allowed {
+            return Err(XmlError::new(
+                XmlErrorKind::InvalidCharacter(reference.to_string()),
+                ErrorContext::new(src, text.slice(start..offset)),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Empties a stack of tags so it can be reused with a different source lifetime.
+///
+/// The vector is empty, so the map never runs; collecting into a type with the same layout reuses the allocation.
+fn recycle<'b>(mut stack: Vec<TagNode<'_>>) -> Vec<TagNode<'b>> {
+    stack.clear();
+    stack
+        .into_iter()
+        .map(|_| unreachable!("Stack was cleared"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorCode;
+
+    #[test]
+    fn test_parser_reuse() {
+        let mut parser = Parser::new();
+
+        let deep = "<a><b><c><d /></c></b></a>".to_string();
+        let doc = parser.parse(&deep).unwrap();
+        assert_eq!(doc, Document::parse_str(&deep).unwrap());
+        drop(doc);
+        drop(deep);
+
+        let capacity = parser.stack.capacity();
+        assert!(capacity >= 3);
+
+        assert!(parser.parse("<a><b></a>").is_err());
+        let doc = parser.parse("<x />").unwrap();
+        assert_eq!(doc.root().name(), "x");
+        assert!(parser.stack.is_empty());
+    }
+
+    #[test]
+    fn test_bin_depth_limit() {
+        let deep = Document::parse_str("<a><b><c /></b></a>").unwrap();
+        let bin = deep.to_bin().unwrap();
+
+        let parser = Parser::new().with_max_depth(2);
+        assert!(parser.to_bin(&deep).is_ok());
+        assert!(parser.from_bin(&bin).is_ok());
+
+        let parser = Parser::new().with_max_depth(1);
+        assert!(parser.to_bin(&deep).is_err());
+        assert!(matches!(
+            parser.from_bin(&bin),
+            Err(BinDecodeError::DepthLimitExceeded(1))
+        ));
+
+        // Siblings at the limit are fine; only nesting counts
+        let wide = Document::parse_str("<a><b /><b /><b /></a>").unwrap();
+        assert!(parser.from_bin(&wide.to_bin().unwrap()).is_ok());
+    }
+
+    #[test]
+    fn test_warnings() {
+        use crate::error::Severity;
+        use std::sync::{Arc, Mutex};
+
+        let warnings = Arc::new(Mutex::new(vec![]));
+        let sink = Arc::clone(&warnings);
+        let mut parser = Parser::new().with_warning_handler(move |w| sink.lock().unwrap().push(w));
+
+        let src = "<!-- c --><!DOCTYPE a [<!ENTITY e 'v'>]><a y='&e;&#x41;' y='2'>&e; &amp; &bad; &#xD800; & x</a>";
+        parser.parse(src).unwrap();
+
+        let warnings = std::mem::take(&mut *warnings.lock().unwrap());
+        assert!(warnings.iter().all(|w| w.severity == Severity::Warning));
+
+        let found: Vec<_> = warnings
+            .iter()
+            .map(|w| (w.code(), w.context.span.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (ErrorCode::LateDoctype, "<!DOCTYPE a [<!ENTITY e 'v'>]>"),
+                (ErrorCode::DuplicateAttribute, "y='2'"),
+                (ErrorCode::SuspiciousEntity, "&bad;"),
+                (ErrorCode::SuspiciousEntity, "&#xD800;"),
+                (ErrorCode::SuspiciousEntity, "&"),
+            ]
+        );
+        assert_eq!(
+            warnings[1].context.related[0].span.as_str(),
+            "y='&e;&#x41;'"
+        );
+
+        // Without a handler, nothing is checked
+        assert!(Parser::new().parse(src).is_ok());
+    }
+
+    #[test]
+    fn test_strict_chars() {
+        use crate::{ControlCharPolicy, XmlWriteOptions};
+
+        let mut parser = Parser::new().with_strict_chars(true);
+        let err = parser.parse("<a x='&#x0;'>&#x41;</a>").unwrap_err();
+        assert_eq!(err.code(), ErrorCode::InvalidCharacter);
+        assert_eq!(err.context.span.as_str(), "&#x0;");
+        assert!(parser.parse("<a>&#xFFFE;</a>").is_err());
+        assert!(parser.parse("<a>&#xZ; &#9;</a>").is_ok());
+        assert!(Parser::new().parse("<a>&#1;</a>").is_ok());
+
+        // Documents written as XML 1.1 can be read back
+        let doc = Document::parse_str("<a>bell</a>").unwrap();
+        let mut owned = doc.to_owned();
+        owned
+            .root
+            .attributes
+            .push(crate::node::OwnedNodeAttribute::new("x", "\u{7}"));
+        let options = XmlWriteOptions::default().with_control_chars(ControlCharPolicy::Xml11);
+        let xml = owned.to_xml_with_options(&options).unwrap();
+        assert!(parser.parse(&xml).is_ok());
+
+        // The push parser checks the same way
+        let err = parser
+            .feed("<a>&#", |_| ParseControl::Continue)
+            .and_then(|_| {
+                parser.feed("1;</a>", |_| ParseControl::Continue)?;
+                parser.finish(|_| ParseControl::Continue)
+            });
+        assert_eq!(err.unwrap_err().code(), ErrorCode::InvalidCharacter);
+    }
+
+    #[test]
+    fn test_strict() {
+        let mut parser = Parser::new().with_strict(true);
+        let structure = |parser: &mut Parser, src: &str| {
+            let err = parser.parse(src).unwrap_err();
+            assert_eq!(err.code(), ErrorCode::InvalidStructure, "{src}");
+            (err.message(), err.context.span.as_str().to_string())
+        };
+
+        assert_eq!(
+            structure(&mut parser, "<a />\n<b x='1'/>"),
+            (
+                "Invalid document structure: A document can only have one root element".to_string(),
+                "<b x='1'/>".to_string()
+            )
+        );
+        assert_eq!(
+            structure(&mut parser, "<a /><!-- c --> <!DOCTYPE a>").1,
+            "<!DOCTYPE a>"
+        );
+        assert_eq!(
+            structure(&mut parser, "<a /><![CDATA[x > y]]>").1,
+            "<![CDATA[x > y]]>"
+        );
+        assert_eq!(structure(&mut parser, "<a /> tail ").1, "tail");
+        assert_eq!(
+            structure(&mut parser, "<!DOCTYPE a><!DOCTYPE b><a />").1,
+            "<!DOCTYPE b>"
+        );
+
+        let err = parser
+            .parse("<!DOCTYPE a [<!ENTITY e 'v'>]><a>&e; &f;</a>")
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::SuspiciousEntity);
+        assert_eq!(err.context.span.as_str(), "&f;");
+
+        let err = parser.parse("<a x='1 & 2' />").unwrap_err();
+        assert_eq!(err.code(), ErrorCode::SuspiciousEntity);
+
+        let err = parser.parse("<a x='1' x='2' />").unwrap_err();
+        assert_eq!(err.code(), ErrorCode::DuplicateAttribute);
+        assert_eq!(err.context.related[0].span.as_str(), "x='1'");
+
+        // Lenient parsing keeps the tokenizer error for misplaced markup
+        let err = Parser::new().parse("<a /><b />").unwrap_err();
+        assert_eq!(err.code(), ErrorCode::Xml);
+    }
+}
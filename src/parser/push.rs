@@ -0,0 +1,531 @@
+use super::Parser;
+use crate::{
+    DeclarationNode, NamedElement, StrSpan,
+    document::maybe_empty,
+    error::{ErrorContext, XmlError, XmlErrorKind, XmlResult},
+    node::{
+        CdataNode, CommentNode, DtdNode, NodeAttribute, NodeName, ProcessingInstructionNode,
+        TagNode, TextNode,
+    },
+};
+use xmlparser::{ElementEnd, Token, Tokenizer};
+
+/// Returned by a push handler to decide whether parsing goes on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseControl {
+    /// Keep parsing.
+    Continue,
+
+    /// Stop parsing; later calls to [`Parser::feed`] do nothing until [`Parser::finish`] is called.
+    Stop,
+}
+
+/// An event produced by [`Parser::feed`], as soon as the part of the document it describes is complete.
+///
+/// Events borrow from the parser's buffer, which discards text once it has been handled.
+/// Spans in events are offsets into that buffer, not into the whole document.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PushEvent<'a> {
+    /// The `<?xml ... ?>` declaration.
+    Declaration(DeclarationNode<'a>),
+
+    /// A DTD.
+    DocumentType(DtdNode<'a>),
+
+    /// The start of a tag, with its attributes; children follow as separate events.
+    StartTag(TagNode<'a>),
+
+    /// The end of a tag. Also sent straight after [`PushEvent::StartTag`] for self-closing tags.
+    EndTag(NodeName<'a>),
+
+    /// A text node, trimmed of leading and trailing whitespace.
+    Text(TextNode<'a>),
+
+    /// A comment.
+    Comment(CommentNode<'a>),
+
+    /// A processing instruction.
+    ProcessingInstruction(ProcessingInstructionNode<'a>),
+
+    /// A CDATA section.
+    Cdata(CdataNode<'a>),
+}
+
+/// Where the push parser is in the document.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum Section {
+    #[default]
+    Prolog,
+    Root,
+    Epilog,
+}
+
+/// State kept between calls to [`Parser::feed`].
+#[derive(Debug, Default)]
+pub(super) struct PushState {
+    /// Text fed so far, from the last checkpoint on
+    buffer: String,
+
+    /// Offset in the buffer where everything before has been handled.
+    ///
+    /// In the prolog, the tokenizer always restarts from the start of the buffer, since a fragment tokenizer
+    /// rejects DTDs; after that, it restarts here, between two complete tokens.
+    checkpoint: usize,
+
+    /// Qualified names of the open tags
+    open: Vec<String>,
+
+    section: Section,
+    stopped: bool,
+
+    /// The declaration gave version 1.1, so restricted characters can be referred to
+    xml11: bool,
+}
+
+impl Parser {
+    /// Feeds the next chunk of a document to the push parser.
+    ///
+    /// `handler` is called for every event that became complete with this chunk, and can return
+    /// [`ParseControl::Stop`] to abort early; the return value reports whether parsing was stopped.
+    /// Call [`Parser::finish`] after the last chunk, or to discard a stopped document.
+    ///
+    /// Text that cannot be handled yet is buffered, so the chunks can be split anywhere.
+    ///
+    /// # Errors
+    /// Returns errors if the document is invalid.
+    /// Syntax errors may only be reported once enough text has arrived, or on [`Parser::finish`].
+    /// The push parser is reset after an error.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{ParseControl, Parser, PushEvent};
+    ///
+    /// let chunks = ["<feed><metadata>ti", "tle</metadata><entry>", "..."];
+    ///
+    /// let mut parser = Parser::new();
+    /// let mut in_metadata = false;
+    /// let mut metadata = None;
+    /// for chunk in chunks {
+    ///     let control = parser
+    ///         .feed(chunk, |event| match event {
+    ///             PushEvent::StartTag(tag) if tag.name() == "metadata" => {
+    ///                 in_metadata = true;
+    ///                 ParseControl::Continue
+    ///             }
+    ///             PushEvent::Text(text) if in_metadata => {
+    ///                 metadata = Some(text.text().to_string());
+    ///                 ParseControl::Stop
+    ///             }
+    ///             _ => ParseControl::Continue,
+    ///         })
+    ///         .unwrap();
+    ///
+    ///     if control == ParseControl::Stop {
+    ///         break;
+    ///     }
+    /// }
+    ///
+    /// parser.finish(|_| ParseControl::Continue).unwrap();
+    /// assert_eq!(metadata.as_deref(), Some("title"));
+    /// ```
+    pub fn feed<F>(&mut self, chunk: &str, mut handler: F) -> XmlResult<ParseControl>
+    where
+        F: FnMut(PushEvent<'_>) -> ParseControl,
+    {
+        if self.push.stopped {
+            return Ok(ParseControl::Stop);
+        }
+
+        self.push.buffer.push_str(chunk);
+        let result = self.run(false, &mut handler);
+        if result.is_err() {
+            self.push = PushState::default();
+        }
+        result
+    }
+
+    /// Ends the document being pushed, handling any text left in the buffer, and resets the push parser.
+    ///
+    /// Does nothing but reset the parser if a handler returned [`ParseControl::Stop`].
+    ///
+    /// # Errors
+    /// Returns errors if the document is invalid or incomplete
+    pub fn finish<F>(&mut self, mut handler: F) -> XmlResult<()>
+    where
+        F: FnMut(PushEvent<'_>) -> ParseControl,
+    {
+        let result = self.finish_inner(&mut handler);
+        self.push = PushState::default();
+        result
+    }
+
+    fn finish_inner<F>(&mut self, handler: &mut F) -> XmlResult<()>
+    where
+        F: FnMut(PushEvent<'_>) -> ParseControl,
+    {
+        if self.push.stopped || self.run(true, handler)? == ParseControl::Stop {
+            return Ok(());
+        }
+
+        let src = self.push.buffer.as_str();
+        match self.push.open.last() {
+            _ if self.push.section == Section::Epilog => Ok(()),
+            None if self.config.allow_missing_root => Ok(()),
+            Some(name) => bail!(
+                src,
+                &StrSpan::end(src),
+                XmlErrorKind::UnclosedTag(name.clone())
+            ),
+            None => bail!(src, XmlErrorKind::UnexpectedEof),
+        }
+    }
+
+    /// Handles every complete token in the buffer, then discards the text that is no longer needed.
+    ///
+    /// When `finishing`, the end of the buffer is the end of the document, so every token is complete.
+    #[expect(
+        clippy::too_many_lines,
+        reason = "State machine, like the batch parser"
+    )]
+    fn run<F>(&mut self, finishing: bool, handler: &mut F) -> XmlResult<ParseControl>
+    where
+        F: FnMut(PushEvent<'_>) -> ParseControl,
+    {
+        let max_depth = self.config.max_depth;
+        let strict_chars = self.config.strict_chars;
+        let PushState {
+            buffer,
+            checkpoint,
+            open,
+            section,
+            stopped,
+            xml11,
+        } = &mut self.push;
+
+        let src = buffer.as_str();
+        let in_prolog = *section == Section::Prolog;
+        let mut tokenizer = if in_prolog {
+            Tokenizer::from(src)
+        } else {
+            Tokenizer::from_fragment(src, *checkpoint..src.len())
+        };
+
+        // Start tag being read; only sent once its attributes are all complete
+        let mut tag: Option<TagNode> = None;
+
+        loop {
+            let token = match tokenizer.next() {
+                Some(Ok(token)) => token,
+                Some(Err(e)) if finishing => bail!(src, XmlErrorKind::Xml(e)),
+
+                // An error is most likely a token cut off by the end of the chunk
+                None | Some(Err(_)) => break,
+            };
+
+            // A DTD that was already handled is still read, to move the tokenizer past its internal subset
+            let span = token.span();
+            let is_dtd_start = matches!(token, Token::DtdStart { .. });
+            if in_prolog && span.end() <= *checkpoint && !is_dtd_start {
+                continue;
+            }
+
+            // The last token may continue in the next chunk
+            if span.end() >= src.len() && !finishing {
+                break;
+            }
+
+            let (event, end) = match token {
+                Token::Declaration {
+                    version,
+                    encoding,
+                    standalone,
+                    span,
+                } => {
+                    *xml11 = version.as_str() == "1.1";
+                    let node = DeclarationNode::new(version, encoding, standalone).with_span(span);
+                    (Some(PushEvent::Declaration(node)), span.end())
+                }
+
+                Token::EmptyDtd { .. } | Token::DtdStart { .. } => {
+                    let node = match DtdNode::parse(token, &mut tokenizer, src) {
+                        Ok(node) => node,
+                        Err(e) if finishing => return Err(e),
+                        Err(_) => break,
+                    };
+
+                    let end = node.span().start() + node.span().len();
+                    if end <= *checkpoint {
+                        continue;
+                    }
+                    if end >= src.len() && !finishing {
+                        break;
+                    }
+                    (Some(PushEvent::DocumentType(node)), end)
+                }
+
+                Token::ElementStart {
+                    prefix,
+                    local,
+                    span,
+                } => {
+                    if *section == Section::Epilog {
+                        bail!(src, span, msg = "Unexpected {} in after root", token.name());
+                    }
+
+                    // The root is at depth 0, so the new tag is at the current number of open tags
+                    if let Some(max_depth) = max_depth
+                        && open.len() > max_depth
+                    {
+                        bail!(src, &span, XmlErrorKind::DepthLimitExceeded(max_depth));
+                    }
+
+                    tag = Some(TagNode::new(maybe_empty(prefix), local).with_span(span));
+                    continue;
+                }
+
+                Token::Attribute {
+                    prefix,
+                    local,
+                    value,
+                    span,
+                    ..
+                } => {
+                    let Some(tag) = tag.as_mut() else {
+                        bail!(src, span, msg = "Bug; Cannot apply attribute; no open tag!");
+                    };
+
+                    let attr =
+                        NodeAttribute::new(maybe_empty(prefix), local, value).with_span(span);
+                    if strict_chars {
+                        super::check_char_references(src, *attr.value(), *xml11)?;
+                    }
+                    tag.push_attribute(attr);
+                    continue;
+                }
+
+                Token::ElementEnd { end, span } => match end {
+                    ElementEnd::Open | ElementEnd::Empty => {
+                        let Some(tag) = tag.take() else {
+                            bail!(src, span, msg = "Bug; Cannot end start tag; no open tag!");
+                        };
+
+                        let name = tag.name().clone();
+                        open.push(name.to_string());
+                        *section = Section::Root;
+
+                        *checkpoint = span.end();
+                        if handler(PushEvent::StartTag(tag)) == ParseControl::Stop {
+                            *stopped = true;
+                            return Ok(ParseControl::Stop);
+                        }
+
+                        if end == ElementEnd::Empty {
+                            open.pop();
+                            if open.is_empty() {
+                                *section = Section::Epilog;
+                            }
+                            (Some(PushEvent::EndTag(name)), span.end())
+                        } else {
+                            (None, span.end())
+                        }
+                    }
+
+                    ElementEnd::Close(prefix, local) => {
+                        let name = NodeName::new(maybe_empty(prefix), local);
+                        match open.pop() {
+                            Some(open_name) if name == open_name.as_str() => {}
+                            Some(open_name) => {
+                                bail!(src, &span, XmlErrorKind::UnclosedTag(open_name));
+                            }
+                            None => bail!(src, span, msg = "Unexpected closing tag"),
+                        }
+
+                        if open.is_empty() {
+                            *section = Section::Epilog;
+                        }
+                        (Some(PushEvent::EndTag(name)), span.end())
+                    }
+                },
+
+                Token::Text { text } => {
+                    let start = text.start();
+                    let trimmed = src[start..text.end()].trim();
+                    if trimmed.is_empty() {
+                        (None, text.end())
+                    } else if open.is_empty() {
+                        bail!(src, text, msg = "Unexpected text outside of the root");
+                    } else {
+                        if strict_chars {
+                            super::check_char_references(
+                                src,
+                                StrSpan::new(trimmed, start),
+                                *xml11,
+                            )?;
+                        }
+                        let node = TextNode::new(text, StrSpan::new(trimmed, start));
+                        (Some(PushEvent::Text(node)), text.end())
+                    }
+                }
+
+                Token::Cdata { text, span } => (
+                    Some(PushEvent::Cdata(CdataNode::new(span, text))),
+                    span.end(),
+                ),
+
+                Token::Comment { text, span } => (
+                    Some(PushEvent::Comment(CommentNode::new(span, text))),
+                    span.end(),
+                ),
+
+                Token::ProcessingInstruction {
+                    target,
+                    content,
+                    span,
+                } => {
+                    let node = ProcessingInstructionNode::new(span, target, content);
+                    (Some(PushEvent::ProcessingInstruction(node)), span.end())
+                }
+
+                _ => {
+                    bail!(src, span, msg = "Unexpected {}", token.name());
+                }
+            };
+
+            *checkpoint = end;
+            if let Some(event) = event
+                && handler(event) == ParseControl::Stop
+            {
+                *stopped = true;
+                return Ok(ParseControl::Stop);
+            }
+        }
+
+        // The prolog is tokenized from the start every time, so it is kept until the root starts
+        drop(tag);
+        if *section != Section::Prolog {
+            buffer.drain(..*checkpoint);
+            *checkpoint = 0;
+        }
+
+        Ok(ParseControl::Continue)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feeds the document one byte at a time, and returns the events as strings
+    fn events(parser: &mut Parser, src: &str) -> XmlResult<Vec<String>> {
+        let mut events = vec![];
+        let mut handler = |event: PushEvent<'_>| {
+            events.push(match event {
+                PushEvent::Declaration(decl) => format!("decl {}", decl.version()),
+                PushEvent::DocumentType(dtd) => format!("dtd {}", dtd.name()),
+                PushEvent::StartTag(tag) => {
+                    format!("start {} {}", tag.name(), tag.attributes().len())
+                }
+                PushEvent::EndTag(name) => format!("end {name}"),
+                PushEvent::Text(text) => format!("text {}", text.text()),
+                PushEvent::Comment(comment) => format!("comment {}", comment.text()),
+                PushEvent::ProcessingInstruction(pi) => format!("pi {}", pi.target()),
+                PushEvent::Cdata(cdata) => format!("cdata {}", cdata.content()),
+            });
+            ParseControl::Continue
+        };
+
+        for i in 0..src.len() {
+            parser.feed(&src[i..=i], &mut handler)?;
+        }
+        parser.finish(&mut handler)?;
+        Ok(events)
+    }
+
+    #[test]
+    fn test_push_events() {
+        let src = "<?xml version=\"1.0\"?><!DOCTYPE a [<!ENTITY e \"x\">]><!--c--><a x=\"1\" y=\"2\"><b/>text<![CDATA[raw]]><?pi?></a>\n";
+        let events = events(&mut Parser::new(), src).unwrap();
+        assert_eq!(
+            events,
+            [
+                "decl 1.0",
+                "dtd a",
+                "comment c",
+                "start a 2",
+                "start b 0",
+                "end b",
+                "text text",
+                "cdata raw",
+                "pi pi",
+                "end a",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_push_parameter_entities() {
+        let src = "<!DOCTYPE a [<!ENTITY % p SYSTEM \"p.ent\"> %p;]><!--c--><a>text</a>";
+        let events = events(&mut Parser::new(), src).unwrap();
+        assert_eq!(
+            events,
+            ["dtd a", "comment c", "start a 0", "text text", "end a"]
+        );
+    }
+
+    #[test]
+    fn test_push_errors() {
+        let mut parser = Parser::new();
+        assert!(events(&mut parser, "<a><b></a>").is_err());
+        assert!(events(&mut parser, "<a><b>").is_err());
+        assert!(events(&mut parser, "<a/><b/>").is_err());
+        assert!(events(&mut parser, "<a/>").is_ok());
+
+        let mut parser = Parser::new().with_max_depth(1);
+        assert!(events(&mut parser, "<a><b><c/></b></a>").is_err());
+    }
+
+    #[test]
+    fn test_push_stop() {
+        let mut parser = Parser::new();
+        let mut seen = 0;
+        let mut handler = |_: PushEvent<'_>| {
+            seen += 1;
+            ParseControl::Stop
+        };
+
+        assert_eq!(
+            parser.feed("<a><b/>", &mut handler).unwrap(),
+            ParseControl::Stop
+        );
+        assert_eq!(
+            parser.feed("<c/></a>", &mut handler).unwrap(),
+            ParseControl::Stop
+        );
+        parser.finish(&mut handler).unwrap();
+        assert_eq!(seen, 1);
+
+        // Parser is reset by finish
+        assert!(parser.push.buffer.is_empty());
+    }
+
+    #[test]
+    fn test_push_discards_handled_text() {
+        let mut parser = Parser::new();
+        let mut tags = 0;
+        let mut handler = |event: PushEvent<'_>| {
+            if let PushEvent::StartTag(_) = event {
+                tags += 1;
+            }
+            ParseControl::Continue
+        };
+
+        parser.feed("<root>", &mut handler).unwrap();
+        for _ in 0..1000 {
+            parser.feed("<item>text</item>", &mut handler).unwrap();
+            assert!(parser.push.buffer.len() < 64);
+        }
+        parser.feed("</root>", &mut handler).unwrap();
+        parser.finish(&mut handler).unwrap();
+        assert_eq!(tags, 1001);
+    }
+}
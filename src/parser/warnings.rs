@@ -0,0 +1,107 @@
+use crate::{
+    StrSpan,
+    error::{ErrorContext, Severity, XmlError, XmlErrorKind},
+};
+
+/// Receives the warnings found while parsing. See [`Parser::with_warning_handler`](super::Parser::with_warning_handler).
+#[derive(Default)]
+pub(crate) struct WarningSink(Option<Box<dyn FnMut(XmlError) + Send>>);
+impl WarningSink {
+    pub fn new(handler: impl FnMut(XmlError) + Send + 'static) -> Self {
+        Self(Some(Box::new(handler)))
+    }
+
+    /// Returns true if warnings are being collected; checks that only produce warnings can be skipped otherwise.
+    pub fn is_active(&self) -> bool {
+        self.0.is_some()
+    }
+
+    /// Reports a warning at the given span.
+    pub fn warn(&mut self, src: &str, span: StrSpan, kind: XmlErrorKind) {
+        if let Some(handler) = &mut self.0 {
+            let warning = XmlError::new(kind, ErrorContext::new(src, span));
+            handler(warning.with_severity(Severity::Warning));
+        }
+    }
+
+    /// Reports a warning that points back at a related span.
+    pub fn warn_related(
+        &mut self,
+        src: &str,
+        span: StrSpan,
+        kind: XmlErrorKind,
+        related: StrSpan,
+        note: &str,
+    ) {
+        if let Some(handler) = &mut self.0 {
+            let warning =
+                XmlError::new(kind, ErrorContext::new(src, span)).with_related(src, related, note);
+            handler(warning.with_severity(Severity::Warning));
+        }
+    }
+
+    /// Reports entity references in raw text that are neither predefined, character references,
+    /// nor declared in `entities`.
+    pub fn check_references(&mut self, src: &str, text: StrSpan, entities: &[&str]) {
+        if !self.is_active() {
+            return;
+        }
+
+        for span in suspicious_references(text, entities) {
+            let kind = XmlErrorKind::SuspiciousEntity(span.text().to_string());
+            self.warn(src, span, kind);
+        }
+    }
+}
+
+/// Finds the entity references in raw text that are malformed, or neither predefined, character references,
+/// nor declared in `entities`. A lone `&` is returned by itself.
+pub(crate) fn suspicious_references<'a>(
+    text: StrSpan<'a>,
+    entities: &'a [&str],
+) -> impl Iterator<Item = StrSpan<'a>> {
+    let raw = text.text();
+    let mut offset = 0;
+    std::iter::from_fn(move || {
+        while let Some(i) = raw[offset..].find('&') {
+            let start = offset + i;
+            let rest = &raw[start + 1..];
+            let len = rest
+                .find(|c: char| c == ';' || c == '&' || c == '<' || c.is_whitespace())
+                .filter(|end| rest[*end..].starts_with(';'));
+            offset = start + 1;
+
+            let Some(len) = len else {
+                return Some(text.slice(start..start + 1));
+            };
+
+            offset = start + len + 2;
+            if !is_known_reference(&rest[..len], entities) {
+                return Some(text.slice(start..start + len + 2));
+            }
+        }
+        None
+    })
+}
+impl std::fmt::Debug for WarningSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("WarningSink")
+            .field(&self.0.as_ref().map(|_| "FnMut(XmlError)"))
+            .finish()
+    }
+}
+
+/// Returns true for predefined entities, well-formed character references, and declared entities.
+fn is_known_reference(name: &str, entities: &[&str]) -> bool {
+    if let Some(hex) = name.strip_prefix("#x") {
+        return u32::from_str_radix(hex, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .is_some();
+    }
+    if let Some(decimal) = name.strip_prefix('#') {
+        return decimal.parse().ok().and_then(char::from_u32).is_some();
+    }
+
+    matches!(name, "lt" | "gt" | "amp" | "apos" | "quot") || entities.contains(&name)
+}
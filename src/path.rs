@@ -0,0 +1,1072 @@
+use crate::{
+    Document, OwnedDocument, StrSpan,
+    node::{Node, NodeAttribute, OwnedNode, OwnedNodeAttribute, OwnedTagNode, TagNode},
+};
+use std::fmt::Write;
+
+/// The top-level section of a document that a [`NodePath`] starts in.
+///
+/// Sections are ordered as they appear in a document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum PathSection {
+    /// The prolog; the first index of the path is the position in [`Document::prolog`].
+    Prolog,
+
+    /// The root node; an empty path refers to the root node itself.
+    Root,
+
+    /// The epilog; the first index of the path is the position in [`Document::epilog`].
+    Epilog,
+}
+
+/// The address of a node in a document, as a sequence of child indices.
+///
+/// Paths are ordered in document order, so sorting a list of paths sorts the nodes they refer to.
+/// Since they contain no references, they can be used to address the same node in both a [`Document`]
+/// and the [`OwnedDocument`] created from it.
+///
+/// Paths display as their child indices, such as `/1/0` for the first child of the second child of the root,
+/// or `prolog/0` for the first node in the prolog. Use [`Document::describe_path`] for a name-based description.
+///
+/// # Example
+/// ```rust
+/// use xmltree::{Document, NodePath};
+///
+/// let doc = Document::parse_str("<a><b /><c>text</c></a>").unwrap();
+/// let path = NodePath::root().child(1);
+///
+/// let c = doc.resolve_tag(&path).unwrap();
+/// assert_eq!(c.name(), "c");
+/// assert_eq!(NodePath::of(&doc, c), Some(path.clone()));
+///
+/// let owned = doc.to_owned();
+/// assert_eq!(owned.resolve_tag(&path).unwrap().name, "c");
+/// assert_eq!(path.to_string(), "/1");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodePath {
+    section: PathSection,
+    indices: Vec<usize>,
+}
+impl NodePath {
+    /// Returns a path to the root node of a document.
+    #[must_use]
+    pub fn root() -> Self {
+        Self {
+            section: PathSection::Root,
+            indices: vec![],
+        }
+    }
+
+    /// Returns a path to the node at `index` in the prolog of a document.
+    #[must_use]
+    pub fn prolog(index: usize) -> Self {
+        Self {
+            section: PathSection::Prolog,
+            indices: vec![index],
+        }
+    }
+
+    /// Returns a path to the node at `index` in the epilog of a document.
+    #[must_use]
+    pub fn epilog(index: usize) -> Self {
+        Self {
+            section: PathSection::Epilog,
+            indices: vec![index],
+        }
+    }
+
+    /// Returns a path to the child at `index` of the node this path refers to.
+    #[must_use]
+    pub fn child(&self, index: usize) -> Self {
+        let mut path = self.clone();
+        path.push(index);
+        path
+    }
+
+    /// Descends into the child at `index` of the node this path refers to.
+    pub fn push(&mut self, index: usize) {
+        self.indices.push(index);
+    }
+
+    /// Returns the section of the document this path starts in.
+    #[must_use]
+    pub fn section(&self) -> PathSection {
+        self.section
+    }
+
+    /// Returns the child indices that make up this path.
+    #[must_use]
+    pub fn indices(&self) -> &[usize] {
+        &self.indices
+    }
+
+    /// Returns the path to the parent of this node, or `None` for the root and top-level prolog or epilog nodes.
+    #[must_use]
+    pub fn parent(&self) -> Option<Self> {
+        let min_len = usize::from(self.section != PathSection::Root);
+        if self.indices.len() <= min_len {
+            return None;
+        }
+
+        let mut path = self.clone();
+        path.indices.pop();
+        Some(path)
+    }
+
+    /// Returns the number of tags between the root and this node.
+    ///
+    /// The root, and top-level prolog or epilog nodes are at depth 0.
+    #[must_use]
+    pub fn depth(&self) -> usize {
+        match self.section {
+            PathSection::Root => self.indices.len(),
+            PathSection::Prolog | PathSection::Epilog => self.indices.len().saturating_sub(1),
+        }
+    }
+
+    /// Returns true if this path refers to `other`, or one of its ancestors.
+    #[must_use]
+    pub fn is_ancestor_of(&self, other: &NodePath) -> bool {
+        self.section == other.section && other.indices.starts_with(&self.indices)
+    }
+
+    /// Find the path of a node in a document, by identity.
+    ///
+    /// The node must be a reference into the document itself; equal nodes elsewhere will not be found.
+    #[must_use]
+    pub fn of<D: PathLookup<N>, N>(document: &D, node: &N) -> Option<Self> {
+        document.path_of(node)
+    }
+}
+impl std::fmt::Display for NodePath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.section {
+            PathSection::Prolog => write!(f, "prolog")?,
+            PathSection::Epilog => write!(f, "epilog")?,
+            PathSection::Root if self.indices.is_empty() => write!(f, "/")?,
+            PathSection::Root => {}
+        }
+
+        for index in &self.indices {
+            write!(f, "/{index}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A document in which the [`NodePath`] of a node of type `N` can be found.
+///
+/// See [`NodePath::of`].
+pub trait PathLookup<N> {
+    /// Find the path of a node in this document, by identity.
+    fn path_of(&self, node: &N) -> Option<NodePath>;
+}
+
+impl<'src> PathLookup<Node<'src>> for Document<'src> {
+    fn path_of(&self, node: &Node<'src>) -> Option<NodePath> {
+        if let Some(index) = self.prolog().iter().position(|n| std::ptr::eq(n, node)) {
+            return Some(NodePath::prolog(index));
+        }
+
+        if let Some(index) = self.epilog().iter().position(|n| std::ptr::eq(n, node)) {
+            return Some(NodePath::epilog(index));
+        }
+
+        let mut stack = vec![(NodePath::root(), self.root())];
+        while let Some((path, tag)) = stack.pop() {
+            for (index, child) in tag.children().iter().enumerate() {
+                if std::ptr::eq(child, node) {
+                    return Some(path.child(index));
+                }
+
+                if let Node::Child(child) = child {
+                    stack.push((path.child(index), child));
+                }
+            }
+        }
+
+        None
+    }
+}
+impl<'src> PathLookup<TagNode<'src>> for Document<'src> {
+    fn path_of(&self, node: &TagNode<'src>) -> Option<NodePath> {
+        let mut stack = vec![(NodePath::root(), self.root())];
+        while let Some((path, tag)) = stack.pop() {
+            if std::ptr::eq(tag, node) {
+                return Some(path);
+            }
+
+            for (index, child) in tag.children().iter().enumerate() {
+                if let Node::Child(child) = child {
+                    stack.push((path.child(index), child));
+                }
+            }
+        }
+
+        None
+    }
+}
+impl PathLookup<OwnedNode> for OwnedDocument {
+    fn path_of(&self, node: &OwnedNode) -> Option<NodePath> {
+        if let Some(index) = self.prolog.iter().position(|n| std::ptr::eq(n, node)) {
+            return Some(NodePath::prolog(index));
+        }
+
+        if let Some(index) = self.epilog.iter().position(|n| std::ptr::eq(n, node)) {
+            return Some(NodePath::epilog(index));
+        }
+
+        let mut stack = vec![(NodePath::root(), &self.root)];
+        while let Some((path, tag)) = stack.pop() {
+            for (index, child) in tag.children.iter().enumerate() {
+                if std::ptr::eq(child, node) {
+                    return Some(path.child(index));
+                }
+
+                if let OwnedNode::Tag(child) = child {
+                    stack.push((path.child(index), child));
+                }
+            }
+        }
+
+        None
+    }
+}
+impl PathLookup<OwnedTagNode> for OwnedDocument {
+    fn path_of(&self, node: &OwnedTagNode) -> Option<NodePath> {
+        let mut stack = vec![(NodePath::root(), &self.root)];
+        while let Some((path, tag)) = stack.pop() {
+            if std::ptr::eq(tag, node) {
+                return Some(path);
+            }
+
+            for (index, child) in tag.children.iter().enumerate() {
+                if let OwnedNode::Tag(child) = child {
+                    stack.push((path.child(index), child));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+impl<'src> Document<'src> {
+    /// Returns the node at the given path.
+    ///
+    /// The root node is not a [`Node`]; use [`Document::resolve_tag`] to resolve paths that may refer to it.
+    #[must_use]
+    pub fn resolve(&self, path: &NodePath) -> Option<&Node<'src>> {
+        let (first, rest) = path.indices().split_first()?;
+        let mut node = match path.section() {
+            PathSection::Prolog => self.prolog().get(*first)?,
+            PathSection::Root => self.root().children().get(*first)?,
+            PathSection::Epilog => self.epilog().get(*first)?,
+        };
+
+        for index in rest {
+            let Node::Child(tag) = node else {
+                return None;
+            };
+            node = tag.children().get(*index)?;
+        }
+
+        Some(node)
+    }
+
+    /// Returns the tag node at the given path, including the root node.
+    #[must_use]
+    pub fn resolve_tag(&self, path: &NodePath) -> Option<&TagNode<'src>> {
+        if path.section() == PathSection::Root && path.indices().is_empty() {
+            return Some(self.root());
+        }
+
+        match self.resolve(path)? {
+            Node::Child(tag) => Some(tag),
+            _ => None,
+        }
+    }
+
+    /// Describes the given path using node names, in an `XPath`-like syntax.
+    ///
+    /// Positions are 1-based, and only included when a node has siblings of the same name or kind.
+    ///
+    /// Returns `None` if the path does not resolve in this document.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{Document, NodePath};
+    ///
+    /// let doc = Document::parse_str("<a><b /><b>text</b></a>").unwrap();
+    /// let path = NodePath::root().child(1).child(0);
+    /// assert_eq!(doc.describe_path(&path).unwrap(), "/a/b[2]/text()");
+    /// ```
+    #[must_use]
+    pub fn describe_path(&self, path: &NodePath) -> Option<String> {
+        let (mut description, mut siblings) = match path.section() {
+            PathSection::Prolog => ("prolog".to_string(), self.prolog()),
+            PathSection::Root => (format!("/{}", self.root().name()), self.root().children()),
+            PathSection::Epilog => ("epilog".to_string(), self.epilog()),
+        };
+
+        for &index in path.indices() {
+            let node = siblings.get(index)?;
+            let step = step_name(node);
+
+            let same = |n: &&Node| step_name(n) == step;
+            let count = siblings.iter().filter(same).count();
+            description.push('/');
+            description.push_str(&step);
+            if count > 1 {
+                let position = siblings[..index].iter().filter(same).count() + 1;
+                let _ = write!(description, "[{position}]");
+            }
+
+            siblings = match node {
+                Node::Child(tag) => tag.children(),
+                _ => &[],
+            };
+        }
+
+        Some(description)
+    }
+
+    /// Finds the deepest node whose span contains the given byte offset in the source.
+    ///
+    /// Children are stored in source order, so each level of the tree is binary searched
+    /// instead of walking the whole document.
+    ///
+    /// Returns `None` if the offset is not inside any node, or if the document has no source
+    /// (for example, if it was decoded from a binary without a source header).
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{Document, NodePath};
+    /// let src = "<a><b>text</b><c /></a>";
+    ///
+    /// let doc = Document::parse_str(src).unwrap();
+    /// let path = doc.node_at_offset(src.find("text").unwrap()).unwrap();
+    /// assert_eq!(path, NodePath::root().child(0).child(0));
+    /// ```
+    #[must_use]
+    pub fn node_at_offset(&self, offset: usize) -> Option<NodePath> {
+        self.source()?;
+
+        if let Some(index) = find_containing(self.prolog(), offset) {
+            return Some(NodePath::prolog(index));
+        }
+
+        if let Some(index) = find_containing(self.epilog(), offset) {
+            return Some(NodePath::epilog(index));
+        }
+
+        if !span_contains(self.root().span(), offset) {
+            return None;
+        }
+
+        let mut path = NodePath::root();
+        let mut node = self.root();
+        while let Some(index) = find_containing(node.children(), offset) {
+            path.push(index);
+            match &node.children()[index] {
+                Node::Child(child) => node = child,
+                _ => break,
+            }
+        }
+
+        Some(path)
+    }
+
+    /// Clones the tag at the given path into a new document rooted at that tag.
+    ///
+    /// If `with_namespaces` is true, namespace declarations (`xmlns` and `xmlns:*` attributes) made by ancestors of the tag
+    /// are copied onto the new root, so that prefixed names in the subtree stay bound.
+    /// Declarations already made by the tag itself take precedence.
+    ///
+    /// Returns `None` if the path does not resolve to a tag in this document.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{Document, NodePath};
+    ///
+    /// let doc = Document::parse_str(r#"<feed xmlns:a="urn:a"><a:entry /></feed>"#).unwrap();
+    /// let entry = doc.extract_owned(&NodePath::root().child(0), true).unwrap();
+    ///
+    /// assert_eq!(entry.to_xml(None).unwrap(), "<a:entry xmlns:a=\"urn:a\" />\n");
+    /// ```
+    #[must_use]
+    pub fn extract_owned(&self, path: &NodePath, with_namespaces: bool) -> Option<OwnedDocument> {
+        let tag = self.resolve_tag(path)?;
+        let mut document = tag.extract_owned();
+        if !with_namespaces {
+            return Some(document);
+        }
+
+        let mut ancestors = vec![self.root()];
+        if let Some((_, indices)) = path.indices().split_last() {
+            for index in indices {
+                let Some(Node::Child(child)) = ancestors.last()?.children().get(*index) else {
+                    return None;
+                };
+                ancestors.push(child);
+            }
+        }
+
+        // Inner declarations replace outer ones with the same name
+        let mut declarations: Vec<&NodeAttribute<'src>> = vec![];
+        for ancestor in ancestors {
+            for attr in ancestor
+                .attributes()
+                .iter()
+                .filter(|a| is_namespace_declaration(a))
+            {
+                declarations.retain(|d| d.name() != attr.name());
+                declarations.push(attr);
+            }
+        }
+
+        let root = &mut document.root;
+        declarations.retain(|d| {
+            let name = d.name();
+            !tag.attributes().iter().any(|a| a.name() == name)
+        });
+        let inherited = declarations.into_iter().map(NodeAttribute::to_owned);
+        root.attributes.splice(0..0, inherited);
+
+        Some(document)
+    }
+
+    /// Returns the attribute with the given name on the node at `path`, or on its nearest ancestor that has it.
+    ///
+    /// This is how inherited attributes like `xml:lang` and `xml:space` apply to a node. For text and other non-tag nodes,
+    /// the search starts at the parent tag. Note that an empty value, such as `xml:lang=""`, is returned as-is,
+    /// even though it resets the inherited value.
+    ///
+    /// Returns `None` if no tag along the path has the attribute, if the path does not resolve in this document,
+    /// or if it refers to the prolog or epilog.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{Document, NodePath};
+    ///
+    /// let doc = Document::parse_str(r#"<doc xml:lang="en"><p xml:space="preserve">Hi <q xml:lang="fr">salut</q></p></doc>"#).unwrap();
+    /// let salut = NodePath::root().child(0).child(1).child(0);
+    /// let hi = NodePath::root().child(0).child(0);
+    ///
+    /// assert_eq!(doc.inherited_attribute(&salut, Some("xml"), "lang").unwrap().value().text(), "fr");
+    /// assert_eq!(doc.inherited_attribute(&hi, Some("xml"), "lang").unwrap().value().text(), "en");
+    /// assert_eq!(doc.inherited_attribute(&salut, Some("xml"), "space").unwrap().value().text(), "preserve");
+    /// assert!(doc.inherited_attribute(&NodePath::root(), Some("xml"), "space").is_none());
+    /// ```
+    #[must_use]
+    pub fn inherited_attribute(
+        &self,
+        path: &NodePath,
+        prefix: Option<&str>,
+        name: &str,
+    ) -> Option<&NodeAttribute<'src>> {
+        if path.section() != PathSection::Root {
+            return None;
+        }
+
+        let mut tags = vec![self.root()];
+        for (i, index) in path.indices().iter().enumerate() {
+            match tags.last()?.children().get(*index)? {
+                Node::Child(tag) => tags.push(tag),
+                _ if i + 1 == path.indices().len() => {}
+                _ => return None,
+            }
+        }
+
+        tags.iter()
+            .rev()
+            .find_map(|tag| tag.get_attribute(prefix, name))
+    }
+
+    /// Returns the tag at `path`, after its ancestors from the root down, or `None` if the path does not lead to a tag.
+    fn tag_with_ancestors(&self, path: &NodePath) -> Option<Vec<&TagNode<'src>>> {
+        if path.section() != PathSection::Root {
+            return None;
+        }
+
+        let mut tags = vec![self.root()];
+        for index in path.indices() {
+            match tags.last()?.children().get(*index)? {
+                Node::Child(tag) => tags.push(tag),
+                _ => return None,
+            }
+        }
+        Some(tags)
+    }
+
+    /// Get an attribute of the tag at `path` by namespace URI and local name, instead of by prefix.
+    ///
+    /// Unlike [`TagNode::get_attribute_ns`], prefixes are resolved using every `xmlns` declaration in scope,
+    /// including those made by ancestors of the tag. Unprefixed attributes are in no namespace, and are found with an empty `uri`.
+    ///
+    /// Returns `None` if no attribute matches, or if the path does not lead to a tag.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{Document, NodePath};
+    ///
+    /// let doc = Document::parse_str(r#"<a xmlns:x="urn:link"><b><c x:href="one" /></b></a>"#).unwrap();
+    /// let c = NodePath::root().child(0).child(0);
+    /// assert_eq!(doc.get_attribute_ns(&c, "urn:link", "href").unwrap().value().text(), "one");
+    /// assert!(doc.resolve_tag(&c).unwrap().get_attribute_ns("urn:link", "href").is_none());
+    /// ```
+    #[must_use]
+    pub fn get_attribute_ns(
+        &self,
+        path: &NodePath,
+        uri: &str,
+        local: &str,
+    ) -> Option<&NodeAttribute<'src>> {
+        let tags = self.tag_with_ancestors(path)?;
+        tags.last()?.attributes().iter().rev().find(|a| {
+            let name = a.name();
+            name.local().text() == local
+                && match name.prefix() {
+                    Some(prefix) => in_scope_namespace(&tags, prefix.text()) == Some(uri),
+                    None => uri.is_empty(),
+                }
+        })
+    }
+
+    /// Returns the child tags of the tag at `path` with the given namespace URI and local name, in order.
+    ///
+    /// Unlike [`TagNode::children_ns`], a child's prefix is resolved using every `xmlns` declaration in scope,
+    /// from the child itself up to the root. Unprefixed children without a default namespace are found with an empty `uri`.
+    ///
+    /// Finds nothing if the path does not lead to a tag.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{Document, NodePath};
+    ///
+    /// let doc = Document::parse_str(r#"<feed xmlns:m="urn:meta"><group><m:entry /><entry /></group></feed>"#).unwrap();
+    /// let group = NodePath::root().child(0);
+    /// assert_eq!(doc.children_ns(&group, "urn:meta", "entry").count(), 1);
+    /// assert_eq!(doc.children_ns(&group, "", "entry").count(), 1);
+    /// ```
+    pub fn children_ns<'a>(
+        &'a self,
+        path: &NodePath,
+        uri: &'a str,
+        local: &'a str,
+    ) -> impl Iterator<Item = &'a TagNode<'src>> + 'a {
+        let tags = self.tag_with_ancestors(path).unwrap_or_default();
+        let children = tags.last().map_or(&[][..], |tag| tag.children());
+        children.iter().filter_map(move |child| match child {
+            Node::Child(child) if child.name().local().text() == local => {
+                let prefix = child.name().prefix().map_or("", |p| p.text());
+                let namespace = child
+                    .declared_namespace(prefix)
+                    .or_else(|| in_scope_namespace(&tags, prefix))
+                    .or(prefix.is_empty().then_some(""));
+                (namespace == Some(uri)).then_some(child)
+            }
+            _ => None,
+        })
+    }
+
+    /// Returns the path and text of every text and CDATA node in document order, borrowed from the document.
+    ///
+    /// Useful for indexing a document for search in one pass. Text is as written in the source,
+    /// so references such as `&amp;` are not decoded; see [`StrSpan::decoded`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{Document, NodePath};
+    ///
+    /// let doc = Document::parse_str("<a><b>one</b><c><![CDATA[two]]><d /></c>three</a>").unwrap();
+    /// let chunks: Vec<_> = doc.text_chunks().collect();
+    /// assert_eq!(chunks, [
+    ///     (NodePath::root().child(0).child(0), "one"),
+    ///     (NodePath::root().child(1).child(0), "two"),
+    ///     (NodePath::root().child(2), "three"),
+    /// ]);
+    /// ```
+    #[must_use]
+    pub fn text_chunks(&self) -> TextChunks<'_, 'src> {
+        TextChunks {
+            path: NodePath::root(),
+            stack: vec![self.root().children().iter().enumerate()],
+        }
+    }
+}
+
+/// Iterator over the text and CDATA nodes of a document, with their paths.
+///
+/// Returned by [`Document::text_chunks`].
+#[derive(Debug, Clone)]
+pub struct TextChunks<'a, 'src> {
+    /// Path of the tag whose children are being walked
+    path: NodePath,
+
+    /// Sibling lists still being walked; the innermost is last, and each but the first is a step of the path
+    stack: Vec<std::iter::Enumerate<std::slice::Iter<'a, Node<'src>>>>,
+}
+impl<'src> Iterator for TextChunks<'_, 'src> {
+    type Item = (NodePath, &'src str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let Some((index, node)) = self.stack.last_mut()?.next() else {
+                self.stack.pop();
+                self.path.indices.pop();
+                continue;
+            };
+
+            match node {
+                Node::Child(tag) => {
+                    self.path.push(index);
+                    self.stack.push(tag.children().iter().enumerate());
+                }
+                Node::Text(text) => return Some((self.path.child(index), text.text().text())),
+                Node::Cdata(cdata) => {
+                    return Some((self.path.child(index), cdata.content().text()));
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Returns the namespace URI bound to `prefix` by the innermost declaration on the given tags, listed from the root down.
+fn in_scope_namespace<'src>(tags: &[&TagNode<'src>], prefix: &str) -> Option<&'src str> {
+    tags.iter()
+        .rev()
+        .find_map(|tag| tag.declared_namespace(prefix))
+}
+
+/// Returns true if the attribute declares a namespace, such as `xmlns="..."` or `xmlns:prefix="..."`.
+fn is_namespace_declaration(attr: &NodeAttribute<'_>) -> bool {
+    match attr.name().prefix() {
+        Some(prefix) => prefix.text() == "xmlns",
+        None => attr.name().local().text() == "xmlns",
+    }
+}
+
+impl OwnedDocument {
+    /// Returns the node at the given path.
+    ///
+    /// The root node is not an [`OwnedNode`]; use [`OwnedDocument::resolve_tag`] to resolve paths that may refer to it.
+    #[must_use]
+    pub fn resolve(&self, path: &NodePath) -> Option<&OwnedNode> {
+        let (first, rest) = path.indices().split_first()?;
+        let mut node = match path.section() {
+            PathSection::Prolog => self.prolog.get(*first)?,
+            PathSection::Root => self.root.children.get(*first)?,
+            PathSection::Epilog => self.epilog.get(*first)?,
+        };
+
+        for index in rest {
+            let OwnedNode::Tag(tag) = node else {
+                return None;
+            };
+            node = tag.children.get(*index)?;
+        }
+
+        Some(node)
+    }
+
+    /// Returns a mutable reference to the node at the given path.
+    ///
+    /// See [`OwnedDocument::resolve`].
+    #[must_use]
+    pub fn resolve_mut(&mut self, path: &NodePath) -> Option<&mut OwnedNode> {
+        let (first, rest) = path.indices().split_first()?;
+        let mut node = match path.section() {
+            PathSection::Prolog => self.prolog.get_mut(*first)?,
+            PathSection::Root => self.root.children.get_mut(*first)?,
+            PathSection::Epilog => self.epilog.get_mut(*first)?,
+        };
+
+        for index in rest {
+            let OwnedNode::Tag(tag) = node else {
+                return None;
+            };
+            node = tag.children.get_mut(*index)?;
+        }
+
+        Some(node)
+    }
+
+    /// Returns the tag node at the given path, including the root node.
+    #[must_use]
+    pub fn resolve_tag(&self, path: &NodePath) -> Option<&OwnedTagNode> {
+        if path.section() == PathSection::Root && path.indices().is_empty() {
+            return Some(&self.root);
+        }
+
+        match self.resolve(path)? {
+            OwnedNode::Tag(tag) => Some(tag),
+            _ => None,
+        }
+    }
+
+    /// Returns a mutable reference to the tag node at the given path, including the root node.
+    #[must_use]
+    pub fn resolve_tag_mut(&mut self, path: &NodePath) -> Option<&mut OwnedTagNode> {
+        if path.section() == PathSection::Root && path.indices().is_empty() {
+            return Some(&mut self.root);
+        }
+
+        match self.resolve_mut(path)? {
+            OwnedNode::Tag(tag) => Some(tag),
+            _ => None,
+        }
+    }
+
+    /// Returns the attribute with the given name on the node at `path`, or on its nearest ancestor that has it.
+    ///
+    /// See [`Document::inherited_attribute`].
+    #[must_use]
+    pub fn inherited_attribute(
+        &self,
+        path: &NodePath,
+        prefix: Option<&str>,
+        name: &str,
+    ) -> Option<&OwnedNodeAttribute> {
+        if path.section() != PathSection::Root {
+            return None;
+        }
+
+        let mut tags = vec![&self.root];
+        for (i, index) in path.indices().iter().enumerate() {
+            match tags.last()?.children.get(*index)? {
+                OwnedNode::Tag(tag) => tags.push(tag),
+                _ if i + 1 == path.indices().len() => {}
+                _ => return None,
+            }
+        }
+
+        tags.iter()
+            .rev()
+            .find_map(|tag| tag.get_attribute(prefix, name))
+    }
+
+    /// Describes the given path using node names, in an `XPath`-like syntax.
+    ///
+    /// See [`Document::describe_path`].
+    #[must_use]
+    pub fn describe_path(&self, path: &NodePath) -> Option<String> {
+        self.borrowed().describe_path(path)
+    }
+}
+
+fn step_name(node: &Node<'_>) -> String {
+    match node {
+        Node::Child(tag) => tag.name().to_string(),
+        Node::Text(_) | Node::Cdata(_) => "text()".to_string(),
+        Node::Comment(_) => "comment()".to_string(),
+        Node::ProcessingInstruction(_) => "processing-instruction()".to_string(),
+        Node::DocumentType(_) => "doctype()".to_string(),
+    }
+}
+
+fn span_contains(span: &StrSpan<'_>, offset: usize) -> bool {
+    span.start() <= offset && offset < span.start() + span.len()
+}
+
+fn find_containing(nodes: &[Node<'_>], offset: usize) -> Option<usize> {
+    let index = nodes.partition_point(|n| n.span().start() + n.span().len() <= offset);
+    let node = nodes.get(index)?;
+    span_contains(node.span(), offset).then_some(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SRC: &str = "<!-- head --><root a=\"1\"><one>text</one><two><three /></two></root><?pi?>";
+
+    #[test]
+    fn test_node_at_offset_nested() {
+        let doc = Document::parse_str(SRC).unwrap();
+
+        let offset = SRC.find("<three").unwrap() + 2;
+        let path = doc.node_at_offset(offset).unwrap();
+        assert_eq!(path, NodePath::root().child(1).child(0));
+
+        let offset = SRC.find("text").unwrap();
+        let path = doc.node_at_offset(offset).unwrap();
+        assert_eq!(path, NodePath::root().child(0).child(0));
+    }
+
+    #[test]
+    fn test_inherited_attribute() {
+        let src = r#"<a xml:lang="en"><b xml:lang=""><c>text</c></b><!-- x --></a>"#;
+        let doc = Document::parse_str(src).unwrap();
+        let owned = doc.to_owned();
+
+        let text = NodePath::root().child(0).child(0).child(0);
+        let lang = doc.inherited_attribute(&text, Some("xml"), "lang").unwrap();
+        assert_eq!(lang.value().text(), "");
+        let lang = owned
+            .inherited_attribute(&text, Some("xml"), "lang")
+            .unwrap();
+        assert_eq!(lang.value, "");
+
+        let comment = NodePath::root().child(1);
+        let lang = owned
+            .inherited_attribute(&comment, Some("xml"), "lang")
+            .unwrap();
+        assert_eq!(lang.value, "en");
+
+        let beyond = comment.child(0);
+        assert!(
+            doc.inherited_attribute(&beyond, Some("xml"), "lang")
+                .is_none()
+        );
+        assert!(
+            owned
+                .inherited_attribute(&beyond, Some("xml"), "lang")
+                .is_none()
+        );
+        assert!(
+            doc.inherited_attribute(&NodePath::root().child(5), Some("xml"), "lang")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_namespaces_in_scope() {
+        let src = concat!(
+            r#"<a xmlns:x="urn:x" xmlns="urn:d"><b xmlns:y="urn:y">"#,
+            r#"<c x:k="1" y:k="2" k="3"><x:d /><d /><y:d xmlns:y="urn:other" /><d xmlns="" /></c>"#,
+            "</b><e /></a>",
+        );
+        let doc = Document::parse_str(src).unwrap();
+        let c = NodePath::root().child(0).child(0);
+
+        let value = |uri| doc.get_attribute_ns(&c, uri, "k").map(|a| a.value().text());
+        assert_eq!(value("urn:x"), Some("1"));
+        assert_eq!(value("urn:y"), Some("2"));
+        assert_eq!(value(""), Some("3"));
+        assert_eq!(value("urn:d"), None);
+
+        let count = |uri| doc.children_ns(&c, uri, "d").count();
+        assert_eq!(count("urn:x"), 1);
+        assert_eq!(count("urn:d"), 1);
+        assert_eq!(count("urn:other"), 1);
+        assert_eq!(count("urn:y"), 0);
+        assert_eq!(count(""), 1);
+
+        // Declarations on descendants are not in scope, and paths must lead to tags
+        let d = c.child(0);
+        assert!(doc.get_attribute_ns(&d, "urn:y", "k").is_none());
+        assert!(
+            doc.get_attribute_ns(&NodePath::root().child(1), "", "k")
+                .is_none()
+        );
+        assert!(
+            doc.get_attribute_ns(&NodePath::root().child(2), "", "k")
+                .is_none()
+        );
+        assert_eq!(doc.children_ns(&NodePath::prolog(0), "", "d").count(), 0);
+    }
+
+    #[test]
+    fn test_node_at_offset_root() {
+        let doc = Document::parse_str(SRC).unwrap();
+
+        let offset = SRC.find("a=").unwrap();
+        assert_eq!(doc.node_at_offset(offset), Some(NodePath::root()));
+    }
+
+    #[test]
+    fn test_node_at_offset_prolog_epilog() {
+        let doc = Document::parse_str(SRC).unwrap();
+
+        let offset = SRC.find("head").unwrap();
+        assert_eq!(doc.node_at_offset(offset), Some(NodePath::prolog(0)));
+
+        let offset = SRC.find("pi").unwrap();
+        assert_eq!(doc.node_at_offset(offset), Some(NodePath::epilog(0)));
+
+        assert_eq!(doc.node_at_offset(SRC.len()), None);
+    }
+
+    #[test]
+    fn test_node_at_offset_unsourced() {
+        let doc = Document::parse_str(SRC).unwrap().to_owned();
+        assert_eq!(doc.borrowed().node_at_offset(0), None);
+    }
+
+    #[test]
+    fn test_path_ordering() {
+        let mut paths = vec![
+            NodePath::epilog(0),
+            NodePath::root().child(1),
+            NodePath::root(),
+            NodePath::prolog(2),
+            NodePath::root().child(0).child(3),
+        ];
+        paths.sort();
+
+        assert_eq!(
+            paths,
+            vec![
+                NodePath::prolog(2),
+                NodePath::root(),
+                NodePath::root().child(0).child(3),
+                NodePath::root().child(1),
+                NodePath::epilog(0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve() {
+        let doc = Document::parse_str(SRC).unwrap();
+
+        let node = doc.resolve(&NodePath::root().child(0).child(0)).unwrap();
+        assert!(matches!(node, Node::Text(t) if t.text() == "text"));
+
+        let tag = doc
+            .resolve_tag(&NodePath::root().child(1).child(0))
+            .unwrap();
+        assert_eq!(tag.name(), "three");
+
+        assert!(matches!(
+            doc.resolve(&NodePath::prolog(0)),
+            Some(Node::Comment(_))
+        ));
+        assert!(doc.resolve(&NodePath::root()).is_none());
+        assert!(doc.resolve(&NodePath::root().child(5)).is_none());
+        assert!(
+            doc.resolve(&NodePath::root().child(0).child(0).child(0))
+                .is_none()
+        );
+        assert_eq!(doc.resolve_tag(&NodePath::root()).unwrap().name(), "root");
+    }
+
+    #[test]
+    fn test_resolve_owned() {
+        let mut doc = Document::parse_str(SRC).unwrap().to_owned();
+        let path = NodePath::root().child(1).child(0);
+
+        doc.resolve_tag_mut(&path).unwrap().name = "four".into();
+        assert_eq!(doc.resolve_tag(&path).unwrap().name, "four");
+        assert!(matches!(
+            doc.resolve(&NodePath::epilog(0)),
+            Some(OwnedNode::ProcessingInstruction(_))
+        ));
+    }
+
+    #[test]
+    fn test_path_of() {
+        let doc = Document::parse_str(SRC).unwrap();
+        for path in [
+            NodePath::prolog(0),
+            NodePath::root().child(0).child(0),
+            NodePath::root().child(1).child(0),
+            NodePath::epilog(0),
+        ] {
+            let node = doc.resolve(&path).unwrap();
+            assert_eq!(NodePath::of(&doc, node), Some(path));
+        }
+
+        assert_eq!(NodePath::of(&doc, doc.root()), Some(NodePath::root()));
+
+        let other = Document::parse_str(SRC).unwrap();
+        assert_eq!(NodePath::of(&doc, other.root()), None);
+
+        let owned = doc.to_owned();
+        let path = NodePath::root().child(1).child(0);
+        let node = owned.resolve_tag(&path).unwrap();
+        assert_eq!(NodePath::of(&owned, node), Some(path));
+    }
+
+    #[test]
+    fn test_path_helpers() {
+        let path = NodePath::root().child(1).child(0);
+        assert_eq!(path.to_string(), "/1/0");
+        assert_eq!(path.depth(), 2);
+        assert_eq!(path.parent(), Some(NodePath::root().child(1)));
+        assert!(NodePath::root().is_ancestor_of(&path));
+        assert!(!path.is_ancestor_of(&NodePath::root()));
+
+        assert_eq!(NodePath::root().to_string(), "/");
+        assert_eq!(NodePath::root().parent(), None);
+        assert_eq!(NodePath::prolog(2).to_string(), "prolog/2");
+        assert_eq!(NodePath::prolog(2).parent(), None);
+        assert_eq!(NodePath::epilog(0).depth(), 0);
+    }
+
+    #[test]
+    fn test_describe_path() {
+        let doc = Document::parse_str(SRC).unwrap();
+        let describe = |path: NodePath| doc.describe_path(&path).unwrap();
+
+        assert_eq!(describe(NodePath::root()), "/root");
+        assert_eq!(
+            describe(NodePath::root().child(1).child(0)),
+            "/root/two/three"
+        );
+        assert_eq!(
+            describe(NodePath::root().child(0).child(0)),
+            "/root/one/text()"
+        );
+        assert_eq!(describe(NodePath::prolog(0)), "prolog/comment()");
+        assert!(doc.describe_path(&NodePath::root().child(9)).is_none());
+    }
+
+    #[test]
+    fn test_extract_owned() {
+        let src = r#"<feed xmlns="urn:feed" xmlns:a="urn:a"><group xmlns:a="urn:b"><a:entry id="1" xmlns="urn:entry"><a:title>t</a:title></a:entry></group></feed>"#;
+        let doc = Document::parse_str(src).unwrap();
+        let path = NodePath::root().child(0).child(0);
+
+        let plain = doc.extract_owned(&path, false).unwrap();
+        assert_eq!(plain.root.attributes.len(), 2);
+        assert_eq!(plain, doc.resolve_tag(&path).unwrap().extract_owned());
+
+        let scoped = doc.extract_owned(&path, true).unwrap();
+        let attributes: Vec<_> = scoped
+            .root
+            .attributes
+            .iter()
+            .map(|a| (a.name.to_string(), a.value.as_str()))
+            .collect();
+        assert_eq!(
+            attributes,
+            [
+                ("xmlns:a".to_string(), "urn:b"),
+                ("id".to_string(), "1"),
+                ("xmlns".to_string(), "urn:entry"),
+            ]
+        );
+
+        let root = doc.extract_owned(&NodePath::root(), true).unwrap();
+        assert_eq!(root, doc.to_owned());
+        assert!(
+            doc.extract_owned(&NodePath::root().child(3), true)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_text_chunks() {
+        let doc = Document::parse_str(
+            "<!-- x --><a>one<b><c>two</c><![CDATA[<three>]]></b><!-- y --><d />four &amp; five</a>",
+        )
+        .unwrap();
+
+        let chunks: Vec<_> = doc.text_chunks().collect();
+        for (path, text) in &chunks {
+            let node = doc.resolve(path).unwrap();
+            assert!(node.span().text().contains(text));
+        }
+        let texts: Vec<_> = chunks.iter().map(|(_, text)| *text).collect();
+        assert_eq!(texts, ["one", "two", "<three>", "four &amp; five"]);
+        assert_eq!(chunks[1].0, NodePath::root().child(1).child(0).child(0));
+        assert_eq!(chunks[3].0, NodePath::root().child(4));
+
+        let doc = Document::parse_str("<a><b /></a>").unwrap();
+        assert_eq!(doc.text_chunks().count(), 0);
+    }
+}
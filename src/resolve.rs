@@ -0,0 +1,191 @@
+//! Resolution of external resources referenced by documents.
+//!
+//! Every subsystem that loads external content goes through a [`UriResolver`], so that an application can enforce
+//! a single policy for what a document is allowed to load. That covers external identifiers in a DTD
+//! ([`ExternalId::load`](crate::node::ExternalId::load)), stylesheets ([`Stylesheet::load`](crate::Stylesheet::load))
+//! and schemas ([`SchemaHint::load`](crate::SchemaHint::load)).
+//!
+//! Two resolvers are provided:
+//! - [`FileResolver`] loads local files, optionally confined to a directory
+//! - [`DenyResolver`] refuses every request
+use crate::{
+    StrSpan,
+    error::{ErrorContext, XmlError, XmlErrorKind, XmlResult},
+};
+use std::path::{Path, PathBuf};
+
+/// Loads the content of external resources on behalf of a document.
+pub trait UriResolver: Send + Sync {
+    /// Fetches the resource at `uri`.
+    ///
+    /// `base` is the URI of the referencing document, if known, against which relative URIs are resolved.
+    ///
+    /// # Errors
+    /// Should return [`XmlErrorKind::ExternalAccessDenied`] if the resource is not allowed by the resolver's policy,
+    /// or another error if it could not be loaded.
+    fn resolve(&self, uri: &str, base: Option<&str>) -> XmlResult<Vec<u8>>;
+}
+
+/// A resolver that refuses to load any external resource.
+///
+/// Use this when parsing untrusted documents.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DenyResolver;
+impl UriResolver for DenyResolver {
+    fn resolve(&self, uri: &str, _base: Option<&str>) -> XmlResult<Vec<u8>> {
+        Err(denied(uri))
+    }
+}
+
+/// A resolver that loads resources from the local filesystem.
+///
+/// Accepts plain paths and `file:` URIs; any other scheme is refused.
+/// Relative URIs are resolved against the directory of the base document, or the current directory if there is none.
+///
+/// If a root directory is set, any resource outside of it is refused.
+#[derive(Debug, Clone, Default)]
+pub struct FileResolver {
+    root: Option<PathBuf>,
+}
+impl FileResolver {
+    /// Creates a resolver that can load any local file.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Confines the resolver to files within the given directory.
+    #[must_use]
+    pub fn with_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.root = Some(root.into());
+        self
+    }
+
+    /// Returns the directory the resolver is confined to, if any.
+    #[must_use]
+    pub fn root(&self) -> Option<&Path> {
+        self.root.as_deref()
+    }
+
+    /// Returns the local path a URI refers to, without checking the root.
+    fn path_of(uri: &str, base: Option<&str>) -> Option<PathBuf> {
+        let path = match uri.split_once(':') {
+            Some(("file", rest)) => Path::new(rest.trim_start_matches("//")).to_path_buf(),
+
+            // A single letter before the colon is a windows drive, not a scheme
+            Some((scheme, _)) if scheme.len() > 1 => return None,
+            _ => PathBuf::from(uri),
+        };
+
+        if path.is_absolute() {
+            return Some(path);
+        }
+
+        let base = base.and_then(|base| Self::path_of(base, None));
+        let dir = base
+            .as_deref()
+            .and_then(Path::parent)
+            .unwrap_or(Path::new(""));
+        Some(dir.join(path))
+    }
+}
+impl UriResolver for FileResolver {
+    fn resolve(&self, uri: &str, base: Option<&str>) -> XmlResult<Vec<u8>> {
+        let Some(path) = Self::path_of(uri, base) else {
+            return Err(denied(uri));
+        };
+
+        if let Some(root) = &self.root {
+            let root = root.canonicalize().map_err(XmlError::from)?;
+            let path = path
+                .canonicalize()
+                .map_err(|e| XmlError::from(e).with_path(path.clone()))?;
+            if !path.starts_with(root) {
+                return Err(denied(uri));
+            }
+        }
+
+        std::fs::read(&path).map_err(|e| XmlError::from(e).with_path(path))
+    }
+}
+
+fn denied(uri: &str) -> XmlError {
+    XmlError::new(
+        XmlErrorKind::ExternalAccessDenied(uri.to_string()),
+        ErrorContext::new("", StrSpan::default()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Document, node::Node};
+
+    #[test]
+    fn test_deny_resolver() {
+        let result = DenyResolver.resolve("file:///etc/passwd", None);
+        assert!(matches!(
+            result.unwrap_err().kind,
+            XmlErrorKind::ExternalAccessDenied(_)
+        ));
+    }
+
+    #[test]
+    fn test_file_resolver() {
+        let resolver = FileResolver::new().with_root("tests");
+        let data = resolver.resolve("good.xml", Some("tests/good.rs")).unwrap();
+        assert_eq!(data, std::fs::read("tests/good.xml").unwrap());
+
+        let data = resolver.resolve("file:tests/good.xml", None).unwrap();
+        assert_eq!(data, std::fs::read("tests/good.xml").unwrap());
+
+        let result = resolver.resolve("Cargo.toml", None);
+        assert!(matches!(
+            result.unwrap_err().kind,
+            XmlErrorKind::ExternalAccessDenied(_)
+        ));
+
+        let result = resolver.resolve("http://example.com/a.dtd", None);
+        assert!(matches!(
+            result.unwrap_err().kind,
+            XmlErrorKind::ExternalAccessDenied(_)
+        ));
+    }
+
+    #[test]
+    fn test_external_id_load() {
+        let src = r#"<!DOCTYPE test SYSTEM "good.xml"><test/>"#;
+        let doc = Document::parse_str(src).unwrap();
+        let Some(Node::DocumentType(dtd)) = doc.prolog().first() else {
+            panic!("Expected DTD");
+        };
+
+        let external_id = dtd.external_id().unwrap();
+        assert_eq!(external_id.system().text(), "good.xml");
+        assert!(external_id.load(&DenyResolver, None).is_err());
+        assert!(
+            external_id
+                .load(&FileResolver::new(), Some("tests/test.xml"))
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_hint_load() {
+        let src = r#"<?xml-stylesheet href="good.xml"?><?xml-model href="../Cargo.toml"?><test/>"#;
+        let doc = Document::parse_str(src).unwrap();
+        let resolver = FileResolver::new().with_root("tests");
+
+        let stylesheet = &doc.stylesheets()[0];
+        assert!(stylesheet.load(&DenyResolver, None).is_err());
+        let data = stylesheet.load(&resolver, Some("tests/test.xml")).unwrap();
+        assert_eq!(data, std::fs::read("tests/good.xml").unwrap());
+
+        let schema = &doc.schema_hints()[0];
+        let result = schema.load(&resolver, Some("tests/test.xml"));
+        assert!(matches!(
+            result.unwrap_err().kind,
+            XmlErrorKind::ExternalAccessDenied(_)
+        ));
+    }
+}
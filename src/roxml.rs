@@ -0,0 +1,217 @@
+//! Conversions to and from `roxmltree` documents.
+//!
+//! `roxmltree` resolves namespaces rather than keeping prefixes, so prefixes and `xmlns` declarations
+//! are rebuilt from the namespaces in scope of each element. It also merges CDATA sections into text,
+//! and does not keep the XML declaration or the DTD.
+use crate::{
+    Document, OwnedDocument, StrSpan, XmlWriteOptions,
+    error::{ErrorContext, XmlError, XmlErrorKind, XmlResult},
+    node::{
+        OwnedCommentNode, OwnedNode, OwnedNodeAttribute, OwnedNodeName,
+        OwnedProcessingInstructionNode, OwnedTagNode, OwnedTextNode,
+    },
+};
+use roxmltree::NodeType;
+
+impl From<&roxmltree::Document<'_>> for OwnedDocument {
+    /// Converts a `roxmltree` document into an owned document.
+    ///
+    /// Text is trimmed, and whitespace-only text is dropped, as it is when parsing.
+    fn from(document: &roxmltree::Document<'_>) -> Self {
+        let root = document.root_element();
+        let prolog = document
+            .root()
+            .children()
+            .take_while(|node| !node.is_element())
+            .filter_map(convert_node)
+            .collect();
+        let epilog = root
+            .next_siblings()
+            .skip(1)
+            .filter_map(convert_node)
+            .collect();
+
+        let mut owned = OwnedDocument::new(convert_tag(root));
+        owned.prolog = prolog;
+        owned.epilog = epilog;
+        owned
+    }
+}
+
+impl From<roxmltree::Document<'_>> for OwnedDocument {
+    fn from(document: roxmltree::Document<'_>) -> Self {
+        Self::from(&document)
+    }
+}
+
+impl Document<'_> {
+    /// Converts this document into a `roxmltree` document, for use with tooling built on it.
+    ///
+    /// The document is written into `buffer`, which the returned document borrows from.
+    /// `roxmltree` merges CDATA sections into text, and does not keep the XML declaration or the DTD.
+    ///
+    /// # Errors
+    /// Returns errors if the document cannot be written, or if `roxmltree` rejects it
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::Document;
+    ///
+    /// let doc = Document::parse_str("<a xmlns:b='urn:b'><b:c>text</b:c></a>").unwrap();
+    /// let mut buffer = String::new();
+    /// let rox = doc.to_roxmltree(&mut buffer).unwrap();
+    ///
+    /// let c = rox.root_element().first_element_child().unwrap();
+    /// assert!(c.has_tag_name(("urn:b", "c")));
+    /// assert_eq!(c.text(), Some("text"));
+    /// ```
+    pub fn to_roxmltree<'a>(&self, buffer: &'a mut String) -> XmlResult<roxmltree::Document<'a>> {
+        *buffer = self.to_xml_with_options(&XmlWriteOptions::default().with_minify(true))?;
+        parse_roxmltree(buffer)
+    }
+}
+
+impl OwnedDocument {
+    /// Converts this document into a `roxmltree` document. See [`Document::to_roxmltree`].
+    ///
+    /// # Errors
+    /// Returns errors if the document cannot be written, or if `roxmltree` rejects it
+    pub fn to_roxmltree<'a>(&self, buffer: &'a mut String) -> XmlResult<roxmltree::Document<'a>> {
+        *buffer = self.to_xml_with_options(&XmlWriteOptions::default().with_minify(true))?;
+        parse_roxmltree(buffer)
+    }
+}
+
+fn parse_roxmltree(src: &str) -> XmlResult<roxmltree::Document<'_>> {
+    let options = roxmltree::ParsingOptions {
+        allow_dtd: true,
+        ..roxmltree::ParsingOptions::default()
+    };
+
+    roxmltree::Document::parse_with_options(src, options).map_err(|e| {
+        XmlError::new(
+            XmlErrorKind::Roxmltree(e),
+            ErrorContext::new(src, StrSpan::default()),
+        )
+    })
+}
+
+fn convert_node(node: roxmltree::Node<'_, '_>) -> Option<OwnedNode> {
+    match node.node_type() {
+        NodeType::Element => Some(OwnedNode::Tag(convert_tag(node))),
+        NodeType::Text => {
+            let text = node.text()?.trim();
+            (!text.is_empty()).then(|| OwnedNode::Text(OwnedTextNode::new(text)))
+        }
+        NodeType::Comment => node
+            .text()
+            .map(|text| OwnedNode::Comment(OwnedCommentNode::new(text))),
+        NodeType::PI => node.pi().map(|pi| {
+            OwnedNode::ProcessingInstruction(OwnedProcessingInstructionNode::new(
+                pi.target.to_string(),
+                pi.value.map(str::to_string),
+            ))
+        }),
+        NodeType::Root => None,
+    }
+}
+
+fn convert_tag(node: roxmltree::Node<'_, '_>) -> OwnedTagNode {
+    let name = node.tag_name();
+    let prefix = name.namespace().and_then(|uri| node.lookup_prefix(uri));
+    let mut tag = OwnedTagNode::new(OwnedNodeName::new(prefix, name.name()));
+
+    // Namespaces in scope that the parent does not have were declared on this element
+    let parent = node.parent_element();
+    for namespace in node.namespaces() {
+        let inherited = parent.is_some_and(|parent| {
+            parent
+                .namespaces()
+                .any(|ns| ns.name() == namespace.name() && ns.uri() == namespace.uri())
+        });
+        if inherited || namespace.name() == Some("xml") {
+            continue;
+        }
+
+        let name = match namespace.name() {
+            Some(prefix) => OwnedNodeName::new(Some("xmlns"), prefix),
+            None => OwnedNodeName::new(None, "xmlns"),
+        };
+        tag.attributes
+            .push(OwnedNodeAttribute::new(name, namespace.uri()));
+    }
+
+    for attribute in node.attributes() {
+        let prefix = attribute
+            .namespace()
+            .and_then(|uri| node.lookup_prefix(uri));
+        let name = OwnedNodeName::new(prefix, attribute.name());
+        tag.attributes
+            .push(OwnedNodeAttribute::new(name, attribute.value()));
+    }
+
+    tag.children = node.children().filter_map(convert_node).collect();
+    tag
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roxmltree_round_trip() {
+        const SRC: &str = "<!-- before --><a:root xmlns:a='urn:a' xmlns='urn:default' a:x='1' y='&amp;'>\
+            <child xmlns:b='urn:b' b:z='2'>  text &lt; </child><![CDATA[data]]><?pi value?></a:root><!-- after -->";
+
+        let rox = roxmltree::Document::parse(SRC).unwrap();
+        assert_eq!(rox.root_element().attribute("y"), Some("&"));
+
+        let owned = OwnedDocument::from(&rox);
+        assert_eq!(
+            owned.prolog,
+            vec![OwnedNode::Comment(OwnedCommentNode::new(" before "))]
+        );
+        assert_eq!(
+            owned.epilog,
+            vec![OwnedNode::Comment(OwnedCommentNode::new(" after "))]
+        );
+        assert!(owned.root.name.equals(Some("a"), "root"));
+        assert_eq!(owned.root.attributes.len(), 4);
+        assert_eq!(owned.root.get_attribute(Some("a"), "x").unwrap().value, "1");
+
+        let OwnedNode::Tag(child) = &owned.root.children[0] else {
+            panic!("expected a tag");
+        };
+        assert_eq!(
+            child.get_attribute(Some("xmlns"), "b").unwrap().value,
+            "urn:b"
+        );
+        assert_eq!(child.get_attribute(Some("b"), "z").unwrap().value, "2");
+        assert_eq!(
+            child.children,
+            vec![OwnedNode::Text(OwnedTextNode::new("text <"))]
+        );
+        assert_eq!(owned.root.children.len(), 3);
+
+        let mut again = String::new();
+        let rox_again = owned.to_roxmltree(&mut again).unwrap();
+        assert_eq!(OwnedDocument::from(rox_again), owned);
+    }
+
+    #[test]
+    fn test_document_to_roxmltree() {
+        const SRC: &str =
+            "<?xml version='1.0'?><a:root xmlns:a='urn:a' a:x='1'><a:child>text</a:child></a:root>";
+
+        let doc = Document::parse_str(SRC).unwrap();
+        let mut buffer = String::new();
+        let rox = doc.to_roxmltree(&mut buffer).unwrap();
+        let root = rox.root_element();
+        assert!(root.has_tag_name(("urn:a", "root")));
+        assert_eq!(root.attribute(("urn:a", "x")), Some("1"));
+        assert_eq!(root.first_element_child().unwrap().text(), Some("text"));
+
+        let owned = OwnedDocument::from(&rox);
+        assert_eq!(owned.root, doc.root().to_owned());
+    }
+}
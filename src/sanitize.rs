@@ -0,0 +1,350 @@
+//! Allow-list cleanup of untrusted documents.
+use crate::{
+    Document, Origin, OwnedDocument,
+    error::{ErrorContext, XmlError, XmlErrorKind, XmlResult},
+    node::{OwnedNode, OwnedNodeName, OwnedTagNode, OwnedTextNode, decode_attribute_value},
+};
+use std::collections::{HashMap, HashSet};
+
+/// An allow-list of elements, attributes and URL schemes, used to clean up documents from untrusted sources.
+///
+/// Anything not allowed is stripped, or refused with an error if the sanitizer is set to reject:
+/// - Elements that are not allowed are unwrapped, keeping their sanitized content, unless they are marked as removed,
+///   in which case their content goes with them. The root element cannot be unwrapped, and is always refused.
+/// - Attributes must be allowed on every element, or on their element in particular.
+/// - Values of URL attributes must be relative, or use one of the allowed schemes. Character references are decoded first.
+/// - DTDs and processing instructions are never allowed, and comments only if enabled.
+///
+/// CDATA sections are turned into text, and the XML declaration is kept. A new sanitizer allows nothing.
+///
+/// # Example
+/// ```rust
+/// use xmltree::{Document, Sanitizer, XmlWriteOptions};
+///
+/// let sanitizer = Sanitizer::new()
+///     .with_elements(["p", "a", "b"])
+///     .with_removed_elements(["script"])
+///     .with_element_attributes("a", ["href"])
+///     .with_url_attributes(["href"])
+///     .with_url_schemes(["https", "mailto"]);
+///
+/// let src = r#"<p onclick="x()">Hi <a href="java&#x73;cript:x()">there</a><i>, <b>you</b></i><script>x()</script></p>"#;
+/// let doc = Document::parse_str(src).unwrap();
+/// let clean = sanitizer.sanitize(&doc).unwrap();
+///
+/// let xml = clean.to_xml_with_options(&XmlWriteOptions::default().with_minify(true)).unwrap();
+/// assert_eq!(xml, "<p>Hi<a>there</a>,<b>you</b></p>\n");
+///
+/// let strict = sanitizer.with_reject(true);
+/// assert!(strict.sanitize(&doc).is_err());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Sanitizer {
+    elements: HashSet<OwnedNodeName>,
+    removed_elements: HashSet<OwnedNodeName>,
+    attributes: HashSet<OwnedNodeName>,
+    element_attributes: HashMap<OwnedNodeName, HashSet<OwnedNodeName>>,
+    url_attributes: HashSet<OwnedNodeName>,
+    url_schemes: HashSet<String>,
+    allow_comments: bool,
+    reject: bool,
+}
+impl Sanitizer {
+    /// Creates a sanitizer that allows nothing.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allows elements with the given names, written as `local` or `prefix:local`.
+    #[must_use]
+    pub fn with_elements<'a>(mut self, names: impl IntoIterator<Item = &'a str>) -> Self {
+        self.elements
+            .extend(names.into_iter().map(OwnedNodeName::from));
+        self
+    }
+
+    /// Removes elements with the given names along with their content, instead of unwrapping them.
+    ///
+    /// Has no effect on allowed elements.
+    #[must_use]
+    pub fn with_removed_elements<'a>(mut self, names: impl IntoIterator<Item = &'a str>) -> Self {
+        self.removed_elements
+            .extend(names.into_iter().map(OwnedNodeName::from));
+        self
+    }
+
+    /// Allows attributes with the given names on every allowed element.
+    #[must_use]
+    pub fn with_attributes<'a>(mut self, names: impl IntoIterator<Item = &'a str>) -> Self {
+        self.attributes
+            .extend(names.into_iter().map(OwnedNodeName::from));
+        self
+    }
+
+    /// Allows attributes with the given names on one element.
+    #[must_use]
+    pub fn with_element_attributes<'a>(
+        mut self,
+        element: &str,
+        names: impl IntoIterator<Item = &'a str>,
+    ) -> Self {
+        self.element_attributes
+            .entry(OwnedNodeName::from(element))
+            .or_default()
+            .extend(names.into_iter().map(OwnedNodeName::from));
+        self
+    }
+
+    /// Marks attributes with the given names as URLs, whose values must be relative or use an allowed scheme.
+    ///
+    /// The attributes must still be allowed separately.
+    #[must_use]
+    pub fn with_url_attributes<'a>(mut self, names: impl IntoIterator<Item = &'a str>) -> Self {
+        self.url_attributes
+            .extend(names.into_iter().map(OwnedNodeName::from));
+        self
+    }
+
+    /// Allows URLs with the given schemes, such as `https`, in URL attributes. Schemes are compared without regard to case.
+    #[must_use]
+    pub fn with_url_schemes<'a>(mut self, schemes: impl IntoIterator<Item = &'a str>) -> Self {
+        self.url_schemes
+            .extend(schemes.into_iter().map(str::to_ascii_lowercase));
+        self
+    }
+
+    /// Sets whether comments are kept.
+    #[must_use]
+    pub fn with_comments(mut self, allow: bool) -> Self {
+        self.allow_comments = allow;
+        self
+    }
+
+    /// Sets whether anything that is not allowed fails the whole document, instead of being stripped.
+    #[must_use]
+    pub fn with_reject(mut self, reject: bool) -> Self {
+        self.reject = reject;
+        self
+    }
+
+    /// Returns an owned copy of the document, containing only what the sanitizer allows.
+    ///
+    /// # Errors
+    /// Returns an [`XmlErrorKind::Disallowed`] error if the root element is not allowed,
+    /// or if the sanitizer is set to reject and anything else is not allowed.
+    pub fn sanitize(&self, document: &Document<'_>) -> XmlResult<OwnedDocument> {
+        let context = Context {
+            sanitizer: self,
+            source: document.source().unwrap_or_default(),
+        };
+
+        let mut owned = document.to_owned();
+        if !self.elements.contains(&owned.root.name) {
+            return Err(
+                context.disallowed(format!("element {}", owned.root.name), &owned.root.origin)
+            );
+        }
+
+        // In document order, so the first problem found is the one reported
+        owned.prolog = context.sanitize_nodes(std::mem::take(&mut owned.prolog))?;
+        context.sanitize_attributes(&mut owned.root)?;
+        owned.root.children = context.sanitize_nodes(std::mem::take(&mut owned.root.children))?;
+        owned.epilog = context.sanitize_nodes(std::mem::take(&mut owned.epilog))?;
+        Ok(owned)
+    }
+
+    /// Returns true if the value of a URL attribute is relative, or uses an allowed scheme.
+    fn is_allowed_url(&self, value: &str) -> bool {
+        // Browsers ignore whitespace and control characters in schemes, so `java\tscript:` is still a script
+        let url: String = decode_attribute_value(value)
+            .chars()
+            .filter(|c| !c.is_ascii_whitespace() && !c.is_control())
+            .collect();
+
+        match url.find([':', '/', '?', '#']) {
+            Some(i) if url[i..].starts_with(':') => {
+                i > 0 && self.url_schemes.contains(&url[..i].to_ascii_lowercase())
+            }
+            _ => true,
+        }
+    }
+}
+
+/// A sanitizer applied to one document.
+struct Context<'a> {
+    sanitizer: &'a Sanitizer,
+    source: &'a str,
+}
+impl Context<'_> {
+    fn disallowed(&self, what: String, origin: &Origin) -> XmlError {
+        let span = origin.span(self.source).unwrap_or_default();
+        XmlError::new(
+            XmlErrorKind::Disallowed(what),
+            ErrorContext::new(self.source, span),
+        )
+    }
+
+    /// Strips something that is not allowed, or fails if the sanitizer rejects instead.
+    fn strip(&self, what: impl FnOnce() -> String, origin: &Origin) -> XmlResult<()> {
+        if self.sanitizer.reject {
+            Err(self.disallowed(what(), origin))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Removes the attributes of an allowed tag that are not allowed on it.
+    fn sanitize_attributes(&self, tag: &mut OwnedTagNode) -> XmlResult<()> {
+        let sanitizer = self.sanitizer;
+        let for_element = sanitizer.element_attributes.get(&tag.name);
+
+        let mut attributes = Vec::with_capacity(tag.attributes.len());
+        for attr in std::mem::take(&mut tag.attributes) {
+            let allowed = sanitizer.attributes.contains(&attr.name)
+                || for_element.is_some_and(|names| names.contains(&attr.name));
+            if !allowed {
+                self.strip(
+                    || format!("attribute {} on {}", attr.name, tag.name),
+                    &attr.origin,
+                )?;
+            } else if sanitizer.url_attributes.contains(&attr.name)
+                && !sanitizer.is_allowed_url(&attr.value)
+            {
+                self.strip(
+                    || format!("URL {} in {}", attr.value, attr.name),
+                    &attr.origin,
+                )?;
+            } else {
+                attributes.push(attr);
+            }
+        }
+        tag.attributes = attributes;
+        Ok(())
+    }
+
+    /// Sanitizes a list of siblings and everything below them, without recursion.
+    fn sanitize_nodes(&self, nodes: Vec<OwnedNode>) -> XmlResult<Vec<OwnedNode>> {
+        // Lists being sanitized, as (remaining input, output, tag to put the output in, or None to unwrap it)
+        let mut stack = vec![(nodes.into_iter(), vec![], None::<OwnedTagNode>)];
+        loop {
+            let (input, output, _) = stack
+                .last_mut()
+                .expect("The outermost list is only popped at the end");
+            let Some(node) = input.next() else {
+                let (_, output, tag) = stack.pop().expect("List was just borrowed");
+                let Some((_, parent, _)) = stack.last_mut() else {
+                    return Ok(output);
+                };
+                match tag {
+                    Some(mut tag) => {
+                        tag.children = output;
+                        parent.push(OwnedNode::Tag(tag));
+                    }
+                    None => parent.extend(output),
+                }
+                continue;
+            };
+
+            match node {
+                OwnedNode::Tag(mut tag) => {
+                    let children = std::mem::take(&mut tag.children).into_iter();
+                    if self.sanitizer.elements.contains(&tag.name) {
+                        self.sanitize_attributes(&mut tag)?;
+                        stack.push((children, vec![], Some(tag)));
+                    } else {
+                        self.strip(|| format!("element {}", tag.name), &tag.origin)?;
+                        if !self.sanitizer.removed_elements.contains(&tag.name) {
+                            stack.push((children, vec![], None));
+                        }
+                    }
+                }
+                OwnedNode::Text(text) => output.push(OwnedNode::Text(text)),
+                OwnedNode::Cdata(cdata) => {
+                    let mut text = OwnedTextNode::new(cdata.content);
+                    text.origin = cdata.origin;
+                    output.push(OwnedNode::Text(text));
+                }
+                OwnedNode::Comment(comment) if self.sanitizer.allow_comments => {
+                    output.push(OwnedNode::Comment(comment));
+                }
+                OwnedNode::Comment(comment) => {
+                    self.strip(|| "comment".to_string(), &comment.origin)?;
+                }
+                OwnedNode::ProcessingInstruction(pi) => {
+                    self.strip(
+                        || format!("processing instruction {}", pi.target),
+                        &pi.origin,
+                    )?;
+                }
+                OwnedNode::DocumentType(dtd) => {
+                    self.strip(|| "DOCTYPE".to_string(), &dtd.origin)?;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorCode;
+
+    #[test]
+    fn test_url_schemes() {
+        let sanitizer = Sanitizer::new().with_url_schemes(["HTTPS"]);
+        assert!(sanitizer.is_allowed_url("https://example.com"));
+        assert!(sanitizer.is_allowed_url("HtTpS://example.com"));
+        assert!(sanitizer.is_allowed_url("/relative/path:with-colon"));
+        assert!(sanitizer.is_allowed_url("page?q=a:b"));
+        assert!(sanitizer.is_allowed_url("#top"));
+
+        assert!(!sanitizer.is_allowed_url("javascript:alert(1)"));
+        assert!(!sanitizer.is_allowed_url(" java\tscript:alert(1)"));
+        assert!(!sanitizer.is_allowed_url("java&#x0A;script:alert(1)"));
+        assert!(!sanitizer.is_allowed_url("&#106;avascript:alert(1)"));
+        assert!(!sanitizer.is_allowed_url(":no-scheme"));
+    }
+
+    #[test]
+    fn test_sanitize() {
+        let src = "<!DOCTYPE r><?pi?><r x='1'><!-- c --><u><u><b y='2'>t</b></u></u><s><b /></s><![CDATA[<x>]]></r>";
+        let doc = Document::parse_str(src).unwrap();
+        let sanitizer = Sanitizer::new()
+            .with_elements(["r", "b"])
+            .with_removed_elements(["s"])
+            .with_element_attributes("b", ["y"]);
+
+        let clean = sanitizer.sanitize(&doc).unwrap();
+        assert!(clean.prolog.is_empty());
+        assert!(clean.root.attributes.is_empty());
+        assert_eq!(clean.root.children.len(), 2);
+        let OwnedNode::Tag(b) = &clean.root.children[0] else {
+            panic!("Expected the unwrapped tag");
+        };
+        assert_eq!(b.name, "b");
+        assert_eq!(b.attributes.len(), 1);
+        assert!(matches!(&clean.root.children[1], OwnedNode::Text(text) if text.text == "<x>"));
+
+        let with_comments = sanitizer
+            .clone()
+            .with_comments(true)
+            .sanitize(&doc)
+            .unwrap();
+        assert!(matches!(
+            with_comments.root.children[0],
+            OwnedNode::Comment(_)
+        ));
+
+        let err = sanitizer
+            .clone()
+            .with_reject(true)
+            .sanitize(&doc)
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::Disallowed);
+        assert_eq!(err.context.span.as_str(), "<!DOCTYPE r>");
+
+        let err = Sanitizer::new().sanitize(&doc).unwrap_err();
+        assert!(matches!(err.kind, XmlErrorKind::Disallowed(what) if what == "element r"));
+    }
+}
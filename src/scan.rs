@@ -0,0 +1,50 @@
+//! Byte scanning helpers, accelerated with `memchr` when the `simd` feature is enabled.
+
+/// Returns the index of the first occurrence of `needle`.
+#[cfg(feature = "simd")]
+pub(crate) fn find_byte(needle: u8, haystack: &[u8]) -> Option<usize> {
+    memchr::memchr(needle, haystack)
+}
+
+/// Returns the index of the first occurrence of `needle`.
+#[cfg(not(feature = "simd"))]
+pub(crate) fn find_byte(needle: u8, haystack: &[u8]) -> Option<usize> {
+    haystack.iter().position(|b| *b == needle)
+}
+
+/// Returns the index of the first occurrence of any of the three bytes.
+#[cfg(feature = "simd")]
+pub(crate) fn find_any3(a: u8, b: u8, c: u8, haystack: &[u8]) -> Option<usize> {
+    memchr::memchr3(a, b, c, haystack)
+}
+
+/// Returns the index of the first occurrence of any of the three bytes.
+#[cfg(not(feature = "simd"))]
+pub(crate) fn find_any3(a: u8, b: u8, c: u8, haystack: &[u8]) -> Option<usize> {
+    haystack.iter().position(|x| *x == a || *x == b || *x == c)
+}
+
+/// Returns the index of the first occurrence of `needle`.
+#[cfg(feature = "simd")]
+pub(crate) fn find_str(needle: &str, haystack: &str) -> Option<usize> {
+    memchr::memmem::find(haystack.as_bytes(), needle.as_bytes())
+}
+
+/// Returns the index of the first occurrence of `needle`.
+#[cfg(not(feature = "simd"))]
+pub(crate) fn find_str(needle: &str, haystack: &str) -> Option<usize> {
+    haystack.find(needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan() {
+        assert_eq!(find_byte(b'<', b"abc<d<"), Some(3));
+        assert_eq!(find_byte(b'<', b"abc"), None);
+        assert_eq!(find_any3(b'>', b'"', b'\'', b"a x='>'>"), Some(4));
+        assert_eq!(find_str("-->", "a - -- -->"), Some(7));
+    }
+}
@@ -0,0 +1,234 @@
+//! Immutable documents shared between threads, with handles to their subtrees.
+//!
+//! Documents are `Send` and `Sync`; a [`SharedDocument`] adds cheap, owned handles to a parsed tree,
+//! so that parts of it can be handed to other threads, such as the workers of a thread pool, without cloning anything.
+use crate::{
+    ArcDocument, Document, NodePath, OwnedDocument,
+    error::XmlResult,
+    node::{NodeAttribute, NodeName, TagNode},
+};
+use std::sync::Arc;
+
+// Fails to compile if a change makes documents unusable from other threads
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Document<'static>>();
+    assert_send_sync::<OwnedDocument>();
+    assert_send_sync::<ArcDocument>();
+    assert_send_sync::<SharedDocument>();
+    assert_send_sync::<SharedTag>();
+};
+
+/// A handle to an immutable parsed document, which can be cloned cheaply and sent to other threads.
+///
+/// Clones share the same tree and source through an [`Arc`]. Tags in it are handed out as [`SharedTag`] handles,
+/// which keep the document alive on their own and point into its stored tree, so moving between them is cheap;
+/// only [`SharedTag::tag`] assembles a subtree.
+///
+/// # Example
+/// ```rust
+/// use xmltree::SharedDocument;
+///
+/// let doc = SharedDocument::parse("<users><user><name>ann</name></user><user><name>bo</name></user></users>").unwrap();
+///
+/// let workers: Vec<_> = doc
+///     .root()
+///     .child_tags()
+///     .map(|user| std::thread::spawn(move || user.tag().child_text(None, "name").unwrap().len()))
+///     .collect();
+/// let lengths: Vec<_> = workers.into_iter().map(|w| w.join().unwrap()).collect();
+/// assert_eq!(lengths, [3, 2]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct SharedDocument(Arc<ArcDocument>);
+impl SharedDocument {
+    /// Parses a document from a shared source. See [`ArcDocument::parse`].
+    ///
+    /// # Errors
+    /// Returns an error if the source is not valid XML.
+    pub fn parse(source: impl Into<Arc<str>>) -> XmlResult<Self> {
+        ArcDocument::parse(source).map(Self::from)
+    }
+
+    /// Returns the document. See [`ArcDocument::document`].
+    #[must_use]
+    pub fn document(&self) -> Document<'_> {
+        self.0.document()
+    }
+
+    /// Returns a handle to the root element.
+    #[must_use]
+    pub fn root(&self) -> SharedTag {
+        SharedTag {
+            document: self.clone(),
+            path: NodePath::root(),
+            index: self.0.root_index(),
+        }
+    }
+
+    /// Returns a handle to the tag at the given path, including the root node.
+    ///
+    /// Returns None if the path does not lead to a tag.
+    #[must_use]
+    pub fn tag(&self, path: &NodePath) -> Option<SharedTag> {
+        Some(SharedTag {
+            document: self.clone(),
+            path: path.clone(),
+            index: self.0.find(path)?,
+        })
+    }
+}
+impl From<ArcDocument> for SharedDocument {
+    fn from(document: ArcDocument) -> Self {
+        Self(Arc::new(document))
+    }
+}
+
+/// A handle to a tag of a [`SharedDocument`], which keeps the document alive and can be sent to other threads.
+///
+/// A handle is the tag's place in the stored tree; cloning it copies only its path.
+#[derive(Debug, Clone)]
+pub struct SharedTag {
+    document: SharedDocument,
+    path: NodePath,
+
+    /// The index of the tag in the table of the [`ArcDocument`].
+    index: usize,
+}
+impl SharedTag {
+    /// Returns the document the tag is in.
+    #[must_use]
+    pub fn document(&self) -> &SharedDocument {
+        &self.document
+    }
+
+    /// Returns the path of the tag in its document.
+    #[must_use]
+    pub fn path(&self) -> &NodePath {
+        &self.path
+    }
+
+    /// Returns the tag, assembling its subtree from the stored tree. See [`ArcDocument::tag`].
+    ///
+    /// To read only the tag itself, [`SharedTag::name`] and [`SharedTag::get_attribute`] are cheaper.
+    #[must_use]
+    pub fn tag(&self) -> TagNode<'_> {
+        self.document.0.tag_at(self.index)
+    }
+
+    /// Returns the name of the tag.
+    #[must_use]
+    pub fn name(&self) -> NodeName<'_> {
+        self.document.0.name_at(self.index)
+    }
+
+    /// Get an attribute of the tag by name. See [`TagNode::get_attribute`].
+    #[must_use]
+    pub fn get_attribute(&self, prefix: Option<&str>, name: &str) -> Option<NodeAttribute<'_>> {
+        self.document
+            .0
+            .attributes_at(self.index)
+            .filter(|a| a.name().equals(prefix, name))
+            .last()
+    }
+
+    /// Returns a handle to the child of this tag at `index`, if it is a tag.
+    #[must_use]
+    pub fn child(&self, index: usize) -> Option<SharedTag> {
+        let child = self.document.0.children_at(self.index).nth(index)?;
+        self.document.0.is_tag(child).then(|| SharedTag {
+            document: self.document.clone(),
+            path: self.path.child(index),
+            index: child,
+        })
+    }
+
+    /// Returns handles to the child tags of this tag, in document order.
+    pub fn child_tags(&self) -> impl Iterator<Item = SharedTag> + '_ {
+        let children = self.document.0.children_at(self.index);
+        (0..children.len()).filter_map(|index| self.child(index))
+    }
+
+    /// Returns a handle to the parent of this tag, or None for the root.
+    #[must_use]
+    pub fn parent(&self) -> Option<SharedTag> {
+        Some(SharedTag {
+            document: self.document.clone(),
+            path: self.path.parent()?,
+            index: self.document.0.parent_at(self.index)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shared_document() {
+        let doc = SharedDocument::parse("<a><b><c x='1' x='2' /></b>text<d /></a>").unwrap();
+        let root = doc.root();
+
+        let children: Vec<_> = root.child_tags().map(|tag| tag.path().clone()).collect();
+        assert_eq!(
+            children,
+            [NodePath::root().child(0), NodePath::root().child(2)]
+        );
+        assert!(root.child(1).is_none());
+
+        let c = doc.tag(&NodePath::root().child(0).child(0)).unwrap();
+        assert_eq!(c.tag().name(), "c");
+        assert_eq!(c.name(), "c");
+        assert_eq!(c.get_attribute(None, "x").unwrap().value().text(), "2");
+        assert!(c.get_attribute(None, "y").is_none());
+        assert_eq!(c.parent().unwrap().name(), "b");
+        assert_eq!(c.parent().unwrap().path(), &NodePath::root().child(0));
+        assert!(root.parent().is_none());
+        assert!(doc.tag(&NodePath::root().child(1)).is_none());
+
+        // Handles keep the document alive, and share it between threads
+        drop(doc);
+        let names = std::thread::scope(|scope| {
+            let workers: Vec<_> = root
+                .child_tags()
+                .map(|tag| scope.spawn(move || tag.tag().name().to_string()))
+                .collect();
+            workers
+                .into_iter()
+                .map(|w| w.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(names, ["b", "d"]);
+    }
+
+    #[test]
+    fn test_shared_document_deeply_nested() {
+        const DEPTH: usize = 10_000;
+
+        // Walking handles does not recurse, but dropping a parsed tree does
+        let test = || {
+            let src = "<a>".repeat(DEPTH) + &"</a>".repeat(DEPTH);
+            let doc = SharedDocument::parse(src).unwrap();
+
+            let mut tag = doc.root();
+            for _ in 1..DEPTH {
+                let child = tag.child_tags().next().unwrap();
+                tag = child;
+            }
+            assert!(tag.child(0).is_none());
+            assert_eq!(tag.tag().name(), "a");
+            assert_eq!(tag.path().indices().len(), DEPTH - 1);
+
+            while let Some(parent) = tag.parent() {
+                tag = parent;
+            }
+            assert_eq!(tag.path(), &NodePath::root());
+        };
+        std::thread::Builder::new()
+            .stack_size(64 << 20)
+            .spawn(test)
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+}
@@ -0,0 +1,139 @@
+//! Snapshot testing of documents, using `insta`.
+//!
+//! Documents are serialized in a canonical form before being compared, so snapshots only change
+//! when the content of a document changes:
+//! - Nodes are indented with two spaces, one node per line
+//! - Attributes are sorted by name
+//!
+//! Use [`assert_xml_snapshot!`](crate::assert_xml_snapshot) in tests, and review changes with `cargo insta review`.
+//!
+//! ```rust
+//! use xmltree::{Document, assert_xml_snapshot};
+//!
+//! let doc = Document::parse_str(r#"<root b="2" a="1"><child /></root>"#).unwrap();
+//! assert_xml_snapshot!(doc, @r#"
+//! <root a="1" b="2">
+//!   <child />
+//! </root>
+//! "#);
+//! ```
+use crate::{
+    Document, OwnedDocument, XmlWriteOptions,
+    node::{OwnedNode, OwnedTagNode, TagNode},
+};
+
+#[doc(hidden)]
+pub use insta;
+
+/// A value that can be serialized in the canonical form used for snapshots.
+pub trait Snapshot {
+    /// Serializes the value in canonical form.
+    ///
+    /// # Panics
+    /// Panics if the value contains strings that cannot be written as XML.
+    fn to_snapshot(&self) -> String;
+}
+impl Snapshot for OwnedDocument {
+    fn to_snapshot(&self) -> String {
+        let mut document = self.clone();
+
+        let mut stack = vec![&mut document.root];
+        while let Some(node) = stack.pop() {
+            node.attributes
+                .sort_by_cached_key(|attribute| attribute.name.to_string());
+
+            for child in &mut node.children {
+                if let OwnedNode::Tag(child) = child {
+                    stack.push(child);
+                }
+            }
+        }
+
+        let options = XmlWriteOptions::default().with_indent("  ");
+        match document.to_xml_with_options(&options) {
+            Ok(xml) => xml,
+            Err(e) => panic!("Could not serialize document for snapshot: {e}"),
+        }
+    }
+}
+impl Snapshot for Document<'_> {
+    fn to_snapshot(&self) -> String {
+        self.to_owned().to_snapshot()
+    }
+}
+impl Snapshot for OwnedTagNode {
+    fn to_snapshot(&self) -> String {
+        OwnedDocument::new(self.clone()).to_snapshot()
+    }
+}
+impl Snapshot for TagNode<'_> {
+    fn to_snapshot(&self) -> String {
+        self.to_owned().to_snapshot()
+    }
+}
+impl<T: Snapshot> Snapshot for &T {
+    fn to_snapshot(&self) -> String {
+        (*self).to_snapshot()
+    }
+}
+
+/// Asserts that a document or tag node matches a stored `insta` snapshot, after canonicalizing it.
+///
+/// Accepts the same forms as `insta::assert_snapshot!`; a value, a named value, or a value with an inline snapshot.
+///
+/// See the [`snapshot`](crate::snapshot) module for details of the canonical form.
+#[macro_export]
+macro_rules! assert_xml_snapshot {
+    ($value:expr, @$snapshot:literal) => {
+        $crate::snapshot::insta::assert_snapshot!(
+            $crate::snapshot::Snapshot::to_snapshot(&$value),
+            @$snapshot
+        )
+    };
+
+    ($name:expr, $value:expr) => {
+        $crate::snapshot::insta::assert_snapshot!(
+            $name,
+            $crate::snapshot::Snapshot::to_snapshot(&$value)
+        )
+    };
+
+    ($value:expr) => {
+        $crate::snapshot::insta::assert_snapshot!($crate::snapshot::Snapshot::to_snapshot(&$value))
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SRC: &str =
+        r#"<?xml version="1.0"?><root z="1" a="2" x:m="3"><b c="1" a="2">text</b></root>"#;
+
+    #[test]
+    fn test_canonical_form() {
+        let doc = Document::parse_str(SRC).unwrap();
+        assert_eq!(
+            doc.to_snapshot(),
+            "<?xml version=\"1.0\" ?>\n<root a=\"2\" x:m=\"3\" z=\"1\">\n  <b a=\"2\" c=\"1\">\n    text\n  </b>\n</root>\n"
+        );
+
+        let crate::node::Node::Child(tag) = &doc.root().children()[0] else {
+            panic!("Expected a tag");
+        };
+        assert_eq!(tag.to_snapshot(), "<b a=\"2\" c=\"1\">\n  text\n</b>\n");
+    }
+
+    #[test]
+    fn test_snapshot_macro() {
+        let doc = Document::parse_str(SRC).unwrap().to_owned();
+        crate::assert_xml_snapshot!(doc, @r#"
+        <?xml version="1.0" ?>
+        <root a="2" x:m="3" z="1">
+          <b a="2" c="1">
+            text
+          </b>
+        </root>
+        "#);
+    }
+}
@@ -0,0 +1,248 @@
+//! Mapping formatted output back to the source it was formatted from.
+use crate::{
+    Document, Parser, StrSpan, XmlWriteOptions,
+    node::{Node, TagNode},
+};
+use std::ops::Range;
+
+/// A node or attribute in formatted output, and where it was in the original source.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SourceMapping {
+    /// Byte range in the formatted output.
+    pub output: Range<usize>,
+
+    /// Byte range in the original source.
+    pub input: Range<usize>,
+}
+
+/// Maps byte ranges of formatted output back to the spans of the source they came from.
+///
+/// There is one mapping for each tag, attribute and other node written from the source, covering the whole node;
+/// text nodes are mapped without their surrounding whitespace. Mappings of tags contain the mappings of their contents.
+///
+/// See [`Document::to_xml_with_source_map`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    /// Sorted by output start, with enclosing mappings before the ones they contain.
+    mappings: Vec<SourceMapping>,
+}
+impl SourceMap {
+    /// Returns the innermost mapping containing the given byte offset of the output.
+    ///
+    /// Returns `None` for output that was not written from the source, such as indentation.
+    #[must_use]
+    pub fn lookup(&self, offset: usize) -> Option<&SourceMapping> {
+        let end = self.mappings.partition_point(|m| m.output.start <= offset);
+        self.mappings[..end]
+            .iter()
+            .rev()
+            .find(|m| m.output.contains(&offset))
+    }
+
+    /// Returns the offset in the source corresponding to the given byte offset of the output.
+    ///
+    /// Inside a node written back unchanged, the offset is carried over exactly;
+    /// otherwise, the start of the innermost node containing it is returned.
+    #[must_use]
+    pub fn input_offset(&self, offset: usize) -> Option<usize> {
+        let mapping = self.lookup(offset)?;
+        if mapping.output.len() == mapping.input.len() {
+            Some(mapping.input.start + (offset - mapping.output.start))
+        } else {
+            Some(mapping.input.start)
+        }
+    }
+
+    /// Returns an iterator over the mappings, in output order.
+    pub fn iter(&self) -> impl Iterator<Item = &SourceMapping> {
+        self.mappings.iter()
+    }
+
+    /// Returns the number of mappings.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.mappings.len()
+    }
+
+    /// Returns true if there are no mappings.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.mappings.is_empty()
+    }
+
+    /// Adds a mapping, if the input span is part of the source; detached and placeholder nodes are not.
+    fn add(&mut self, src: &str, input: &StrSpan, output: &StrSpan, shift: usize) {
+        let range = input.start()..input.start() + input.len();
+        let from_source = src
+            .get(range.clone())
+            .is_some_and(|text| !text.is_empty() && std::ptr::eq(text, input.text()));
+        if from_source {
+            let start = output.start() + shift;
+            self.mappings.push(SourceMapping {
+                output: start..start + output.len(),
+                input: range,
+            });
+        }
+    }
+}
+
+impl Document<'_> {
+    /// Formats the document like [`Document::to_xml_with_options`], also returning a [`SourceMap`]
+    /// from the output back to this document's source.
+    ///
+    /// Useful for tools that report problems in formatted output at their original position.
+    /// Nodes added or edited without a source position are not mapped, and neither is their content if the
+    /// formatted tree no longer lines up with the original, such as text removed by [`ControlCharPolicy::Strip`](crate::ControlCharPolicy::Strip).
+    /// Documents without a source, such as ones read from the binary format, get an empty map.
+    ///
+    /// # Errors
+    /// Can fail if a string in the document cannot be entity encoded,
+    /// or contains characters rejected by [`XmlWriteOptions::control_chars`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{Document, XmlWriteOptions};
+    ///
+    /// let src = "<config><port value='80'/>\n\n   <host>example.com</host></config>";
+    /// let doc = Document::parse_str(src).unwrap();
+    ///
+    /// let (xml, map) = doc.to_xml_with_source_map(&XmlWriteOptions::default()).unwrap();
+    /// let host = xml.find("example.com").unwrap();
+    ///
+    /// let mapping = map.lookup(host).unwrap();
+    /// assert_eq!(&src[mapping.input.clone()], "example.com");
+    /// assert_eq!(map.input_offset(host + 7), src.find(".com"));
+    /// ```
+    pub fn to_xml_with_source_map(
+        &self,
+        options: &XmlWriteOptions,
+    ) -> std::io::Result<(String, SourceMap)> {
+        let output = self.to_xml_with_options(options)?;
+        let mut map = SourceMap::default();
+        let Some(src) = self.source() else {
+            return Ok((output, map));
+        };
+
+        // Formatting keeps the structure of the tree, so the output parses back into the same nodes
+        let formatted = Parser::new()
+            .with_allow_missing_root(true)
+            .parse(&output)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?;
+        let shift = output.len() - formatted.source().map_or(0, str::len);
+
+        if let (Some(input), Some(output)) = (self.declaration(), formatted.declaration()) {
+            map.add(src, input.span(), output.span(), shift);
+        }
+
+        let mut tags: Vec<(&TagNode, &TagNode)> = vec![(self.root(), formatted.root())];
+        let mut lists = vec![
+            (self.prolog(), formatted.prolog()),
+            (self.epilog(), formatted.epilog()),
+        ];
+        loop {
+            if let Some((input, output)) = tags.pop() {
+                map.add(src, input.span(), output.span(), shift);
+
+                // Paired by name, since the output may be sorted
+                let mut unpaired: Vec<_> = input.attributes().iter().collect();
+                for b in output.attributes() {
+                    if let Some(i) = unpaired.iter().position(|a| a.name() == b.name()) {
+                        map.add(src, unpaired.remove(i).span(), b.span(), shift);
+                    }
+                }
+                lists.push((input.children(), output.children()));
+                continue;
+            }
+
+            let Some((input, output)) = lists.pop() else {
+                break;
+            };
+            let aligned = input.len() == output.len()
+                && input
+                    .iter()
+                    .zip(output)
+                    .all(|(a, b)| std::mem::discriminant(a) == std::mem::discriminant(b));
+            if !aligned {
+                continue;
+            }
+
+            for (a, b) in input.iter().zip(output) {
+                match (a, b) {
+                    (Node::Child(a), Node::Child(b)) => tags.push((a, b)),
+                    (Node::Text(a), Node::Text(b)) => map.add(src, a.text(), b.text(), shift),
+                    (a, b) => map.add(src, a.span(), b.span(), shift),
+                }
+            }
+        }
+
+        map.mappings
+            .sort_by_key(|m| (m.output.start, std::cmp::Reverse(m.output.end)));
+        Ok((output, map))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::{NodeAttribute, TextNode};
+
+    #[test]
+    fn test_source_map() {
+        let src = "\u{FEFF}<?xml version='1.0'?>\n<!-- c --><a   x='1'><b>one</b>\n\n<b>two, three</b></a>";
+        let mut doc = Document::parse_str(src).unwrap();
+        let src = doc.source().unwrap();
+        let options = XmlWriteOptions::default().with_preserve_bom(true);
+
+        let (xml, map) = doc.to_xml_with_source_map(&options).unwrap();
+        for mapping in map.iter() {
+            let input = &src[mapping.input.clone()];
+            let output = &xml[mapping.output.clone()];
+            if !input.starts_with('<') && !input.contains('=') {
+                assert_eq!(input, output);
+            }
+        }
+
+        // Innermost first
+        let two = xml.find("two").unwrap();
+        assert_eq!(&src[map.lookup(two).unwrap().input.clone()], "two, three");
+        let tab = xml.find("\t<b>").unwrap();
+        assert_eq!(
+            &src[map.lookup(tab).unwrap().input.clone()],
+            &src[src.find("<a").unwrap()..]
+        );
+        assert_eq!(map.lookup(0), None);
+        assert_eq!(map.input_offset(xml.find("x=").unwrap()), src.find("x="));
+        assert_eq!(
+            map.input_offset(xml.find("c -->").unwrap()),
+            src.find("c -->")
+        );
+
+        // Edited nodes are not mapped, but the rest still are
+        doc.root_mut()
+            .attributes_mut()
+            .push(NodeAttribute::detached(None, "y", "2"));
+        doc.root_mut()
+            .children_mut()
+            .insert(0, Node::Text(TextNode::detached("new")));
+        let (xml, map) = doc.to_xml_with_source_map(&options).unwrap();
+        assert_eq!(
+            map.lookup(xml.find("new").unwrap()).unwrap().input.start,
+            src.find("<a").unwrap()
+        );
+        assert_eq!(
+            map.lookup(xml.find("y=").unwrap()).unwrap().input.start,
+            src.find("<a").unwrap()
+        );
+        assert!(map.iter().any(|m| &src[m.input.clone()] == "one"));
+
+        // Sorted attributes still map to their own source
+        let options = options.with_sort_attributes(crate::AttrSort::Custom(|a, b| b.cmp(a)));
+        let (xml, map) = doc.to_xml_with_source_map(&options).unwrap();
+        assert!(xml.contains("<a y=\"2\" x=\"1\">"));
+        assert_eq!(map.input_offset(xml.find("x=").unwrap()), src.find("x="));
+
+        let owned = doc.to_owned();
+        let (_, map) = owned.borrowed().to_xml_with_source_map(&options).unwrap();
+        assert!(map.is_empty());
+    }
+}
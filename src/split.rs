@@ -0,0 +1,212 @@
+//! Splitting a stream of concatenated documents, such as a message dump, into separate documents.
+use crate::{
+    Document, OwnedDocument, StrSpan,
+    error::{ErrorContext, XmlError, XmlErrorKind, XmlResult},
+};
+use std::io::Read;
+
+/// Size of each read from the underlying reader.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Longest markup opening the scanner needs to see whole before deciding what it is; `<![CDATA[`.
+const LONGEST_OPENING: usize = 9;
+
+/// Splits a stream of documents written back to back into separate documents, parsing each one independently.
+///
+/// A new document starts at every `<?xml ...?>` declaration after the first, so each document should start with one.
+/// Declarations inside comments or CDATA sections are not mistaken for the start of a document.
+/// Streams of elements without declarations can be read whole with [`Document::parse_forest`] instead.
+///
+/// The stream is read in chunks, and only one document is held in memory at a time.
+/// It must be UTF-8.
+///
+/// # Example
+/// ```rust
+/// let dump = "<?xml version='1.0'?><msg id='1' /><?xml version='1.0'?>\n<msg id='2'><![CDATA[<?xml]]></msg>\n";
+///
+/// let ids: Vec<_> = xmltree::split_documents(dump.as_bytes())
+///     .map(|doc| doc.unwrap().root.attributes[0].value.clone())
+///     .collect();
+/// assert_eq!(ids, ["1", "2"]);
+/// ```
+pub fn split_documents<R: Read>(reader: R) -> DocumentSplitter<R> {
+    DocumentSplitter {
+        reader,
+        buffer: String::new(),
+        partial: vec![],
+        scanned: 0,
+        eof: false,
+    }
+}
+
+/// An iterator over the documents in a stream; see [`split_documents`].
+#[derive(Debug)]
+pub struct DocumentSplitter<R> {
+    reader: R,
+
+    /// Text read but not yet returned, starting at the current document
+    buffer: String,
+
+    /// Bytes of a character cut off at the end of the last read
+    partial: Vec<u8>,
+
+    /// Offset in the buffer up to which markup has been scanned without finding the next document
+    scanned: usize,
+
+    eof: bool,
+}
+
+impl<R: Read> DocumentSplitter<R> {
+    /// Returns the source text of the next document, without parsing it, or `None` at the end of the stream.
+    ///
+    /// Useful for parsing the document as a borrowed [`Document`] instead of an owned one.
+    ///
+    /// # Errors
+    /// Returns an error if the reader fails, or the stream is not valid UTF-8.
+    pub fn next_source(&mut self) -> XmlResult<Option<String>> {
+        loop {
+            if let Some(end) = self.find_next_document() {
+                let source = self.buffer.drain(..end).collect();
+                self.scanned = 0;
+                return Ok(Some(source));
+            }
+
+            if self.eof {
+                self.scanned = 0;
+                let source = std::mem::take(&mut self.buffer);
+                return Ok((!source.trim().is_empty()).then_some(source));
+            }
+
+            self.fill()?;
+        }
+    }
+
+    /// Reads the next chunk of the stream onto the end of the buffer.
+    fn fill(&mut self) -> XmlResult<()> {
+        let mut bytes = std::mem::take(&mut self.partial);
+        let start = bytes.len();
+        bytes.resize(start + CHUNK_SIZE, 0);
+        let read = self.reader.read(&mut bytes[start..])?;
+        bytes.truncate(start + read);
+        self.eof = read == 0;
+
+        let valid = match std::str::from_utf8(&bytes) {
+            Ok(text) => text.len(),
+            Err(e) if e.error_len().is_none() && !self.eof => e.valid_up_to(),
+            Err(e) => {
+                return Err(XmlError::new(
+                    XmlErrorKind::MalformedEncoding(format!("UTF-8: {e}")),
+                    ErrorContext::new("", StrSpan::default()),
+                ));
+            }
+        };
+
+        let (text, partial) = bytes.split_at(valid);
+        self.buffer
+            .push_str(std::str::from_utf8(text).unwrap_or_default());
+        self.partial = partial.to_vec();
+        Ok(())
+    }
+
+    /// Scans the buffer for the declaration starting the next document, returning its offset.
+    ///
+    /// Returns `None` if there is none in the text read so far; scanning resumes where it stopped once more is read.
+    fn find_next_document(&mut self) -> Option<usize> {
+        let first = self.buffer.len() - self.buffer.trim_start().len();
+        let mut pos = self.scanned.max(first);
+
+        while let Some(offset) = self.buffer[pos..].find('<') {
+            let start = pos + offset;
+            let rest = &self.buffer[start..];
+            if rest.len() < LONGEST_OPENING && !self.eof {
+                self.scanned = start;
+                return None;
+            }
+
+            let is_declaration = rest.starts_with("<?xml")
+                && rest[5..].starts_with(|c: char| c.is_ascii_whitespace());
+            if is_declaration && start > first {
+                return Some(start);
+            }
+
+            // Markup that can contain a declaration without starting a document is skipped whole
+            let skip = [("<!--", "-->"), ("<![CDATA[", "]]>"), ("<?", "?>")]
+                .into_iter()
+                .find(|(open, _)| rest.starts_with(open));
+            let Some((open, close)) = skip else {
+                pos = start + 1;
+                continue;
+            };
+            let Some(end) = rest[open.len()..].find(close) else {
+                self.scanned = start;
+                return None;
+            };
+            pos = start + open.len() + end + close.len();
+        }
+
+        self.scanned = self.buffer.len();
+        None
+    }
+}
+
+impl<R: Read> Iterator for DocumentSplitter<R> {
+    type Item = XmlResult<OwnedDocument>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let source = match self.next_source() {
+            Ok(source) => source?,
+            Err(e) => return Some(Err(e)),
+        };
+        Some(Document::parse_str(&source).map(|document| document.to_owned()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reads a few bytes at a time, to split documents and characters across reads.
+    struct Trickle<'a>(&'a [u8]);
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let len = self.0.len().min(buf.len()).min(3);
+            buf[..len].copy_from_slice(&self.0[..len]);
+            self.0 = &self.0[len..];
+            Ok(len)
+        }
+    }
+
+    #[test]
+    fn test_split_documents() {
+        let dump = concat!(
+            "\n<?xml version='1.0'?>\n<a>é</a>\n",
+            "<?xml version='1.0'?><!-- <?xml version='1.0'?> --><b><?pi <?xml ?></b>",
+            "<?xml version='1.0'?><c />\n\n",
+        );
+
+        let mut splitter = split_documents(Trickle(dump.as_bytes()));
+        assert_eq!(
+            splitter.next_source().unwrap().unwrap(),
+            "\n<?xml version='1.0'?>\n<a>é</a>\n"
+        );
+
+        let names: Vec<_> = splitter
+            .map(|doc| doc.unwrap().root.name.to_string())
+            .collect();
+        assert_eq!(names, ["b", "c"]);
+
+        // Each document is parsed on its own, so one bad document does not stop the rest
+        let dump = "<?xml version='1.0'?><a></b><?xml version='1.0'?><b />";
+        let results: Vec<_> = split_documents(dump.as_bytes()).collect();
+        assert!(results[0].is_err());
+        assert!(results[1].is_ok());
+
+        assert_eq!(split_documents(&b" \n"[..]).count(), 0);
+        assert!(
+            split_documents(&b"<a>\xFF</a>"[..])
+                .next()
+                .unwrap()
+                .is_err()
+        );
+    }
+}
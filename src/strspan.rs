@@ -1,4 +1,8 @@
-use crate::to_bin::{BinDecodeError, Decoder, Encoder, ToBinHandler};
+use crate::{
+    entities::decode_references,
+    to_bin::{BinDecodeError, Decoder, Encoder, ToBinHandler},
+};
+use std::borrow::Cow;
 
 /// A span of a string in the input XML.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
@@ -24,6 +28,27 @@ impl<'a> StrSpan<'a> {
         }
     }
 
+    /// Returns the part of this span covering the given byte range of its text.
+    pub(crate) fn slice(&self, range: std::ops::Range<usize>) -> Self {
+        StrSpan {
+            text: &self.text[range.clone()],
+            start: self.start + range.start,
+        }
+    }
+
+    /// Returns the span of the source covering the given range, or an empty span at its start if the range is not in it.
+    pub(crate) fn in_source(src: &'a str, range: std::ops::Range<usize>) -> Self {
+        StrSpan {
+            text: src.get(range.clone()).unwrap_or_default(),
+            start: range.start,
+        }
+    }
+
+    /// Returns the range of the input XML the span covers.
+    pub(crate) fn range(&self) -> std::ops::Range<usize> {
+        self.start..self.start + self.text.len()
+    }
+
     /// Extend the range of this span to include the other span.
     pub fn extend(&mut self, other: &StrSpan<'a>, src: &'a str) {
         let start = self.start.min(other.start);
@@ -46,6 +71,48 @@ impl<'a> StrSpan<'a> {
         self.text
     }
 
+    /// Returns the span's text with character references and the predefined entities (`&lt;`, `&gt;`, `&amp;`, `&apos;`, `&quot;`) decoded.
+    ///
+    /// References to other entities are kept as written; see [`Document::expand_entities`](crate::Document::expand_entities) for those.
+    /// Line breaks are normalized first, as with [`StrSpan::normalized`], so a `&#13;` reference still decodes to a carriage return.
+    /// Other whitespace is kept as it is, so use [`NodeAttribute::decoded_value`](crate::node::NodeAttribute::decoded_value) for attribute values.
+    /// Borrows from the source when there is nothing to decode, so only text containing references or carriage returns allocates.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{Document, node::Node};
+    ///
+    /// let doc = Document::parse_str("<a>x &lt; y&#10;&custom;</a>").unwrap();
+    /// let Node::Text(text) = &doc.root().children()[0] else { unreachable!() };
+    /// assert_eq!(text.text().decoded(), "x < y\n&custom;");
+    /// ```
+    #[must_use]
+    pub fn decoded(&self) -> Cow<'a, str> {
+        match normalize_line_endings(self.text) {
+            Cow::Borrowed(text) => decode_references(text),
+            Cow::Owned(text) => Cow::Owned(decode_references(&text).into_owned()),
+        }
+    }
+
+    /// Returns the span's text with line breaks normalized as the XML spec requires: `\r\n` and a lone `\r` become `\n`.
+    ///
+    /// The span itself keeps the raw text, so offsets into the source stay valid.
+    /// Borrows from the source when the text has no carriage returns.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{Document, node::Node};
+    ///
+    /// let doc = Document::parse_str("<a><![CDATA[one\r\ntwo\rthree]]></a>").unwrap();
+    /// let Node::Cdata(cdata) = &doc.root().children()[0] else { unreachable!() };
+    /// assert_eq!(cdata.content().text(), "one\r\ntwo\rthree");
+    /// assert_eq!(cdata.content().normalized(), "one\ntwo\nthree");
+    /// ```
+    #[must_use]
+    pub fn normalized(&self) -> Cow<'a, str> {
+        normalize_line_endings(self.text)
+    }
+
     /// Returns the length of the span.
     #[inline]
     #[must_use]
@@ -87,9 +154,19 @@ impl<'a> StrSpan<'a> {
     }
 }
 
+/// Replaces `\r\n` and lone `\r` line breaks with `\n`, borrowing the text if it has none.
+pub(crate) fn normalize_line_endings(text: &str) -> Cow<'_, str> {
+    if text.contains('\r') {
+        Cow::Owned(text.replace("\r\n", "\n").replace('\r', "\n"))
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
 impl<'src> ToBinHandler<'src> for StrSpan<'src> {
     fn write(&self, encoder: &mut Encoder) -> std::io::Result<()> {
         if encoder.has_source_header() {
+            encoder.check_source_span(self.text, self.start);
             self.start.write(encoder)?;
             self.text.len().write(encoder)?;
         } else {
@@ -103,7 +180,10 @@ impl<'src> ToBinHandler<'src> for StrSpan<'src> {
         if let Some(src) = decoder.source() {
             let start = usize::read(decoder)?;
             let len = usize::read(decoder)?;
-            let text = &src[start..start + len];
+            let text = start
+                .checked_add(len)
+                .and_then(|end| src.get(start..end))
+                .ok_or(BinDecodeError::InvalidSpan)?;
 
             Ok(StrSpan { text, start })
         } else {
@@ -268,6 +348,81 @@ impl PartialEq<StringSpan> for &str {
     }
 }
 
+/// Where an owned node was converted from, as a byte range of the source it was parsed from.
+///
+/// Owned nodes keep their origin through changes to the tree, so their original locations can still be reported.
+/// Nodes created directly, or converted from nodes that have no span, have no origin.
+/// Nodes read from the binary format without their source have ranges into the binary data instead.
+///
+/// Origins are ignored by equality and hashing, so owned nodes compare the same wherever they came from;
+/// every origin is equal to every other. Compare [`Origin::range`] to tell them apart.
+///
+/// # Example
+/// ```rust
+/// use xmltree::{Document, error::{ErrorContext, XmlError, XmlErrorKind}, node::OwnedNode};
+///
+/// let src = "<a>\n  <b x=\"1\" />\n</a>";
+/// let mut owned = Document::parse_str(src).unwrap().to_owned();
+/// owned.root.children.reverse();
+///
+/// let OwnedNode::Tag(b) = &owned.root.children[0] else { panic!() };
+/// assert_eq!(b.origin.range(), Some(6..17));
+///
+/// let span = b.attributes[0].origin.span(src).unwrap();
+/// let err = XmlError::new(XmlErrorKind::Custom("Bad x".into()), ErrorContext::new(src, span));
+/// assert_eq!(err.context.position(), (2, 6));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Origin(Option<(usize, usize)>);
+impl Origin {
+    /// Returns the start offset of the origin in the source, if known.
+    #[must_use]
+    pub fn start(&self) -> Option<usize> {
+        self.0.map(|(start, _)| start)
+    }
+
+    /// Returns the byte range of the origin in the source, if known.
+    #[must_use]
+    pub fn range(&self) -> Option<std::ops::Range<usize>> {
+        self.0.map(|(start, len)| start..start + len)
+    }
+
+    /// Returns true if the origin is known.
+    #[must_use]
+    pub fn is_known(&self) -> bool {
+        self.0.is_some()
+    }
+
+    /// Returns the span of the origin in the given source.
+    ///
+    /// Returns None if the origin is unknown, or does not fit in the source.
+    #[must_use]
+    pub fn span<'a>(&self, source: &'a str) -> Option<StrSpan<'a>> {
+        let range = self.range()?;
+        let text = source.get(range.clone())?;
+        Some(StrSpan::new(text, range.start))
+    }
+}
+impl PartialEq for Origin {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+impl Eq for Origin {}
+impl std::hash::Hash for Origin {
+    fn hash<H: std::hash::Hasher>(&self, _: &mut H) {}
+}
+impl From<&StrSpan<'_>> for Origin {
+    /// Empty spans belong to nodes that were not parsed, so they have no origin.
+    fn from(span: &StrSpan<'_>) -> Self {
+        if span.is_empty() {
+            Self(None)
+        } else {
+            Self(Some((span.start(), span.len())))
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -354,4 +509,15 @@ mod tests {
         assert!(empty_span.is_empty());
         assert!(!non_empty_span.is_empty());
     }
+
+    #[test]
+    fn test_strspan_normalized() {
+        let span = StrSpan::new("a\r\nb\rc\nd &#13;", 0);
+        assert_eq!(span.normalized(), "a\nb\nc\nd &#13;");
+        assert_eq!(span.decoded(), "a\nb\nc\nd \r");
+        assert!(matches!(
+            StrSpan::new("a\nb", 0).normalized(),
+            Cow::Borrowed(_)
+        ));
+    }
 }
@@ -0,0 +1,168 @@
+//! Export of the structure of a document into data formats such as YAML and TOML.
+//!
+//! See [`OwnedDocument::to_yaml`] for how documents are mapped.
+use crate::{
+    OwnedDocument,
+    node::{OwnedNode, OwnedTagNode},
+};
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
+
+/// A node in the mapped structure of a document.
+enum Value {
+    Text(String),
+    Map(Vec<(String, Value)>),
+    List(Vec<Value>),
+}
+impl Value {
+    fn from_document(document: &OwnedDocument) -> Self {
+        let root = &document.root;
+        Self::Map(vec![(root.name.to_string(), Self::from_tag(root))])
+    }
+
+    fn from_tag(tag: &OwnedTagNode) -> Self {
+        let mut text = vec![];
+        let mut children: Vec<(String, Value)> = vec![];
+        for child in &tag.children {
+            match child {
+                OwnedNode::Text(node) => text.push(node.text.as_str()),
+                OwnedNode::Cdata(node) => text.push(node.content.as_str()),
+                OwnedNode::Tag(child) => {
+                    let name = child.name.to_string();
+                    let value = Self::from_tag(child);
+                    match children.iter_mut().find(|(key, _)| *key == name) {
+                        Some((_, Self::List(list))) => list.push(value),
+                        Some((_, existing)) => {
+                            let first = std::mem::replace(existing, Self::List(vec![]));
+                            *existing = Self::List(vec![first, value]);
+                        }
+                        None => children.push((name, value)),
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let text = text.join(" ");
+        if tag.attributes.is_empty() && children.is_empty() {
+            return Self::Text(text);
+        }
+
+        let mut entries = Vec::with_capacity(tag.attributes.len() + children.len() + 1);
+        for attribute in &tag.attributes {
+            let key = format!("@{}", attribute.name);
+            let value = Self::Text(attribute.value.clone());
+            match entries.iter_mut().find(|(k, _)| *k == key) {
+                Some((_, existing)) => *existing = value,
+                None => entries.push((key, value)),
+            }
+        }
+        if !text.is_empty() {
+            entries.push(("#text".to_string(), Self::Text(text)));
+        }
+        entries.extend(children);
+        Self::Map(entries)
+    }
+}
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Text(text) => serializer.serialize_str(text),
+            Self::Map(entries) => {
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (key, value) in entries {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+            Self::List(values) => {
+                let mut seq = serializer.serialize_seq(Some(values.len()))?;
+                for value in values {
+                    seq.serialize_element(value)?;
+                }
+                seq.end()
+            }
+        }
+    }
+}
+
+impl OwnedDocument {
+    /// Exports the structure of this document as YAML.
+    ///
+    /// Documents are mapped as follows:
+    /// - The document is a map with a single key; the name of the root element.
+    /// - An element with no attributes and no child elements is a string of its text, which is empty if it has none.
+    /// - Any other element is a map of:
+    ///     - Its attributes, keyed by their name with an `@` prefix, such as `@id`.
+    ///     - Its text, if any, keyed by `#text`.
+    ///     - Its child elements, keyed by their name, in order of first appearance.
+    ///       Names that appear more than once map to a list of the elements, in document order.
+    /// - Names keep their namespace prefix, such as `xs:element`.
+    /// - Text and CDATA directly inside an element are joined with a space between each part.
+    /// - Comments, processing instructions, and the DTD are not exported.
+    ///
+    /// # Errors
+    /// Returns an error if the structure cannot be serialized
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::Document;
+    ///
+    /// let doc = Document::parse_str("<config version='2'><name>app</name><port>80</port><port>443</port></config>").unwrap();
+    /// let yaml = doc.to_owned().to_yaml().unwrap();
+    /// assert_eq!(yaml, "config:\n  '@version': '2'\n  name: app\n  port:\n  - '80'\n  - '443'\n");
+    /// ```
+    #[cfg(feature = "yaml")]
+    pub fn to_yaml(&self) -> std::io::Result<String> {
+        serde_yaml::to_string(&Value::from_document(self)).map_err(std::io::Error::other)
+    }
+
+    /// Exports the structure of this document as TOML.
+    ///
+    /// Documents are mapped the same way as [`OwnedDocument::to_yaml`].
+    ///
+    /// # Errors
+    /// Returns an error if the structure cannot be serialized
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::Document;
+    ///
+    /// let doc = Document::parse_str("<config version='2'><name>app</name><port>80</port><port>443</port></config>").unwrap();
+    /// let toml = doc.to_owned().to_toml().unwrap();
+    /// assert_eq!(toml, "[config]\n\"@version\" = \"2\"\nname = \"app\"\nport = [\"80\", \"443\"]\n");
+    /// ```
+    #[cfg(feature = "toml")]
+    pub fn to_toml(&self) -> std::io::Result<String> {
+        toml::to_string(&Value::from_document(self)).map_err(std::io::Error::other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Document;
+
+    const SRC: &str = "<a:root id='1'>head<item>one</item><!-- skipped --><item k='v'/><empty/><![CDATA[tail]]></a:root>";
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_to_yaml() {
+        let doc = Document::parse_str(SRC).unwrap().to_owned();
+        assert_eq!(
+            doc.to_yaml().unwrap(),
+            "a:root:\n  '@id': '1'\n  '#text': head tail\n  item:\n  - one\n  - '@k': v\n  empty: ''\n"
+        );
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn test_to_toml() {
+        let doc = Document::parse_str(SRC).unwrap().to_owned();
+        let value: toml::Value = doc.to_toml().unwrap().parse().unwrap();
+        let root = &value["a:root"];
+        assert_eq!(root["@id"].as_str(), Some("1"));
+        assert_eq!(root["#text"].as_str(), Some("head tail"));
+        assert_eq!(root["item"][0].as_str(), Some("one"));
+        assert_eq!(root["item"][1]["@k"].as_str(), Some("v"));
+        assert_eq!(root["empty"].as_str(), Some(""));
+    }
+}
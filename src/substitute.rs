@@ -0,0 +1,231 @@
+//! Placeholder substitution in text and attribute values, for filling in documents used as templates.
+use crate::{
+    OwnedDocument,
+    node::{OwnedNode, OwnedTagNode},
+};
+
+/// Options controlling how placeholders are found by [`OwnedDocument::substitute_with_options`].
+///
+/// By default, placeholders look like `${name}`.
+///
+/// # Example
+/// ```rust
+/// use xmltree::{Document, SubstituteOptions, XmlWriteOptions};
+///
+/// let doc = Document::parse_str("<job name='{{name}}'>run {{cmd}}</job>").unwrap();
+/// let mut owned = doc.to_owned();
+///
+/// let options = SubstituteOptions::default().with_delimiters("{{", "}}");
+/// owned.substitute_with_options(&options, |name| match name {
+///     "name" => Some("nightly".to_string()),
+///     "cmd" => Some("a && b".to_string()),
+///     _ => None,
+/// });
+///
+/// let xml = owned.to_xml_with_options(&XmlWriteOptions::default().with_minify(true)).unwrap();
+/// assert_eq!(xml, "<job name=\"nightly\">run a &amp;&amp; b</job>\n");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SubstituteOptions {
+    /// The text that starts a placeholder. Nothing is substituted if it is empty.
+    pub open: String,
+
+    /// The text that ends a placeholder. Nothing is substituted if it is empty.
+    pub close: String,
+
+    /// Also substitute placeholders in the content of CDATA sections.
+    pub include_cdata: bool,
+}
+impl Default for SubstituteOptions {
+    fn default() -> Self {
+        Self {
+            open: "${".to_string(),
+            close: "}".to_string(),
+            include_cdata: false,
+        }
+    }
+}
+impl SubstituteOptions {
+    /// Sets the text that starts and ends a placeholder.
+    #[must_use]
+    pub fn with_delimiters(mut self, open: impl Into<String>, close: impl Into<String>) -> Self {
+        self.open = open.into();
+        self.close = close.into();
+        self
+    }
+
+    /// Sets whether placeholders in the content of CDATA sections are substituted.
+    #[must_use]
+    pub fn with_include_cdata(mut self, include: bool) -> Self {
+        self.include_cdata = include;
+        self
+    }
+
+    /// Replaces the placeholders in a string, returning how many were replaced.
+    ///
+    /// Replacement values are not searched for placeholders. Placeholders the callback returns `None` for,
+    /// and unterminated ones, are left as they are.
+    fn apply(&self, input: &mut String, f: &mut dyn FnMut(&str) -> Option<String>) -> usize {
+        if self.open.is_empty() || self.close.is_empty() || !input.contains(&self.open) {
+            return 0;
+        }
+
+        let mut output = String::with_capacity(input.len());
+        let mut count = 0;
+        let mut rest = input.as_str();
+        while let Some(start) = rest.find(&self.open) {
+            let after_open = &rest[start + self.open.len()..];
+            let Some(end) = after_open.find(&self.close) else {
+                break;
+            };
+
+            let placeholder_end = start + self.open.len() + end + self.close.len();
+            output.push_str(&rest[..start]);
+            match f(&after_open[..end]) {
+                Some(value) => {
+                    output.push_str(&value);
+                    count += 1;
+                }
+                None => output.push_str(&rest[start..placeholder_end]),
+            }
+            rest = &rest[placeholder_end..];
+        }
+
+        if count > 0 {
+            output.push_str(rest);
+            *input = output;
+        }
+        count
+    }
+}
+
+impl OwnedTagNode {
+    /// Replaces `${name}` placeholders in the attribute values and text at or below this tag; see [`OwnedDocument::substitute`].
+    pub fn substitute(&mut self, f: impl FnMut(&str) -> Option<String>) -> usize {
+        self.substitute_with_options(&SubstituteOptions::default(), f)
+    }
+
+    /// Replaces placeholders at or below this tag, using the given options; see [`OwnedDocument::substitute_with_options`].
+    pub fn substitute_with_options(
+        &mut self,
+        options: &SubstituteOptions,
+        mut f: impl FnMut(&str) -> Option<String>,
+    ) -> usize {
+        let mut count = 0;
+        let mut stack = vec![self];
+        while let Some(tag) = stack.pop() {
+            for attr in &mut tag.attributes {
+                count += options.apply(&mut attr.value, &mut f);
+            }
+
+            for child in tag.children.iter_mut().rev() {
+                match child {
+                    OwnedNode::Tag(child) => stack.push(child),
+                    OwnedNode::Text(text) => count += options.apply(&mut text.text, &mut f),
+                    OwnedNode::Cdata(cdata) if options.include_cdata => {
+                        count += options.apply(&mut cdata.content, &mut f);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        count
+    }
+}
+
+impl OwnedDocument {
+    /// Replaces `${name}` placeholders in attribute values and text across the document, returning how many were replaced.
+    ///
+    /// The callback is given the name between the delimiters, and returns the value to insert, or `None` to leave the placeholder as it is.
+    /// Values are inserted as plain text, and are escaped when the document is written, so they cannot inject markup.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{Document, XmlWriteOptions};
+    ///
+    /// let doc = Document::parse_str("<job host='${host}'><arg>${arg}</arg><arg>${missing}</arg></job>").unwrap();
+    /// let mut owned = doc.to_owned();
+    ///
+    /// let replaced = owned.substitute(|name| match name {
+    ///     "host" => Some("build-01".to_string()),
+    ///     "arg" => Some("<all>".to_string()),
+    ///     _ => None,
+    /// });
+    /// assert_eq!(replaced, 2);
+    ///
+    /// let xml = owned.to_xml_with_options(&XmlWriteOptions::default().with_minify(true)).unwrap();
+    /// assert_eq!(xml, "<job host=\"build-01\"><arg>&lt;all&gt;</arg><arg>${missing}</arg></job>\n");
+    /// ```
+    pub fn substitute(&mut self, f: impl FnMut(&str) -> Option<String>) -> usize {
+        self.root.substitute(f)
+    }
+
+    /// Replaces placeholders in attribute values and text across the document, using the given options.
+    ///
+    /// See [`OwnedDocument::substitute`] and [`SubstituteOptions`].
+    pub fn substitute_with_options(
+        &mut self,
+        options: &SubstituteOptions,
+        f: impl FnMut(&str) -> Option<String>,
+    ) -> usize {
+        self.root.substitute_with_options(options, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(options: &SubstituteOptions, input: &str) -> (String, usize) {
+        let mut output = input.to_string();
+        let count = options.apply(&mut output, &mut |name| {
+            (name != "keep").then(|| format!("[{name}]"))
+        });
+        (output, count)
+    }
+
+    #[test]
+    fn test_substitute_str() {
+        let options = SubstituteOptions::default();
+        assert_eq!(
+            apply(&options, "a ${x} b ${y}"),
+            ("a [x] b [y]".to_string(), 2)
+        );
+        assert_eq!(
+            apply(&options, "${keep} ${x}"),
+            ("${keep} [x]".to_string(), 1)
+        );
+        assert_eq!(
+            apply(&options, "${x} ${open"),
+            ("[x] ${open".to_string(), 1)
+        );
+        assert_eq!(apply(&options, "${}${${x}"), ("[][${x]".to_string(), 2));
+        assert_eq!(
+            apply(&options, "no placeholders"),
+            ("no placeholders".to_string(), 0)
+        );
+
+        let options = SubstituteOptions::default().with_delimiters("%", "%");
+        assert_eq!(
+            apply(&options, "%a%%b% 100%"),
+            ("[a][b] 100%".to_string(), 2)
+        );
+
+        let options = SubstituteOptions::default().with_delimiters("", "}");
+        assert_eq!(apply(&options, "${x}"), ("${x}".to_string(), 0));
+    }
+
+    #[test]
+    fn test_substitute_cdata() {
+        let doc = crate::Document::parse_str("<a><![CDATA[${x}]]>${x}</a>").unwrap();
+        let mut owned = doc.to_owned();
+        assert_eq!(owned.substitute(|_| Some("v".to_string())), 1);
+
+        let options = SubstituteOptions::default().with_include_cdata(true);
+        assert_eq!(
+            owned.substitute_with_options(&options, |_| Some("v".to_string())),
+            1
+        );
+        assert!(matches!(&owned.root.children[0], OwnedNode::Cdata(cdata) if cdata.content == "v"));
+    }
+}
@@ -0,0 +1,235 @@
+//! Extraction of flat records, such as CSV rows, from repeated elements.
+use crate::{
+    Matcher,
+    node::{Node, TagNode},
+};
+
+/// Where the value of a column comes from, relative to the row tag.
+#[derive(Debug)]
+enum Column {
+    /// The value of an attribute of the row.
+    Attribute(Option<String>, String),
+
+    /// The text of the row.
+    Text,
+
+    /// The text of the first matching tag below the row.
+    ChildText(Matcher),
+
+    /// The value of an attribute of the first matching tag below the row.
+    ChildAttribute(Matcher, Option<String>, String),
+}
+impl Column {
+    fn value(&self, row: &TagNode<'_>) -> Option<String> {
+        match self {
+            Self::Attribute(prefix, name) => row
+                .get_attribute(prefix.as_deref(), name)
+                .map(|attr| attr.value().text().to_string()),
+            Self::Text => Some(text_of(row)),
+            Self::ChildText(matcher) => find_below(row, matcher).map(text_of),
+            Self::ChildAttribute(matcher, prefix, name) => find_below(row, matcher)
+                .and_then(|tag| tag.get_attribute(prefix.as_deref(), name))
+                .map(|attr| attr.value().text().to_string()),
+        }
+    }
+}
+
+/// Returns the first tag below `tag`, but not `tag` itself, that matches.
+fn find_below<'a, 'src>(tag: &'a TagNode<'src>, matcher: &Matcher) -> Option<&'a TagNode<'src>> {
+    tag.children().iter().find_map(|child| match child {
+        Node::Child(child) => child.find_first(matcher),
+        _ => None,
+    })
+}
+
+/// Returns the direct text and CDATA children of a tag, joined with a space between each part.
+fn text_of(tag: &TagNode<'_>) -> String {
+    let parts: Vec<_> = tag
+        .children()
+        .iter()
+        .filter_map(|child| match child {
+            Node::Text(text) => Some(text.text().text()),
+            Node::Cdata(cdata) => Some(cdata.content().text()),
+            _ => None,
+        })
+        .collect();
+    parts.join(" ")
+}
+
+/// Extracts a table of records from a tree, with one row per matching tag.
+///
+/// Rows are the tags matching the row [`Matcher`], in document order.
+/// Each column takes its value from the row tag, or from a tag below it; missing values are empty strings.
+/// Values are taken as they appear in the source, so entities are not decoded.
+///
+/// # Example
+/// ```rust
+/// use xmltree::{Document, Matcher, TableExtractor};
+///
+/// let doc = Document::parse_str(r#"<books>
+///     <book id="1"><title>Dune</title><price currency="USD">9.99</price></book>
+///     <book id="2"><title>Emma, Vol. 1</title></book>
+/// </books>"#).unwrap();
+///
+/// let table = TableExtractor::new(Matcher::new().with_name(None, "book"))
+///     .with_attribute_column("id", None, "id")
+///     .with_child_column("title", Matcher::new().with_name(None, "title"))
+///     .with_child_column("price", Matcher::new().with_name(None, "price"))
+///     .with_child_attribute_column("currency", Matcher::new().with_name(None, "price"), None, "currency");
+///
+/// let rows: Vec<_> = table.rows(doc.root()).collect();
+/// assert_eq!(rows[0], ["1", "Dune", "9.99", "USD"]);
+/// assert_eq!(rows[1], ["2", "Emma, Vol. 1", "", ""]);
+///
+/// let mut csv = vec![];
+/// table.write_csv(doc.root(), &mut csv).unwrap();
+/// assert_eq!(
+///     String::from_utf8(csv).unwrap(),
+///     "id,title,price,currency\r\n1,Dune,9.99,USD\r\n2,\"Emma, Vol. 1\",,\r\n"
+/// );
+/// ```
+#[derive(Debug)]
+pub struct TableExtractor {
+    rows: Matcher,
+    headers: Vec<String>,
+    columns: Vec<Column>,
+}
+impl TableExtractor {
+    /// Creates an extractor with one row per tag matching `rows`, and no columns.
+    #[must_use]
+    pub fn new(rows: Matcher) -> Self {
+        Self {
+            rows,
+            headers: vec![],
+            columns: vec![],
+        }
+    }
+
+    fn with_column(mut self, header: &str, column: Column) -> Self {
+        self.headers.push(header.to_string());
+        self.columns.push(column);
+        self
+    }
+
+    /// Adds a column with the value of an attribute of the row tag.
+    #[must_use]
+    pub fn with_attribute_column(self, header: &str, prefix: Option<&str>, name: &str) -> Self {
+        let column = Column::Attribute(prefix.map(str::to_string), name.to_string());
+        self.with_column(header, column)
+    }
+
+    /// Adds a column with the direct text and CDATA of the row tag, joined with a space between each part.
+    #[must_use]
+    pub fn with_text_column(self, header: &str) -> Self {
+        self.with_column(header, Column::Text)
+    }
+
+    /// Adds a column with the text of the first tag below the row tag that matches.
+    ///
+    /// The text is taken as in [`TableExtractor::with_text_column`].
+    #[must_use]
+    pub fn with_child_column(self, header: &str, matcher: Matcher) -> Self {
+        self.with_column(header, Column::ChildText(matcher))
+    }
+
+    /// Adds a column with the value of an attribute of the first tag below the row tag that matches.
+    #[must_use]
+    pub fn with_child_attribute_column(
+        self,
+        header: &str,
+        matcher: Matcher,
+        prefix: Option<&str>,
+        name: &str,
+    ) -> Self {
+        let column = Column::ChildAttribute(matcher, prefix.map(str::to_string), name.to_string());
+        self.with_column(header, column)
+    }
+
+    /// Returns the headers of the columns, in order.
+    #[must_use]
+    pub fn headers(&self) -> &[String] {
+        &self.headers
+    }
+
+    /// Returns an iterator over the rows found at or below `tag`, in document order.
+    pub fn rows<'a>(&'a self, tag: &'a TagNode<'_>) -> impl Iterator<Item = Vec<String>> + 'a {
+        tag.find_all(&self.rows).into_iter().map(|row| {
+            self.columns
+                .iter()
+                .map(|column| column.value(row).unwrap_or_default())
+                .collect()
+        })
+    }
+
+    /// Writes the headers and the rows found at or below `tag` as CSV.
+    ///
+    /// Fields are separated by commas, and records end with `\r\n`.
+    /// Fields containing a comma, quote, or line break are quoted, with quotes doubled.
+    ///
+    /// # Errors
+    /// Returns an error if the writer fails
+    pub fn write_csv<W: std::io::Write>(
+        &self,
+        tag: &TagNode<'_>,
+        mut writer: W,
+    ) -> std::io::Result<()> {
+        write_csv_record(&mut writer, &self.headers)?;
+        for row in self.rows(tag) {
+            write_csv_record(&mut writer, &row)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_csv_record<W: std::io::Write>(writer: &mut W, fields: &[String]) -> std::io::Result<()> {
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            writer.write_all(b",")?;
+        }
+
+        if field.contains([',', '"', '\r', '\n']) {
+            write!(writer, "\"{}\"", field.replace('"', "\"\""))?;
+        } else {
+            writer.write_all(field.as_bytes())?;
+        }
+    }
+    writer.write_all(b"\r\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Document;
+
+    #[test]
+    fn test_table_extractor() {
+        let doc = Document::parse_str(
+            r#"<root><row a="1">x<![CDATA[y]]><inner><deep b='say "hi"'>z</deep></inner></row><row /></root>"#,
+        )
+        .unwrap();
+
+        let table = TableExtractor::new(Matcher::new().with_name(None, "row"))
+            .with_attribute_column("a", None, "a")
+            .with_text_column("text")
+            .with_child_column("deep", Matcher::new().with_name(None, "deep"))
+            .with_child_attribute_column("b", Matcher::new().with_name(None, "deep"), None, "b")
+            .with_child_column("self", Matcher::new().with_name(None, "row"));
+        assert_eq!(table.headers(), ["a", "text", "deep", "b", "self"]);
+
+        let rows: Vec<_> = table.rows(doc.root()).collect();
+        assert_eq!(
+            rows,
+            [
+                vec!["1", "x y", "z", "say \"hi\"", ""],
+                vec!["", "", "", "", ""]
+            ]
+        );
+
+        let mut csv = vec![];
+        table.write_csv(doc.root(), &mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "a,text,deep,b,self\r\n1,x y,z,\"say \"\"hi\"\"\",\r\n,,,,\r\n"
+        );
+    }
+}
@@ -0,0 +1,234 @@
+//! Assertions for comparing documents in tests.
+//!
+//! Use [`assert_xml_eq!`](crate::assert_xml_eq) to compare two documents by content, as [`Document::semantic_eq`] does.
+//! On failure, both documents are written in a normalized form and the lines that differ are shown:
+//! - Nodes are indented with two spaces, one node per line
+//! - Differences ignored by the [`CompareOptions`] are removed; attributes are sorted, whitespace is collapsed, and comments are dropped
+//!
+//! ```rust
+//! use xmltree::{CompareOptions, Document, assert_xml_eq};
+//!
+//! let doc = Document::parse_str("<a y='2' x='1'><!-- note --><b>some  text</b></a>").unwrap();
+//! assert_xml_eq!(doc, "<a x='1' y='2'>\n  <b>some text</b>\n</a>");
+//!
+//! // Strings are parsed, so golden files can be compared directly
+//! let golden = doc.to_xml(None).unwrap();
+//! assert_xml_eq!(doc, golden, CompareOptions::strict());
+//! ```
+use crate::{
+    CompareOptions, Document, OwnedDocument, XmlWriteOptions,
+    node::{OwnedNode, OwnedTagNode, TagNode},
+};
+
+/// A value that can be compared by [`assert_xml_eq!`](crate::assert_xml_eq); a document, tag, or XML string.
+pub trait TestInput {
+    /// Converts the value to a document for comparison.
+    ///
+    /// # Panics
+    /// Panics if the value is a string that is not valid XML.
+    fn to_test_document(&self) -> OwnedDocument;
+}
+impl TestInput for str {
+    fn to_test_document(&self) -> OwnedDocument {
+        match Document::parse_str(self) {
+            Ok(document) => document.to_owned(),
+            Err(e) => panic!("Could not parse XML for comparison: {e}"),
+        }
+    }
+}
+impl TestInput for String {
+    fn to_test_document(&self) -> OwnedDocument {
+        self.as_str().to_test_document()
+    }
+}
+impl TestInput for OwnedDocument {
+    fn to_test_document(&self) -> OwnedDocument {
+        self.clone()
+    }
+}
+impl TestInput for Document<'_> {
+    fn to_test_document(&self) -> OwnedDocument {
+        self.to_owned()
+    }
+}
+impl TestInput for OwnedTagNode {
+    fn to_test_document(&self) -> OwnedDocument {
+        OwnedDocument::new(self.clone())
+    }
+}
+impl TestInput for TagNode<'_> {
+    fn to_test_document(&self) -> OwnedDocument {
+        OwnedDocument::new(self.to_owned())
+    }
+}
+impl<T: TestInput + ?Sized> TestInput for &T {
+    fn to_test_document(&self) -> OwnedDocument {
+        (*self).to_test_document()
+    }
+}
+
+/// Compares two documents by content, returning a readable line diff of them if they are not equal.
+///
+/// See the [`testing`](crate::testing) module for details of the diff.
+///
+/// # Panics
+/// Panics if either document contains strings that cannot be written as XML.
+#[must_use]
+pub fn xml_diff(
+    left: &OwnedDocument,
+    right: &OwnedDocument,
+    options: CompareOptions,
+) -> Option<String> {
+    if left.semantic_eq(right, options) {
+        return None;
+    }
+
+    let left = normalized(left, options);
+    let right = normalized(right, options);
+    let left: Vec<_> = left.lines().collect();
+    let right: Vec<_> = right.lines().collect();
+
+    let mut diff = String::from("XML documents are not equal\n--- left\n+++ right\n");
+    for (marker, line) in diff_lines(&left, &right) {
+        diff.push(marker);
+        diff.push(' ');
+        diff.push_str(line);
+        diff.push('\n');
+    }
+    Some(diff)
+}
+
+/// Writes a document with the differences ignored by `options` removed.
+fn normalized(document: &OwnedDocument, options: CompareOptions) -> String {
+    let mut document = document.clone();
+    let keep = |node: &OwnedNode| match node {
+        OwnedNode::Comment(_) => !options.ignore_comments,
+        OwnedNode::ProcessingInstruction(_) => !options.ignore_processing_instructions,
+        _ => true,
+    };
+    document.prolog.retain(keep);
+    document.epilog.retain(keep);
+
+    let mut stack = vec![&mut document.root];
+    while let Some(tag) = stack.pop() {
+        if options.ignore_attribute_order {
+            tag.attributes
+                .sort_by_cached_key(|attr| (attr.name.to_string(), attr.value.clone()));
+        }
+
+        tag.children.retain(keep);
+        for child in &mut tag.children {
+            match child {
+                OwnedNode::Tag(child) => stack.push(child),
+                OwnedNode::Text(text) if options.ignore_whitespace => {
+                    text.text = text.text.split_whitespace().collect::<Vec<_>>().join(" ");
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let options = XmlWriteOptions::default().with_indent("  ");
+    match document.to_xml_with_options(&options) {
+        Ok(xml) => xml,
+        Err(e) => panic!("Could not serialize document for comparison: {e}"),
+    }
+}
+
+/// Diffs two lists of lines, marking each line with ` `, `-` or `+`.
+fn diff_lines<'a>(left: &[&'a str], right: &[&'a str]) -> Vec<(char, &'a str)> {
+    // Length of the longest common subsequence of left[i..] and right[j..]
+    let width = right.len() + 1;
+    let mut lengths = vec![0usize; (left.len() + 1) * width];
+    for i in (0..left.len()).rev() {
+        for j in (0..right.len()).rev() {
+            lengths[i * width + j] = if left[i] == right[j] {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::with_capacity(left.len().max(right.len()));
+    let (mut i, mut j) = (0, 0);
+    while i < left.len() || j < right.len() {
+        if i < left.len() && j < right.len() && left[i] == right[j] {
+            lines.push((' ', left[i]));
+            i += 1;
+            j += 1;
+        } else if j == right.len()
+            || (i < left.len() && lengths[(i + 1) * width + j] >= lengths[i * width + j + 1])
+        {
+            lines.push(('-', left[i]));
+            i += 1;
+        } else {
+            lines.push(('+', right[j]));
+            j += 1;
+        }
+    }
+    lines
+}
+
+/// Asserts that two documents are equal by content, printing a diff of them if they are not.
+///
+/// Each side can be a [`Document`], [`OwnedDocument`], tag node, or a string of XML to parse.
+/// Differences are ignored as selected by an optional [`CompareOptions`], which defaults to [`CompareOptions::default`].
+///
+/// See the [`testing`](crate::testing) module for details.
+#[macro_export]
+macro_rules! assert_xml_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        $crate::assert_xml_eq!($left, $right, $crate::CompareOptions::default())
+    };
+
+    ($left:expr, $right:expr, $options:expr $(,)?) => {
+        if let Some(diff) = $crate::testing::xml_diff(
+            &$crate::testing::TestInput::to_test_document(&$left),
+            &$crate::testing::TestInput::to_test_document(&$right),
+            $options,
+        ) {
+            panic!("{diff}");
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xml_diff() {
+        let left = "<a y='2' x='1'><!--c--><b>1</b><c>same  text</c></a>".to_test_document();
+        let right = "<a x='1' y='2'><b>2</b><c>same text</c><d/></a>".to_test_document();
+
+        assert_eq!(
+            xml_diff(&left, &right, CompareOptions::default()).unwrap(),
+            concat!(
+                "XML documents are not equal\n--- left\n+++ right\n",
+                "  <a x=\"1\" y=\"2\">\n",
+                "    <b>\n",
+                "-     1\n",
+                "+     2\n",
+                "    </b>\n",
+                "    <c>\n",
+                "      same text\n",
+                "    </c>\n",
+                "+   <d />\n",
+                "  </a>\n",
+            )
+        );
+
+        assert_eq!(xml_diff(&left, &left, CompareOptions::strict()), None);
+    }
+
+    #[test]
+    fn test_assert_xml_eq() {
+        let doc = Document::parse_str("<a><b x='1'/></a>").unwrap();
+        crate::assert_xml_eq!(doc, "<a>\n  <b x='1' />\n</a>");
+        crate::assert_xml_eq!(doc.root(), doc.to_owned(), CompareOptions::strict());
+
+        let result = std::panic::catch_unwind(|| crate::assert_xml_eq!("<a/>", "<b/>"));
+        assert!(result.is_err());
+    }
+}
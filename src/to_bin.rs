@@ -1,18 +1,116 @@
 //! Module for compiling data structures into byte arrays and decoding them back.
 //!
 //! Includes support for the arena allocator to store strings and other data types.
-use std::{io::Write, path::PathBuf};
+//!
+//! With the `derive` feature, [`ToBinHandler`] can be derived for custom types with `#[derive(ToBin)]`:
+//! ```rust
+//! # #[cfg(feature = "derive")] {
+//! use xmltree::{Document, to_bin::{Decoder, Encoder, ToBin, ToBinHandler}};
+//!
+//! #[derive(ToBin)]
+//! struct CacheEntry<'src> {
+//!     key: String,
+//!     hits: usize,
+//!     document: Document<'src>,
+//! }
+//!
+//! let document = Document::parse_str("<a><b /></a>").unwrap();
+//! let entry = CacheEntry { key: "a".to_string(), hits: 3, document };
+//!
+//! let mut encoder = Encoder::new();
+//! entry.write(&mut encoder).unwrap();
+//! let bin = encoder.into_inner();
+//!
+//! let decoded = CacheEntry::read(&mut Decoder::new(&bin)).unwrap();
+//! assert_eq!(decoded.hits, 3);
+//! assert_eq!(decoded.document.root().name(), "a");
+//! # }
+//! ```
+use std::{collections::HashMap, io::Write, path::PathBuf};
+
+#[cfg(feature = "derive")]
+pub use xmltree_derive::ToBin;
+
+/// The newest version of the binary document format; written by default, and the newest that can be read.
+///
+/// - Version 1 has no version header, and stores every string inline.
+/// - Version 2 starts with a version header, and may use the [`Capabilities`] it lists.
+/// - Version 3 stores the span of the whole comment along with its text.
+/// - Version 4 stores the internal subset of a DTD, so it is kept for nodes without a source.
+pub const FORMAT_VERSION: u8 = 4;
+
+/// Marks the start of the version header
+const HEADER_VERSION: &[u8] = b"XMLV";
+
+/// Optional features of the binary format, listed in the version header.
+///
+/// A decoder refuses data that uses capabilities it does not know,
+/// instead of misreading it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Capabilities(u32);
+impl Capabilities {
+    /// Repeated names are written once, then referred to by index. See [`Encoder::with_string_table`].
+    pub const STRING_TABLE: Self = Self(1);
+
+    /// The data ends with a CRC-32 of everything before it, checked when decoding.
+    pub const CHECKSUM: Self = Self(2);
+
+    /// Every capability understood by this version of the crate.
+    pub const SUPPORTED: Self = Self(Self::STRING_TABLE.0 | Self::CHECKSUM.0);
+
+    /// No optional features.
+    #[must_use]
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Creates a set of capabilities from its raw bits, including unknown ones.
+    #[must_use]
+    pub const fn from_bits(bits: u32) -> Self {
+        Self(bits)
+    }
+
+    /// Returns the raw bits of the set.
+    #[must_use]
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Returns true if every capability in `other` is also in this set.
+    #[must_use]
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+impl std::ops::BitOr for Capabilities {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
 
 /// Binary decoder for reading data from a byte stream.
 ///
 /// Uses an arena for allocating string references.
 ///
 /// WARNING: This structure can cause a stack-overflow for very deep trees!
-/// Use only on trusted data!
+/// Use only on trusted data, or set a limit with [`Decoder::with_max_depth`].
 pub struct Decoder<'src> {
     buf: &'src [u8],
     cursor: usize,
     src: Option<&'src str>,
+
+    /// Current tag nesting depth, and the deepest allowed
+    depth: usize,
+    max_depth: Option<usize>,
+
+    /// Strings read so far through [`Decoder::read_shared_str`], if the string table is enabled
+    strings: Option<Vec<&'src str>>,
+
+    /// Format version and capabilities of the data, from [`Decoder::read_format_header`]
+    version: u8,
+    capabilities: Capabilities,
 }
 impl<'src> Decoder<'src> {
     /// Creates a new `Decoder` instance for the the given byte stream and arena.
@@ -22,6 +120,11 @@ impl<'src> Decoder<'src> {
             buf,
             cursor: 0,
             src: None,
+            depth: 0,
+            max_depth: None,
+            strings: None,
+            version: 1,
+            capabilities: Capabilities::empty(),
         }
     }
 
@@ -83,15 +186,203 @@ impl<'src> Decoder<'src> {
     pub fn source(&self) -> Option<&'src str> {
         self.src
     }
+
+    /// Limits how deeply tags can be nested below the root.
+    ///
+    /// Uses the same depth as [`Parser::with_max_depth`](crate::Parser::with_max_depth): the root is at depth 0.
+    pub fn with_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = Some(max_depth);
+    }
+
+    /// Records that a nested tag is being decoded; must be paired with [`Decoder::exit_tag`].
+    ///
+    /// # Errors
+    /// Fails if the tag would be deeper than the limit set with [`Decoder::with_max_depth`].
+    pub fn enter_tag(&mut self) -> Result<(), BinDecodeError> {
+        // The first tag entered is the root, at depth 0
+        if let Some(max_depth) = self.max_depth
+            && self.depth > max_depth
+        {
+            return Err(BinDecodeError::DepthLimitExceeded(max_depth));
+        }
+
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// Records that a nested tag has been decoded.
+    pub fn exit_tag(&mut self) {
+        self.depth = self.depth.saturating_sub(1);
+    }
+
+    /// Reads the version header written by [`Encoder::write_format_header`], if there is one.
+    ///
+    /// Data without a header is treated as version 1, with no capabilities.
+    ///
+    /// # Errors
+    /// Fails if the data is truncated, or uses a version or capabilities this crate does not understand.
+    pub fn read_format_header(&mut self) -> Result<(), BinDecodeError> {
+        let end = self.cursor + HEADER_VERSION.len();
+        if self.buf.get(self.cursor..end) != Some(HEADER_VERSION) {
+            self.version = 1;
+            self.capabilities = Capabilities::empty();
+            return Ok(());
+        }
+        self.cursor = end;
+
+        let version = u8::read(self)?;
+        if !(2..=FORMAT_VERSION).contains(&version) {
+            return Err(BinDecodeError::UnsupportedVersion {
+                found: version,
+                supported: FORMAT_VERSION,
+            });
+        }
+
+        let mut bits = [0u8; 4];
+        self.read_exact(&mut bits)?;
+        let capabilities = Capabilities::from_bits(u32::from_le_bytes(bits));
+        if !Capabilities::SUPPORTED.contains(capabilities) {
+            return Err(BinDecodeError::UnsupportedCapabilities {
+                found: capabilities.bits(),
+                supported: Capabilities::SUPPORTED.bits(),
+            });
+        }
+
+        self.version = version;
+        self.capabilities = capabilities;
+        Ok(())
+    }
+
+    /// Returns the format version of the data; 1 until a version header is read.
+    #[must_use]
+    pub fn format_version(&self) -> u8 {
+        self.version
+    }
+
+    /// Returns the capabilities listed in the version header; empty until one is read.
+    #[must_use]
+    pub fn capabilities(&self) -> Capabilities {
+        self.capabilities
+    }
+
+    /// Reads a checksum written by [`Encoder::write_checksum`], and compares it to the data read since `start`.
+    ///
+    /// # Errors
+    /// Fails if the data is truncated, or the checksum does not match.
+    pub fn read_checksum(&mut self, start: usize) -> Result<(), BinDecodeError> {
+        let found = crc32(&self.buf[start..self.cursor]);
+        let mut expected = [0u8; 4];
+        self.read_exact(&mut expected)?;
+        let expected = u32::from_le_bytes(expected);
+
+        if found != expected {
+            return Err(BinDecodeError::ChecksumMismatch { expected, found });
+        }
+        Ok(())
+    }
+
+    /// Checks the last 4 bytes of the buffer as a checksum of the data from `start` up to them.
+    ///
+    /// Used to tell corrupted data apart from other decoding errors, before the checksum is reached.
+    ///
+    /// # Errors
+    /// Fails if the checksum does not match, or there is no room for one.
+    pub fn verify_trailing_checksum(&self, start: usize) -> Result<(), BinDecodeError> {
+        let Some(end) = self.buf.len().checked_sub(4).filter(|end| *end >= start) else {
+            return Err(BinDecodeError::UnexpectedEof);
+        };
+
+        let mut expected = [0u8; 4];
+        expected.copy_from_slice(&self.buf[end..]);
+        let expected = u32::from_le_bytes(expected);
+        let found = crc32(&self.buf[start..end]);
+
+        if found != expected {
+            return Err(BinDecodeError::ChecksumMismatch { expected, found });
+        }
+        Ok(())
+    }
+
+    /// Moves past an encoded [`Node`](crate::node::Node) without building it.
+    ///
+    /// Tags are skipped along with their descendants, without allocating for them.
+    /// Names are still read, so a string table stays in step with the encoder.
+    ///
+    /// # Errors
+    /// Fails if the data is corrupted or truncated, or tags are nested deeper than the limit.
+    pub fn skip_node(&mut self) -> Result<(), BinDecodeError> {
+        crate::node::Node::skip(self)
+    }
+
+    /// Starts a new, empty string table. See [`Encoder::with_string_table`].
+    pub fn with_string_table(&mut self) {
+        self.strings = Some(vec![]);
+    }
+
+    /// Stops using the string table.
+    pub fn without_string_table(&mut self) {
+        self.strings = None;
+    }
+
+    /// Returns true if shared strings are read through a string table.
+    #[must_use]
+    pub fn has_string_table(&self) -> bool {
+        self.strings.is_some()
+    }
+
+    /// Reads a string written with [`Encoder::write_shared_str`].
+    ///
+    /// # Errors
+    /// Fails if the data is truncated, or refers to a string that has not been read yet.
+    pub fn read_shared_str(&mut self) -> Result<&'src str, BinDecodeError> {
+        if !self.has_string_table() {
+            return <&str>::read(self);
+        }
+
+        let mut index = [0u8; 4];
+        self.read_exact(&mut index)?;
+        let index = u32::from_le_bytes(index);
+        if index != NEW_STRING {
+            let strings = self.strings.as_deref().unwrap_or_default();
+            return strings
+                .get(index as usize)
+                .copied()
+                .ok_or(BinDecodeError::InvalidStringReference(index));
+        }
+
+        let text = <&str>::read(self)?;
+        if let Some(strings) = &mut self.strings {
+            strings.push(text);
+        }
+        Ok(text)
+    }
 }
 
+/// Marker written in place of a string table index, when the string follows inline
+const NEW_STRING: u32 = u32::MAX;
+
 /// Binary encoder for writing data to a byte stream.
 ///
 /// WARNING: This structure can cause a stack-overflow for very deep trees!
-/// Use only on trusted data!
+/// Use only on trusted data, or set a limit with [`Encoder::with_max_depth`].
 pub struct Encoder {
     buf: Vec<u8>,
     source_header_flag: bool,
+
+    /// Current tag nesting depth, and the deepest allowed
+    depth: usize,
+    max_depth: Option<usize>,
+
+    /// Address range of the source string, used to check that spans still point into it
+    source_range: Option<std::ops::Range<usize>>,
+    source_mismatch: bool,
+
+    /// Index of each string written through [`Encoder::write_shared_str`], if the string table is enabled
+    strings: Option<HashMap<String, u32>>,
+
+    /// Format version and capabilities to write, for compatibility with older readers
+    version: u8,
+    capabilities: Capabilities,
 }
 impl Default for Encoder {
     fn default() -> Self {
@@ -105,6 +396,13 @@ impl Encoder {
         Self {
             buf: Vec::new(),
             source_header_flag: false,
+            depth: 0,
+            max_depth: None,
+            source_range: None,
+            source_mismatch: false,
+            strings: None,
+            version: FORMAT_VERSION,
+            capabilities: Capabilities::SUPPORTED,
         }
     }
 
@@ -113,6 +411,51 @@ impl Encoder {
         self.source_header_flag = true;
     }
 
+    /// Enables the source header, and records the source so spans written afterwards can be checked against it.
+    pub(crate) fn with_checked_source(&mut self, source: &str) {
+        let start = source.as_ptr() as usize;
+        self.source_header_flag = true;
+        self.source_range = Some(start..start + source.len());
+        self.source_mismatch = false;
+    }
+
+    /// Disables the source header, and stops checking spans against the source.
+    pub(crate) fn without_source(&mut self) {
+        self.source_header_flag = false;
+        self.source_range = None;
+        self.source_mismatch = false;
+    }
+
+    /// Checks that a span written as an offset really is the text at that offset in the source.
+    ///
+    /// Spans of nodes that were added or modified after parsing may point elsewhere.
+    pub(crate) fn check_source_span(&mut self, text: &str, start: usize) {
+        let Some(range) = &self.source_range else {
+            return;
+        };
+
+        let in_source = if text.is_empty() {
+            start <= range.len()
+        } else {
+            let address = text.as_ptr() as usize;
+            address >= range.start
+                && address + text.len() <= range.end
+                && address - range.start == start
+        };
+        self.source_mismatch |= !in_source;
+    }
+
+    /// Returns true if a span that does not match the source was written since the source was set.
+    #[must_use]
+    pub(crate) fn has_source_mismatch(&self) -> bool {
+        self.source_mismatch
+    }
+
+    /// Discards everything written after the given length.
+    pub(crate) fn truncate(&mut self, len: usize) {
+        self.buf.truncate(len);
+    }
+
     /// If true, strings should be stored as offsets into the source string.
     #[must_use]
     pub fn has_source_header(&self) -> bool {
@@ -137,6 +480,149 @@ impl Encoder {
         self.buf
     }
 
+    /// Limits how deeply tags can be nested below the root.
+    ///
+    /// Uses the same depth as [`Parser::with_max_depth`](crate::Parser::with_max_depth): the root is at depth 0.
+    pub fn with_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = Some(max_depth);
+    }
+
+    /// Records that a nested tag is being encoded; must be paired with [`Encoder::exit_tag`].
+    ///
+    /// # Errors
+    /// Fails if the tag would be deeper than the limit set with [`Encoder::with_max_depth`].
+    pub fn enter_tag(&mut self) -> std::io::Result<()> {
+        if let Some(max_depth) = self.max_depth
+            && self.depth > max_depth
+        {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Tags are nested deeper than the limit of {max_depth}"),
+            ));
+        }
+
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// Records that a nested tag has been encoded.
+    pub fn exit_tag(&mut self) {
+        self.depth = self.depth.saturating_sub(1);
+    }
+
+    /// Sets the format version to write, so the data can be read by older versions of this crate.
+    ///
+    /// Version 1 has no version header and no capabilities. Defaults to [`FORMAT_VERSION`].
+    pub fn with_format_version(&mut self, version: u8) {
+        self.version = version;
+    }
+
+    /// Sets which optional features may be used when writing. Defaults to [`Capabilities::SUPPORTED`].
+    ///
+    /// Ignored for format version 1.
+    pub fn with_capabilities(&mut self, capabilities: Capabilities) {
+        self.capabilities = capabilities;
+    }
+
+    /// Returns the format version that will be written.
+    #[must_use]
+    pub fn format_version(&self) -> u8 {
+        self.version
+    }
+
+    /// Returns the capabilities that may be used; always empty for format version 1.
+    #[must_use]
+    pub fn capabilities(&self) -> Capabilities {
+        if self.version < 2 {
+            Capabilities::empty()
+        } else {
+            self.capabilities
+        }
+    }
+
+    /// Writes the version header, listing the format version and capabilities.
+    ///
+    /// Writes nothing for format version 1, which has no header.
+    ///
+    /// # Errors
+    /// Fails if the format version is newer than [`FORMAT_VERSION`], or the buffer cannot be resized.
+    pub fn write_format_header(&mut self) -> std::io::Result<()> {
+        match self.version {
+            0 => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Format version 0 does not exist",
+            )),
+            1 => Ok(()),
+            version if version > FORMAT_VERSION => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("Format version {version} is newer than the supported {FORMAT_VERSION}"),
+            )),
+            version => {
+                self.write_all(HEADER_VERSION)?;
+                version.write(self)?;
+                let bits = self.capabilities.bits().to_le_bytes();
+                self.write_all(&bits)
+            }
+        }
+    }
+
+    /// Writes a CRC-32 of everything written since `start`.
+    ///
+    /// # Errors
+    /// Can fail if the buffer cannot be resized.
+    pub fn write_checksum(&mut self, start: usize) -> std::io::Result<()> {
+        let checksum = crc32(&self.buf[start..]);
+        self.write_all(&checksum.to_le_bytes())
+    }
+
+    /// Starts a new, empty string table.
+    ///
+    /// While enabled, each string written with [`Encoder::write_shared_str`] is stored inline once,
+    /// and every later copy is written as a 4-byte back-reference to it.
+    /// The decoder must enable its own table at the same point in the stream.
+    pub fn with_string_table(&mut self) {
+        self.strings = Some(HashMap::new());
+    }
+
+    /// Stops using the string table.
+    pub fn without_string_table(&mut self) {
+        self.strings = None;
+    }
+
+    /// Returns true if shared strings are written through a string table.
+    #[must_use]
+    pub fn has_string_table(&self) -> bool {
+        self.strings.is_some()
+    }
+
+    /// Writes a string that is likely to repeat, such as an element name.
+    ///
+    /// Without a string table, this is the same as writing the `&str`.
+    ///
+    /// # Errors
+    /// Can fail if the buffer cannot be resized.
+    pub fn write_shared_str(&mut self, text: &str) -> std::io::Result<()> {
+        let Some(strings) = &mut self.strings else {
+            return text.write(self);
+        };
+
+        if let Some(index) = strings.get(text) {
+            let index = *index;
+            return self.write_all(&index.to_le_bytes());
+        }
+
+        // The table is full; later copies of this string are written inline as well
+        let index = u32::try_from(strings.len())
+            .ok()
+            .filter(|index| *index != NEW_STRING);
+        if let Some(index) = index {
+            strings.insert(text.to_string(), index);
+        }
+
+        self.write_all(&NEW_STRING.to_le_bytes())?;
+        text.write(self)
+    }
+
     /// Write bytes to the encoder.
     ///
     /// # Errors
@@ -146,6 +632,34 @@ impl Encoder {
     }
 }
 
+/// CRC-32 with the IEEE polynomial, as used by zip and PNG.
+fn crc32(bytes: &[u8]) -> u32 {
+    const TABLE: [u32; 256] = {
+        let mut table = [0u32; 256];
+        let mut i = 0u32;
+        while i < 256 {
+            let mut crc = i;
+            let mut bit = 0;
+            while bit < 8 {
+                crc = if crc & 1 == 0 {
+                    crc >> 1
+                } else {
+                    (crc >> 1) ^ 0xEDB8_8320
+                };
+                bit += 1;
+            }
+            table[i as usize] = crc;
+            i += 1;
+        }
+        table
+    };
+
+    let crc = bytes.iter().fold(!0u32, |crc, byte| {
+        TABLE[((crc ^ u32::from(*byte)) & 0xFF) as usize] ^ (crc >> 8)
+    });
+    !crc
+}
+
 /// Binary handler trait for encoding and decoding data types.
 pub trait ToBinHandler<'src>: Sized {
     /// Writes the value to the encoder.  
@@ -335,6 +849,48 @@ pub enum BinDecodeError {
     /// Error occurred while trying to read the header from the stream.
     #[error("Data did not have a valid header")]
     InvalidHeader,
+
+    /// A span referred to a range outside of the source string.
+    #[error("Span is outside of the source string")]
+    InvalidSpan,
+
+    /// Tags were nested deeper than the configured limit.
+    #[error("Tags are nested deeper than the limit of {0}")]
+    DepthLimitExceeded(usize),
+
+    /// A string table back-reference pointed past the strings read so far.
+    #[error("String table reference {0} is out of bounds")]
+    InvalidStringReference(u32),
+
+    /// The checksum at the end of the data does not match its contents.
+    #[error("Checksum mismatch; expected {expected:#010x}, found {found:#010x}")]
+    ChecksumMismatch {
+        /// The checksum stored in the data.
+        expected: u32,
+
+        /// The checksum of the data as read.
+        found: u32,
+    },
+
+    /// The data was written with a format version this crate cannot read.
+    #[error("Unsupported format version {found}; versions up to {supported} are supported")]
+    UnsupportedVersion {
+        /// The version in the data.
+        found: u8,
+
+        /// The newest version this crate can read.
+        supported: u8,
+    },
+
+    /// The data uses optional features this crate does not know.
+    #[error("Unsupported format capabilities {found:#x}; supported are {supported:#x}")]
+    UnsupportedCapabilities {
+        /// The capability bits in the data.
+        found: u32,
+
+        /// The capability bits this crate can read.
+        supported: u32,
+    },
 }
 
 #[cfg(test)]
@@ -420,6 +976,63 @@ mod tests {
         assert_eq!(<(u8, String)>::read(&mut decoder).unwrap(), input);
     }
 
+    #[test]
+    fn test_shared_str_encoding_decoding() {
+        let mut encoder = Encoder::new();
+        encoder.with_string_table();
+        for text in ["row", "cell", "row", "row"] {
+            encoder.write_shared_str(text).unwrap();
+        }
+
+        // Two inline strings, then two 4-byte references
+        let buffer = encoder.into_inner();
+        assert_eq!(buffer.len(), 2 * (4 + 8) + 3 + 4 + 2 * 4);
+
+        let mut decoder = Decoder::new(buffer.as_slice());
+        decoder.with_string_table();
+        for text in ["row", "cell", "row", "row"] {
+            assert_eq!(decoder.read_shared_str().unwrap(), text);
+        }
+    }
+
+    #[test]
+    fn test_format_header() {
+        let mut encoder = Encoder::new();
+        encoder.write_format_header().unwrap();
+        let buffer = encoder.into_inner();
+
+        let mut decoder = Decoder::new(buffer.as_slice());
+        decoder.read_format_header().unwrap();
+        assert_eq!(decoder.format_version(), FORMAT_VERSION);
+        assert_eq!(decoder.capabilities(), Capabilities::SUPPORTED);
+
+        let mut newer = buffer.clone();
+        newer[4] = FORMAT_VERSION + 1;
+        assert!(matches!(
+            Decoder::new(&newer).read_format_header(),
+            Err(BinDecodeError::UnsupportedVersion { found, supported: FORMAT_VERSION })
+                if found == FORMAT_VERSION + 1
+        ));
+
+        let mut unknown = buffer;
+        unknown[5] |= 0x80;
+        assert!(matches!(
+            Decoder::new(&unknown).read_format_header(),
+            Err(BinDecodeError::UnsupportedCapabilities { .. })
+        ));
+
+        // Version 1 has no header, so the decoder stays where it was
+        let mut decoder = Decoder::new(b"XML2");
+        decoder.read_format_header().unwrap();
+        assert_eq!((decoder.format_version(), decoder.cursor()), (1, 0));
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
     #[test]
     fn test_pathbuf_encoding_decoding() {
         let mut encoder = Encoder::new();
@@ -1,13 +1,434 @@
 //! XML formatting module
 //!
 //! Use [`Document::to_xml`] unless you need to write the XML to a file or other writer.
-use crate::Document;
-use crate::node::{EntityDefinition, ExternalId, Node, NodeName, TagNode};
+use crate::node::{EntityDefinition, ExternalId, Node, OwnedTagNode, TagNode};
+use crate::strspan::normalize_line_endings;
+use crate::{CowNode, CowTag, Document, OwnedDocument};
 use htmlentity::entity::ICodedDataTrait;
 use htmlentity::entity::{CharacterSet, EncodeType, encode};
+use std::borrow::Cow;
+use std::fmt::Write;
 
 const TAB: &str = "\t";
 
+/// Options controlling how a document is formatted as XML.
+///
+/// # Example
+/// ```rust
+/// use xmltree::{ControlCharPolicy, Document, XmlWriteOptions};
+///
+/// let doc = Document::parse_str("<test>text</test>").unwrap();
+/// let options = XmlWriteOptions::default()
+///     .with_indent("  ")
+///     .with_control_chars(ControlCharPolicy::Strip);
+///
+/// let xml = doc.to_xml_with_options(&options).unwrap();
+/// assert_eq!(xml, "<test>\n  text\n</test>\n");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[expect(clippy::struct_excessive_bools, reason = "Independent switches")]
+pub struct XmlWriteOptions {
+    /// The string used to indent nested nodes. Defaults to a tab.
+    pub indent: String,
+
+    /// How to handle characters that are not allowed in XML 1.0 documents.
+    pub control_chars: ControlCharPolicy,
+
+    /// Write nodes without indentation or line breaks between them.
+    ///
+    /// Line breaks inside text and attribute values are written as character references,
+    /// so the output never spans more than one line. A document still ends with a line break.
+    pub minify: bool,
+
+    /// Write a UTF-8 byte order mark before documents that were parsed with one. See [`Document::has_bom`].
+    pub preserve_bom: bool,
+
+    /// Write line breaks as `\r\n`, for consumers that expect Windows line endings.
+    ///
+    /// This covers the breaks between nodes and those inside text, comments and attribute values,
+    /// which are written as `\r\n` whether they were `\n`, `\r\n` or `\r` in the tree.
+    pub crlf: bool,
+
+    /// Wrap text nodes at word boundaries, so that lines fit in this many columns, counting indentation.
+    ///
+    /// Indentation counts one column per character, tabs included. Words longer than the width are not broken.
+    /// Wrapping implies [`XmlWriteOptions::reindent_text`], and joins the words of each line with single spaces.
+    /// Ignored when minifying.
+    pub wrap_width: Option<usize>,
+
+    /// Write each line of multi-line text at the indentation of the text node, trimming its original indentation.
+    ///
+    /// Whitespace at the ends of lines is not kept, so this should not be used where it matters, as with `xml:space="preserve"`.
+    /// Ignored when minifying.
+    pub reindent_text: bool,
+
+    /// Write the attributes of each tag in this order, instead of the order they are in the tree.
+    ///
+    /// Useful for output that stays the same however the tree was built, such as generated files kept under version control.
+    /// The sort is stable. [`XmlWriter`](crate::XmlWriter) writes attributes as they are given, and does not sort them.
+    pub sort_attributes: Option<AttrSort>,
+
+    /// Which comments to write. Defaults to all of them.
+    pub comments: CommentPolicy,
+
+    /// Chooses the indentation of each node, instead of repeating [`XmlWriteOptions::indent`] once per level.
+    ///
+    /// Ignored when minifying.
+    pub indent_fn: Option<IndentFn>,
+}
+impl Default for XmlWriteOptions {
+    fn default() -> Self {
+        Self {
+            indent: TAB.to_string(),
+            control_chars: ControlCharPolicy::default(),
+            minify: false,
+            preserve_bom: false,
+            crlf: false,
+            wrap_width: None,
+            reindent_text: false,
+            sort_attributes: None,
+            comments: CommentPolicy::default(),
+            indent_fn: None,
+        }
+    }
+}
+impl XmlWriteOptions {
+    /// Sets the string used to indent nested nodes.
+    #[must_use]
+    pub fn with_indent(mut self, indent: impl Into<String>) -> Self {
+        self.indent = indent.into();
+        self
+    }
+
+    /// Sets how characters that are not allowed in XML 1.0 documents are handled.
+    #[must_use]
+    pub fn with_control_chars(mut self, policy: ControlCharPolicy) -> Self {
+        self.control_chars = policy;
+        self
+    }
+
+    /// Sets whether nodes are written without indentation or line breaks.
+    #[must_use]
+    pub fn with_minify(mut self, minify: bool) -> Self {
+        self.minify = minify;
+        self
+    }
+
+    /// Sets whether documents parsed with a byte order mark are written with one.
+    #[must_use]
+    pub fn with_preserve_bom(mut self, preserve_bom: bool) -> Self {
+        self.preserve_bom = preserve_bom;
+        self
+    }
+
+    /// Sets whether line breaks are written as `\r\n` instead of `\n`.
+    #[must_use]
+    pub fn with_crlf(mut self, crlf: bool) -> Self {
+        self.crlf = crlf;
+        self
+    }
+
+    /// Sets the column at which text nodes are wrapped.
+    #[must_use]
+    pub fn with_wrap_width(mut self, width: usize) -> Self {
+        self.wrap_width = Some(width);
+        self
+    }
+
+    /// Sets whether each line of multi-line text is written at the indentation of the text node.
+    #[must_use]
+    pub fn with_reindent_text(mut self, reindent_text: bool) -> Self {
+        self.reindent_text = reindent_text;
+        self
+    }
+
+    /// Sets the order the attributes of each tag are written in.
+    #[must_use]
+    pub fn with_sort_attributes(mut self, sort: AttrSort) -> Self {
+        self.sort_attributes = Some(sort);
+        self
+    }
+
+    /// Sets which comments are written.
+    #[must_use]
+    pub fn with_comments(mut self, policy: CommentPolicy) -> Self {
+        self.comments = policy;
+        self
+    }
+
+    /// Sets a callback that chooses the indentation of each node; see [`IndentFn`].
+    #[must_use]
+    pub fn with_indent_fn(mut self, indent_fn: fn(&[&str]) -> Cow<'static, str>) -> Self {
+        self.indent_fn = Some(IndentFn(indent_fn));
+        self
+    }
+
+    /// Sorts attributes, given by name, in the order set by [`XmlWriteOptions::sort_attributes`].
+    pub(crate) fn order_attributes<V>(&self, attributes: &mut [(String, V)]) {
+        if let Some(sort) = self.sort_attributes {
+            attributes.sort_by(|(a, _), (b, _)| sort.compare(a, b));
+        }
+    }
+
+    /// Returns the indentation for the given depth, and the line break written after each node.
+    pub(crate) fn layout(&self, depth: usize) -> (String, &'static str) {
+        if self.minify {
+            (String::new(), "")
+        } else {
+            (self.indent.repeat(depth), self.line_break())
+        }
+    }
+
+    /// Returns the indentation for a node inside `depth` elements, and the line break written after it.
+    ///
+    /// The names of the elements, as written, are only read if an [`IndentFn`] is set.
+    pub(crate) fn layout_in(&self, depth: usize, names: &[String]) -> (String, &'static str) {
+        match self.indent_fn {
+            Some(IndentFn(indent_fn)) if !self.minify => {
+                let names: Vec<&str> = names.iter().map(String::as_str).collect();
+                (indent_fn(&names).into_owned(), self.line_break())
+            }
+            _ => self.layout(depth),
+        }
+    }
+
+    /// Lays out encoded text as indented lines inside `depth` elements, wrapping and re-indenting it if the options ask for it.
+    pub(crate) fn layout_text(&self, text: &str, depth: usize, names: &[String]) -> String {
+        let (tab, nl) = self.layout_in(depth, names);
+        if self.minify || (self.wrap_width.is_none() && !self.reindent_text) {
+            return format!("{tab}{text}{nl}");
+        }
+
+        let indent = tab.chars().count();
+        let mut output = String::with_capacity(text.len() + tab.len());
+        for line in text.lines().map(str::trim) {
+            let Some(width) = self.wrap_width.filter(|_| !line.is_empty()) else {
+                let tab = if line.is_empty() { "" } else { &tab };
+                let _ = write!(output, "{tab}{line}{nl}");
+                continue;
+            };
+
+            let mut column = 0;
+            for word in line.split_whitespace() {
+                let len = word.chars().count();
+                if column > 0 && indent + column + 1 + len > width {
+                    output.push_str(nl);
+                    column = 0;
+                }
+
+                if column == 0 {
+                    output.push_str(&tab);
+                } else {
+                    output.push(' ');
+                    column += 1;
+                }
+                output.push_str(word);
+                column += len;
+            }
+            output.push_str(nl);
+        }
+        output
+    }
+
+    /// Returns the line break written at the end of a document, even when minified.
+    pub(crate) fn line_break(&self) -> &'static str {
+        if self.crlf { "\r\n" } else { "\n" }
+    }
+
+    pub(crate) fn from_tab_char(tab_char: Option<&str>) -> Self {
+        Self::default().with_indent(tab_char.unwrap_or(TAB))
+    }
+}
+
+/// An order for the attributes of each tag; see [`XmlWriteOptions::sort_attributes`].
+///
+/// # Example
+/// ```rust
+/// use xmltree::{AttrSort, Document, XmlWriteOptions};
+///
+/// let doc = Document::parse_str(r#"<a z="1" xmlns:b="urn:b" b:y="2" x="3" xmlns="urn:a" />"#).unwrap();
+///
+/// let options = XmlWriteOptions::default().with_sort_attributes(AttrSort::XmlnsFirst);
+/// assert_eq!(
+///     doc.to_xml_with_options(&options).unwrap(),
+///     "<a xmlns=\"urn:a\" xmlns:b=\"urn:b\" x=\"3\" z=\"1\" b:y=\"2\" />\n"
+/// );
+///
+/// let options = XmlWriteOptions::default().with_sort_attributes(AttrSort::Custom(|a, b| b.cmp(a)));
+/// assert!(doc.to_xml_with_options(&options).unwrap().starts_with("<a z=\"1\" xmlns:b="));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub enum AttrSort {
+    /// By prefix, then local name, with unprefixed names first; the order of [`OwnedTagNode::sort_attributes`].
+    Alphabetical,
+
+    /// Namespace declarations first, with `xmlns` before `xmlns:prefix`, then the other attributes alphabetically.
+    XmlnsFirst,
+
+    /// By a comparison of attribute names, written as `local` or `prefix:local`.
+    Custom(fn(&str, &str) -> std::cmp::Ordering),
+}
+impl PartialEq for AttrSort {
+    /// Custom orders are equal only if they use the same function.
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Custom(a), Self::Custom(b)) => std::ptr::fn_addr_eq(*a, *b),
+            _ => std::mem::discriminant(self) == std::mem::discriminant(other),
+        }
+    }
+}
+impl Eq for AttrSort {}
+impl AttrSort {
+    fn compare(self, a: &str, b: &str) -> std::cmp::Ordering {
+        // Splits a name into its prefix and local name, for alphabetical order
+        let key = |name| match name {
+            "xmlns" => (true, None, name),
+            _ => match name.split_once(':') {
+                Some(("xmlns", local)) => (true, Some("xmlns"), local),
+                Some((prefix, local)) => (false, Some(prefix), local),
+                None => (false, None, name),
+            },
+        };
+
+        let ((a_ns, a_prefix, a_local), (b_ns, b_prefix, b_local)) = (key(a), key(b));
+        match self {
+            Self::Alphabetical => (a_prefix, a_local).cmp(&(b_prefix, b_local)),
+            Self::XmlnsFirst => (!a_ns, a_prefix, a_local).cmp(&(!b_ns, b_prefix, b_local)),
+            Self::Custom(compare) => compare(a, b),
+        }
+    }
+}
+
+/// A callback choosing the indentation of each node; see [`XmlWriteOptions::indent_fn`].
+///
+/// It is given the names of the elements enclosing the node, outermost first, as `local` or `prefix:local`;
+/// the depth of the node is their number. Closing tags are indented like their opening tag.
+/// Declarations inside a DTD are always indented with [`XmlWriteOptions::indent`].
+///
+/// The callback gets the enclosing elements rather than the depth and the node itself, since layouts such as
+/// leaving the content of `<pre>` unindented depend on every element a node is inside, not only its parent or itself.
+/// The names are only collected while writing if a callback is set.
+///
+/// # Example
+/// ```rust
+/// use std::borrow::Cow;
+/// use xmltree::{Document, XmlWriteOptions};
+///
+/// // Nothing inside <pre> is indented, and levels past the second use a single space
+/// fn indent(ancestors: &[&str]) -> Cow<'static, str> {
+///     if ancestors.contains(&"pre") {
+///         Cow::Borrowed("")
+///     } else {
+///         let depth = ancestors.len();
+///         Cow::Owned("  ".repeat(depth.min(2)) + &" ".repeat(depth.saturating_sub(2)))
+///     }
+/// }
+///
+/// let doc = Document::parse_str("<a><b><c><d /></c><pre><i>x</i></pre></b></a>").unwrap();
+/// let xml = doc.to_xml_with_options(&XmlWriteOptions::default().with_indent_fn(indent)).unwrap();
+/// assert_eq!(xml, concat!(
+///     "<a>\n",
+///     "  <b>\n",
+///     "    <c>\n     <d />\n    </c>\n",
+///     "    <pre>\n<i>\nx\n</i>\n    </pre>\n",
+///     "  </b>\n",
+///     "</a>\n",
+/// ));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct IndentFn(pub fn(&[&str]) -> Cow<'static, str>);
+impl PartialEq for IndentFn {
+    /// Callbacks are equal only if they are the same function.
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::fn_addr_eq(self.0, other.0)
+    }
+}
+impl Eq for IndentFn {}
+
+/// Which comments the writer keeps; see [`XmlWriteOptions::comments`].
+///
+/// Whatever the policy, `--` is not allowed inside a comment, or `-` at its end, so kept comments have a space
+/// written between such hyphens, and after a final one, instead of producing invalid XML.
+///
+/// # Example
+/// ```rust
+/// use xmltree::{CommentPolicy, Document, XmlWriteOptions};
+///
+/// let doc = Document::parse_str("<!-- License: MIT --><a><!-- TODO: remove --><b /></a>").unwrap();
+///
+/// let options = XmlWriteOptions::default()
+///     .with_minify(true)
+///     .with_comments(CommentPolicy::KeepPrefixed("License:".to_string()));
+/// assert_eq!(doc.to_xml_with_options(&options).unwrap(), "<!-- License: MIT --><a><b /></a>\n");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub enum CommentPolicy {
+    /// Write every comment.
+    #[default]
+    Keep,
+
+    /// Write no comments.
+    Drop,
+
+    /// Write only comments whose text starts with the given prefix, ignoring leading whitespace; such as license headers.
+    KeepPrefixed(String),
+}
+impl CommentPolicy {
+    /// Returns true if a comment with the given text is written.
+    pub(crate) fn keeps(&self, text: &str) -> bool {
+        match self {
+            Self::Keep => true,
+            Self::Drop => false,
+            Self::KeepPrefixed(prefix) => text.trim_start().starts_with(prefix.as_str()),
+        }
+    }
+}
+
+/// Separates hyphens that would end a comment early or make it invalid: `--` inside it, or `-` at its end.
+pub(crate) fn escape_comment(text: &str) -> Cow<'_, str> {
+    if !text.contains("--") && !text.ends_with('-') {
+        return Cow::Borrowed(text);
+    }
+
+    let mut escaped = String::with_capacity(text.len() + 2);
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        escaped.push(c);
+        if c == '-' && chars.peek().is_none_or(|next| *next == '-') {
+            escaped.push(' ');
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+/// How the writer handles characters outside the XML 1.0 `Char` production, such as most control characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ControlCharPolicy {
+    /// Write the characters as they are. This is the default, and how documents have always been written.
+    ///
+    /// The output is then not well-formed, and other parsers, including this one, may refuse to read it back.
+    #[default]
+    Keep,
+
+    /// Fail with an [`std::io::ErrorKind::InvalidData`] error.
+    Error,
+
+    /// Silently remove the characters from the output.
+    Strip,
+
+    /// Write the document as XML 1.1, where restricted characters can be written as character references.
+    ///
+    /// The declaration is always written with version `1.1`, and is added if the document has none.
+    ///
+    /// `U+0000`, `U+FFFE` and `U+FFFF` are not allowed in XML 1.1 either, and will still fail.
+    Xml11,
+}
+
+/// Returns true if the character is allowed in an XML 1.0 document.
+pub(crate) fn is_xml10_char(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\r' | '\u{20}'..='\u{D7FF}' | '\u{E000}'..='\u{FFFD}' | '\u{10000}'..)
+}
+
 /// Flatten a document as a formatted XML string using the given writer.
 ///
 /// # Errors
@@ -15,18 +436,40 @@ const TAB: &str = "\t";
 pub fn write_xml(
     writer: &mut dyn std::io::Write,
     document: &Document,
-    tab_char: Option<&str>,
+    options: &XmlWriteOptions,
+) -> std::io::Result<()> {
+    write_document(writer, document, options, |writer| {
+        write_tag(writer, document.root(), options, &Ancestors::default())
+    })
+}
+
+/// Writes the declaration, prolog and epilog of a document around a root written by the callback.
+///
+/// The callback is not called for documents without a root.
+pub(crate) fn write_document(
+    writer: &mut dyn std::io::Write,
+    document: &Document,
+    options: &XmlWriteOptions,
+    write_root: impl FnOnce(&mut dyn std::io::Write) -> std::io::Result<()>,
 ) -> std::io::Result<()> {
-    let tab_char = tab_char.unwrap_or(TAB);
+    let xml11 = options.control_chars == ControlCharPolicy::Xml11;
+
+    if options.preserve_bom && document.has_bom() {
+        writer.write_all("\u{FEFF}".as_bytes())?;
+    }
 
     //
     // Write the XML declaration
     if let Some(declaration) = &document.declaration() {
-        let version = encode_entities(declaration.version().text())?;
+        let version = if xml11 {
+            "1.1".to_string()
+        } else {
+            encode_entities(declaration.version().text(), options)?
+        };
         writer.write_all(format!(r#"<?xml version="{version}""#).as_bytes())?;
 
         if let Some(encoding) = &declaration.encoding() {
-            let encoding = encode_entities(encoding.text())?;
+            let encoding = encode_entities(encoding.text(), options)?;
             writer.write_all(format!(r#" encoding="{encoding}""#).as_bytes())?;
         }
 
@@ -35,73 +478,241 @@ pub fn write_xml(
             writer.write_all(format!(r#" standalone="{standalone}""#).as_bytes())?;
         }
 
-        writer.write_all(b" ?>\n")?;
+        let (_, nl) = options.layout(0);
+        writer.write_all(format!(" ?>{nl}").as_bytes())?;
+    } else if xml11 {
+        let (_, nl) = options.layout(0);
+        writer.write_all(format!("<?xml version=\"1.1\" ?>{nl}").as_bytes())?;
     }
 
     //
     // Write the prolog section
     for item in document.prolog() {
-        write_node(writer, item, tab_char, 0)?;
+        write_node(writer, item, options, &Ancestors::default())?;
     }
 
     //
-    // Write the root node
-    let mut stack = vec![(NodeTask::OpenNode(document.root()), 0)];
+    // Write the root node; documents built for recovery may not have one
+    if document.has_root() {
+        write_root(writer)?;
+    }
+
+    //
+    // Write the epilog section
+    // Not valud XML but, can exist
+    for item in document.epilog() {
+        write_node(writer, item, options, &Ancestors::default())?;
+    }
+
+    if options.minify {
+        writer.write_all(options.line_break().as_bytes())?;
+    }
+
+    Ok(())
+}
+
+/// Write each child of the root on its own line, minified, as a stream of records.
+///
+/// The root tag itself, and the nodes around it, are not written.
+pub(crate) fn write_records(
+    writer: &mut dyn std::io::Write,
+    root: &TagNode<'_>,
+    options: &XmlWriteOptions,
+) -> std::io::Result<()> {
+    let options = options.clone().with_minify(true);
+    for child in root.children() {
+        write_node(writer, child, &options, &Ancestors::default())?;
+        writer.write_all(options.line_break().as_bytes())?;
+    }
+
+    Ok(())
+}
+
+/// The elements enclosing a node being written.
+///
+/// Their names, as written, are only kept if an [`IndentFn`] needs them; otherwise only their number is.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Ancestors {
+    depth: usize,
+    names: Vec<String>,
+}
+impl Ancestors {
+    /// Enters the element with the given name, as written.
+    fn push(&mut self, name: &str, options: &XmlWriteOptions) {
+        self.depth += 1;
+        if options.indent_fn.is_some() && !options.minify {
+            self.names.push(name.to_string());
+        }
+    }
+
+    /// Leaves the innermost element.
+    fn pop(&mut self) {
+        self.depth = self.depth.saturating_sub(1);
+        self.names.truncate(self.depth);
+    }
+
+    /// Returns the indentation for a node inside these elements, and the line break written after it.
+    fn layout(&self, options: &XmlWriteOptions) -> (String, &'static str) {
+        options.layout_in(self.depth, &self.names)
+    }
+}
+
+/// Write a tag node and all of its descendants, inside the given elements.
+pub(crate) fn write_tag(
+    writer: &mut dyn std::io::Write,
+    node: &TagNode<'_>,
+    options: &XmlWriteOptions,
+    ancestors: &Ancestors,
+) -> std::io::Result<()> {
+    // The open elements, which tasks are always popped inside of
+    let mut path = ancestors.clone();
+    let mut stack = vec![NodeTask::OpenNode(node)];
     loop {
-        let Some((task, depth)) = stack.pop() else {
+        let Some(task) = stack.pop() else {
             break;
         };
-        let tab = tab_char.repeat(depth as usize);
 
         match task {
-            NodeTask::Close(node_name) => {
-                let name = encode_entities(&node_name.to_string())?;
-                writer.write_all(format!("{tab}</{name}>\n").as_bytes())?;
+            NodeTask::Close(name) => {
+                path.pop();
+                let (tab, nl) = path.layout(options);
+                writer.write_all(format!("{tab}</{name}>{nl}").as_bytes())?;
             }
 
             NodeTask::OpenKind(node_kind) => {
                 if let Node::Child(node) = node_kind {
-                    stack.push((NodeTask::OpenNode(node), depth));
+                    stack.push(NodeTask::OpenNode(node));
                 } else {
-                    write_node(writer, node_kind, tab_char, depth)?;
+                    write_node(writer, node_kind, options, &path)?;
                 }
             }
 
             NodeTask::OpenNode(node) => {
-                let name = encode_entities(&node.name().to_string())?;
+                let (tab, nl) = path.layout(options);
+                let name = encode_entities(&node.name().to_string(), options)?;
                 writer.write_all(format!("{tab}<{name}").as_bytes())?;
 
-                for attr in node.attributes() {
-                    let attr_name = encode_entities(&attr.name().to_string())?;
-                    let attr_value = encode_entities(attr.value().text())?;
+                let mut attributes: Vec<_> = node
+                    .attributes()
+                    .iter()
+                    .map(|a| (a.name().to_string(), a.value().text()))
+                    .collect();
+                options.order_attributes(&mut attributes);
+                for (attr_name, attr_value) in attributes {
+                    let attr_name = encode_entities(&attr_name, options)?;
+                    let attr_value = encode_entities(attr_value, options)?;
                     writer.write_all(format!(r#" {attr_name}="{attr_value}""#).as_bytes())?;
                 }
 
                 if node.children().is_empty() {
-                    writer.write_all(b" />\n")?;
+                    writer.write_all(format!(" />{nl}").as_bytes())?;
                     continue;
                 }
 
-                writer.write_all(b">\n")?;
-                stack.push((NodeTask::Close(node.name()), depth));
+                writer.write_all(format!(">{nl}").as_bytes())?;
+                path.push(&name, options);
+                stack.push(NodeTask::Close(name));
                 for child in node.children().iter().rev() {
-                    stack.push((NodeTask::OpenKind(child), depth + 1));
+                    stack.push(NodeTask::OpenKind(child));
                 }
             }
         }
     }
 
-    //
-    // Write the epilog section
-    // Not valud XML but, can exist
-    for item in document.epilog() {
-        write_node(writer, item, tab_char, 0)?;
+    Ok(())
+}
+
+/// Write a tag of a hybrid tree, writing its unchanged parts straight from the borrowed nodes.
+pub(crate) fn write_cow_tag(
+    writer: &mut dyn std::io::Write,
+    node: &CowTag<'_, '_>,
+    options: &XmlWriteOptions,
+    ancestors: &mut Ancestors,
+) -> std::io::Result<()> {
+    if !node.is_modified() {
+        return write_tag(writer, node.original(), options, ancestors);
     }
 
+    let (tab, nl) = ancestors.layout(options);
+    let name = encode_entities(&node.name().to_string(), options)?;
+    writer.write_all(format!("{tab}<{name}").as_bytes())?;
+
+    let mut attributes = node.written_attributes();
+    options.order_attributes(&mut attributes);
+    for (attr_name, attr_value) in attributes {
+        let attr_name = encode_entities(&attr_name, options)?;
+        let attr_value = encode_entities(attr_value, options)?;
+        writer.write_all(format!(r#" {attr_name}="{attr_value}""#).as_bytes())?;
+    }
+
+    let children = node.children();
+    if children.is_empty() {
+        writer.write_all(format!(" />{nl}").as_bytes())?;
+        return Ok(());
+    }
+
+    writer.write_all(format!(">{nl}").as_bytes())?;
+    ancestors.push(&name, options);
+    for child in children.iter() {
+        match child {
+            CowNode::Borrowed(child) => write_node(writer, child, options, ancestors)?,
+            CowNode::Tag(child) => write_cow_tag(writer, child, options, ancestors)?,
+            CowNode::Owned(child) => write_node(writer, &child.borrowed(), options, ancestors)?,
+        }
+    }
+    ancestors.pop();
+    writer.write_all(format!("{tab}</{name}>{nl}").as_bytes())?;
+
     Ok(())
 }
 
-fn encode_entities(input: &str) -> std::io::Result<String> {
+pub(crate) fn encode_entities(input: &str, options: &XmlWriteOptions) -> std::io::Result<String> {
+    if input.chars().all(is_xml10_char) {
+        return Ok(encode_line_breaks(encode_html(input)?, options));
+    }
+
+    let mut output = String::with_capacity(input.len());
+    let mut segment_start = 0;
+    for (i, c) in input.char_indices() {
+        if is_xml10_char(c) {
+            continue;
+        }
+
+        output.push_str(&encode_html(&input[segment_start..i])?);
+        segment_start = i + c.len_utf8();
+
+        match options.control_chars {
+            ControlCharPolicy::Keep => output.push(c),
+            ControlCharPolicy::Strip => {}
+            ControlCharPolicy::Xml11 if !matches!(c, '\0' | '\u{FFFE}' | '\u{FFFF}') => {
+                let _ = write!(output, "&#x{:X};", u32::from(c));
+            }
+            _ => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("Character U+{:04X} is not allowed in XML", u32::from(c)),
+                ));
+            }
+        }
+    }
+
+    output.push_str(&encode_html(&input[segment_start..])?);
+    Ok(encode_line_breaks(output, options))
+}
+
+/// Writes line breaks as character references, so minified output stays on one line,
+/// or as `\r\n` if [`XmlWriteOptions::crlf`] is set.
+fn encode_line_breaks(input: String, options: &XmlWriteOptions) -> String {
+    if options.minify && input.contains(['\n', '\r']) {
+        input.replace('\n', "&#xA;").replace('\r', "&#xD;")
+    } else if options.crlf && input.contains(['\n', '\r']) {
+        normalize_line_endings(&input).replace('\n', "\r\n")
+    } else {
+        input
+    }
+}
+
+fn encode_html(input: &str) -> std::io::Result<String> {
     encode(
         input.as_bytes(),
         &EncodeType::NamedOrHex,
@@ -111,110 +722,170 @@ fn encode_entities(input: &str) -> std::io::Result<String> {
     .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))
 }
 
-fn write_node(
+pub(crate) fn write_node(
     writer: &mut dyn std::io::Write,
     node: &Node<'_>,
-    tab_char: &str,
-    depth: u8,
+    options: &XmlWriteOptions,
+    ancestors: &Ancestors,
 ) -> std::io::Result<()> {
-    let tab = tab_char.repeat(depth as usize);
+    let (tab, nl) = ancestors.layout(options);
 
     match node {
-        Node::Comment(str_span) => {
-            let comment = encode_entities(str_span.text())?;
-            writer.write_all(format!("{tab}<!--{comment}-->\n").as_bytes())?;
+        Node::Comment(comment_node) => {
+            let text = comment_node.text().text();
+            if options.comments.keeps(text) {
+                let comment = encode_entities(&escape_comment(text), options)?;
+                writer.write_all(format!("{tab}<!--{comment}-->{nl}").as_bytes())?;
+            }
         }
 
         Node::Text(text_node) => {
-            let text = encode_entities(text_node.text().text())?;
-            writer.write_all(format!("{tab}{text}\n").as_bytes())?;
+            let text = encode_entities(text_node.text().text(), options)?;
+            writer.write_all(
+                options
+                    .layout_text(&text, ancestors.depth, &ancestors.names)
+                    .as_bytes(),
+            )?;
         }
 
         Node::ProcessingInstruction(processing_instruction_node) => {
-            let target = encode_entities(processing_instruction_node.target().text())?;
+            let target = encode_entities(processing_instruction_node.target().text(), options)?;
             writer.write_all(format!("{tab}<?{target}").as_bytes())?;
 
             if let Some(content) = &processing_instruction_node.content() {
-                let content = encode_entities(content.text())?;
+                let content = encode_entities(content.text(), options)?;
                 writer.write_all(format!(" {content}").as_bytes())?;
             }
 
-            writer.write_all(b"?>\n")?;
+            writer.write_all(format!("?>{nl}").as_bytes())?;
         }
 
         Node::DocumentType(dtd_node) => {
-            let name = encode_entities(dtd_node.name().text())?;
+            let name = encode_entities(dtd_node.name().text(), options)?;
             writer.write_all(format!("{tab}<!DOCTYPE {name}").as_bytes())?;
 
             if let Some(external_id) = &dtd_node.external_id() {
                 match external_id {
                     ExternalId::Public(name, value) => {
-                        let name = encode_entities(name.text())?;
-                        let value = encode_entities(value.text())?;
+                        let name = encode_entities(name.text(), options)?;
+                        let value = encode_entities(value.text(), options)?;
                         writer.write_all(format!(r#" PUBLIC "{name}" "{value}""#).as_bytes())?;
                     }
                     ExternalId::System(value) => {
-                        let value = encode_entities(value.text())?;
+                        let value = encode_entities(value.text(), options)?;
                         writer.write_all(format!(r#" SYSTEM "{value}""#).as_bytes())?;
                     }
                 }
             }
 
-            if !dtd_node.entities().is_empty() {
-                writer.write_all(b" [\n")?;
+            // The internal subset is markup, and may hold declarations that are not interpreted
+            let items = dtd_node.internal_subset_items();
+            if !items.is_empty() {
+                let (tab, _) = options.layout(ancestors.depth + 1);
+                writer.write_all(format!(" [{nl}").as_bytes())?;
+                for item in items {
+                    writer.write_all(format!("{tab}{item}{nl}").as_bytes())?;
+                }
+                writer.write_all(b"]")?;
+            } else if dtd_node.internal_subset().is_none() && !dtd_node.entities().is_empty() {
+                writer.write_all(format!(" [{nl}").as_bytes())?;
                 for entity in dtd_node.entities() {
-                    let tab = tab_char.repeat((depth + 1) as usize);
+                    let (tab, _) = options.layout(ancestors.depth + 1);
 
-                    let entity_name = encode_entities(entity.name.text())?;
+                    let entity_name = encode_entities(entity.name.text(), options)?;
                     writer.write_all(format!("{tab}<!ENTITY {entity_name}").as_bytes())?;
 
                     match &entity.definition {
                         EntityDefinition::EntityValue(value) => {
-                            let value = encode_entities(value.text())?;
+                            let value = encode_entities(value.text(), options)?;
                             writer.write_all(format!(r#" "{value}""#).as_bytes())?;
                         }
 
                         EntityDefinition::ExternalId(ExternalId::System(value)) => {
-                            let value = encode_entities(value.text())?;
+                            let value = encode_entities(value.text(), options)?;
                             writer.write_all(format!(r#" SYSTEM "{value}""#).as_bytes())?;
                         }
 
                         EntityDefinition::ExternalId(ExternalId::Public(name, value)) => {
-                            let name = encode_entities(name.text())?;
-                            let value = encode_entities(value.text())?;
+                            let name = encode_entities(name.text(), options)?;
+                            let value = encode_entities(value.text(), options)?;
                             writer
                                 .write_all(format!(r#" PUBLIC "{name}" "{value}""#).as_bytes())?;
                         }
                     }
 
-                    writer.write_all(b">\n")?;
+                    writer.write_all(format!(">{nl}").as_bytes())?;
                 }
                 writer.write_all(b"]")?;
             }
 
-            writer.write_all(b">\n")?;
+            writer.write_all(format!(">{nl}").as_bytes())?;
         }
 
         Node::Cdata(cdata_node) => {
-            let cdata = encode_entities(cdata_node.content().text())?;
-            writer.write_all(format!("{tab}<![CDATA[{cdata}]]>\n").as_bytes())?;
+            let cdata = encode_entities(cdata_node.content().text(), options)?;
+            writer.write_all(format!("{tab}<![CDATA[{cdata}]]>{nl}").as_bytes())?;
         }
 
-        Node::Child(_) => (),
+        Node::Child(node) => write_tag(writer, node, options, ancestors)?,
     }
 
     Ok(())
 }
 
-enum NodeTask<'src> {
-    OpenNode(&'src TagNode<'src>),
-    OpenKind(&'src Node<'src>),
-    Close(&'src NodeName<'src>),
+/// Renders XML into a formatter, using the default write options and without the final line break.
+///
+/// Characters that XML cannot hold are stripped instead of failing, since formatting must not fail for any tree.
+fn display_with(
+    f: &mut std::fmt::Formatter<'_>,
+    write: impl FnOnce(&mut Vec<u8>, &XmlWriteOptions) -> std::io::Result<()>,
+) -> std::fmt::Result {
+    let options = XmlWriteOptions::default().with_control_chars(ControlCharPolicy::Strip);
+    let mut buffer = vec![];
+    write(&mut buffer, &options).map_err(|_| std::fmt::Error)?;
+
+    let xml = String::from_utf8(buffer).map_err(|_| std::fmt::Error)?;
+    f.write_str(xml.strip_suffix('\n').unwrap_or(&xml))
+}
+
+impl std::fmt::Display for Node<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        display_with(f, |w, options| {
+            write_node(w, self, options, &Ancestors::default())
+        })
+    }
+}
+
+impl std::fmt::Display for TagNode<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        display_with(f, |w, options| {
+            write_tag(w, self, options, &Ancestors::default())
+        })
+    }
+}
+
+impl std::fmt::Display for OwnedTagNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.borrowed().fmt(f)
+    }
+}
+
+impl std::fmt::Display for OwnedDocument {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        display_with(f, |w, options| write_xml(w, &self.borrowed(), options))
+    }
+}
+
+enum NodeTask<'a, 'src> {
+    OpenNode(&'a TagNode<'src>),
+    OpenKind(&'a Node<'src>),
+    Close(String),
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::node::{OwnedNode, OwnedNodeAttribute, OwnedTextNode};
 
     #[test]
     fn test_write_xml_with_declaration() {
@@ -300,4 +971,278 @@ mod tests {
             "<!DOCTYPE root [\n\t<!ENTITY example \"example value\">\n]>\n<root>\n\t&amp;example;\n</root>\n"
         );
     }
+
+    #[test]
+    fn test_write_xml_control_chars() {
+        let mut root = OwnedTagNode::new("root");
+        root.attributes
+            .push(OwnedNodeAttribute::new("attr", "a\u{1}b"));
+        root.children
+            .push(OwnedNode::Text(OwnedTextNode::new("x\u{8}y")));
+        let document = OwnedDocument::new(root);
+
+        let xml = document.to_xml(None).unwrap();
+        assert_eq!(xml, "<root attr=\"a\u{1}b\">\n\tx\u{8}y\n</root>\n");
+
+        let options = XmlWriteOptions::default().with_control_chars(ControlCharPolicy::Error);
+        let err = document.to_xml_with_options(&options).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        let options = XmlWriteOptions::default().with_control_chars(ControlCharPolicy::Strip);
+        let xml = document.to_xml_with_options(&options).unwrap();
+        assert_eq!(xml, "<root attr=\"ab\">\n\txy\n</root>\n");
+
+        let options = XmlWriteOptions::default().with_control_chars(ControlCharPolicy::Xml11);
+        let xml = document.to_xml_with_options(&options).unwrap();
+        assert_eq!(
+            xml,
+            "<?xml version=\"1.1\" ?>\n<root attr=\"a&#x1;b\">\n\tx&#x8;y\n</root>\n"
+        );
+    }
+
+    #[test]
+    fn test_write_xml_xml11_rejects_nul() {
+        let mut root = OwnedTagNode::new("root");
+        root.children
+            .push(OwnedNode::Text(OwnedTextNode::new("\0")));
+        let document = OwnedDocument::new(root);
+
+        let options = XmlWriteOptions::default().with_control_chars(ControlCharPolicy::Xml11);
+        assert!(document.to_xml_with_options(&options).is_err());
+    }
+
+    #[test]
+    fn test_display_nodes() {
+        let xml = "<root><child a=\"1\">text<!--c--><sub /></child>tail</root>";
+        let document = Document::parse_str(xml).unwrap();
+        let child = &document.root().children()[0];
+
+        assert_eq!(
+            child.to_string(),
+            "<child a=\"1\">\n\ttext\n\t<!--c-->\n\t<sub />\n</child>"
+        );
+        assert_eq!(document.root().children()[1].to_string(), "tail");
+
+        let Node::Child(tag) = child else {
+            panic!("Expected tag");
+        };
+        assert_eq!(tag.to_string(), child.to_string());
+        assert_eq!(tag.to_owned().to_string(), child.to_string());
+        assert_eq!(
+            document.to_owned().to_string(),
+            document.to_xml(None).unwrap().trim_end()
+        );
+
+        assert_eq!(
+            format!("{:#?}", document.root()),
+            "root\n  child a=\"1\"\n    \"text\"\n    comment \"c\"\n    sub\n  \"tail\""
+        );
+        assert_eq!(
+            format!("{:#?}", document.root().to_owned()),
+            format!("{:#?}", document.root())
+        );
+        assert!(format!("{:?}", document.root()).starts_with("TagNode { span:"));
+
+        // Characters XML cannot hold are left out, rather than failing
+        let mut root = OwnedTagNode::new("root");
+        root.children
+            .push(OwnedNode::Text(OwnedTextNode::new("a\u{1}b")));
+        assert_eq!(root.to_string(), "<root>\n\tab\n</root>");
+        assert_eq!(root.borrowed().to_string(), root.to_string());
+        assert_eq!(
+            OwnedDocument::new(root).to_string(),
+            "<root>\n\tab\n</root>"
+        );
+    }
+
+    #[test]
+    fn test_write_minified() {
+        let xml = "<?xml version=\"1.0\"?><root a=\"x\ny\"><child>line one\nline two</child><!-- c --></root>";
+        let doc = Document::parse_str(xml).unwrap();
+        let options = XmlWriteOptions::default().with_minify(true);
+
+        let xml2 = doc.to_xml_with_options(&options).unwrap();
+        assert_eq!(
+            xml2,
+            "<?xml version=\"1.0\" ?><root a=\"x&#xA;y\"><child>line one&#xA;line two</child><!-- c --></root>\n"
+        );
+
+        let mut records = vec![];
+        doc.to_xml_records_with_writer(&mut records, &XmlWriteOptions::default())
+            .unwrap();
+        let records = String::from_utf8(records).unwrap();
+        assert_eq!(records.lines().count(), 2);
+        assert_eq!(
+            records,
+            "<child>line one&#xA;line two</child>\n<!-- c -->\n"
+        );
+    }
+
+    #[test]
+    fn test_write_crlf() {
+        let xml = "<root a=\"x\r\ny\"><child>one\r\ntwo\rthree\nfour</child></root>";
+        let doc = Document::parse_str(xml).unwrap();
+        let options = XmlWriteOptions::default().with_crlf(true);
+
+        let xml2 = doc.to_xml_with_options(&options).unwrap();
+        assert_eq!(
+            xml2,
+            "<root a=\"x\r\ny\">\r\n\t<child>\r\n\t\tone\r\ntwo\r\nthree\r\nfour\r\n\t</child>\r\n</root>\r\n"
+        );
+
+        let minified = doc.to_xml_with_options(&options.with_minify(true)).unwrap();
+        assert!(minified.ends_with("</root>\r\n"));
+        assert_eq!(minified.matches('\n').count(), 1);
+    }
+
+    #[test]
+    fn test_write_wrapped_text() {
+        let xml = "<doc><p>The quick brown fox jumps over the lazy dog, extraordinarily</p><pre>one\n      two\n\n  three</pre></doc>";
+        let doc = Document::parse_str(xml).unwrap();
+
+        let options = XmlWriteOptions::default()
+            .with_indent("  ")
+            .with_wrap_width(20);
+        assert_eq!(
+            doc.to_xml_with_options(&options).unwrap(),
+            concat!(
+                "<doc>\n  <p>\n",
+                "    The quick brown\n    fox jumps over\n    the lazy dog,\n    extraordinarily\n",
+                "  </p>\n  <pre>\n",
+                "    one\n    two\n\n    three\n",
+                "  </pre>\n</doc>\n",
+            )
+        );
+
+        let options = XmlWriteOptions::default()
+            .with_reindent_text(true)
+            .with_crlf(true);
+        let xml2 = doc.to_xml_with_options(&options).unwrap();
+        assert!(xml2.contains("\t\tone\r\n\t\ttwo\r\n\r\n\t\tthree\r\n"));
+
+        // Minified output stays on one line
+        let options = XmlWriteOptions::default()
+            .with_wrap_width(10)
+            .with_minify(true);
+        assert_eq!(
+            doc.to_xml_with_options(&options).unwrap().lines().count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_write_sorted_attributes() {
+        let doc =
+            Document::parse_str("<a c='1' b:x='2' xmlns:b='urn:b' a='3'><b a='4' /></a>").unwrap();
+        let options = XmlWriteOptions::default()
+            .with_minify(true)
+            .with_sort_attributes(AttrSort::Alphabetical);
+        let expected = "<a a=\"3\" c=\"1\" b:x=\"2\" xmlns:b=\"urn:b\"><b a=\"4\" /></a>\n";
+        assert_eq!(doc.to_xml_with_options(&options).unwrap(), expected);
+
+        // Edited tags are sorted the same way
+        let mut cow = doc.to_cow();
+        cow.root_mut().set_attribute("d", "5");
+        assert_eq!(
+            cow.to_xml_with_options(&options).unwrap(),
+            expected.replace("c=\"1\"", "c=\"1\" d=\"5\"")
+        );
+    }
+
+    #[test]
+    fn test_write_comments() {
+        let mut doc = Document::parse_str("<!--a--><r><!-- b --><c /></r><!--c-->").unwrap();
+        doc.root_mut()
+            .children_mut()
+            .push(Node::Comment(crate::node::CommentNode::detached(
+                "x -- y --- z-",
+            )));
+        let options = XmlWriteOptions::default().with_minify(true);
+
+        let xml = doc.to_xml_with_options(&options).unwrap();
+        assert_eq!(
+            xml,
+            "<!--a--><r><!-- b --><c /><!--x - - y - - - z- --></r><!--c-->\n"
+        );
+        assert!(Document::parse_str(&xml).is_ok());
+
+        let options = options.with_comments(CommentPolicy::Drop);
+        assert_eq!(doc.to_xml_with_options(&options).unwrap(), "<r><c /></r>\n");
+
+        let options = options.with_comments(CommentPolicy::KeepPrefixed("b".to_string()));
+        assert_eq!(
+            doc.to_xml_with_options(&options).unwrap(),
+            "<r><!-- b --><c /></r>\n"
+        );
+    }
+
+    #[test]
+    fn test_write_indent_fn() {
+        fn indent(ancestors: &[&str]) -> Cow<'static, str> {
+            if ancestors.contains(&"pre") {
+                Cow::Borrowed("")
+            } else {
+                Cow::Owned("-".repeat(ancestors.len()))
+            }
+        }
+
+        let doc = Document::parse_str("<a><!--c--><pre><b>x</b></pre><d /></a>").unwrap();
+        let options = XmlWriteOptions::default().with_indent_fn(indent);
+        let expected = "<a>\n-<!--c-->\n-<pre>\n<b>\nx\n</b>\n-</pre>\n-<d />\n</a>\n";
+        assert_eq!(doc.to_xml_with_options(&options).unwrap(), expected);
+
+        let mut cow = doc.to_cow();
+        cow.root_mut().set_attribute("k", "v");
+        assert!(
+            cow.to_xml_with_options(&options)
+                .unwrap()
+                .starts_with("<a k=\"v\">\n-<!--c-->\n")
+        );
+
+        let mut writer = crate::XmlWriter::new(Vec::new(), options.clone());
+        writer.start_element("a").unwrap();
+        writer.comment("c").unwrap();
+        writer.start_element("pre").unwrap();
+        writer.start_element("b").unwrap();
+        writer.text("x").unwrap();
+        writer.end_element().unwrap();
+        writer.end_element().unwrap();
+        writer.start_element("d").unwrap();
+        writer.end_element().unwrap();
+        writer.end_element().unwrap();
+        let written = writer.finish().unwrap();
+        assert_eq!(String::from_utf8(written).unwrap(), expected);
+
+        // Minifying ignores the callback
+        let xml = doc.to_xml_with_options(&options.with_minify(true)).unwrap();
+        assert_eq!(xml, "<a><!--c--><pre><b>x</b></pre><d /></a>\n");
+    }
+
+    #[test]
+    fn test_write_deeply_nested() {
+        const DEPTH: usize = 10_000;
+
+        // Writing does not recurse, but dropping the tree does
+        let test = || {
+            let src = "<a>".repeat(DEPTH) + &"</a>".repeat(DEPTH);
+            let doc = Document::parse_str(&src).unwrap();
+
+            let xml = doc.to_xml(Some(" ")).unwrap();
+            let deepest = format!("\n{}<a />\n", " ".repeat(DEPTH - 1));
+            assert!(xml.contains(&deepest));
+            assert!(xml.ends_with("\n </a>\n</a>\n"));
+
+            let minified = doc
+                .to_xml_with_options(&XmlWriteOptions::default().with_minify(true))
+                .unwrap();
+            let expected = "<a>".repeat(DEPTH - 1) + "<a />" + &"</a>".repeat(DEPTH - 1) + "\n";
+            assert_eq!(minified, expected);
+        };
+        std::thread::Builder::new()
+            .stack_size(64 << 20)
+            .spawn(test)
+            .unwrap()
+            .join()
+            .unwrap();
+    }
 }
@@ -0,0 +1,515 @@
+//! Change tracking for owned documents, so that edits can be listed and written back on their own.
+//!
+//! Re-serializing a large document after a small edit rewrites every byte of it, and loses its original formatting.
+//! A [`TrackedDocument`] records which nodes were changed since the document was parsed;
+//! the changes can be listed to emit a diff, or written back by patching only those nodes into the original source.
+use crate::{
+    Document, NodePath, Origin, OwnedDocument, PathSection, XmlWriteOptions,
+    node::{OwnedNode, OwnedNodeAttribute, OwnedTagNode},
+    to_xml::{Ancestors, write_node, write_tag},
+};
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+/// How a node of a [`TrackedDocument`] was changed.
+///
+/// Ordered from the narrowest change to the widest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum NodeChange {
+    /// Only the attributes of the element were changed; its name and content are as parsed.
+    Attributes,
+
+    /// The node may have been changed in any way, including its descendants.
+    Node,
+}
+
+/// An owned document that records which nodes were changed since it was parsed.
+///
+/// Nodes are edited through [`TrackedDocument::tag_mut`], [`TrackedDocument::node_mut`] and [`TrackedDocument::attributes_mut`],
+/// which mark them as changed; a node changed as a whole includes all of its descendants.
+/// [`TrackedDocument::to_xml_patched`] then copies the original source, rewriting only the changed nodes.
+///
+/// # Example
+/// ```rust
+/// use xmltree::{Document, NodeChange, NodePath, XmlWriteOptions, node::OwnedNodeAttribute};
+///
+/// let src = "<config>\n  <server port='80'/>\n  <!-- keep me -->\n  <client>old</client>\n</config>";
+/// let doc = Document::parse_str(src).unwrap();
+/// let mut tracked = doc.to_tracked();
+///
+/// let server = NodePath::root().child(0);
+/// tracked.attributes_mut(&server).unwrap()[0] = OwnedNodeAttribute::new("port", "8080");
+///
+/// let client = NodePath::root().child(2);
+/// tracked.tag_mut(&client).unwrap().children.clear();
+///
+/// let changes: Vec<_> = tracked.changes().collect();
+/// assert_eq!(changes, [(&server, NodeChange::Attributes), (&client, NodeChange::Node)]);
+///
+/// let xml = tracked.to_xml_patched(&XmlWriteOptions::default()).unwrap();
+/// assert_eq!(xml, "<config>\n  <server port=\"8080\"/>\n  <!-- keep me -->\n  <client />\n</config>");
+/// ```
+#[derive(Debug, Clone)]
+pub struct TrackedDocument {
+    document: OwnedDocument,
+    source: Option<String>,
+
+    /// Changed nodes; none is inside a node changed as a whole
+    changes: BTreeMap<NodePath, Change>,
+
+    /// Set once the document was edited in ways not tied to a node
+    rewrite_all: bool,
+}
+impl TrackedDocument {
+    /// Starts tracking changes to a document, converted from the given source.
+    ///
+    /// Without a source, or one the document was not converted from, [`TrackedDocument::to_xml_patched`] writes the whole document.
+    #[must_use]
+    pub fn new(document: OwnedDocument, source: Option<String>) -> Self {
+        Self {
+            document,
+            source,
+            changes: BTreeMap::new(),
+            rewrite_all: false,
+        }
+    }
+
+    /// Returns the document, with its changes.
+    #[must_use]
+    pub fn document(&self) -> &OwnedDocument {
+        &self.document
+    }
+
+    /// Returns the source the document was converted from.
+    #[must_use]
+    pub fn source(&self) -> Option<&str> {
+        self.source.as_deref()
+    }
+
+    /// Stops tracking changes, returning the document.
+    #[must_use]
+    pub fn into_inner(self) -> OwnedDocument {
+        self.document
+    }
+
+    /// Returns the tag at the given path, including the root node, for editing; it is marked as changed as a whole.
+    ///
+    /// Returns None, marking nothing, if the path does not lead to a tag.
+    pub fn tag_mut(&mut self, path: &NodePath) -> Option<&mut OwnedTagNode> {
+        let origin = self.document.resolve_tag(path)?.origin;
+        mark(&mut self.changes, path.clone(), NodeChange::Node, origin);
+        self.document.resolve_tag_mut(path)
+    }
+
+    /// Returns the node at the given path for editing; it is marked as changed as a whole.
+    ///
+    /// Returns None, marking nothing, if the path does not lead to a node. The root node is not an [`OwnedNode`];
+    /// use [`TrackedDocument::tag_mut`] to edit it.
+    pub fn node_mut(&mut self, path: &NodePath) -> Option<&mut OwnedNode> {
+        let origin = node_origin(self.document.resolve(path)?);
+        mark(&mut self.changes, path.clone(), NodeChange::Node, origin);
+        self.document.resolve_mut(path)
+    }
+
+    /// Returns the attributes of the tag at the given path for editing; only its start tag is marked as changed.
+    ///
+    /// Returns None, marking nothing, if the path does not lead to a tag.
+    pub fn attributes_mut(&mut self, path: &NodePath) -> Option<&mut Vec<OwnedNodeAttribute>> {
+        let origin = self.document.resolve_tag(path)?.origin;
+        mark(
+            &mut self.changes,
+            path.clone(),
+            NodeChange::Attributes,
+            origin,
+        );
+        self.document
+            .resolve_tag_mut(path)
+            .map(|tag| &mut tag.attributes)
+    }
+
+    /// Returns the whole document for editing, such as to change the prolog; all of it is marked as changed.
+    pub fn document_mut(&mut self) -> &mut OwnedDocument {
+        self.rewrite_all = true;
+        &mut self.document
+    }
+
+    /// Returns the changed nodes in document order, and how they were changed.
+    ///
+    /// Nodes inside a node changed as a whole are not listed separately.
+    /// Edits made through [`TrackedDocument::document_mut`] are not tied to a node, and are not listed.
+    pub fn changes(&self) -> impl Iterator<Item = (&NodePath, NodeChange)> {
+        self.changes
+            .iter()
+            .map(|(path, change)| (path, change.kind))
+    }
+
+    /// Returns true if anything in the document was marked as changed.
+    #[must_use]
+    pub fn is_modified(&self) -> bool {
+        self.rewrite_all || !self.changes.is_empty()
+    }
+
+    /// Returns true if the node at the given path was marked as changed, or is inside a node changed as a whole.
+    #[must_use]
+    pub fn is_changed(&self, path: &NodePath) -> bool {
+        if self.rewrite_all || self.changes.contains_key(path) {
+            return true;
+        }
+
+        let mut ancestor = path.parent();
+        while let Some(path) = ancestor {
+            if self.changes.get(&path).map(|c| c.kind) == Some(NodeChange::Node) {
+                return true;
+            }
+            ancestor = path.parent();
+        }
+        false
+    }
+
+    /// Writes the document by copying its source, with only the changed nodes rewritten using the given options.
+    ///
+    /// Everything else, including formatting and comments, is kept byte for byte. Changed nodes are written
+    /// as if at the top level of the document, without a trailing line break; for attribute changes, only the start tag is rewritten.
+    /// Changed nodes replace the part of the source they were parsed from, even if they were replaced by other nodes;
+    /// changes that cannot be located in the source rewrite their closest located parent instead.
+    ///
+    /// The whole document is written with [`OwnedDocument::to_xml_with_options`] if there is no source,
+    /// or if it was edited through [`TrackedDocument::document_mut`].
+    ///
+    /// # Errors
+    /// Can fail if a string in a changed node cannot be entity encoded,
+    /// or contains characters rejected by [`XmlWriteOptions::control_chars`].
+    pub fn to_xml_patched(&self, options: &XmlWriteOptions) -> std::io::Result<String> {
+        let Some(source) = self.source.as_deref().filter(|_| !self.rewrite_all) else {
+            return self.document.to_xml_with_options(options);
+        };
+
+        let mut patches = BTreeMap::new();
+        for (path, change) in &self.changes {
+            let (mut path, mut change) = (path.clone(), *change);
+            while locate(source, change).is_none() {
+                let Some(parent) = path.parent() else {
+                    return self.document.to_xml_with_options(options);
+                };
+
+                // Ancestors not changed as a whole are still where they were parsed from
+                let origin = match self.changes.get(&parent) {
+                    Some(change) => change.origin,
+                    None => self.origin_of(&parent).unwrap_or_default(),
+                };
+                change = Change {
+                    kind: NodeChange::Node,
+                    origin,
+                };
+                path = parent;
+            }
+            mark(&mut patches, path, change.kind, change.origin);
+        }
+
+        let mut output = String::with_capacity(source.len());
+        if self.document.bom && options.preserve_bom {
+            output.push('\u{FEFF}');
+        }
+
+        let mut cursor = 0;
+        for (path, change) in patches {
+            let range = locate(source, change).unwrap_or_default();
+            if range.start < cursor {
+                // The origins do not follow the source, so it cannot be patched
+                return self.document.to_xml_with_options(options);
+            }
+
+            output.push_str(&source[cursor..range.start]);
+            output.push_str(&self.write_change(&path, change.kind, options)?);
+            cursor = range.end;
+        }
+        output.push_str(&source[cursor..]);
+        Ok(output)
+    }
+
+    /// Returns the current origin of the node at the given path, including the root node.
+    fn origin_of(&self, path: &NodePath) -> Option<Origin> {
+        if path.section() == PathSection::Root && path.indices().is_empty() {
+            Some(self.document.root.origin)
+        } else {
+            self.document.resolve(path).map(node_origin)
+        }
+    }
+
+    /// Writes the replacement for a change; the start tag without its closing `>` or `/>` for attribute changes.
+    fn write_change(
+        &self,
+        path: &NodePath,
+        change: NodeChange,
+        options: &XmlWriteOptions,
+    ) -> std::io::Result<String> {
+        let mut buffer = vec![];
+        if change == NodeChange::Attributes {
+            if let Some(tag) = self.document.resolve_tag(path) {
+                write_start_tag(&mut buffer, tag, options)?;
+            }
+        } else if path.section() == PathSection::Root && path.indices().is_empty() {
+            write_tag(
+                &mut buffer,
+                &self.document.root.borrowed(),
+                options,
+                &Ancestors::default(),
+            )?;
+        } else if let Some(node) = self.document.resolve(path) {
+            write_node(
+                &mut buffer,
+                &node.borrowed(),
+                options,
+                &Ancestors::default(),
+            )?;
+        }
+
+        let mut text = String::from_utf8(buffer).map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Failed to convert to UTF-8: {e}"),
+            )
+        })?;
+        let len = text
+            .strip_suffix(options.line_break())
+            .map_or(text.len(), str::len);
+        text.truncate(len);
+        Ok(text)
+    }
+}
+
+impl Document<'_> {
+    /// Converts the document to an owned [`TrackedDocument`], which records the changes made to it.
+    ///
+    /// The source is copied along with the document, so that the changes can be patched into it.
+    #[must_use]
+    pub fn to_tracked(&self) -> TrackedDocument {
+        TrackedDocument::new(self.to_owned(), self.source().map(str::to_string))
+    }
+}
+
+/// A recorded change to a node, and where the node was parsed from before it was first changed.
+#[derive(Debug, Clone, Copy)]
+struct Change {
+    kind: NodeChange,
+    origin: Origin,
+}
+
+/// Records a change; changes inside a node changed as a whole are dropped, and a node keeps its widest change.
+///
+/// The origin is only recorded the first time a node is changed, since the node may have been replaced since.
+fn mark(
+    changes: &mut BTreeMap<NodePath, Change>,
+    path: NodePath,
+    kind: NodeChange,
+    origin: Origin,
+) {
+    let mut covering = Some(path.clone());
+    while let Some(ancestor) = covering {
+        if changes.get(&ancestor).map(|c| c.kind) == Some(NodeChange::Node) {
+            return;
+        }
+        covering = ancestor.parent();
+    }
+
+    if kind == NodeChange::Node {
+        changes.retain(|other, _| !path.is_ancestor_of(other));
+    }
+    let entry = changes.entry(path).or_insert(Change { kind, origin });
+    entry.kind = entry.kind.max(kind);
+}
+
+fn node_origin(node: &OwnedNode) -> Origin {
+    match node {
+        OwnedNode::Tag(tag) => tag.origin,
+        OwnedNode::Text(text) => text.origin,
+        OwnedNode::Comment(comment) => comment.origin,
+        OwnedNode::ProcessingInstruction(pi) => pi.origin,
+        OwnedNode::DocumentType(dtd) => dtd.origin,
+        OwnedNode::Cdata(cdata) => cdata.origin,
+    }
+}
+
+/// Finds the part of the source a change replaces; the start tag for attribute changes, and the trimmed text for text nodes.
+fn locate(source: &str, change: Change) -> Option<Range<usize>> {
+    let span = change.origin.span(source)?;
+    let text = span.text();
+    match change.kind {
+        NodeChange::Attributes => Some(span.start()..span.start() + start_tag_len(text)?),
+
+        // Only markup starts with `<`
+        NodeChange::Node if !text.starts_with('<') => {
+            let start = span.start() + (text.len() - text.trim_start().len());
+            Some(start..span.start() + text.trim_end().len().max(start - span.start()))
+        }
+        NodeChange::Node => Some(span.start()..span.start() + text.len()),
+    }
+}
+
+/// Writes the start tag of an element, without its closing `>` or `/>`.
+fn write_start_tag(
+    writer: &mut dyn std::io::Write,
+    tag: &OwnedTagNode,
+    options: &XmlWriteOptions,
+) -> std::io::Result<()> {
+    let shallow = OwnedTagNode {
+        name: tag.name.clone(),
+        attributes: tag.attributes.clone(),
+        children: vec![],
+        origin: tag.origin,
+    };
+
+    let mut buffer = vec![];
+    let options = options.clone().with_minify(true);
+    write_tag(
+        &mut buffer,
+        &shallow.borrowed(),
+        &options,
+        &Ancestors::default(),
+    )?;
+    let end = buffer.len().saturating_sub(" />".len());
+    writer.write_all(&buffer[..end])
+}
+
+/// Returns the length of the start tag at the beginning of the text, up to any whitespace before its closing `>` or `/>`.
+fn start_tag_len(text: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => {
+                let tag = &text[..i];
+                return Some(tag.strip_suffix('/').unwrap_or(tag).trim_end().len());
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::OwnedCommentNode;
+
+    const SRC: &str = "<?xml version='1.0'?>\n<!-- head -->\n<root a = '1'>\n    <one x=\"a>b\" />\n    <two>\n        text &amp; more\n    </two>\n</root>\n";
+
+    #[test]
+    fn test_tracked_changes() {
+        let doc = Document::parse_str(SRC).unwrap();
+        let mut tracked = doc.to_tracked();
+        assert!(!tracked.is_modified());
+        assert_eq!(
+            tracked.to_xml_patched(&XmlWriteOptions::default()).unwrap(),
+            SRC
+        );
+
+        let one = NodePath::root().child(0);
+        let two = NodePath::root().child(1);
+        let text = two.child(0);
+        tracked.attributes_mut(&one).unwrap();
+        tracked.node_mut(&text).unwrap();
+        assert!(tracked.is_changed(&text));
+        assert!(!tracked.is_changed(&two));
+
+        // Changing a node as a whole covers the changes inside it, and widens its own
+        tracked.tag_mut(&two).unwrap();
+        tracked.attributes_mut(&two).unwrap();
+        tracked.node_mut(&text).unwrap();
+        let changes: Vec<_> = tracked.changes().collect();
+        assert_eq!(
+            changes,
+            [(&one, NodeChange::Attributes), (&two, NodeChange::Node)]
+        );
+        assert!(tracked.is_changed(&text));
+        assert!(!tracked.is_changed(&NodePath::root()));
+
+        assert!(tracked.tag_mut(&NodePath::root().child(5)).is_none());
+        assert!(tracked.node_mut(&NodePath::root()).is_none());
+        assert_eq!(tracked.changes().count(), 2);
+    }
+
+    #[test]
+    fn test_tracked_patch() {
+        let doc = Document::parse_str(SRC).unwrap();
+        let options = XmlWriteOptions::default();
+
+        // Start tags are found past quoted `>`, and keep their closing
+        let mut tracked = doc.to_tracked();
+        let one = NodePath::root().child(0);
+        tracked.attributes_mut(&one).unwrap()[0].value = "c".to_string();
+        tracked.attributes_mut(&NodePath::root()).unwrap().clear();
+        let xml = tracked.to_xml_patched(&options).unwrap();
+        assert_eq!(
+            xml,
+            SRC.replace("<root a = '1'>", "<root>")
+                .replace("x=\"a>b\" />", "x=\"c\" />")
+        );
+
+        // Text keeps the whitespace around it
+        let mut tracked = doc.to_tracked();
+        let text = NodePath::root().child(1).child(0);
+        let OwnedNode::Text(node) = tracked.node_mut(&text).unwrap() else {
+            panic!("Expected text");
+        };
+        node.text = "new".to_string();
+        let xml = tracked.to_xml_patched(&options).unwrap();
+        assert_eq!(xml, SRC.replace("text &amp; more", "new"));
+
+        // New nodes are written where the node they replaced was
+        let mut tracked = doc.to_tracked();
+        *tracked.node_mut(&text).unwrap() = OwnedNode::Comment(OwnedCommentNode::new("c"));
+        let xml = tracked.to_xml_patched(&options).unwrap();
+        assert_eq!(xml, SRC.replace("text &amp; more", "<!--c-->"));
+
+        // A node that cannot be located rewrites its parent instead
+        let mut owned = doc.to_owned();
+        let OwnedNode::Tag(two) = &mut owned.root.children[1] else {
+            panic!("Expected tag");
+        };
+        two.origin = Origin::default();
+        let mut tracked = TrackedDocument::new(owned, Some(SRC.to_string()));
+        tracked.attributes_mut(&NodePath::root().child(1)).unwrap();
+        let xml = tracked.to_xml_patched(&options).unwrap();
+        assert!(xml.starts_with("<?xml version='1.0'?>\n<!-- head -->\n<root a=\"1\">\n\t<one"));
+
+        // Edits to the whole document rewrite all of it
+        let mut tracked = doc.to_tracked();
+        tracked.document_mut().prolog.clear();
+        let xml = tracked.to_xml_patched(&options).unwrap();
+        assert_eq!(
+            xml,
+            tracked.document().to_xml_with_options(&options).unwrap()
+        );
+
+        let tracked = TrackedDocument::new(doc.to_owned(), None);
+        let xml = tracked.to_xml_patched(&options).unwrap();
+        assert_eq!(xml, doc.to_xml_with_options(&options).unwrap());
+    }
+
+    #[test]
+    fn test_tracked_replaced_node() {
+        let doc = Document::parse_str("<r><a>1</a><b>2</b></r>").unwrap();
+        let options = XmlWriteOptions::default().with_minify(true);
+        let (a, b) = (NodePath::root().child(0), NodePath::root().child(1));
+
+        // The replacement keeps the origin of the node it was cloned from, but is written where the old node was
+        let mut tracked = doc.to_tracked();
+        let clone = tracked.document().resolve(&b).unwrap().clone();
+        *tracked.node_mut(&a).unwrap() = clone;
+        let xml = tracked.to_xml_patched(&options).unwrap();
+        assert_eq!(xml, "<r><b>2</b><b>2</b></r>");
+
+        // Later edits do not move the recorded position
+        let clone = tracked.document().resolve(&b).unwrap().clone();
+        tracked.attributes_mut(&a).unwrap();
+        *tracked.node_mut(&a).unwrap() = clone;
+        tracked
+            .attributes_mut(&b)
+            .unwrap()
+            .push(OwnedNodeAttribute::new("k", "v"));
+        let xml = tracked.to_xml_patched(&options).unwrap();
+        assert_eq!(xml, "<r><b>2</b><b k=\"v\">2</b></r>");
+    }
+}
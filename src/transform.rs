@@ -0,0 +1,202 @@
+//! Rewriting passes over owned trees.
+use crate::{
+    OwnedDocument,
+    node::{OwnedNode, OwnedTagNode},
+};
+
+/// What to do with a node visited by [`OwnedDocument::transform`].
+///
+/// Changes made to the node through the `&mut` reference given to the callback are kept
+/// by every action except [`TransformAction::Remove`] and [`TransformAction::Replace`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransformAction {
+    /// Keep the node, then visit its children.
+    Keep,
+
+    /// Remove the node and everything below it.
+    Remove,
+
+    /// Replace the node with the given nodes, which are not visited.
+    Replace(Vec<OwnedNode>),
+
+    /// Visit the children first, then call the callback again with the rewritten node.
+    ///
+    /// The action returned by the second call is applied; returning `RecurseThenRewrite` again keeps the node.
+    RecurseThenRewrite,
+}
+
+/// A list of siblings being rewritten, and the tag they belong to.
+struct Frame {
+    input: std::vec::IntoIter<OwnedNode>,
+    output: Vec<OwnedNode>,
+
+    /// The tag the siblings are the children of, and whether to call the callback on it again afterwards
+    parent: Option<(OwnedNode, bool)>,
+}
+impl Frame {
+    fn new(nodes: Vec<OwnedNode>, parent: Option<(OwnedNode, bool)>) -> Self {
+        Self {
+            output: Vec::with_capacity(nodes.len()),
+            input: nodes.into_iter(),
+            parent,
+        }
+    }
+}
+
+/// Applies the action returned for a node that was already visited.
+fn apply(output: &mut Vec<OwnedNode>, node: OwnedNode, action: TransformAction) {
+    match action {
+        TransformAction::Keep | TransformAction::RecurseThenRewrite => output.push(node),
+        TransformAction::Remove => {}
+        TransformAction::Replace(nodes) => output.extend(nodes),
+    }
+}
+
+/// Rewrites a list of siblings and everything below them, without recursion.
+fn transform_nodes(
+    nodes: &mut Vec<OwnedNode>,
+    f: &mut dyn FnMut(&mut OwnedNode) -> TransformAction,
+) {
+    let mut stack = vec![Frame::new(std::mem::take(nodes), None)];
+    loop {
+        let frame = stack
+            .last_mut()
+            .expect("The outermost frame is only popped at the end");
+        let Some(mut node) = frame.input.next() else {
+            let frame = stack.pop().expect("Frame was just borrowed");
+            let Some((mut node, revisit)) = frame.parent else {
+                *nodes = frame.output;
+                return;
+            };
+
+            if let OwnedNode::Tag(tag) = &mut node {
+                tag.children = frame.output;
+            }
+
+            let output = &mut stack
+                .last_mut()
+                .expect("Parents are below their children")
+                .output;
+            if revisit {
+                let action = f(&mut node);
+                apply(output, node, action);
+            } else {
+                output.push(node);
+            }
+            continue;
+        };
+
+        let action = f(&mut node);
+        let revisit = match action {
+            TransformAction::Keep => false,
+            TransformAction::RecurseThenRewrite => true,
+            action => {
+                apply(&mut frame.output, node, action);
+                continue;
+            }
+        };
+
+        if let OwnedNode::Tag(tag) = &mut node {
+            let children = std::mem::take(&mut tag.children);
+            stack.push(Frame::new(children, Some((node, revisit))));
+        } else if revisit {
+            let action = f(&mut node);
+            apply(&mut frame.output, node, action);
+        } else {
+            frame.output.push(node);
+        }
+    }
+}
+
+impl OwnedTagNode {
+    /// Rewrites every node below this tag with the callback, in document order.
+    ///
+    /// The tag itself is not passed to the callback. See [`OwnedDocument::transform`].
+    pub fn transform(&mut self, mut f: impl FnMut(&mut OwnedNode) -> TransformAction) {
+        transform_nodes(&mut self.children, &mut f);
+    }
+}
+
+impl OwnedDocument {
+    /// Rewrites every node of the document with the callback, in document order.
+    ///
+    /// The callback can change the node in place, and its [`TransformAction`] decides whether the node is kept,
+    /// removed or replaced. The root element is not a node, so it is not passed to the callback;
+    /// its children, and the prolog and epilog, are. The walk does not recurse, so it is safe on deeply nested documents.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmltree::{Document, TransformAction, XmlWriteOptions, node::OwnedNode};
+    ///
+    /// let doc = Document::parse_str("<form><?script run()?><input password=\"hunter2\" /></form>").unwrap();
+    /// let mut owned = doc.to_owned();
+    ///
+    /// owned.transform(|node| match node {
+    ///     OwnedNode::ProcessingInstruction(pi) if pi.target == "script" => TransformAction::Remove,
+    ///     OwnedNode::Tag(tag) => {
+    ///         if let Some(attr) = tag.get_attribute_mut(None, "password") {
+    ///             attr.value = "***".to_string();
+    ///         }
+    ///         TransformAction::Keep
+    ///     }
+    ///     _ => TransformAction::Keep,
+    /// });
+    ///
+    /// let xml = owned.to_xml_with_options(&XmlWriteOptions::default().with_minify(true)).unwrap();
+    /// assert_eq!(xml, "<form><input password=\"***\" /></form>\n");
+    /// ```
+    pub fn transform(&mut self, mut f: impl FnMut(&mut OwnedNode) -> TransformAction) {
+        transform_nodes(&mut self.prolog, &mut f);
+        transform_nodes(&mut self.root.children, &mut f);
+        transform_nodes(&mut self.epilog, &mut f);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Document, node::OwnedTextNode};
+
+    #[test]
+    fn test_transform() {
+        let doc = Document::parse_str("<!-- a --><r><b><c>1</c><c>2</c></b><d /></r>").unwrap();
+
+        // Children are visited before the rewrite of their parent, which sees the result
+        let mut owned = doc.to_owned();
+        let mut visited = vec![];
+        owned.transform(|node| match node {
+            OwnedNode::Comment(_) => TransformAction::Remove,
+            OwnedNode::Text(text) => {
+                visited.push(text.text.clone());
+                TransformAction::Replace(vec![OwnedNode::Text(OwnedTextNode::new("x"))])
+            }
+            OwnedNode::Tag(tag) if tag.name == "b" && tag.children.len() == 2 => {
+                visited.push("b".to_string());
+                let rewritten = tag.children.iter().all(|c| {
+                    matches!(c, OwnedNode::Tag(c) if matches!(c.children.as_slice(), [OwnedNode::Text(t)] if t.text == "x"))
+                });
+                if rewritten {
+                    TransformAction::Replace(tag.children.drain(..).collect())
+                } else {
+                    TransformAction::RecurseThenRewrite
+                }
+            }
+            OwnedNode::Tag(tag) if tag.name == "d" => TransformAction::RecurseThenRewrite,
+            _ => TransformAction::Keep,
+        });
+
+        assert_eq!(visited, ["b", "1", "2", "b"]);
+        assert!(owned.prolog.is_empty());
+
+        let names: Vec<_> = owned
+            .root
+            .children
+            .iter()
+            .map(|c| match c {
+                OwnedNode::Tag(tag) => tag.name.to_string(),
+                _ => "#other".to_string(),
+            })
+            .collect();
+        assert_eq!(names, ["c", "c", "d"]);
+    }
+}
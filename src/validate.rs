@@ -0,0 +1,243 @@
+//! Validation of documents against user-provided validators.
+//!
+//! This crate does not load or compile DTDs or schemas; a [`Validator`] is anything that can check a parsed document,
+//! such as a grammar compiled by another crate, or a closure.
+//!
+//! A [`ValidatorRegistry`] holds validators that were built once by the caller, and shares them between every document
+//! and thread it validates, so an expensive grammar is not rebuilt for each document.
+use crate::{
+    Document,
+    error::{ErrorContext, XmlError, XmlErrorKind, XmlResult},
+    node::Node,
+};
+use std::{collections::HashMap, sync::Arc};
+
+/// A check that can be run against a parsed document.
+///
+/// Implemented for any `Fn(&Document) -> XmlResult<()>` closure.
+pub trait Validator: Send + Sync {
+    /// Validates the document.
+    ///
+    /// # Errors
+    /// Should return an error describing the first problem found in the document.
+    fn validate(&self, document: &Document<'_>) -> XmlResult<()>;
+}
+impl<F> Validator for F
+where
+    F: Fn(&Document<'_>) -> XmlResult<()> + Send + Sync,
+{
+    fn validate(&self, document: &Document<'_>) -> XmlResult<()> {
+        self(document)
+    }
+}
+
+/// A set of validators, keyed by the document type they apply to.
+///
+/// The registry only dispatches documents to validators; building them, such as compiling a schema, is up to the caller.
+///
+/// Validators are matched to a document by the name of its `<!DOCTYPE>`, or by the name of its root node if it has none.
+/// The registry can be cloned cheaply, and validators are shared between clones.
+///
+/// # Example
+/// ```rust
+/// use xmltree::{Document, validate::ValidatorRegistry};
+///
+/// let mut registry = ValidatorRegistry::new();
+/// registry.register("book", |doc: &Document| {
+///     match doc.root().get_attribute(None, "isbn") {
+///         Some(_) => Ok(()),
+///         None => Err(xmltree::validate::invalid(doc, "A book must have an ISBN")),
+///     }
+/// });
+///
+/// let documents = vec![
+///     Document::parse_str(r#"<book isbn="123" />"#).unwrap(),
+///     Document::parse_str("<book />").unwrap(),
+/// ];
+///
+/// let results = registry.validate_batch(&documents);
+/// assert!(results[0].is_ok());
+/// assert!(results[1].is_err());
+/// ```
+#[derive(Clone, Default)]
+pub struct ValidatorRegistry {
+    validators: HashMap<String, Arc<dyn Validator>>,
+}
+impl ValidatorRegistry {
+    /// Create a new empty registry.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a validator for documents of the given type, replacing any existing one.
+    pub fn register(&mut self, doctype: impl Into<String>, validator: impl Validator + 'static) {
+        self.validators.insert(doctype.into(), Arc::new(validator));
+    }
+
+    /// Returns the validator registered for the given document type, if any.
+    #[must_use]
+    pub fn get(&self, doctype: &str) -> Option<&Arc<dyn Validator>> {
+        self.validators.get(doctype)
+    }
+
+    /// Returns the validator that applies to the given document, if any.
+    ///
+    /// The document's `<!DOCTYPE>` name is used if present, otherwise the name of its root node.
+    #[must_use]
+    pub fn validator_for(&self, document: &Document<'_>) -> Option<&Arc<dyn Validator>> {
+        self.get(&doctype(document))
+    }
+
+    /// Validates a document with the validator that applies to it.
+    ///
+    /// # Errors
+    /// Returns an error if no validator applies to the document, or if the document is invalid.
+    pub fn validate(&self, document: &Document<'_>) -> XmlResult<()> {
+        let doctype = doctype(document);
+        let Some(validator) = self.get(&doctype) else {
+            return Err(invalid(
+                document,
+                &format!("No validator registered for document type `{doctype}`"),
+            ));
+        };
+
+        validator.validate(document)
+    }
+
+    /// Validates a batch of documents concurrently, using all available cores.
+    ///
+    /// Results are returned in the same order as the documents.
+    /// Without threads, as on `wasm32-unknown-unknown`, the documents are validated in turn on the calling thread.
+    #[must_use]
+    pub fn validate_batch(&self, documents: &[Document<'_>]) -> Vec<XmlResult<()>> {
+        let threads = std::thread::available_parallelism().map_or(1, std::num::NonZeroUsize::get);
+        if threads == 1 || documents.len() < 2 {
+            return documents
+                .iter()
+                .map(|document| self.validate(document))
+                .collect();
+        }
+        let chunk_size = documents.len().div_ceil(threads).max(1);
+
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = documents
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .map(|document| self.validate(document))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .flat_map(|handle| match handle.join() {
+                    Ok(results) => results,
+                    Err(panic) => std::panic::resume_unwind(panic),
+                })
+                .collect()
+        })
+    }
+}
+impl std::fmt::Debug for ValidatorRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ValidatorRegistry")
+            .field("doctypes", &self.validators.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// Returns the document type validators are looked up by; the `<!DOCTYPE>` name, or the name of the root node.
+fn doctype(document: &Document<'_>) -> String {
+    let dtd_name = document.prolog().iter().find_map(|node| match node {
+        Node::DocumentType(dtd) => Some(dtd.name().text()),
+        _ => None,
+    });
+
+    match dtd_name {
+        Some(name) => name.to_string(),
+        None => document.root().name().to_string(),
+    }
+}
+
+/// Creates a validation error for the given document, pointing at its root node.
+///
+/// Convenience for writing [`Validator`] implementations.
+#[must_use]
+pub fn invalid(document: &Document<'_>, message: &str) -> XmlError {
+    XmlError::new(
+        XmlErrorKind::Custom(message.to_string()),
+        ErrorContext::new(
+            document.source().unwrap_or_default(),
+            *document.root().span(),
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> ValidatorRegistry {
+        let mut registry = ValidatorRegistry::new();
+        registry.register("a", |doc: &Document<'_>| {
+            if doc.root().children().is_empty() {
+                Err(invalid(doc, "empty"))
+            } else {
+                Ok(())
+            }
+        });
+        registry.register("b", |_: &Document<'_>| Ok(()));
+        registry
+    }
+
+    #[test]
+    fn test_validator_lookup() {
+        let registry = registry();
+
+        let doc = Document::parse_str("<!DOCTYPE b><a />").unwrap();
+        assert!(registry.validate(&doc).is_ok());
+
+        let doc = Document::parse_str("<a />").unwrap();
+        assert!(registry.validate(&doc).is_err());
+
+        let doc = Document::parse_str("<c />").unwrap();
+        assert!(registry.validator_for(&doc).is_none());
+        assert!(registry.validate(&doc).is_err());
+
+        // Errors name the document type that was looked up
+        let doc = Document::parse_str("<!DOCTYPE d><a />").unwrap();
+        let err = registry.validate(&doc).unwrap_err();
+        assert!(err.to_string().contains("document type `d`"), "{err}");
+    }
+
+    #[test]
+    fn test_validate_batch() {
+        let registry = registry();
+        let sources: Vec<String> = (0..100)
+            .map(|i| {
+                if i % 3 == 0 {
+                    "<a />".to_string()
+                } else {
+                    format!("<a><n>{i}</n></a>")
+                }
+            })
+            .collect();
+        let documents: Vec<_> = sources
+            .iter()
+            .map(|s| Document::parse_str(s).unwrap())
+            .collect();
+
+        let results = registry.validate_batch(&documents);
+        assert_eq!(results.len(), 100);
+        for (i, result) in results.iter().enumerate() {
+            assert_eq!(result.is_ok(), i % 3 != 0);
+        }
+
+        assert!(registry.validate_batch(&[]).is_empty());
+    }
+}
@@ -0,0 +1,278 @@
+//! Bindings for running the parser and formatter from JavaScript, through `wasm-bindgen`.
+//!
+//! Every function takes the document as a string. Structured results are returned as JSON strings,
+//! to be read with `JSON.parse`, and failures are thrown as JavaScript errors.
+//!
+//! Values in the JSON trees are raw, as they appear in the source; entity references are not expanded.
+use crate::{
+    Document, Matcher, StrSpan, XmlWriteOptions,
+    error::{XmlError, XmlResult},
+    node::{Node, TagNode},
+};
+use std::fmt::Write;
+use wasm_bindgen::prelude::*;
+
+/// Formats a document, indenting nested nodes with `indent`, or writing it on a single line if `minify` is set.
+///
+/// # Errors
+/// Throws if the document is not valid XML
+#[wasm_bindgen]
+pub fn format(src: &str, indent: &str, minify: bool) -> Result<String, JsError> {
+    format_document(src, indent, minify).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Parses a document into a JSON tree.
+///
+/// The result is an object with `declaration`, `prolog`, `root` and `epilog` keys.
+/// Nodes are objects with a `type` of `element`, `text`, `cdata`, `comment`, `pi` or `doctype`;
+/// elements have `name`, `attributes` as a list of `{ name, value }` objects, and `children`.
+///
+/// # Errors
+/// Throws if the document is not valid XML
+#[wasm_bindgen]
+pub fn parse(src: &str) -> Result<String, JsError> {
+    let document = Document::parse_str(src).map_err(|e| JsError::new(&e.to_string()))?;
+    Ok(document_json(&document))
+}
+
+/// Checks a document, returning every problem found as a JSON list.
+///
+/// Problems are objects with `code`, `severity`, `message`, `line` and `column` keys; an empty list means the document is valid.
+/// See [`Document::parse_all_errors`].
+#[wasm_bindgen]
+#[must_use]
+pub fn validate(src: &str) -> String {
+    let (_, problems) = Document::parse_all_errors(src);
+    problems_json(&problems)
+}
+
+/// Finds the elements with the given name, written as `local` or `prefix:local`,
+/// returning a JSON list of each one formatted as XML.
+///
+/// # Errors
+/// Throws if the document is not valid XML
+#[wasm_bindgen]
+pub fn query(src: &str, name: &str) -> Result<String, JsError> {
+    query_document(src, name).map_err(|e| JsError::new(&e.to_string()))
+}
+
+fn format_document(src: &str, indent: &str, minify: bool) -> XmlResult<String> {
+    let document = Document::parse_str(src)?;
+    let options = XmlWriteOptions::default()
+        .with_indent(indent)
+        .with_minify(minify);
+    Ok(document.to_xml_with_options(&options)?)
+}
+
+fn query_document(src: &str, name: &str) -> XmlResult<String> {
+    let document = Document::parse_str(src)?;
+    let matcher = match name.split_once(':') {
+        Some((prefix, local)) => Matcher::new().with_name(Some(prefix), local),
+        None => Matcher::new().with_name(None, name),
+    };
+
+    let mut json = String::from("[");
+    for (i, tag) in document.root().find_all(&matcher).into_iter().enumerate() {
+        if i > 0 {
+            json.push(',');
+        }
+        push_string(&mut json, &tag.to_string());
+    }
+    json.push(']');
+    Ok(json)
+}
+
+fn problems_json(problems: &[XmlError]) -> String {
+    let mut json = String::from("[");
+    for (i, problem) in problems.iter().enumerate() {
+        if i > 0 {
+            json.push(',');
+        }
+        let (line, column) = problem.context.position();
+        json.push_str("{\"code\":");
+        push_string(&mut json, problem.code().as_str());
+        json.push_str(",\"severity\":");
+        push_string(&mut json, &problem.severity.to_string());
+        json.push_str(",\"message\":");
+        push_string(&mut json, &problem.kind.to_string());
+        let _ = write!(json, ",\"line\":{line},\"column\":{column}}}");
+    }
+    json.push(']');
+    json
+}
+
+/// Work left to do while writing a tree as JSON.
+enum Task<'a, 'src> {
+    Tag(&'a TagNode<'src>),
+    Node(&'a Node<'src>),
+    Raw(&'static str),
+}
+
+/// Pushes the tasks that write a list of nodes, in reverse so they are popped in order.
+fn push_list<'a, 'src>(stack: &mut Vec<Task<'a, 'src>>, nodes: &'a [Node<'src>]) {
+    stack.push(Task::Raw("]"));
+    for (i, node) in nodes.iter().enumerate().rev() {
+        stack.push(Task::Node(node));
+        if i > 0 {
+            stack.push(Task::Raw(","));
+        }
+    }
+    stack.push(Task::Raw("["));
+}
+
+/// Writes a document as a JSON tree, without recursion.
+fn document_json(document: &Document<'_>) -> String {
+    let mut json = String::from("{\"declaration\":");
+    match document.declaration() {
+        Some(declaration) => {
+            json.push_str("{\"version\":");
+            push_string(&mut json, declaration.version().text());
+            json.push_str(",\"encoding\":");
+            push_optional(&mut json, declaration.encoding().map(StrSpan::text));
+            let standalone = declaration
+                .standalone()
+                .map(|s| if s { "yes" } else { "no" });
+            json.push_str(",\"standalone\":");
+            push_optional(&mut json, standalone);
+            json.push('}');
+        }
+        None => json.push_str("null"),
+    }
+
+    let mut stack = vec![Task::Raw("}")];
+    push_list(&mut stack, document.epilog());
+    stack.push(Task::Raw(",\"epilog\":"));
+    if document.has_root() {
+        stack.push(Task::Tag(document.root()));
+    } else {
+        stack.push(Task::Raw("null"));
+    }
+    stack.push(Task::Raw(",\"root\":"));
+    push_list(&mut stack, document.prolog());
+    stack.push(Task::Raw(",\"prolog\":"));
+
+    while let Some(task) = stack.pop() {
+        let node = match task {
+            Task::Raw(text) => {
+                json.push_str(text);
+                continue;
+            }
+            Task::Tag(tag) => {
+                json.push_str("{\"type\":\"element\",\"name\":");
+                push_string(&mut json, &tag.name().to_string());
+                json.push_str(",\"attributes\":[");
+                for (i, attr) in tag.attributes().iter().enumerate() {
+                    if i > 0 {
+                        json.push(',');
+                    }
+                    json.push_str("{\"name\":");
+                    push_string(&mut json, &attr.name().to_string());
+                    json.push_str(",\"value\":");
+                    push_string(&mut json, attr.value().text());
+                    json.push('}');
+                }
+                json.push_str("],\"children\":");
+                stack.push(Task::Raw("}"));
+                push_list(&mut stack, tag.children());
+                continue;
+            }
+            Task::Node(Node::Child(tag)) => {
+                stack.push(Task::Tag(tag));
+                continue;
+            }
+            Task::Node(node) => node,
+        };
+
+        let (kind, fields): (&str, Vec<(&str, Option<&str>)>) = match node {
+            Node::Text(text) => ("text", vec![("text", Some(text.text().text()))]),
+            Node::Cdata(cdata) => ("cdata", vec![("text", Some(cdata.content().text()))]),
+            Node::Comment(comment) => ("comment", vec![("text", Some(comment.text().text()))]),
+            Node::ProcessingInstruction(pi) => (
+                "pi",
+                vec![
+                    ("target", Some(pi.target().text())),
+                    ("content", pi.content().map(StrSpan::text)),
+                ],
+            ),
+            Node::DocumentType(dtd) => ("doctype", vec![("name", Some(dtd.name().text()))]),
+            Node::Child(_) => unreachable!("Tags are handled above"),
+        };
+
+        let _ = write!(json, "{{\"type\":\"{kind}\"");
+        for (key, value) in fields {
+            let _ = write!(json, ",\"{key}\":");
+            push_optional(&mut json, value);
+        }
+        json.push('}');
+    }
+    json
+}
+
+fn push_optional(json: &mut String, value: Option<&str>) {
+    match value {
+        Some(value) => push_string(json, value),
+        None => json.push_str("null"),
+    }
+}
+
+/// Writes a JSON string literal.
+fn push_string(json: &mut String, value: &str) {
+    json.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if u32::from(c) < 0x20 => {
+                let _ = write!(json, "\\u{:04x}", u32::from(c));
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document_json() {
+        let src =
+            "<?xml version=\"1.0\"?><!-- \"c\" --><a x='1&amp;2'>t\t<b/><![CDATA[d]]><?p?></a>";
+        let document = Document::parse_str(src).unwrap();
+        assert_eq!(
+            document_json(&document),
+            concat!(
+                r#"{"declaration":{"version":"1.0","encoding":null,"standalone":null},"#,
+                r#""prolog":[{"type":"comment","text":" \"c\" "}],"#,
+                r#""root":{"type":"element","name":"a","attributes":[{"name":"x","value":"1&amp;2"}],"children":["#,
+                r#"{"type":"text","text":"t"},{"type":"element","name":"b","attributes":[],"children":[]},"#,
+                r#"{"type":"cdata","text":"d"},{"type":"pi","target":"p","content":null}]},"#,
+                r#""epilog":[]}"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_bindings() {
+        assert_eq!(
+            format_document("<a><b/></a>", "", true).unwrap(),
+            "<a><b /></a>\n"
+        );
+        assert!(format_document("<a></b>", "", true).is_err());
+
+        assert_eq!(
+            query_document("<a><n:b>1</n:b><b/></a>", "n:b").unwrap(),
+            r#"["<n:b>\n\t1\n</n:b>"]"#
+        );
+
+        assert_eq!(validate("<a/>"), "[]");
+        assert_eq!(
+            validate("<a></b>"),
+            r#"[{"code":"XML0003","severity":"error","message":"Unclosed tag: a","line":1,"column":4}]"#
+        );
+    }
+}
@@ -0,0 +1,464 @@
+//! Streaming XML writer, for documents too large to build as a tree first.
+use crate::to_xml::{
+    ControlCharPolicy, XmlWriteOptions, encode_entities, escape_comment, is_xml10_char,
+};
+use std::io::{Error, ErrorKind, Result, Write};
+use xmlparser::XmlCharExt;
+
+/// Writes an XML document one node at a time, without building a tree in memory.
+///
+/// Output is formatted like [`OwnedDocument::to_xml_with_options`](crate::OwnedDocument::to_xml_with_options);
+/// text and attribute values are escaped, nested nodes are indented, and empty elements are written as `<name />`.
+///
+/// The writer keeps a stack of open elements, so [`XmlWriter::end_element`] always closes the most recent one.
+/// Calls that would produce a malformed document, such as text outside the root or an element name with spaces in it,
+/// fail with an [`ErrorKind::InvalidInput`] error. Call [`XmlWriter::finish`] once done, to check every element was closed.
+///
+/// CDATA content is written as-is, with any `]]>` split across two sections.
+///
+/// # Example
+/// ```rust
+/// use xmltree::{XmlWriteOptions, XmlWriter};
+///
+/// let mut writer = XmlWriter::new(vec![], XmlWriteOptions::default().with_indent("  "));
+/// writer.start_element("export")?;
+/// for id in 0..2 {
+///     writer.start_element("row")?;
+///     writer.attribute("id", &id.to_string())?;
+///     writer.text("a < b")?;
+///     writer.end_element()?;
+/// }
+/// writer.end_element()?;
+///
+/// let xml = String::from_utf8(writer.finish()?).unwrap();
+/// assert_eq!(xml, concat!(
+///     "<export>\n",
+///     "  <row id=\"0\">\n    a &lt; b\n  </row>\n",
+///     "  <row id=\"1\">\n    a &lt; b\n  </row>\n",
+///     "</export>\n",
+/// ));
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct XmlWriter<W: Write> {
+    writer: W,
+    options: XmlWriteOptions,
+
+    /// Names of the open elements, innermost last.
+    open: Vec<String>,
+
+    /// A start tag has been written without its closing `>`, so attributes can still be added.
+    in_start_tag: bool,
+
+    /// Anything has been written, so a declaration can no longer be.
+    started: bool,
+
+    /// The root element has been started.
+    has_root: bool,
+
+    /// The number of open elements that were opened before the writer was created, and cannot be ended by it.
+    floor: usize,
+}
+
+impl<W: Write> XmlWriter<W> {
+    /// Creates a writer that writes a document to `writer`, formatted with the given options.
+    ///
+    /// Output is written in many small pieces, so `writer` should usually be buffered.
+    pub fn new(writer: W, options: XmlWriteOptions) -> Self {
+        Self {
+            writer,
+            options,
+            open: vec![],
+            in_start_tag: false,
+            started: false,
+            has_root: false,
+            floor: 0,
+        }
+    }
+
+    /// Creates a writer that continues inside an element opened elsewhere, such as the root of an existing file.
+    ///
+    /// If `in_start_tag` is set, the start tag of the element is still waiting for its closing `>`.
+    pub(crate) fn inside(
+        writer: W,
+        options: XmlWriteOptions,
+        name: &str,
+        in_start_tag: bool,
+    ) -> Self {
+        Self {
+            writer,
+            options,
+            open: vec![name.to_string()],
+            in_start_tag,
+            started: true,
+            has_root: true,
+            floor: 1,
+        }
+    }
+
+    /// Ends every element opened by a writer from [`XmlWriter::inside`], returning the underlying writer,
+    /// and whether the outer start tag is still waiting for its closing `>`.
+    pub(crate) fn finish_inside(mut self) -> Result<(W, bool)> {
+        while self.open.len() > self.floor {
+            self.end_element()?;
+        }
+        Ok((self.writer, self.in_start_tag))
+    }
+
+    /// Returns the number of elements that are open.
+    #[must_use]
+    pub fn depth(&self) -> usize {
+        self.open.len()
+    }
+
+    /// Writes the XML declaration, with an optional encoding and standalone flag.
+    ///
+    /// The version is `1.1` if the options use [`ControlCharPolicy::Xml11`], and `1.0` otherwise.
+    /// With that policy, a declaration is also written automatically before the first node.
+    ///
+    /// # Errors
+    /// Fails if anything has already been written, or the writer fails.
+    pub fn declaration(&mut self, encoding: Option<&str>, standalone: Option<bool>) -> Result<()> {
+        if self.started {
+            return Err(misuse("The declaration must be written first"));
+        }
+        self.started = true;
+
+        let version = if self.options.control_chars == ControlCharPolicy::Xml11 {
+            "1.1"
+        } else {
+            "1.0"
+        };
+        write!(self.writer, r#"<?xml version="{version}""#)?;
+
+        if let Some(encoding) = encoding {
+            let encoding = encode_entities(encoding, &self.options)?;
+            write!(self.writer, r#" encoding="{encoding}""#)?;
+        }
+
+        if let Some(standalone) = standalone {
+            let standalone = if standalone { "yes" } else { "no" };
+            write!(self.writer, r#" standalone="{standalone}""#)?;
+        }
+
+        let (_, nl) = self.options.layout(0);
+        write!(self.writer, " ?>{nl}")
+    }
+
+    /// Opens an element, named as `local` or `prefix:local`.
+    ///
+    /// Its attributes can be written with [`XmlWriter::attribute`] until anything else is written.
+    ///
+    /// # Errors
+    /// Fails if the name is not a valid XML name, the document already has a root element and this would be a second one,
+    /// or the writer fails.
+    pub fn start_element(&mut self, name: &str) -> Result<()> {
+        check_qname(name)?;
+        if self.open.is_empty() && self.has_root {
+            return Err(misuse("The document already has a root element"));
+        }
+        self.begin_node()?;
+
+        let (tab, _) = self.options.layout_in(self.open.len(), &self.open);
+        write!(self.writer, "{tab}<{name}")?;
+
+        self.open.push(name.to_string());
+        self.in_start_tag = true;
+        self.has_root = true;
+        Ok(())
+    }
+
+    /// Adds an attribute to the element just opened, named as `local` or `prefix:local`.
+    ///
+    /// # Errors
+    /// Fails if the name is not a valid XML name, anything other than attributes was written since
+    /// [`XmlWriter::start_element`], or the writer fails.
+    pub fn attribute(&mut self, name: &str, value: &str) -> Result<()> {
+        check_qname(name)?;
+        if !self.in_start_tag || self.open.len() <= self.floor {
+            return Err(misuse(
+                "Attributes must be written directly after their element is opened",
+            ));
+        }
+
+        let value = encode_entities(value, &self.options)?;
+        write!(self.writer, r#" {name}="{value}""#)
+    }
+
+    /// Writes a text node in the current element. Empty text writes nothing.
+    ///
+    /// # Errors
+    /// Fails if no element is open, or the writer fails.
+    pub fn text(&mut self, text: &str) -> Result<()> {
+        if self.open.is_empty() {
+            return Err(misuse("Text must be written inside an element"));
+        }
+        if text.is_empty() {
+            return Ok(());
+        }
+        self.begin_node()?;
+
+        let text = encode_entities(text, &self.options)?;
+        let text = self.options.layout_text(&text, self.open.len(), &self.open);
+        self.writer.write_all(text.as_bytes())
+    }
+
+    /// Writes a CDATA section in the current element.
+    ///
+    /// # Errors
+    /// Fails if no element is open, the content has characters not allowed in XML that the options do not keep or strip,
+    /// or the writer fails.
+    pub fn cdata(&mut self, content: &str) -> Result<()> {
+        if self.open.is_empty() {
+            return Err(misuse("CDATA must be written inside an element"));
+        }
+
+        // Character references are not read inside CDATA, so there is no way to write restricted characters
+        let mut checked = String::with_capacity(content.len());
+        for c in content.chars() {
+            if is_xml10_char(c) || self.options.control_chars == ControlCharPolicy::Keep {
+                checked.push(c);
+            } else if self.options.control_chars != ControlCharPolicy::Strip {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Character U+{:04X} is not allowed in CDATA", u32::from(c)),
+                ));
+            }
+        }
+
+        self.begin_node()?;
+        let content = checked.replace("]]>", "]]]]><![CDATA[>");
+        let (tab, nl) = self.options.layout_in(self.open.len(), &self.open);
+        write!(self.writer, "{tab}<![CDATA[{content}]]>{nl}")
+    }
+
+    /// Writes a comment, inside the current element or around the root.
+    ///
+    /// Comments left out by [`XmlWriteOptions::comments`] are skipped, and hyphens are separated as described in [`CommentPolicy`](crate::CommentPolicy).
+    ///
+    /// # Errors
+    /// Fails if the writer fails.
+    pub fn comment(&mut self, text: &str) -> Result<()> {
+        if !self.options.comments.keeps(text) {
+            return Ok(());
+        }
+        self.begin_node()?;
+
+        let text = encode_entities(&escape_comment(text), &self.options)?;
+        let (tab, nl) = self.options.layout_in(self.open.len(), &self.open);
+        write!(self.writer, "{tab}<!--{text}-->{nl}")
+    }
+
+    /// Writes a processing instruction, inside the current element or around the root.
+    ///
+    /// # Errors
+    /// Fails if the target is not a valid XML name, or the writer fails.
+    pub fn processing_instruction(&mut self, target: &str, content: Option<&str>) -> Result<()> {
+        if !is_name(target) {
+            return Err(invalid_name(target));
+        }
+        self.begin_node()?;
+
+        let (tab, nl) = self.options.layout_in(self.open.len(), &self.open);
+        write!(self.writer, "{tab}<?{target}")?;
+
+        if let Some(content) = content {
+            let content = encode_entities(content, &self.options)?;
+            write!(self.writer, " {content}")?;
+        }
+
+        write!(self.writer, "?>{nl}")
+    }
+
+    /// Closes the most recently opened element.
+    ///
+    /// # Errors
+    /// Fails if no element is open, or the writer fails.
+    pub fn end_element(&mut self) -> Result<()> {
+        if self.open.len() <= self.floor {
+            return Err(misuse("There is no open element to end"));
+        }
+        let name = self.open.pop().unwrap_or_default();
+
+        let (tab, nl) = self.options.layout_in(self.open.len(), &self.open);
+        if self.in_start_tag {
+            self.in_start_tag = false;
+            write!(self.writer, " />{nl}")
+        } else {
+            write!(self.writer, "{tab}</{name}>{nl}")
+        }
+    }
+
+    /// Finishes the document, returning the underlying writer once it is flushed.
+    ///
+    /// # Errors
+    /// Fails if no root element was written, elements are still open, or the writer fails.
+    pub fn finish(mut self) -> Result<W> {
+        if !self.has_root {
+            return Err(misuse("The document has no root element"));
+        }
+        if !self.open.is_empty() {
+            let message = format!("{} elements are still open", self.open.len());
+            return Err(Error::new(ErrorKind::InvalidInput, message));
+        }
+
+        if self.options.minify {
+            self.writer
+                .write_all(self.options.line_break().as_bytes())?;
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    /// Prepares to write a node; writes the automatic declaration, and completes any open start tag.
+    fn begin_node(&mut self) -> Result<()> {
+        if !self.started && self.options.control_chars == ControlCharPolicy::Xml11 {
+            self.declaration(None, None)?;
+        }
+        self.started = true;
+
+        if self.in_start_tag {
+            self.in_start_tag = false;
+            let (_, nl) = self.options.layout(0);
+            write!(self.writer, ">{nl}")?;
+        }
+        Ok(())
+    }
+}
+
+fn misuse(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidInput, message)
+}
+
+fn invalid_name(name: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidInput,
+        format!("`{name}` is not a valid XML name"),
+    )
+}
+
+/// Checks that a name is `local` or `prefix:local`, where both parts are names without colons.
+fn check_qname(name: &str) -> Result<()> {
+    let (prefix, local) = name.split_once(':').unwrap_or(("a", name));
+    if [prefix, local]
+        .iter()
+        .all(|part| is_name(part) && !part.contains(':'))
+    {
+        Ok(())
+    } else {
+        Err(invalid_name(name))
+    }
+}
+
+/// Returns true if the text is an XML [Name](https://www.w3.org/TR/xml/#NT-Name).
+fn is_name(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars.next().is_some_and(|c| c.is_xml_name_start()) && chars.all(|c| c.is_xml_name())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Document;
+
+    #[test]
+    fn test_matches_tree_writer() {
+        let src =
+            "<?xml version='1.0' encoding='UTF-8'?><!--c--><a x='1 2'><b/><c>t</c><?pi data?></a>";
+        let document = Document::parse_str(src).unwrap();
+
+        for options in [
+            XmlWriteOptions::default(),
+            XmlWriteOptions::default().with_minify(true),
+        ] {
+            let mut writer = XmlWriter::new(vec![], options.clone());
+            writer.declaration(Some("UTF-8"), None).unwrap();
+            writer.comment("c").unwrap();
+            writer.start_element("a").unwrap();
+            writer.attribute("x", "1 2").unwrap();
+            writer.start_element("b").unwrap();
+            writer.end_element().unwrap();
+            writer.start_element("c").unwrap();
+            writer.text("t").unwrap();
+            writer.end_element().unwrap();
+            writer.processing_instruction("pi", Some("data")).unwrap();
+            writer.end_element().unwrap();
+            assert_eq!(writer.depth(), 0);
+
+            let xml = String::from_utf8(writer.finish().unwrap()).unwrap();
+            assert_eq!(xml, document.to_xml_with_options(&options).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_cdata() {
+        let mut writer = XmlWriter::new(vec![], XmlWriteOptions::default().with_minify(true));
+        writer.start_element("a").unwrap();
+        writer.cdata("x]]>y").unwrap();
+        writer.end_element().unwrap();
+        let xml = String::from_utf8(writer.finish().unwrap()).unwrap();
+        assert_eq!(xml, "<a><![CDATA[x]]]]><![CDATA[>y]]></a>\n");
+
+        assert!(Document::parse_str(&xml).is_ok());
+    }
+
+    #[test]
+    fn test_misuse() {
+        let mut writer = XmlWriter::new(vec![], XmlWriteOptions::default());
+        let kind = |r: Result<()>| r.unwrap_err().kind();
+
+        assert_eq!(kind(writer.text("t")), ErrorKind::InvalidInput);
+        assert_eq!(kind(writer.end_element()), ErrorKind::InvalidInput);
+        assert_eq!(kind(writer.attribute("x", "1")), ErrorKind::InvalidInput);
+
+        writer.start_element("a").unwrap();
+        writer.text("t").unwrap();
+        assert_eq!(kind(writer.attribute("x", "1")), ErrorKind::InvalidInput);
+        assert_eq!(
+            kind(writer.declaration(None, None)),
+            ErrorKind::InvalidInput
+        );
+        writer.end_element().unwrap();
+
+        assert_eq!(kind(writer.start_element("b")), ErrorKind::InvalidInput);
+
+        let mut writer = XmlWriter::new(vec![], XmlWriteOptions::default());
+        writer.start_element("a").unwrap();
+        assert_eq!(writer.finish().unwrap_err().kind(), ErrorKind::InvalidInput);
+
+        let options = XmlWriteOptions::default().with_control_chars(ControlCharPolicy::Error);
+        let mut writer = XmlWriter::new(vec![], options);
+        writer.start_element("a").unwrap();
+        assert_eq!(kind(writer.cdata("\u{1}")), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_invalid_names() {
+        let mut writer = XmlWriter::new(vec![], XmlWriteOptions::default().with_minify(true));
+        let kind = |r: Result<()>| r.unwrap_err().kind();
+
+        for name in ["", "a b", "1a", "a>", ":a", "a:", "a:b:c", "a&b"] {
+            assert_eq!(
+                kind(writer.start_element(name)),
+                ErrorKind::InvalidInput,
+                "{name}"
+            );
+        }
+        assert_eq!(
+            kind(writer.processing_instruction("p i", None)),
+            ErrorKind::InvalidInput
+        );
+
+        writer.start_element("p:é-1.x").unwrap();
+        assert_eq!(
+            kind(writer.attribute("x=\"1\" y", "2")),
+            ErrorKind::InvalidInput
+        );
+        writer.attribute("xml:lang", "en").unwrap();
+        writer.processing_instruction("pi", None).unwrap();
+        writer.end_element().unwrap();
+
+        let xml = String::from_utf8(writer.finish().unwrap()).unwrap();
+        assert_eq!(xml, "<p:é-1.x xml:lang=\"en\"><?pi?></p:é-1.x>\n");
+    }
+}
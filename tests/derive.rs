@@ -0,0 +1,157 @@
+#![cfg(feature = "derive")]
+use std::borrow::Cow;
+use xmltree::{
+    Document, OwnedDocument,
+    error::XmlErrorKind,
+    from_xml::FromXml,
+    into_xml::ToXml,
+    to_bin::{BinDecodeError, Decoder, Encoder, ToBin, ToBinHandler},
+};
+
+#[derive(Debug, PartialEq, ToBin)]
+struct Record {
+    name: String,
+    tags: Vec<String>,
+    document: Option<OwnedDocument>,
+}
+
+#[derive(Debug, PartialEq, ToBin)]
+struct Pair<T>(T, T);
+
+#[derive(Debug, PartialEq, ToBin)]
+enum Entry<'src> {
+    Empty,
+    Text(&'src str),
+    Tagged { id: usize, tags: Vec<String> },
+}
+
+fn round_trip<'src, T: ToBinHandler<'src>>(value: &T, buffer: &'src mut Vec<u8>) -> T {
+    let mut encoder = Encoder::new();
+    value.write(&mut encoder).unwrap();
+    *buffer = encoder.into_inner();
+    T::read(&mut Decoder::new(buffer)).unwrap()
+}
+
+#[test]
+fn test_derive_round_trip() {
+    let record = Record {
+        name: "cache".to_string(),
+        tags: vec!["a".to_string(), "b".to_string()],
+        document: Some(
+            xmltree::Document::parse_str("<root a='1'>text</root>")
+                .unwrap()
+                .to_owned(),
+        ),
+    };
+
+    let mut buffer = vec![];
+    assert_eq!(round_trip(&record, &mut buffer), record);
+
+    let entries = vec![
+        Entry::Empty,
+        Entry::Text("hello"),
+        Entry::Tagged {
+            id: 7,
+            tags: record.tags,
+        },
+    ];
+    let mut buffer = vec![];
+    assert_eq!(round_trip(&entries, &mut buffer), entries);
+
+    let pair = Pair(1u8, 2u8);
+    let mut buffer = vec![];
+    assert_eq!(round_trip(&pair, &mut buffer), pair);
+}
+
+#[test]
+fn test_derive_invalid_variant() {
+    let data = [9u8];
+    assert!(matches!(
+        Entry::read(&mut Decoder::new(&data)),
+        Err(BinDecodeError::InvalidEnumVariant)
+    ));
+}
+
+#[derive(Debug, PartialEq, FromXml, ToXml)]
+struct Feed<'src> {
+    #[xml(attribute, rename = "xml:lang")]
+    lang: Option<Cow<'src, str>>,
+    title: Cow<'src, str>,
+    #[xml(rename = "entry")]
+    entries: Vec<FeedEntry>,
+}
+
+#[derive(Debug, PartialEq, FromXml, ToXml)]
+struct FeedEntry {
+    #[xml(attribute)]
+    id: u32,
+    #[xml(attribute)]
+    draft: Option<bool>,
+    #[xml(text)]
+    summary: String,
+}
+
+#[test]
+fn test_derive_from_xml() {
+    let src = "<feed xml:lang='en'><title>News</title><entry id='1'>First &amp; best</entry><entry id='2' draft='true'/></feed>";
+    let doc = Document::parse_str(src).unwrap();
+    let feed: Feed = doc.parse_into().unwrap();
+    assert!(matches!(feed.title, Cow::Borrowed("News")));
+    assert_eq!(
+        feed,
+        Feed {
+            lang: Some(Cow::Borrowed("en")),
+            title: Cow::Borrowed("News"),
+            entries: vec![
+                FeedEntry {
+                    id: 1,
+                    draft: None,
+                    summary: "First & best".to_string(),
+                },
+                FeedEntry {
+                    id: 2,
+                    draft: Some(true),
+                    summary: String::new(),
+                },
+            ],
+        }
+    );
+
+    let src = "<feed>\n  <title>News</title>\n  <entry id='1' draft='no'/>\n</feed>";
+    let doc = Document::parse_str(src).unwrap();
+    let err = doc.parse_into::<Feed>().unwrap_err();
+    assert!(matches!(err.kind, XmlErrorKind::Conversion(_)));
+    assert_eq!(err.context.position(), (3, 17));
+
+    let doc = Document::parse_str("<feed><entry id='1'/></feed>").unwrap();
+    let err = doc.parse_into::<Feed>().unwrap_err();
+    assert_eq!(
+        err.kind.to_string(),
+        "Conversion failed: `feed` is missing child `title`"
+    );
+}
+
+#[test]
+fn test_derive_to_xml() {
+    let feed = Feed {
+        lang: None,
+        title: Cow::Borrowed("News"),
+        entries: vec![
+            FeedEntry {
+                id: 1,
+                draft: Some(false),
+                summary: "Fish & chips".to_string(),
+            },
+            FeedEntry {
+                id: 2,
+                draft: None,
+                summary: String::new(),
+            },
+        ],
+    };
+
+    let xml = feed.to_xml_document("feed").to_xml(None).unwrap();
+    let doc = Document::parse_str(&xml).unwrap();
+    assert_eq!(doc.root().children().len(), 3);
+    assert_eq!(doc.parse_into::<Feed>().unwrap(), feed);
+}
@@ -1,4 +1,4 @@
-use xmltree::Document;
+use xmltree::{CompareOptions, Document, assert_xml_eq};
 
 const SRC: &str = include_str!("good.xml");
 const CMP: &str = include_str!("good.xml.parsed");
@@ -12,33 +12,8 @@ fn test_good() {
 
     let str = document.to_xml(None).unwrap();
     //    std::fs::write("tests/good.xml.parsed", &str).unwrap();
-    if str != CMP {
-        let mut src_lines = str.lines();
-        for (i, line) in CMP.lines().enumerate() {
-            match src_lines.next() {
-                None => {
-                    panic!(
-                        "Diff at line {}: Not enough rows in parsed document!",
-                        i + 1
-                    );
-                }
-
-                Some(l) => {
-                    if line == l {
-                        continue;
-                    } else {
-                        eprintln!("Diff at line {}", i + 1);
-
-                        eprintln!("Expected: {line}");
-                        eprintln!("Got: {l}");
-                        break;
-                    }
-                }
-            }
-        }
-
-        panic!("Parsed document does not match expected output");
-    }
+    assert_xml_eq!(str, CMP, CompareOptions::strict());
+    assert_eq!(str, CMP, "Formatting does not match expected output");
 }
 
 #[test]
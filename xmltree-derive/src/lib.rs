@@ -0,0 +1,403 @@
+//! # xmltree-derive
+//! Derive macros for the binary serializer and typed conversion of `xmltree`.
+//!
+//! Enable the `derive` feature of `xmltree` and use them as `xmltree::to_bin::ToBin`, `xmltree::from_xml::FromXml`
+//! and `xmltree::into_xml::ToXml`, rather than depending on this crate directly.
+#![warn(missing_docs)]
+#![warn(clippy::pedantic)]
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{
+    Data, DeriveInput, Fields, GenericArgument, GenericParam, Lifetime, LifetimeParam, LitStr,
+    PathArguments, Type, parse_macro_input, parse_quote,
+};
+
+/// Derives `ToBinHandler` for a struct or enum, writing each field in declaration order.
+///
+/// Every field must implement `ToBinHandler` itself.
+/// Enums are written as a one-byte variant index, followed by the fields of the variant,
+/// so they can have at most 256 variants.
+///
+/// If the type has lifetime parameters, the first one is the lifetime of the decoded data,
+/// so fields such as a `Document<'src>` can borrow from it.
+///
+/// Fields are written in the order they are declared, so reordering them changes the format.
+#[proc_macro_derive(ToBin)]
+pub fn derive_to_bin(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Derives `FromXml` for a struct with named fields, reading each field from the tag.
+///
+/// Fields are read from the only child tag named after the field, unless marked:
+/// - `#[xml(attribute)]` reads the field from an attribute
+/// - `#[xml(text)]` reads the field from the text of the tag itself
+/// - `#[xml(rename = "name")]` looks for a different name, which can have a prefix as `prefix:local`
+///
+/// `Option<T>` fields are `None` if the attribute or child is missing, and `Vec<T>` fields collect every child with the name.
+/// Other fields are required.
+///
+/// If the type has lifetime parameters, the first one is the lifetime of the source, so fields can borrow from it.
+#[proc_macro_derive(FromXml, attributes(xml))]
+pub fn derive_from_xml(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_from_xml(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Derives `ToXml` for a struct with named fields, writing each field into the tag.
+///
+/// Fields are written as a child tag named after the field, unless marked, with the same attributes as `FromXml`:
+/// - `#[xml(attribute)]` writes the field as an attribute
+/// - `#[xml(text)]` writes the field as the text of the tag itself
+/// - `#[xml(rename = "name")]` writes a different name, which can have a prefix as `prefix:local`
+///
+/// `Option<T>` fields are skipped if they are `None`, and `Vec<T>` fields write a child for each item.
+/// Fields are written in the order they are declared.
+#[proc_macro_derive(ToXml, attributes(xml))]
+pub fn derive_to_xml(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_to_xml(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Adds a lifetime for the source to types that do not borrow, returning the generics and the lifetime.
+fn source_lifetime(input: &DeriveInput) -> (syn::Generics, Lifetime) {
+    let mut generics = input.generics.clone();
+    let src = if let Some(param) = input.generics.lifetimes().next() {
+        param.lifetime.clone()
+    } else {
+        let lifetime = Lifetime::new("'__src", Span::call_site());
+        let param = LifetimeParam::new(lifetime.clone());
+        generics.params.insert(0, GenericParam::Lifetime(param));
+        lifetime
+    };
+    (generics, src)
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+
+    // Types that do not borrow get a lifetime of their own for the decoder
+    let (mut generics, src) = source_lifetime(input);
+
+    for param in generics.type_params_mut() {
+        param
+            .bounds
+            .push(parse_quote!(::xmltree::to_bin::ToBinHandler<#src>));
+    }
+
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+
+    let (write, read) = match &input.data {
+        Data::Struct(data) => expand_struct(&data.fields),
+        Data::Enum(data) => {
+            if data.variants.len() > 256 {
+                return Err(syn::Error::new_spanned(
+                    name,
+                    "ToBin supports at most 256 enum variants",
+                ));
+            }
+            expand_enum(data.variants.iter().map(|v| (&v.ident, &v.fields)))
+        }
+        Data::Union(data) => {
+            return Err(syn::Error::new_spanned(
+                data.union_token,
+                "ToBin cannot be derived for unions",
+            ));
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::xmltree::to_bin::ToBinHandler<#src> for #name #ty_generics #where_clause {
+            fn write(&self, encoder: &mut ::xmltree::to_bin::Encoder) -> ::std::io::Result<()> {
+                #write
+                Ok(())
+            }
+
+            fn read(
+                decoder: &mut ::xmltree::to_bin::Decoder<#src>,
+            ) -> ::std::result::Result<Self, ::xmltree::to_bin::BinDecodeError> {
+                #read
+            }
+        }
+    })
+}
+
+/// Names used to bind each field of a struct or variant in a pattern.
+fn bindings(fields: &Fields) -> Vec<syn::Ident> {
+    (0..fields.len())
+        .map(|i| format_ident!("__field{i}"))
+        .collect()
+}
+
+/// Destructuring pattern for the fields, binding them to the names from [`bindings`].
+fn pattern(fields: &Fields, bindings: &[syn::Ident]) -> TokenStream2 {
+    match fields {
+        Fields::Named(named) => {
+            let idents = named.named.iter().map(|f| &f.ident);
+            quote!({ #(#idents: #bindings),* })
+        }
+        Fields::Unnamed(_) => quote!(( #(#bindings),* )),
+        Fields::Unit => quote!(),
+    }
+}
+
+/// Constructor for the fields, reading each one from the decoder in order.
+fn constructor(fields: &Fields) -> TokenStream2 {
+    let read = quote!(::xmltree::to_bin::ToBinHandler::read(decoder)?);
+    match fields {
+        Fields::Named(named) => {
+            let idents = named.named.iter().map(|f| &f.ident);
+            quote!({ #(#idents: #read),* })
+        }
+        Fields::Unnamed(unnamed) => {
+            let reads = unnamed.unnamed.iter().map(|_| &read);
+            quote!(( #(#reads),* ))
+        }
+        Fields::Unit => quote!(),
+    }
+}
+
+fn expand_struct(fields: &Fields) -> (TokenStream2, TokenStream2) {
+    let bindings = bindings(fields);
+    let pattern = pattern(fields, &bindings);
+    let constructor = constructor(fields);
+
+    let write = quote! {
+        let Self #pattern = self;
+        #( ::xmltree::to_bin::ToBinHandler::write(#bindings, encoder)?; )*
+    };
+    let read = quote!(Ok(Self #constructor));
+    (write, read)
+}
+
+fn expand_enum<'a>(
+    variants: impl Iterator<Item = (&'a syn::Ident, &'a Fields)>,
+) -> (TokenStream2, TokenStream2) {
+    let mut write_arms = vec![];
+    let mut read_arms = vec![];
+
+    for (index, (ident, fields)) in variants.enumerate() {
+        let index = u8::try_from(index).expect("variant count is checked before expanding");
+        let bindings = bindings(fields);
+        let pattern = pattern(fields, &bindings);
+        let constructor = constructor(fields);
+
+        write_arms.push(quote! {
+            Self::#ident #pattern => {
+                ::xmltree::to_bin::ToBinHandler::write(&#index, encoder)?;
+                #( ::xmltree::to_bin::ToBinHandler::write(#bindings, encoder)?; )*
+            }
+        });
+        read_arms.push(quote! {
+            #index => Ok(Self::#ident #constructor),
+        });
+    }
+
+    let write = quote! {
+        match self {
+            #(#write_arms)*
+        }
+    };
+    let read = quote! {
+        match <u8 as ::xmltree::to_bin::ToBinHandler>::read(decoder)? {
+            #(#read_arms)*
+            _ => Err(::xmltree::to_bin::BinDecodeError::InvalidEnumVariant),
+        }
+    };
+    (write, read)
+}
+
+/// Where a field of a `FromXml` struct is read from.
+enum Source {
+    Child,
+    Attribute,
+    Text,
+}
+
+/// Returns the type inside `Wrapper<T>`, if `ty` is written as that wrapper.
+fn wrapped<'a>(ty: &'a Type, wrapper: &str) -> Option<&'a Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != wrapper {
+        return None;
+    }
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) if args.args.len() == 1 => match &args.args[0] {
+            GenericArgument::Type(inner) => Some(inner),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Reads the `#[xml(...)]` attributes of a field, returning where it is stored and the name to use.
+fn field_options(field: &syn::Field) -> syn::Result<(Source, String)> {
+    let ident = field.ident.as_ref().expect("fields are named");
+    let mut source = Source::Child;
+    let mut xml_name = ident.to_string().trim_start_matches("r#").to_string();
+
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("xml")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("attribute") {
+                source = Source::Attribute;
+            } else if meta.path.is_ident("text") {
+                source = Source::Text;
+            } else if meta.path.is_ident("rename") {
+                xml_name = meta.value()?.parse::<LitStr>()?.value();
+            } else {
+                return Err(meta.error("expected `attribute`, `text` or `rename`"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok((source, xml_name))
+}
+
+fn expand_from_xml(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            name,
+            "FromXml can only be derived for structs",
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new_spanned(
+            &data.fields,
+            "FromXml can only be derived for structs with named fields",
+        ));
+    };
+
+    let (mut generics, src) = source_lifetime(input);
+    for param in generics.type_params_mut() {
+        param
+            .bounds
+            .push(parse_quote!(::xmltree::from_xml::FromXml<#src>));
+    }
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+
+    let mut reads = vec![];
+    for field in &fields.named {
+        let ident = field.ident.as_ref().expect("fields are named");
+        let (source, xml_name) = field_options(field)?;
+
+        let is_option = wrapped(&field.ty, "Option").is_some();
+        let is_vec = wrapped(&field.ty, "Vec").is_some();
+        let read = match source {
+            Source::Text => quote!(::xmltree::from_xml::text(tag, source)?),
+            Source::Attribute if is_vec => {
+                return Err(syn::Error::new_spanned(
+                    &field.ty,
+                    "attributes cannot be read into a Vec",
+                ));
+            }
+            Source::Attribute if is_option => {
+                quote!(::xmltree::from_xml::attribute(tag, source, #xml_name)?)
+            }
+            Source::Attribute => {
+                quote!(::xmltree::from_xml::required_attribute(tag, source, #xml_name)?)
+            }
+            Source::Child if is_option => {
+                quote!(::xmltree::from_xml::child(tag, source, #xml_name)?)
+            }
+            Source::Child if is_vec => {
+                quote!(::xmltree::from_xml::children(tag, source, #xml_name)?)
+            }
+            Source::Child => {
+                quote!(::xmltree::from_xml::required_child(tag, source, #xml_name)?)
+            }
+        };
+        reads.push(quote!(#ident: #read));
+    }
+
+    Ok(quote! {
+        impl #impl_generics ::xmltree::from_xml::FromXml<#src> for #name #ty_generics #where_clause {
+            fn from_xml(
+                tag: &::xmltree::node::TagNode<#src>,
+                source: &#src str,
+            ) -> ::xmltree::error::XmlResult<Self> {
+                Ok(Self {
+                    #(#reads),*
+                })
+            }
+        }
+    })
+}
+
+fn expand_to_xml(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            name,
+            "ToXml can only be derived for structs",
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new_spanned(
+            &data.fields,
+            "ToXml can only be derived for structs with named fields",
+        ));
+    };
+
+    let mut generics = input.generics.clone();
+    for param in generics.type_params_mut() {
+        param.bounds.push(parse_quote!(::xmltree::into_xml::ToXml));
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let mut writes = vec![];
+    for field in &fields.named {
+        let ident = field.ident.as_ref().expect("fields are named");
+        let (source, xml_name) = field_options(field)?;
+
+        let is_option = wrapped(&field.ty, "Option").is_some();
+        let is_vec = wrapped(&field.ty, "Vec").is_some();
+        let write = |value: TokenStream2| match source {
+            Source::Text => quote!(::xmltree::into_xml::push_text(&mut tag, #value)),
+            Source::Attribute => {
+                quote!(::xmltree::into_xml::push_attribute(&mut tag, #xml_name, #value))
+            }
+            Source::Child => quote!(::xmltree::into_xml::push_child(&mut tag, #xml_name, #value)),
+        };
+        let statement = if is_option {
+            let write = write(quote!(value));
+            quote!(if let Some(value) = &self.#ident { #write; })
+        } else if is_vec {
+            if !matches!(source, Source::Child) {
+                return Err(syn::Error::new_spanned(
+                    &field.ty,
+                    "only child tags can be written from a Vec",
+                ));
+            }
+            let write = write(quote!(value));
+            quote!(for value in &self.#ident { #write; })
+        } else {
+            let write = write(quote!(&self.#ident));
+            quote!(#write;)
+        };
+        writes.push(statement);
+    }
+
+    Ok(quote! {
+        impl #impl_generics ::xmltree::into_xml::ToXml for #name #ty_generics #where_clause {
+            fn to_xml_tag(&self, name: &str) -> ::xmltree::node::OwnedTagNode {
+                let mut tag = ::xmltree::node::OwnedTagNode::new(name);
+                #(#writes)*
+                tag
+            }
+        }
+    })
+}